@@ -0,0 +1,116 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use crate::rules_and_declarations::AtRuleType;
+use std::collections::HashMap;
+
+/// What follows the prelude of an at-rule.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AtRuleContents {
+    /// The at-rule ends with a `;` semicolon. Example: `@import`.
+    PreludeOnly,
+    /// The at-rule has a block that contains a list of rules,
+    /// to be parsed with `parse_nested_rules`. Example: `@media`.
+    Rules,
+    /// The at-rule has a block that contains a list of declarations or descriptors,
+    /// to be parsed with `DeclarationListParser`. Example: `@font-face`.
+    Declarations,
+}
+
+impl AtRuleContents {
+    /// Return the value for `AtRuleParser::parse_prelude` that matches these contents.
+    pub fn at_rule_type<P>(self, prelude: P) -> AtRuleType<P, P> {
+        match self {
+            AtRuleContents::PreludeOnly => AtRuleType::WithoutBlock(prelude),
+            AtRuleContents::Rules | AtRuleContents::Declarations => AtRuleType::WithBlock(prelude),
+        }
+    }
+}
+
+/// Return what follows the prelude of an at-rule defined in a CSS specification,
+/// given its name without the `@` marker, matched case-insensitively in the ASCII range.
+///
+/// Unknown at-rules return `None`, and are typically kept unparsed or rejected.
+pub fn classify_at_rule(name: &str) -> Option<AtRuleContents> {
+    use self::AtRuleContents::*;
+    ascii_case_insensitive_phf_map! {
+        contents -> AtRuleContents = {
+            "charset" => PreludeOnly,
+            "import" => PreludeOnly,
+            "namespace" => PreludeOnly,
+
+            "media" => Rules,
+            "supports" => Rules,
+            "document" => Rules,
+            "-moz-document" => Rules,
+            "container" => Rules,
+            "layer" => Rules,
+            "scope" => Rules,
+            "starting-style" => Rules,
+            "keyframes" => Rules,
+            "-webkit-keyframes" => Rules,
+            "-moz-keyframes" => Rules,
+
+            "font-face" => Declarations,
+            "font-palette-values" => Declarations,
+            "counter-style" => Declarations,
+            "property" => Declarations,
+            "page" => Declarations,
+            "viewport" => Declarations,
+            "-ms-viewport" => Declarations,
+            "top-left-corner" => Declarations,
+            "top-left" => Declarations,
+            "top-center" => Declarations,
+            "top-right" => Declarations,
+            "top-right-corner" => Declarations,
+            "bottom-left-corner" => Declarations,
+            "bottom-left" => Declarations,
+            "bottom-center" => Declarations,
+            "bottom-right" => Declarations,
+            "bottom-right-corner" => Declarations,
+            "left-top" => Declarations,
+            "left-middle" => Declarations,
+            "left-bottom" => Declarations,
+            "right-top" => Declarations,
+            "right-middle" => Declarations,
+            "right-bottom" => Declarations,
+        }
+    }
+    contents(name).cloned()
+}
+
+/// A table of what follows the prelude of at-rules,
+/// based on `classify_at_rule` with additions or overrides.
+///
+/// Note that `@layer` can also be a statement (`@layer a, b;`) without a block,
+/// and `@page` blocks can contain margin at-rules as well as declarations.
+#[derive(Clone, Debug, Default)]
+pub struct AtRuleClassifier {
+    overrides: HashMap<String, Option<AtRuleContents>>,
+}
+
+impl AtRuleClassifier {
+    /// Create a table with only the at-rules known to `classify_at_rule`.
+    pub fn new() -> AtRuleClassifier {
+        AtRuleClassifier::default()
+    }
+
+    /// Classify an at-rule, or with `None` make it unknown.
+    ///
+    /// The name is matched case-insensitively in the ASCII range.
+    pub fn set(&mut self, name: &str, contents: Option<AtRuleContents>) {
+        self.overrides.insert(name.to_ascii_lowercase(), contents);
+    }
+
+    /// Return what follows the prelude of the at-rule with the given name,
+    /// or `None` if it is unknown.
+    pub fn classify(&self, name: &str) -> Option<AtRuleContents> {
+        if !self.overrides.is_empty() {
+            if let Some(&contents) = self.overrides.get(&*name.to_ascii_lowercase()) {
+                return contents;
+            }
+        }
+        classify_at_rule(name)
+    }
+}
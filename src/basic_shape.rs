@@ -0,0 +1,394 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! https://drafts.csswg.org/css-shapes-1/#basic-shape-functions
+
+use crate::cow_rc_str::CowRcStr;
+use crate::dimensions::LengthPercentage;
+use crate::parser::{BasicParseError, FromCss, ParseError, Parser};
+use crate::position::Position;
+use crate::serializer::{serialize_string, ToCss};
+use crate::tokenizer::Token;
+use std::fmt;
+
+/// A `<basic-shape>` value, such as of `clip-path` or `shape-outside`,
+/// with its arguments as written.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BasicShape<'i> {
+    /// `inset()`
+    Inset {
+        /// The one to four offsets from the top, right, bottom and left edges,
+        /// as in the `margin` shorthand.
+        offsets: Vec<LengthPercentage>,
+        /// The radii after `round`, if any.
+        round: Option<BorderRadius>,
+    },
+    /// `circle()`
+    Circle {
+        /// The radius, if specified. It defaults to `closest-side`.
+        radius: Option<ShapeRadius>,
+        /// The center after `at`, if any.
+        position: Option<Position>,
+    },
+    /// `ellipse()`
+    Ellipse {
+        /// The horizontal and vertical radii, if specified. They default to `closest-side`.
+        radii: Option<(ShapeRadius, ShapeRadius)>,
+        /// The center after `at`, if any.
+        position: Option<Position>,
+    },
+    /// `polygon()`
+    Polygon {
+        /// The fill rule, if specified.
+        fill_rule: Option<FillRule>,
+        /// The vertices, at least one.
+        points: Vec<(LengthPercentage, LengthPercentage)>,
+    },
+    /// `path()`
+    Path {
+        /// The fill rule, if specified.
+        fill_rule: Option<FillRule>,
+        /// The SVG path data string, unescaped.
+        path: CowRcStr<'i>,
+    },
+}
+
+/// The radius of a `circle()` or `ellipse()`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ShapeRadius {
+    /// A non-negative length or percentage.
+    Length(LengthPercentage),
+    /// `closest-side`
+    ClosestSide,
+    /// `farthest-side`
+    FarthestSide,
+}
+
+/// A fill rule of `polygon()` or `path()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FillRule {
+    /// `nonzero`
+    Nonzero,
+    /// `evenodd`
+    Evenodd,
+}
+
+/// The radii of the corners of an `inset()`, with the syntax of `border-radius`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BorderRadius {
+    /// One to four horizontal radii.
+    pub horizontal: Vec<LengthPercentage>,
+    /// The one to four vertical radii after `/`, or none if they are the horizontal ones.
+    pub vertical: Vec<LengthPercentage>,
+}
+
+impl<'i> BasicShape<'i> {
+    /// Parse a basic shape function, accepting any string in `path()`.
+    pub fn parse<'t>(input: &mut Parser<'i, 't>) -> Result<Self, BasicParseError<'i>> {
+        BasicShape::parse_with_path_validator(input, |_| true)
+    }
+
+    /// Parse a basic shape function, rejecting `path()` when `is_valid_path`
+    /// returns false for its string, which is SVG path data that this crate does not parse.
+    pub fn parse_with_path_validator<'t, F>(
+        input: &mut Parser<'i, 't>,
+        is_valid_path: F,
+    ) -> Result<Self, BasicParseError<'i>>
+    where
+        F: FnOnce(&str) -> bool,
+    {
+        let location = input.current_source_location();
+        let name = input.expect_function()?.clone();
+        let result: Result<_, ParseError<()>> = input.parse_nested_block(|input| {
+            Ok(match_ignore_ascii_case! { &name,
+                "inset" => parse_inset(input)?,
+                "circle" => {
+                    let radius = input.try_parse(parse_shape_radius).ok();
+                    BasicShape::Circle { radius, position: parse_at_position(input)? }
+                },
+                "ellipse" => {
+                    let radii = input.try_parse(|input| -> Result<_, BasicParseError> {
+                        Ok((parse_shape_radius(input)?, parse_shape_radius(input)?))
+                    }).ok();
+                    BasicShape::Ellipse { radii, position: parse_at_position(input)? }
+                },
+                "polygon" => {
+                    let fill_rule = parse_fill_rule(input);
+                    let points = input.parse_comma_separated(|input| {
+                        Ok((LengthPercentage::parse(input)?, LengthPercentage::parse(input)?))
+                    })?;
+                    BasicShape::Polygon { fill_rule, points }
+                },
+                "path" => {
+                    let fill_rule = parse_fill_rule(input);
+                    let location = input.current_source_location();
+                    let path = input.expect_string_cloned()?;
+                    if !is_valid_path(&path) {
+                        let token = Token::QuotedString(path);
+                        return Err(location.new_unexpected_token_error(token));
+                    }
+                    BasicShape::Path { fill_rule, path }
+                },
+                _ => {
+                    let token = Token::Function(name.clone());
+                    return Err(location.new_unexpected_token_error(token))
+                }
+            })
+        });
+        result.map_err(ParseError::basic)
+    }
+}
+
+fn parse_inset<'i, 't>(input: &mut Parser<'i, 't>) -> Result<BasicShape<'i>, BasicParseError<'i>> {
+    let offsets = parse_one_to_four(input, LengthPercentage::parse)?;
+    let round = if input
+        .try_parse(|input| input.expect_ident_matching("round"))
+        .is_ok()
+    {
+        let horizontal = parse_one_to_four(input, parse_non_negative)?;
+        let vertical = if input.try_parse(|input| input.expect_delim('/')).is_ok() {
+            parse_one_to_four(input, parse_non_negative)?
+        } else {
+            Vec::new()
+        };
+        Some(BorderRadius {
+            horizontal,
+            vertical,
+        })
+    } else {
+        None
+    };
+    Ok(BasicShape::Inset { offsets, round })
+}
+
+fn parse_one_to_four<'i, 't, F>(
+    input: &mut Parser<'i, 't>,
+    mut parse: F,
+) -> Result<Vec<LengthPercentage>, BasicParseError<'i>>
+where
+    F: FnMut(&mut Parser<'i, 't>) -> Result<LengthPercentage, BasicParseError<'i>>,
+{
+    let mut values = vec![parse(input)?];
+    while values.len() < 4 {
+        match input.try_parse(&mut parse) {
+            Ok(value) => values.push(value),
+            Err(_) => break,
+        }
+    }
+    Ok(values)
+}
+
+fn parse_non_negative<'i, 't>(
+    input: &mut Parser<'i, 't>,
+) -> Result<LengthPercentage, BasicParseError<'i>> {
+    let location = input.current_source_location();
+    let state = input.state();
+    let value = LengthPercentage::parse(input)?;
+    let is_negative = match value {
+        LengthPercentage::Length(length) => length.value < 0.,
+        LengthPercentage::Percentage { unit_value } => unit_value < 0.,
+    };
+    if is_negative {
+        input.reset(&state);
+        let token = input.next()?.clone();
+        return Err(location.new_basic_unexpected_token_error(token));
+    }
+    Ok(value)
+}
+
+fn parse_shape_radius<'i, 't>(
+    input: &mut Parser<'i, 't>,
+) -> Result<ShapeRadius, BasicParseError<'i>> {
+    if let Ok(length) = input.try_parse(parse_non_negative) {
+        return Ok(ShapeRadius::Length(length));
+    }
+    let location = input.current_source_location();
+    let ident = input.expect_ident()?;
+    Ok(match_ignore_ascii_case! { ident,
+        "closest-side" => ShapeRadius::ClosestSide,
+        "farthest-side" => ShapeRadius::FarthestSide,
+        _ => {
+            let token = Token::Ident(ident.clone());
+            return Err(location.new_basic_unexpected_token_error(token))
+        }
+    })
+}
+
+/// Parse an optional `at <position>`.
+fn parse_at_position<'i, 't>(
+    input: &mut Parser<'i, 't>,
+) -> Result<Option<Position>, BasicParseError<'i>> {
+    if input
+        .try_parse(|input| input.expect_ident_matching("at"))
+        .is_ok()
+    {
+        Ok(Some(Position::parse(input)?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Parse an optional fill rule followed by a comma.
+fn parse_fill_rule<'i, 't>(input: &mut Parser<'i, 't>) -> Option<FillRule> {
+    input
+        .try_parse(|input| -> Result<_, BasicParseError> {
+            let location = input.current_source_location();
+            let ident = input.expect_ident()?;
+            let fill_rule = match_ignore_ascii_case! { ident,
+                "nonzero" => FillRule::Nonzero,
+                "evenodd" => FillRule::Evenodd,
+                _ => {
+                    let token = Token::Ident(ident.clone());
+                    return Err(location.new_basic_unexpected_token_error(token))
+                }
+            };
+            input.expect_comma()?;
+            Ok(fill_rule)
+        })
+        .ok()
+}
+
+impl<'i, E> FromCss<'i, E> for BasicShape<'i> {
+    fn from_css<'t>(input: &mut Parser<'i, 't>) -> Result<Self, ParseError<'i, E>> {
+        Ok(BasicShape::parse(input)?)
+    }
+}
+
+impl<'i> ToCss for BasicShape<'i> {
+    fn to_css<W>(&self, dest: &mut W) -> fmt::Result
+    where
+        W: fmt::Write,
+    {
+        match *self {
+            BasicShape::Inset {
+                ref offsets,
+                ref round,
+            } => {
+                dest.write_str("inset(")?;
+                write_space_separated(offsets, dest)?;
+                if let Some(ref round) = *round {
+                    dest.write_str(" round ")?;
+                    write_space_separated(&round.horizontal, dest)?;
+                    if !round.vertical.is_empty() {
+                        dest.write_str(" / ")?;
+                        write_space_separated(&round.vertical, dest)?;
+                    }
+                }
+            }
+            BasicShape::Circle {
+                ref radius,
+                ref position,
+            } => {
+                dest.write_str("circle(")?;
+                if let Some(ref radius) = *radius {
+                    radius.to_css(dest)?;
+                }
+                write_at_position(position, radius.is_some(), dest)?;
+            }
+            BasicShape::Ellipse {
+                ref radii,
+                ref position,
+            } => {
+                dest.write_str("ellipse(")?;
+                if let Some((ref x, ref y)) = *radii {
+                    x.to_css(dest)?;
+                    dest.write_str(" ")?;
+                    y.to_css(dest)?;
+                }
+                write_at_position(position, radii.is_some(), dest)?;
+            }
+            BasicShape::Polygon {
+                fill_rule,
+                ref points,
+            } => {
+                dest.write_str("polygon(")?;
+                write_fill_rule(fill_rule, dest)?;
+                for (i, (x, y)) in points.iter().enumerate() {
+                    if i > 0 {
+                        dest.write_str(", ")?;
+                    }
+                    x.to_css(dest)?;
+                    dest.write_str(" ")?;
+                    y.to_css(dest)?;
+                }
+            }
+            BasicShape::Path {
+                fill_rule,
+                ref path,
+            } => {
+                dest.write_str("path(")?;
+                write_fill_rule(fill_rule, dest)?;
+                serialize_string(path, dest)?;
+            }
+        }
+        dest.write_str(")")
+    }
+}
+
+fn write_space_separated<W>(values: &[LengthPercentage], dest: &mut W) -> fmt::Result
+where
+    W: fmt::Write,
+{
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            dest.write_str(" ")?;
+        }
+        value.to_css(dest)?;
+    }
+    Ok(())
+}
+
+fn write_at_position<W>(
+    position: &Option<Position>,
+    after_radius: bool,
+    dest: &mut W,
+) -> fmt::Result
+where
+    W: fmt::Write,
+{
+    if let Some(ref position) = *position {
+        if after_radius {
+            dest.write_str(" ")?;
+        }
+        dest.write_str("at ")?;
+        position.to_css(dest)?;
+    }
+    Ok(())
+}
+
+fn write_fill_rule<W>(fill_rule: Option<FillRule>, dest: &mut W) -> fmt::Result
+where
+    W: fmt::Write,
+{
+    if let Some(fill_rule) = fill_rule {
+        fill_rule.to_css(dest)?;
+        dest.write_str(", ")?;
+    }
+    Ok(())
+}
+
+impl ToCss for ShapeRadius {
+    fn to_css<W>(&self, dest: &mut W) -> fmt::Result
+    where
+        W: fmt::Write,
+    {
+        match *self {
+            ShapeRadius::Length(ref length) => length.to_css(dest),
+            ShapeRadius::ClosestSide => dest.write_str("closest-side"),
+            ShapeRadius::FarthestSide => dest.write_str("farthest-side"),
+        }
+    }
+}
+
+impl ToCss for FillRule {
+    fn to_css<W>(&self, dest: &mut W) -> fmt::Result
+    where
+        W: fmt::Write,
+    {
+        dest.write_str(match *self {
+            FillRule::Nonzero => "nonzero",
+            FillRule::Evenodd => "evenodd",
+        })
+    }
+}
@@ -0,0 +1,231 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Stylesheets shaped like common real-world CSS, and helpers to tokenize or parse them.
+//!
+//! These back the crate’s own benchmarks (with the `bench` feature),
+//! and can be reused by downstream benchmarks to compare against the same inputs.
+
+use crate::parser::{ParseError, Parser, ParserInput};
+use crate::rules_and_declarations::{
+    AtRuleParser, AtRuleType, DeclarationListParser, DeclarationParser, QualifiedRuleParser,
+    RuleListParser,
+};
+use crate::tokenizer::{SourceLocation, Token, TokenKind};
+use crate::CowRcStr;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// A readable, commented stylesheet in the style of a CSS framework:
+/// many selectors per rule, vendor prefixes, media queries and custom properties.
+pub fn framework_like() -> String {
+    let mut css = String::from(
+        ":root {\n  --primary: #0d6efd;\n  --font-stack: system-ui, -apple-system, \"Segoe UI\";\n}\n",
+    );
+    for i in 0..200 {
+        write!(
+            css,
+            "/* Component {i} */\n\
+             .btn-{i}, .btn-{i}:hover, a.btn-{i}[href^=\"http\"] > .icon {{\n  \
+             display: inline-block;\n  \
+             padding: .375rem .75rem;\n  \
+             font-family: var(--font-stack);\n  \
+             color: rgba(13, 110, 253, 0.{i});\n  \
+             background-image: url(data:image/png;base64,iVBORw0KGgo{i}=);\n  \
+             -webkit-transition: color .15s ease-in-out, background-color .15s ease-in-out;\n  \
+             transition: color .15s ease-in-out, background-color .15s ease-in-out !important;\n\
+             }}\n\
+             @media (min-width: {width}px) {{\n  \
+             .col-md-{i} {{ flex: 0 0 auto; width: {percent}%; }}\n\
+             }}\n",
+            i = i,
+            width = 576 + i * 4,
+            percent = (i % 12 + 1) as f32 * 100. / 12.
+        )
+        .unwrap();
+    }
+    css
+}
+
+/// A minified application stylesheet: no whitespace or comments between tokens.
+pub fn minified_app() -> String {
+    let mut css = String::new();
+    for i in 0..400 {
+        write!(
+            css,
+            ".app-{i}>.x{{margin:0 auto;border:1px solid #e{d}e{d}e{d};\
+             transform:translate({i}px,-50%) rotate({i}deg)}}\
+             #id{i}:not(.hidden)::before{{content:\"\\201C\";font:italic 700 1.{d}em/1 serif}}",
+            i = i,
+            d = i % 10
+        )
+        .unwrap();
+    }
+    css
+}
+
+/// A large generated utility stylesheet: thousands of tiny single-declaration rules.
+pub fn utility_classes() -> String {
+    let properties = [
+        ("m", "margin"),
+        ("p", "padding"),
+        ("mt", "margin-top"),
+        ("pb", "padding-bottom"),
+        ("w", "width"),
+        ("gap", "gap"),
+    ];
+    let mut css = String::new();
+    for &(prefix, property) in &properties {
+        for size in 0..400 {
+            writeln!(
+                css,
+                ".{prefix}-{size} {{ {property}: {rem}rem; }}",
+                prefix = prefix,
+                size = size,
+                property = property,
+                rem = size as f32 / 4.
+            )
+            .unwrap();
+        }
+    }
+    css
+}
+
+/// About 64 KiB of a single token of the given kind, repeated and separated by spaces
+/// (other than comments, which are not separated), to benchmark tokenizing that kind.
+///
+/// Panics for kinds other than `Ident`, `Hash`, `IDHash`, `QuotedString`, `UnquotedUrl`,
+/// `Number`, `Percentage`, `Dimension` and `Comment`.
+pub fn repeated_token(kind: TokenKind) -> String {
+    let (token, separator) = match kind {
+        TokenKind::Ident => ("font-family", " "),
+        TokenKind::Hash => ("#0d6efd", " "),
+        TokenKind::IDHash => ("#header", " "),
+        TokenKind::QuotedString => ("\"Helvetica Neue\"", " "),
+        TokenKind::UnquotedUrl => ("url(img/icons/arrow.png)", " "),
+        TokenKind::Number => ("-1.5e3", " "),
+        TokenKind::Percentage => ("33.333%", " "),
+        TokenKind::Dimension => ("12.5px", " "),
+        TokenKind::Comment => ("/* Component */", ""),
+        _ => panic!("no sample token of kind {:?}", kind),
+    };
+    let mut css = String::new();
+    while css.len() < 64 * 1024 {
+        css.push_str(token);
+        css.push_str(separator);
+    }
+    css
+}
+
+/// Tokenize the whole input, including the contents of blocks and functions.
+///
+/// Returns the number of tokens.
+pub fn tokenize_all(css: &str) -> usize {
+    let mut input = ParserInput::new(css);
+    let mut parser = Parser::new(&mut input);
+    count_tokens(&mut parser, &mut |_| {})
+}
+
+/// Tokenize the whole input and count how many tokens of each kind it contains.
+pub fn count_tokens_by_kind(css: &str) -> HashMap<TokenKind, usize> {
+    let mut counts = HashMap::new();
+    let mut input = ParserInput::new(css);
+    let mut parser = Parser::new(&mut input);
+    count_tokens(&mut parser, &mut |token| {
+        *counts.entry(token.kind()).or_insert(0) += 1
+    });
+    counts
+}
+
+/// Parse the input as a stylesheet, parsing every rule body as a list of declarations.
+///
+/// Returns the number of rules and declarations that were parsed successfully.
+pub fn parse_all(css: &str) -> usize {
+    let mut input = ParserInput::new(css);
+    let mut parser = Parser::new(&mut input);
+    RuleListParser::new_for_stylesheet(&mut parser, CountingParser)
+        .filter_map(Result::ok)
+        .sum()
+}
+
+fn count_tokens<'i, 't>(parser: &mut Parser<'i, 't>, each: &mut dyn FnMut(&Token<'i>)) -> usize {
+    let mut count = 0;
+    while let Ok(token) = parser.next_including_whitespace_and_comments() {
+        count += 1;
+        each(token);
+        if token.is_block() {
+            let result: Result<_, ParseError<()>> =
+                parser.parse_nested_block(|parser| Ok(count_tokens(parser, each)));
+            count += result.unwrap();
+        }
+    }
+    count
+}
+
+struct CountingParser;
+
+impl<'i> DeclarationParser<'i> for CountingParser {
+    type Declaration = usize;
+    type Error = ();
+
+    fn parse_value<'t>(
+        &mut self,
+        _name: CowRcStr<'i>,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<usize, ParseError<'i, ()>> {
+        count_tokens(input, &mut |_| {});
+        Ok(1)
+    }
+}
+
+impl<'i> AtRuleParser<'i> for CountingParser {
+    type PreludeNoBlock = ();
+    type PreludeBlock = ();
+    type AtRule = usize;
+    type Error = ();
+
+    fn parse_prelude<'t>(
+        &mut self,
+        _name: CowRcStr<'i>,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<AtRuleType<(), ()>, ParseError<'i, ()>> {
+        count_tokens(input, &mut |_| {});
+        Ok(AtRuleType::WithBlock(()))
+    }
+
+    fn parse_block<'t>(
+        &mut self,
+        _prelude: (),
+        _location: SourceLocation,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<usize, ParseError<'i, ()>> {
+        let nested: usize = RuleListParser::new_for_nested_rule(input, CountingParser)
+            .filter_map(Result::ok)
+            .sum();
+        Ok(1 + nested)
+    }
+}
+
+impl<'i> QualifiedRuleParser<'i> for CountingParser {
+    type Prelude = ();
+    type QualifiedRule = usize;
+    type Error = ();
+
+    fn parse_prelude<'t>(&mut self, input: &mut Parser<'i, 't>) -> Result<(), ParseError<'i, ()>> {
+        count_tokens(input, &mut |_| {});
+        Ok(())
+    }
+
+    fn parse_block<'t>(
+        &mut self,
+        _prelude: (),
+        _location: SourceLocation,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<usize, ParseError<'i, ()>> {
+        let declarations: usize = DeclarationListParser::new(input, CountingParser)
+            .filter_map(Result::ok)
+            .sum();
+        Ok(1 + declarations)
+    }
+}
@@ -0,0 +1,253 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use crate::tokenizer::{is_css_whitespace, SourceLocation, Token, Tokenizer};
+use std::cmp;
+use std::str;
+
+/// How many bytes past the end of a token the tokenizer may look at
+/// to decide where that token ends, for example `!--` after `<`
+/// or `e+1` after a number.
+const MAX_LOOKAHEAD: usize = 3;
+
+/// Tokenizes a stylesheet that arrives in several chunks, such as from the network.
+///
+/// A token is passed to the callback once enough input has arrived that it can not change,
+/// so a string or identifier split between two chunks is seen as one token.
+/// Tokens borrow from an internal buffer and are only valid during the callback:
+/// copy or serialize what needs to be kept.
+///
+/// Input that has been tokenized is dropped from the buffer,
+/// so memory use depends on the size of the chunks and of the longest token,
+/// not of the whole stylesheet.
+pub struct ChunkedTokenizer {
+    buffer: String,
+    /// Where the start of `buffer` is in the whole input.
+    buffer_location: SourceLocation,
+}
+
+impl ChunkedTokenizer {
+    /// Create a tokenizer with no input yet.
+    pub fn new() -> ChunkedTokenizer {
+        ChunkedTokenizer {
+            buffer: String::new(),
+            buffer_location: SourceLocation { line: 0, column: 1 },
+        }
+    }
+
+    /// Append a chunk of input,
+    /// and pass each token that is now complete to `callback` with its location.
+    pub fn push_str<F>(&mut self, chunk: &str, callback: F)
+    where
+        F: FnMut(Token, SourceLocation),
+    {
+        self.buffer.push_str(chunk);
+        self.tokenize(false, callback)
+    }
+
+    /// Signal the end of the input, and pass the remaining tokens to `callback`.
+    pub fn finish<F>(mut self, callback: F)
+    where
+        F: FnMut(Token, SourceLocation),
+    {
+        self.tokenize(true, callback)
+    }
+
+    fn tokenize<F>(&mut self, at_end: bool, mut callback: F)
+    where
+        F: FnMut(Token, SourceLocation),
+    {
+        let (consumed, consumed_location) =
+            tokenize_str(&self.buffer, self.buffer_location, at_end, &mut callback);
+        self.buffer.drain(..consumed);
+        self.buffer_location = consumed_location;
+    }
+}
+
+/// Pass the tokens of `input`, which starts at `start` in the whole input, to `callback`,
+/// up to the first token that more input could change unless `at_end`.
+///
+/// Returns the byte index in `input` and the location where that token starts.
+fn tokenize_str<F>(
+    input: &str,
+    start: SourceLocation,
+    at_end: bool,
+    callback: &mut F,
+) -> (usize, SourceLocation)
+where
+    F: FnMut(Token, SourceLocation),
+{
+    let to_whole_input = |location: SourceLocation| SourceLocation {
+        line: location.line,
+        column: if location.line == start.line {
+            location.column + start.column - 1
+        } else {
+            location.column
+        },
+    };
+
+    let mut tokenizer = Tokenizer::with_first_line_number(input, start.line);
+    let mut consumed = 0;
+    let mut consumed_location = start;
+    loop {
+        let location = to_whole_input(tokenizer.current_source_location());
+        let token = match tokenizer.next() {
+            Ok(token) => token,
+            Err(()) => break,
+        };
+        let end = tokenizer.position().byte_index();
+        if !at_end && !is_complete(&token, &input[end..]) {
+            break;
+        }
+        callback(token, location);
+        consumed = end;
+        consumed_location = to_whole_input(tokenizer.current_source_location());
+    }
+    (consumed, consumed_location)
+}
+
+impl Default for ChunkedTokenizer {
+    fn default() -> Self {
+        ChunkedTokenizer::new()
+    }
+}
+
+/// Tokenizes a stylesheet given as bytes, decoding them as UTF-8,
+/// and passes each token to `callback` with its location.
+///
+/// A leading UTF-8 BOM is removed and invalid byte sequences are replaced with U+FFFD,
+/// as `String::from_utf8_lossy` does, but without copying the input:
+/// valid UTF-8 is tokenized in place, and only the tokens around invalid byte sequences
+/// are buffered, as with `ChunkedTokenizer`.
+/// Tokens are only valid during the callback.
+///
+/// Other encodings must be decoded first, see `stylesheet_encoding`.
+pub fn tokenize_bytes_lossy<F>(css: &[u8], mut callback: F)
+where
+    F: FnMut(Token, SourceLocation),
+{
+    let css = if css.starts_with(b"\xEF\xBB\xBF") {
+        &css[3..]
+    } else {
+        css
+    };
+    match str::from_utf8(css) {
+        Ok(css) => {
+            let mut tokenizer = Tokenizer::new(css);
+            loop {
+                let location = tokenizer.current_source_location();
+                match tokenizer.next() {
+                    Ok(token) => callback(token, location),
+                    Err(()) => break,
+                }
+            }
+        }
+        Err(_) => {
+            let mut boundary = Boundary {
+                tokenizer: ChunkedTokenizer::new(),
+                tokenized_len: 0,
+            };
+            let mut rest = css;
+            loop {
+                let (valid, invalid) = match str::from_utf8(rest) {
+                    Ok(valid) => (valid, None),
+                    Err(error) => {
+                        let (valid, invalid) = rest.split_at(error.valid_up_to());
+                        // Safety: `from_utf8` checked the bytes before `valid_up_to`.
+                        let valid = unsafe { str::from_utf8_unchecked(valid) };
+                        // `None` for an incomplete sequence at the end of the input.
+                        let invalid_len = error.error_len().unwrap_or(invalid.len());
+                        (valid, Some(&invalid[invalid_len..]))
+                    }
+                };
+                boundary.tokenize_in_place(valid, invalid.is_none(), &mut callback);
+                match invalid {
+                    Some(after_invalid) => {
+                        boundary.tokenizer.buffer.push('\u{FFFD}');
+                        rest = after_invalid;
+                    }
+                    None => break,
+                }
+            }
+            boundary.tokenizer.finish(callback)
+        }
+    }
+}
+
+/// The tokens around the invalid byte sequences of `tokenize_bytes_lossy`.
+struct Boundary {
+    /// Buffers the tokens that contain or follow a replacement character,
+    /// until one ends in valid UTF-8 that can be tokenized in place.
+    tokenizer: ChunkedTokenizer,
+    /// The size of the buffer when it was last tokenized.
+    tokenized_len: usize,
+}
+
+impl Boundary {
+    /// Pass the tokens of `valid`, which follows the buffer, to `callback`,
+    /// and keep in the buffer those that more input could change unless `at_end`.
+    fn tokenize_in_place<F>(&mut self, mut valid: &str, at_end: bool, callback: &mut F)
+    where
+        F: FnMut(Token, SourceLocation),
+    {
+        if !self.tokenizer.buffer.is_empty() {
+            valid = &valid[self.push_until_token_boundary(valid, callback)..];
+            if !self.tokenizer.buffer.is_empty() {
+                return;
+            }
+        }
+        let start = self.tokenizer.buffer_location;
+        let (consumed, location) = tokenize_str(valid, start, at_end, callback);
+        self.tokenizer.buffer.push_str(&valid[consumed..]);
+        self.tokenizer.buffer_location = location;
+        self.tokenized_len = self.tokenizer.buffer.len();
+    }
+
+    /// Append `valid` to the buffer until the buffer is tokenized up to a token
+    /// that starts in `valid`, and return where that token starts in `valid`,
+    /// after which the buffer is empty.
+    /// Otherwise all of `valid` is appended.
+    ///
+    /// The buffer is tokenized again after its size has doubled,
+    /// so that a token that spans many invalid byte sequences is tokenized in linear time.
+    fn push_until_token_boundary<F>(&mut self, valid: &str, callback: &mut F) -> usize
+    where
+        F: FnMut(Token, SourceLocation),
+    {
+        let mut pushed = 0;
+        while pushed < valid.len() {
+            let buffer_len = self.tokenizer.buffer.len();
+            let next_tokenize_len = cmp::max(2 * self.tokenized_len, buffer_len + 64);
+            let mut end = cmp::min(pushed + next_tokenize_len - buffer_len, valid.len());
+            while !valid.is_char_boundary(end) {
+                end += 1
+            }
+            self.tokenizer.buffer.push_str(&valid[pushed..end]);
+            pushed = end;
+            if self.tokenizer.buffer.len() < next_tokenize_len {
+                break;
+            }
+            self.tokenizer.tokenize(false, &mut *callback);
+            self.tokenized_len = self.tokenizer.buffer.len();
+            if self.tokenized_len <= pushed {
+                // The first token left starts in `valid`.
+                let token_start = pushed - self.tokenized_len;
+                self.tokenizer.buffer.clear();
+                return token_start;
+            }
+        }
+        pushed
+    }
+}
+
+/// Whether more input after `rest` could not change `token`, which is just before `rest`.
+fn is_complete(token: &Token, rest: &str) -> bool {
+    match *token {
+        // `url(` followed by whitespace is a function token if a quote follows the whitespace.
+        Token::Function(ref name) if name.eq_ignore_ascii_case("url") => {
+            rest.trim_start_matches(is_css_whitespace).len() >= MAX_LOOKAHEAD
+        }
+        _ => rest.len() >= MAX_LOOKAHEAD,
+    }
+}
@@ -5,7 +5,7 @@
 use std::f32::consts::PI;
 use std::fmt;
 
-use super::{BasicParseError, ParseError, Parser, ToCss, Token};
+use super::{BasicParseError, FromCss, ParseError, Parser, ParserInput, ToCss, Token, Void};
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
@@ -78,6 +78,52 @@ impl RGBA {
     pub fn alpha_f32(&self) -> f32 {
         self.alpha as f32 / 255.0
     }
+
+    /// Constructs a new RGBA value from a packed integer,
+    /// with red in the most significant byte and alpha in the least: `0xRRGGBBAA`.
+    #[inline]
+    pub fn from_rgba_u32(packed: u32) -> Self {
+        let [red, green, blue, alpha] = packed.to_be_bytes();
+        Self::new(red, green, blue, alpha)
+    }
+
+    /// Returns this color as a packed integer,
+    /// with red in the most significant byte and alpha in the least: `0xRRGGBBAA`.
+    #[inline]
+    pub fn to_rgba_u32(&self) -> u32 {
+        u32::from_be_bytes([self.red, self.green, self.blue, self.alpha])
+    }
+
+    /// Constructs a new RGBA value from a packed integer,
+    /// with alpha in the most significant byte and blue in the least: `0xAARRGGBB`.
+    #[inline]
+    pub fn from_argb_u32(packed: u32) -> Self {
+        let [alpha, red, green, blue] = packed.to_be_bytes();
+        Self::new(red, green, blue, alpha)
+    }
+
+    /// Returns this color as a packed integer,
+    /// with alpha in the most significant byte and blue in the least: `0xAARRGGBB`.
+    #[inline]
+    pub fn to_argb_u32(&self) -> u32 {
+        u32::from_be_bytes([self.alpha, self.red, self.green, self.blue])
+    }
+}
+
+/// Red, green, blue, and alpha, in that order.
+impl From<[u8; 4]> for RGBA {
+    #[inline]
+    fn from([red, green, blue, alpha]: [u8; 4]) -> Self {
+        Self::new(red, green, blue, alpha)
+    }
+}
+
+/// Red, green, blue, and alpha, in that order.
+impl From<RGBA> for [u8; 4] {
+    #[inline]
+    fn from(rgba: RGBA) -> Self {
+        [rgba.red, rgba.green, rgba.blue, rgba.alpha]
+    }
 }
 
 #[cfg(feature = "serde")]
@@ -106,36 +152,136 @@ impl ToCss for RGBA {
     where
         W: fmt::Write,
     {
-        let serialize_alpha = self.alpha != 255;
+        let has_alpha = self.alpha != 255;
 
-        dest.write_str(if serialize_alpha { "rgba(" } else { "rgb(" })?;
+        dest.write_str(if has_alpha { "rgba(" } else { "rgb(" })?;
         self.red.to_css(dest)?;
         dest.write_str(", ")?;
         self.green.to_css(dest)?;
         dest.write_str(", ")?;
         self.blue.to_css(dest)?;
-        if serialize_alpha {
+        if has_alpha {
             dest.write_str(", ")?;
-
-            // Try first with two decimal places, then with three.
-            let mut rounded_alpha = (self.alpha_f32() * 100.).round() / 100.;
-            if clamp_unit_f32(rounded_alpha) != self.alpha {
-                rounded_alpha = (self.alpha_f32() * 1000.).round() / 1000.;
-            }
-
-            rounded_alpha.to_css(dest)?;
+            serialize_alpha(self.alpha, dest)?;
         }
         dest.write_char(')')
     }
 }
 
+impl RGBA {
+    /// Serialize this color in its shortest form: `#rgb`, `#rrggbb` or a shorter color keyword
+    /// such as `red` or `navy`, or `rgba()` as with `to_css` if it is not opaque.
+    ///
+    /// Hex notation with an alpha component is not used, since it is less widely supported.
+    pub fn to_css_shortest<W>(&self, dest: &mut W) -> fmt::Result
+    where
+        W: fmt::Write,
+    {
+        if self.alpha != 255 {
+            return self.to_css(dest);
+        }
+        let is_short = |c: u8| c >> 4 == c & 0x0F;
+        let hex = if is_short(self.red) && is_short(self.green) && is_short(self.blue) {
+            format!(
+                "#{:x}{:x}{:x}",
+                self.red & 0x0F,
+                self.green & 0x0F,
+                self.blue & 0x0F
+            )
+        } else {
+            format!("#{:02x}{:02x}{:02x}", self.red, self.green, self.blue)
+        };
+        let keyword = SHORT_COLOR_KEYWORDS
+            .iter()
+            .find(|&&(_, rgb)| rgb == (self.red, self.green, self.blue))
+            .map(|&(keyword, _)| keyword)
+            .filter(|keyword| keyword.len() < hex.len());
+        dest.write_str(keyword.unwrap_or(&hex))
+    }
+}
+
+/// The color keywords that are shorter than `#rrggbb`, for `RGBA::to_css_shortest`.
+static SHORT_COLOR_KEYWORDS: &[(&str, (u8, u8, u8))] = &[
+    ("red", (255, 0, 0)),
+    ("tan", (210, 180, 140)),
+    ("aqua", (0, 255, 255)),
+    ("blue", (0, 0, 255)),
+    ("gold", (255, 215, 0)),
+    ("gray", (128, 128, 128)),
+    ("lime", (0, 255, 0)),
+    ("navy", (0, 0, 128)),
+    ("peru", (205, 133, 63)),
+    ("pink", (255, 192, 203)),
+    ("plum", (221, 160, 221)),
+    ("snow", (255, 250, 250)),
+    ("teal", (0, 128, 128)),
+    ("azure", (240, 255, 255)),
+    ("beige", (245, 245, 220)),
+    ("black", (0, 0, 0)),
+    ("brown", (165, 42, 42)),
+    ("coral", (255, 127, 80)),
+    ("green", (0, 128, 0)),
+    ("ivory", (255, 255, 240)),
+    ("khaki", (240, 230, 140)),
+    ("linen", (250, 240, 230)),
+    ("olive", (128, 128, 0)),
+    ("wheat", (245, 222, 179)),
+    ("white", (255, 255, 255)),
+    ("bisque", (255, 228, 196)),
+    ("indigo", (75, 0, 130)),
+    ("maroon", (128, 0, 0)),
+    ("orange", (255, 165, 0)),
+    ("orchid", (218, 112, 214)),
+    ("purple", (128, 0, 128)),
+    ("salmon", (250, 128, 114)),
+    ("sienna", (160, 82, 45)),
+    ("silver", (192, 192, 192)),
+    ("tomato", (255, 99, 71)),
+    ("violet", (238, 130, 238)),
+    ("yellow", (255, 255, 0)),
+];
+
+/// Serialize an alpha value stored as an 8-bit integer, per CSSOM:
+/// https://drafts.csswg.org/cssom/#serializing-css-values
+///
+/// This is the shortest decimal with at most two decimal places that rounds back
+/// to the same integer, or with three decimal places if there is none.
+fn serialize_alpha<W>(alpha: u8, dest: &mut W) -> fmt::Result
+where
+    W: fmt::Write,
+{
+    // Try first with two decimal places, then with three.
+    let alpha_f32 = alpha as f32 / 255.;
+    let mut rounded_alpha = (alpha_f32 * 100.).round() / 100.;
+    if clamp_unit_f32(rounded_alpha) != alpha {
+        rounded_alpha = (alpha_f32 * 1000.).round() / 1000.;
+    }
+    rounded_alpha.to_css(dest)
+}
+
 /// A <color> value.
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub enum Color {
     /// The 'currentcolor' keyword
     CurrentColor,
-    /// Everything else gets converted to RGBA during parsing
+    /// Everything else gets converted to RGBA during parsing,
+    /// unless `ColorComponentParser::preserve_hsl_and_hwb` returns true.
     RGBA(RGBA),
+    /// An `hsl()` or `hsla()` color.
+    /// See `ColorComponentParser::preserve_hsl_and_hwb`.
+    Hsl(Hsla),
+    /// An `hwb()` color, only parsed with `ColorComponentParser::preserve_hsl_and_hwb`.
+    Hwb(Hwba),
+    /// Never returned. Matches on `Color` need a wildcard arm,
+    /// so that new variants can be added without a breaking change.
+    #[doc(hidden)]
+    __Nonexhaustive(Void),
+}
+
+impl<'i, E> FromCss<'i, E> for Color {
+    fn from_css<'t>(input: &mut Parser<'i, 't>) -> Result<Self, ParseError<'i, E>> {
+        Ok(Color::parse(input)?)
+    }
 }
 
 impl ToCss for Color {
@@ -146,11 +292,134 @@ impl ToCss for Color {
         match *self {
             Color::CurrentColor => dest.write_str("currentcolor"),
             Color::RGBA(ref rgba) => rgba.to_css(dest),
+            Color::Hsl(ref hsla) => hsla.to_css(dest),
+            Color::Hwb(ref hwba) => hwba.to_css(dest),
+            Color::__Nonexhaustive(void) => match void {},
+        }
+    }
+}
+
+impl Color {
+    /// Serialize this color in its shortest form, as with `RGBA::to_css_shortest`.
+    ///
+    /// `hsl()` and `hwb()` colors are converted to RGBA first.
+    pub fn to_css_shortest<W>(&self, dest: &mut W) -> fmt::Result
+    where
+        W: fmt::Write,
+    {
+        match self.to_rgba() {
+            Some(rgba) => rgba.to_css_shortest(dest),
+            None => self.to_css(dest),
+        }
+    }
+
+    /// Return this color converted to RGBA, or `None` for `currentcolor`.
+    pub fn to_rgba(&self) -> Option<RGBA> {
+        match *self {
+            Color::CurrentColor => None,
+            Color::RGBA(rgba) => Some(rgba),
+            Color::Hsl(ref hsla) => Some(hsla.to_rgba()),
+            Color::Hwb(ref hwba) => Some(hwba.to_rgba()),
+            Color::__Nonexhaustive(void) => match void {},
+        }
+    }
+}
+
+/// A color with hue, saturation, lightness, and alpha components.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Hsla {
+    /// The hue, in degrees from 0 to 360.
+    pub hue: f32,
+    /// The saturation, from 0.0 to 1.0.
+    pub saturation: f32,
+    /// The lightness, from 0.0 to 1.0.
+    pub lightness: f32,
+    /// The alpha component, from 0.0 to 1.0.
+    pub alpha: f32,
+}
+
+impl Hsla {
+    /// Convert to RGBA, per CSS Color Module Level 3.
+    pub fn to_rgba(&self) -> RGBA {
+        let (red, green, blue) = hsl_to_rgb(self.hue, self.saturation, self.lightness);
+        RGBA::from_floats(red, green, blue, self.alpha)
+    }
+}
+
+impl ToCss for Hsla {
+    fn to_css<W>(&self, dest: &mut W) -> fmt::Result
+    where
+        W: fmt::Write,
+    {
+        let has_alpha = self.alpha != 1.;
+
+        dest.write_str(if has_alpha { "hsla(" } else { "hsl(" })?;
+        self.hue.to_css(dest)?;
+        dest.write_str(", ")?;
+        (self.saturation * 100.).to_css(dest)?;
+        dest.write_str("%, ")?;
+        (self.lightness * 100.).to_css(dest)?;
+        dest.write_char('%')?;
+        if has_alpha {
+            dest.write_str(", ")?;
+            serialize_alpha(clamp_unit_f32(self.alpha), dest)?;
+        }
+        dest.write_char(')')
+    }
+}
+
+/// A color with hue, whiteness, blackness, and alpha components.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Hwba {
+    /// The hue, in degrees from 0 to 360.
+    pub hue: f32,
+    /// The whiteness, from 0.0 to 1.0.
+    pub whiteness: f32,
+    /// The blackness, from 0.0 to 1.0.
+    pub blackness: f32,
+    /// The alpha component, from 0.0 to 1.0.
+    pub alpha: f32,
+}
+
+impl Hwba {
+    /// Convert to RGBA, per CSS Color Module Level 4.
+    pub fn to_rgba(&self) -> RGBA {
+        // https://drafts.csswg.org/css-color-4/#hwb-to-rgb
+        let (red, green, blue) = if self.whiteness + self.blackness >= 1. {
+            let gray = self.whiteness / (self.whiteness + self.blackness);
+            (gray, gray, gray)
+        } else {
+            let (red, green, blue) = hsl_to_rgb(self.hue, 1., 0.5);
+            let scale =
+                |value: f32| value * (1. - self.whiteness - self.blackness) + self.whiteness;
+            (scale(red), scale(green), scale(blue))
+        };
+        RGBA::from_floats(red, green, blue, self.alpha)
+    }
+}
+
+impl ToCss for Hwba {
+    fn to_css<W>(&self, dest: &mut W) -> fmt::Result
+    where
+        W: fmt::Write,
+    {
+        dest.write_str("hwb(")?;
+        self.hue.to_css(dest)?;
+        dest.write_char(' ')?;
+        (self.whiteness * 100.).to_css(dest)?;
+        dest.write_str("% ")?;
+        (self.blackness * 100.).to_css(dest)?;
+        dest.write_char('%')?;
+        if self.alpha != 1. {
+            dest.write_str(" / ")?;
+            serialize_alpha(clamp_unit_f32(self.alpha), dest)?;
         }
+        dest.write_char(')')
     }
 }
 
 /// Either a number or a percentage.
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum NumberOrPercentage {
     /// `<number>`.
     Number {
@@ -250,6 +519,16 @@ pub trait ColorComponentParser<'i> {
         input.expect_number().map_err(From::from)
     }
 
+    /// Return whether `hsl()` and `hwb()` colors are kept as `Color::Hsl` and `Color::Hwb`
+    /// with their components, instead of being converted to `Color::RGBA`.
+    ///
+    /// This is `false` by default, in which case `hsl()` colors are converted
+    /// and `hwb()` is not parsed at all: `hwb()` is only supported with its components.
+    /// `Color::to_rgba` can be used to convert them later.
+    fn preserve_hsl_and_hwb(&self) -> bool {
+        false
+    }
+
     /// Parse a `<number>` value or a `<percentage>` value.
     fn parse_number_or_percentage<'t>(
         &self,
@@ -269,6 +548,38 @@ impl<'i> ColorComponentParser<'i> for DefaultComponentParser {
     type Error = ();
 }
 
+/// Information about how a color was written, beyond its value.
+///
+/// See `Color::parse_with_details`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ColorDetails {
+    /// Whether a component was out of its range and was clamped,
+    /// such as 300 in `rgb(300, 0, 0)` or 150% in `hsl(0, 150%, 50%)`,
+    /// or whether a hue was outside of 0 to 360 degrees and wrapped around.
+    ///
+    /// Rounding is not considered clamping.
+    pub out_of_range: bool,
+    /// For `rgb()` and `rgba()`, the red, green, and blue components as written,
+    /// before clamping and rounding.
+    pub rgb_components: Option<RgbComponents>,
+    /// For color functions, the alpha component as written, if any.
+    pub alpha: Option<NumberOrPercentage>,
+    /// For a color keyword such as `red`, `transparent`, or `currentcolor`,
+    /// the keyword in ASCII lower case.
+    ///
+    /// This is `None` for hex colors and color functions.
+    pub keyword: Option<String>,
+}
+
+/// The red, green, and blue components of an `rgb()` or `rgba()` function.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RgbComponents {
+    /// `<number>` components, nominally from 0 to 255.
+    Numbers([f32; 3]),
+    /// `<percentage>` components, divided by 100 so that the nominal range is 0.0 to 1.0.
+    Percentages([f32; 3]),
+}
+
 impl Color {
     /// Parse a <color> value, per CSS Color Module Level 3.
     ///
@@ -280,22 +591,24 @@ impl Color {
     where
         ComponentParser: ColorComponentParser<'i>,
     {
-        let location = input.current_source_location();
-        let token = input.next()?;
-        match *token {
-            Token::Hash(ref value) | Token::IDHash(ref value) => {
-                Color::parse_hash(value.as_bytes())
-            }
-            Token::Ident(ref value) => parse_color_keyword(&*value),
-            Token::Function(ref name) => {
-                let name = name.clone();
-                return input.parse_nested_block(|arguments| {
-                    parse_color_function(component_parser, &*name, arguments)
-                });
-            }
-            _ => Err(()),
-        }
-        .map_err(|()| location.new_unexpected_token_error(token.clone()))
+        let mut details = ColorDetails::default();
+        parse_color(component_parser, input, &mut details)
+    }
+
+    /// Like `parse_with`, but also return information about how the color was written,
+    /// for example for linters.
+    ///
+    /// The color itself is the same as returned by `parse_with`.
+    pub fn parse_with_details<'i, 't, ComponentParser>(
+        component_parser: &ComponentParser,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<(Color, ColorDetails), ParseError<'i, ComponentParser::Error>>
+    where
+        ComponentParser: ColorComponentParser<'i>,
+    {
+        let mut details = ColorDetails::default();
+        let color = parse_color(component_parser, input, &mut details)?;
+        Ok((color, details))
     }
 
     /// Parse a <color> value, per CSS Color Module Level 3.
@@ -304,6 +617,18 @@ impl Color {
         Self::parse_with(&component_parser, input).map_err(ParseError::basic)
     }
 
+    /// Parse a string that contains a single <color> value, such as `"rgb(1, 2, 3)"`.
+    ///
+    /// Whitespace and comments around the value are ignored,
+    /// anything else is an error with its location in the string.
+    pub fn parse_str<'i>(css: &'i str) -> Result<Color, BasicParseError<'i>> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        let result: Result<_, ParseError<()>> =
+            parser.parse_entirely(|input| Self::parse_with(&DefaultComponentParser, input));
+        result.map_err(ParseError::basic)
+    }
+
     /// Parse a color hash, without the leading '#' character.
     #[inline]
     pub fn parse_hash(value: &[u8]) -> Result<Self, ()> {
@@ -335,6 +660,33 @@ impl Color {
     }
 }
 
+fn parse_color<'i, 't, ComponentParser>(
+    component_parser: &ComponentParser,
+    input: &mut Parser<'i, 't>,
+    details: &mut ColorDetails,
+) -> Result<Color, ParseError<'i, ComponentParser::Error>>
+where
+    ComponentParser: ColorComponentParser<'i>,
+{
+    let location = input.current_source_location();
+    let token = input.next()?;
+    match *token {
+        Token::Hash(ref value) | Token::IDHash(ref value) => Color::parse_hash(value.as_bytes()),
+        Token::Ident(ref value) => parse_color_keyword(&*value).map(|color| {
+            details.keyword = Some(value.to_ascii_lowercase());
+            color
+        }),
+        Token::Function(ref name) => {
+            let name = name.clone();
+            return input.parse_nested_block(|arguments| {
+                parse_color_function(component_parser, &*name, arguments, details)
+            });
+        }
+        _ => Err(()),
+    }
+    .map_err(|()| location.new_unexpected_token_error(token.clone()))
+}
+
 #[inline]
 fn rgb(red: u8, green: u8, blue: u8) -> Color {
     rgba(red, green, blue, 255)
@@ -553,18 +905,36 @@ fn clamp_floor_256_f32(val: f32) -> u8 {
     val.round().max(0.).min(255.) as u8
 }
 
+fn check_range(value: f32, max: f32, details: &mut ColorDetails) {
+    if value < 0. || value > max {
+        details.out_of_range = true
+    }
+}
+
+/// The components of a color function, before the alpha component.
+enum ColorFunctionComponents {
+    Rgb(u8, u8, u8),
+    Hsl(f32, f32, f32),
+    Hwb(f32, f32, f32),
+}
+
 #[inline]
 fn parse_color_function<'i, 't, ComponentParser>(
     component_parser: &ComponentParser,
     name: &str,
     arguments: &mut Parser<'i, 't>,
+    details: &mut ColorDetails,
 ) -> Result<Color, ParseError<'i, ComponentParser::Error>>
 where
     ComponentParser: ColorComponentParser<'i>,
 {
-    let (red, green, blue, uses_commas) = match_ignore_ascii_case! { name,
-        "rgb" | "rgba" => parse_rgb_components_rgb(component_parser, arguments)?,
-        "hsl" | "hsla" => parse_rgb_components_hsl(component_parser, arguments)?,
+    let (components, uses_commas) = match_ignore_ascii_case! { name,
+        "rgb" | "rgba" => parse_rgb_components_rgb(component_parser, arguments, details)?,
+        "hsl" | "hsla" => parse_components_hsl(component_parser, arguments, details)?,
+        // Only with the components, for parsers that opted in.
+        "hwb" if component_parser.preserve_hsl_and_hwb() => {
+            parse_components_hwb(component_parser, arguments, details)?
+        },
         _ => return Err(arguments.new_unexpected_token_error(Token::Ident(name.to_owned().into()))),
     };
 
@@ -574,32 +944,57 @@ where
         } else {
             arguments.expect_delim('/')?;
         };
-        clamp_unit_f32(
-            component_parser
-                .parse_number_or_percentage(arguments)?
-                .unit_value(),
-        )
+        let alpha = component_parser.parse_number_or_percentage(arguments)?;
+        details.alpha = Some(alpha);
+        check_range(alpha.unit_value(), 1., details);
+        alpha.unit_value().max(0.).min(1.)
     } else {
-        255
+        1.
     };
 
     arguments.expect_exhausted()?;
-    Ok(rgba(red, green, blue, alpha))
+    let preserve = component_parser.preserve_hsl_and_hwb();
+    Ok(match components {
+        ColorFunctionComponents::Rgb(red, green, blue) => {
+            rgba(red, green, blue, clamp_unit_f32(alpha))
+        }
+        ColorFunctionComponents::Hsl(hue, saturation, lightness) => {
+            let hsla = Hsla {
+                hue,
+                saturation,
+                lightness,
+                alpha,
+            };
+            if preserve {
+                Color::Hsl(hsla)
+            } else {
+                Color::RGBA(hsla.to_rgba())
+            }
+        }
+        // Only parsed with `preserve_hsl_and_hwb`.
+        ColorFunctionComponents::Hwb(hue, whiteness, blackness) => Color::Hwb(Hwba {
+            hue,
+            whiteness,
+            blackness,
+            alpha,
+        }),
+    })
 }
 
 #[inline]
 fn parse_rgb_components_rgb<'i, 't, ComponentParser>(
     component_parser: &ComponentParser,
     arguments: &mut Parser<'i, 't>,
-) -> Result<(u8, u8, u8, bool), ParseError<'i, ComponentParser::Error>>
+    details: &mut ColorDetails,
+) -> Result<(ColorFunctionComponents, bool), ParseError<'i, ComponentParser::Error>>
 where
     ComponentParser: ColorComponentParser<'i>,
 {
     // Either integers or percentages, but all the same type.
     // https://drafts.csswg.org/css-color/#rgb-functions
     let (red, is_number) = match component_parser.parse_number_or_percentage(arguments)? {
-        NumberOrPercentage::Number { value } => (clamp_floor_256_f32(value), true),
-        NumberOrPercentage::Percentage { unit_value } => (clamp_unit_f32(unit_value), false),
+        NumberOrPercentage::Number { value } => (value, true),
+        NumberOrPercentage::Percentage { unit_value } => (unit_value, false),
     };
 
     let uses_commas = arguments.try_parse(|i| i.expect_comma()).is_ok();
@@ -607,52 +1002,115 @@ where
     let green;
     let blue;
     if is_number {
-        green = clamp_floor_256_f32(component_parser.parse_number(arguments)?);
+        green = component_parser.parse_number(arguments)?;
         if uses_commas {
             arguments.expect_comma()?;
         }
-        blue = clamp_floor_256_f32(component_parser.parse_number(arguments)?);
+        blue = component_parser.parse_number(arguments)?;
     } else {
-        green = clamp_unit_f32(component_parser.parse_percentage(arguments)?);
+        green = component_parser.parse_percentage(arguments)?;
         if uses_commas {
             arguments.expect_comma()?;
         }
-        blue = clamp_unit_f32(component_parser.parse_percentage(arguments)?);
+        blue = component_parser.parse_percentage(arguments)?;
     }
 
-    Ok((red, green, blue, uses_commas))
+    let max = if is_number { 255. } else { 1. };
+    for &value in &[red, green, blue] {
+        check_range(value, max, details);
+    }
+    details.rgb_components = Some(if is_number {
+        RgbComponents::Numbers([red, green, blue])
+    } else {
+        RgbComponents::Percentages([red, green, blue])
+    });
+    let clamp = if is_number {
+        clamp_floor_256_f32
+    } else {
+        clamp_unit_f32
+    };
+    let components = ColorFunctionComponents::Rgb(clamp(red), clamp(green), clamp(blue));
+    Ok((components, uses_commas))
 }
 
+/// Parse a hue, and two percentages with or without commas.
+///
+/// Returns the hue in degrees from 0 to 360, and the percentages clamped to 0.0 ... 1.0.
 #[inline]
-fn parse_rgb_components_hsl<'i, 't, ComponentParser>(
+fn parse_hue_and_percentages<'i, 't, ComponentParser>(
     component_parser: &ComponentParser,
     arguments: &mut Parser<'i, 't>,
-) -> Result<(u8, u8, u8, bool), ParseError<'i, ComponentParser::Error>>
+    allow_commas: bool,
+    details: &mut ColorDetails,
+) -> Result<(f32, f32, f32, bool), ParseError<'i, ComponentParser::Error>>
 where
     ComponentParser: ColorComponentParser<'i>,
 {
     // Hue given as an angle
     // https://drafts.csswg.org/css-values/#angles
     let hue_degrees = component_parser.parse_angle_or_number(arguments)?.degrees();
+    check_range(hue_degrees / 360., 1., details);
 
     // Subtract an integer before rounding, to avoid some rounding errors:
     let hue_normalized_degrees = hue_degrees - 360. * (hue_degrees / 360.).floor();
-    let hue = hue_normalized_degrees / 360.;
 
-    // Saturation and lightness are clamped to 0% ... 100%
-    // https://drafts.csswg.org/css-color/#the-hsl-notation
-    let uses_commas = arguments.try_parse(|i| i.expect_comma()).is_ok();
+    let uses_commas = allow_commas && arguments.try_parse(|i| i.expect_comma()).is_ok();
 
-    let saturation = component_parser.parse_percentage(arguments)?;
-    let saturation = saturation.max(0.).min(1.);
+    let first = component_parser.parse_percentage(arguments)?;
+    check_range(first, 1., details);
 
     if uses_commas {
         arguments.expect_comma()?;
     }
 
-    let lightness = component_parser.parse_percentage(arguments)?;
-    let lightness = lightness.max(0.).min(1.);
+    let second = component_parser.parse_percentage(arguments)?;
+    check_range(second, 1., details);
 
+    Ok((
+        hue_normalized_degrees,
+        first.max(0.).min(1.),
+        second.max(0.).min(1.),
+        uses_commas,
+    ))
+}
+
+#[inline]
+fn parse_components_hsl<'i, 't, ComponentParser>(
+    component_parser: &ComponentParser,
+    arguments: &mut Parser<'i, 't>,
+    details: &mut ColorDetails,
+) -> Result<(ColorFunctionComponents, bool), ParseError<'i, ComponentParser::Error>>
+where
+    ComponentParser: ColorComponentParser<'i>,
+{
+    // Saturation and lightness are clamped to 0% ... 100%
+    // https://drafts.csswg.org/css-color/#the-hsl-notation
+    let (hue, saturation, lightness, uses_commas) =
+        parse_hue_and_percentages(component_parser, arguments, true, details)?;
+    let components = ColorFunctionComponents::Hsl(hue, saturation, lightness);
+    Ok((components, uses_commas))
+}
+
+#[inline]
+fn parse_components_hwb<'i, 't, ComponentParser>(
+    component_parser: &ComponentParser,
+    arguments: &mut Parser<'i, 't>,
+    details: &mut ColorDetails,
+) -> Result<(ColorFunctionComponents, bool), ParseError<'i, ComponentParser::Error>>
+where
+    ComponentParser: ColorComponentParser<'i>,
+{
+    // https://drafts.csswg.org/css-color-4/#the-hwb-notation
+    let (hue, whiteness, blackness, uses_commas) =
+        parse_hue_and_percentages(component_parser, arguments, false, details)?;
+    let components = ColorFunctionComponents::Hwb(hue, whiteness, blackness);
+    Ok((components, uses_commas))
+}
+
+/// Convert HSL to red, green, and blue from 0.0 to 1.0.
+///
+/// The hue is in degrees from 0 to 360, saturation and lightness from 0.0 to 1.0.
+fn hsl_to_rgb(hue: f32, saturation: f32, lightness: f32) -> (f32, f32, f32) {
     // https://drafts.csswg.org/css-color/#hsl-color
     // except with h pre-multiplied by 3, to avoid some rounding errors.
     fn hue_to_rgb(m1: f32, m2: f32, mut h3: f32) -> f32 {
@@ -679,9 +1137,9 @@ where
         lightness + saturation - lightness * saturation
     };
     let m1 = lightness * 2. - m2;
-    let hue_times_3 = hue * 3.;
-    let red = clamp_unit_f32(hue_to_rgb(m1, m2, hue_times_3 + 1.));
-    let green = clamp_unit_f32(hue_to_rgb(m1, m2, hue_times_3));
-    let blue = clamp_unit_f32(hue_to_rgb(m1, m2, hue_times_3 - 1.));
-    return Ok((red, green, blue, uses_commas));
+    let hue_times_3 = hue / 360. * 3.;
+    let red = hue_to_rgb(m1, m2, hue_times_3 + 1.);
+    let green = hue_to_rgb(m1, m2, hue_times_3);
+    let blue = hue_to_rgb(m1, m2, hue_times_3 - 1.);
+    (red, green, blue)
 }
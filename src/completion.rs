@@ -0,0 +1,386 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use crate::at_rules::{classify_at_rule, AtRuleContents};
+use crate::cow_rc_str::CowRcStr;
+use crate::tokenizer::{SourceLocation, Token, Tokenizer};
+
+/// A construct that the end of the input closed, found by `complete_input`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Incomplete {
+    /// What is incomplete.
+    pub kind: IncompleteKind,
+    /// The start of the construct, or for a block the location of its `(`, `[` or `{`.
+    pub location: SourceLocation,
+}
+
+/// The kind of an `Incomplete` construct.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IncompleteKind {
+    /// A comment without `*/`.
+    Comment,
+    /// A string without its closing quote, which is given.
+    QuotedString(char),
+    /// A `url(` token without `)`.
+    Url,
+    /// A block or function without its closing character, which is given.
+    Block(char),
+}
+
+/// The result of `complete_input`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InputCompletion {
+    /// The incomplete constructs, innermost first.
+    pub incomplete: Vec<Incomplete>,
+    /// The text that completes them when appended to the input, such as `")}`.
+    pub suffix: String,
+}
+
+/// Find what the end of the input leaves incomplete: a comment, string or URL,
+/// and the enclosing blocks, such as in a buffer that is being typed in an editor.
+///
+/// The CSS Syntax specification already closes these at the end of the input,
+/// so any parser gives a best-effort result for such input without an error.
+/// This lets editors flag the parsed constructs as incomplete rather than invalid,
+/// and code that needs complete input append the `suffix`,
+/// which tokenizes the same as the end of the input, including after a trailing `\`.
+pub fn complete_input(css: &str) -> InputCompletion {
+    let mut tokenizer = Tokenizer::new(css);
+    let mut blocks = Vec::new();
+    let mut last = None;
+    loop {
+        let start = tokenizer.position();
+        let location = tokenizer.current_source_location();
+        let token = match tokenizer.next() {
+            Ok(token) => token,
+            Err(()) => break,
+        };
+        let closing = match token {
+            Token::Function(_) | Token::ParenthesisBlock => Some(')'),
+            Token::SquareBracketBlock => Some(']'),
+            Token::CurlyBracketBlock => Some('}'),
+            _ => None,
+        };
+        if let Some(closing) = closing {
+            let mut location = tokenizer.current_source_location();
+            // The last character of the opening token.
+            location.column -= 1;
+            blocks.push((closing, location));
+        }
+        match token {
+            Token::CloseParenthesis | Token::CloseSquareBracket | Token::CloseCurlyBracket => {
+                let closing = match token {
+                    Token::CloseParenthesis => ')',
+                    Token::CloseSquareBracket => ']',
+                    _ => '}',
+                };
+                if blocks.last().map(|&(c, _)| c) == Some(closing) {
+                    blocks.pop();
+                }
+            }
+            _ => {}
+        }
+        last = Some((token, tokenizer.slice_from(start), location));
+    }
+
+    let mut incomplete = Vec::new();
+    let mut suffix = String::new();
+    if ends_with_escape(css) {
+        // A `\` at the end of the input escapes the end of the input,
+        // and would escape the suffix otherwise.
+        match last {
+            Some((Token::Comment(_), ..)) => {}
+            // A `\` and a newline are ignored in strings.
+            Some((Token::QuotedString(_), ..)) => suffix.push('\n'),
+            // Elsewhere, a `\` at the end of the input is U+FFFD.
+            _ => suffix.push('\u{FFFD}'),
+        }
+    }
+    match last {
+        Some((Token::Comment(_), slice, location)) if !is_closed_comment(slice) => {
+            incomplete.push(Incomplete {
+                kind: IncompleteKind::Comment,
+                location,
+            });
+            suffix.push_str("*/")
+        }
+        Some((Token::QuotedString(_), slice, location))
+            if !is_closed_by(&slice[1..], slice.as_bytes()[0] as char) =>
+        {
+            let quote = slice.as_bytes()[0] as char;
+            incomplete.push(Incomplete {
+                kind: IncompleteKind::QuotedString(quote),
+                location,
+            });
+            suffix.push(quote)
+        }
+        Some((Token::UnquotedUrl(_), slice, location))
+        | Some((Token::BadUrl(_), slice, location))
+            if !is_closed_by(slice, ')') =>
+        {
+            incomplete.push(Incomplete {
+                kind: IncompleteKind::Url,
+                location,
+            });
+            suffix.push(')')
+        }
+        _ => {}
+    }
+    for &(closing, location) in blocks.iter().rev() {
+        incomplete.push(Incomplete {
+            kind: IncompleteKind::Block(closing),
+            location,
+        });
+        suffix.push(closing)
+    }
+    InputCompletion { incomplete, suffix }
+}
+
+/// Whether `css` ends with a `\` that is not itself escaped.
+fn ends_with_escape(css: &str) -> bool {
+    let backslashes = css.len() - css.trim_end_matches('\\').len();
+    backslashes % 2 == 1
+}
+
+/// Whether a `Token::Comment` ends with `*/`.
+fn is_closed_comment(slice: &str) -> bool {
+    slice.len() >= 4 && slice.ends_with("*/")
+}
+
+/// Whether `slice` ends with the given character, unescaped.
+fn is_closed_by(slice: &str, closing: char) -> bool {
+    slice.ends_with(closing) && !ends_with_escape(&slice[..slice.len() - 1])
+}
+
+/// What can be written at an offset in a stylesheet, found by `completion_context`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CompletionContext<'i> {
+    /// The prelude of a qualified rule, typically a selector,
+    /// including a keyframe selector and a nested rule in a declaration block.
+    Selector,
+    /// The name of an at-rule, after `@`.
+    AtRuleName,
+    /// The prelude of the at-rule with the given name, without the `@` marker.
+    AtRulePrelude(CowRcStr<'i>),
+    /// The name of a property or descriptor in a declaration block.
+    PropertyName,
+    /// The value of the property or descriptor with the given name.
+    PropertyValue(CowRcStr<'i>),
+    /// A comment.
+    Comment,
+    /// The block of an at-rule of unknown syntax.
+    Unknown,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Contents {
+    Rules,
+    Declarations,
+    Unknown,
+}
+
+/// The statement being tokenized in a block.
+#[derive(Clone)]
+enum Statement<'i> {
+    Start,
+    AtRule(CowRcStr<'i>),
+    /// A qualified rule, or tokens in a declaration block that are not a declaration (yet).
+    Other,
+    /// The name of a declaration, before `:`.
+    Name(CowRcStr<'i>),
+    Value(CowRcStr<'i>),
+}
+
+struct Frame<'i> {
+    closing: char,
+    /// `None` for a function or `()` or `[]` block, in which the statement continues.
+    contents: Option<Contents>,
+    statement: Statement<'i>,
+}
+
+/// Return what can be written at the given byte offset of a stylesheet,
+/// such as in an editor at the cursor, which may be at the end of an incomplete stylesheet.
+///
+/// The offset is in a token when it is after its first character, so `col|` is
+/// in the property name `col` and `color:|` is in the value of `color`.
+/// In a declaration block, what follows the offset tells a declaration such as `a:hover`
+/// from a nested rule such as `a:hover { … }`.
+pub fn completion_context(input: &str, offset: usize) -> CompletionContext<'_> {
+    let mut tokenizer = Tokenizer::new(input);
+    let mut frames = vec![Frame {
+        closing: '\0',
+        contents: Some(Contents::Rules),
+        statement: Statement::Start,
+    }];
+    let mut last = None;
+    while tokenizer.position().byte_index() < offset {
+        let start = tokenizer.position();
+        let token = match tokenizer.next() {
+            Ok(token) => token,
+            Err(()) => break,
+        };
+        match token {
+            Token::WhiteSpace(_) | Token::Comment(_) => {}
+            Token::Function(_) | Token::ParenthesisBlock | Token::SquareBracketBlock => {
+                let closing = if token == Token::SquareBracketBlock {
+                    ']'
+                } else {
+                    ')'
+                };
+                statement_mut(&mut frames).start(&token);
+                frames.push(Frame {
+                    closing,
+                    contents: None,
+                    statement: Statement::Start,
+                })
+            }
+            Token::CurlyBracketBlock => {
+                let (contents, statement) = {
+                    let frame = statement_frame_mut(&mut frames);
+                    let statement = std::mem::replace(&mut frame.statement, Statement::Start);
+                    (frame.contents, statement)
+                };
+                let contents = match (contents, statement) {
+                    (Some(Contents::Unknown), _) => Contents::Unknown,
+                    (_, Statement::AtRule(ref name)) => match classify_at_rule(name) {
+                        // Nested group rules such as `@media` contain declarations.
+                        Some(AtRuleContents::Rules) if contents == Some(Contents::Declarations) => {
+                            Contents::Declarations
+                        }
+                        Some(AtRuleContents::Rules) => Contents::Rules,
+                        Some(AtRuleContents::Declarations) => Contents::Declarations,
+                        _ => Contents::Unknown,
+                    },
+                    _ => Contents::Declarations,
+                };
+                // Close the `()` and `[]` blocks of the prelude,
+                // as the parser would once the rule is parsed.
+                while frames.last().unwrap().contents.is_none() {
+                    frames.pop();
+                }
+                frames.push(Frame {
+                    closing: '}',
+                    contents: Some(contents),
+                    statement: Statement::Start,
+                })
+            }
+            Token::CloseParenthesis | Token::CloseSquareBracket | Token::CloseCurlyBracket => {
+                let closing = match token {
+                    Token::CloseParenthesis => ')',
+                    Token::CloseSquareBracket => ']',
+                    _ => '}',
+                };
+                if frames.len() > 1 && frames.last().unwrap().closing == closing {
+                    frames.pop();
+                } else if closing == '}' {
+                    // Close the blocks of a statement that the `}` ends.
+                    while frames.len() > 1 && frames.last().unwrap().contents.is_none() {
+                        frames.pop();
+                    }
+                    if frames.len() > 1 {
+                        frames.pop();
+                    }
+                }
+            }
+            Token::Semicolon if frames.last().unwrap().contents.is_some() => {
+                frames.last_mut().unwrap().statement = Statement::Start
+            }
+            Token::Colon => {
+                let statement = statement_mut(&mut frames);
+                if let Statement::Name(ref name) = *statement {
+                    *statement = Statement::Value(name.clone())
+                } else {
+                    statement.start(&token)
+                }
+            }
+            ref token => statement_mut(&mut frames).start(token),
+        }
+        last = Some((token, tokenizer.slice_from(start)));
+    }
+
+    match last {
+        // In a comment, unless it ends at the offset.
+        Some((Token::Comment(_), slice))
+            if tokenizer.position().byte_index() > offset || !is_closed_comment(slice) =>
+        {
+            return CompletionContext::Comment
+        }
+        Some((Token::AtKeyword(_), _)) | Some((Token::Delim('@'), _)) => {
+            return CompletionContext::AtRuleName
+        }
+        _ => {}
+    }
+    let frame = statement_frame(&frames);
+    match (frame.contents, &frame.statement) {
+        (Some(Contents::Unknown), _) => CompletionContext::Unknown,
+        (_, Statement::AtRule(name)) => CompletionContext::AtRulePrelude(name.clone()),
+        (Some(Contents::Rules), _) => CompletionContext::Selector,
+        (_, statement) => {
+            let nested_blocks = frames
+                .iter()
+                .rev()
+                .take_while(|frame| frame.contents.is_none())
+                .count();
+            if is_rule_prelude(&mut tokenizer, nested_blocks) {
+                return CompletionContext::Selector;
+            }
+            match *statement {
+                Statement::Value(ref name) => CompletionContext::PropertyValue(name.clone()),
+                Statement::Other => CompletionContext::Selector,
+                _ => CompletionContext::PropertyName,
+            }
+        }
+    }
+}
+
+impl<'i> Statement<'i> {
+    /// Update the statement for a token other than whitespace or a comment.
+    fn start(&mut self, token: &Token<'i>) {
+        *self = match (&*self, token) {
+            (Statement::Start, Token::AtKeyword(name)) => Statement::AtRule(name.clone()),
+            (Statement::Start, Token::Ident(name)) => Statement::Name(name.clone()),
+            (Statement::Name(_), _) | (Statement::Start, _) => Statement::Other,
+            _ => return,
+        }
+    }
+}
+
+fn statement_frame<'a, 'i>(frames: &'a [Frame<'i>]) -> &'a Frame<'i> {
+    frames
+        .iter()
+        .rev()
+        .find(|frame| frame.contents.is_some())
+        .unwrap()
+}
+
+fn statement_frame_mut<'a, 'i>(frames: &'a mut [Frame<'i>]) -> &'a mut Frame<'i> {
+    frames
+        .iter_mut()
+        .rev()
+        .find(|frame| frame.contents.is_some())
+        .unwrap()
+}
+
+fn statement_mut<'a, 'i>(frames: &'a mut [Frame<'i>]) -> &'a mut Statement<'i> {
+    &mut statement_frame_mut(frames).statement
+}
+
+/// Whether the rest of the statement, in `nested_blocks` blocks, has a `{` block.
+fn is_rule_prelude(tokenizer: &mut Tokenizer, mut nested_blocks: usize) -> bool {
+    while let Ok(token) = tokenizer.next() {
+        match token {
+            Token::CurlyBracketBlock if nested_blocks == 0 => return true,
+            Token::Semicolon | Token::CloseCurlyBracket if nested_blocks == 0 => return false,
+            Token::Function(_)
+            | Token::ParenthesisBlock
+            | Token::SquareBracketBlock
+            | Token::CurlyBracketBlock => nested_blocks += 1,
+            Token::CloseParenthesis | Token::CloseSquareBracket | Token::CloseCurlyBracket => {
+                nested_blocks = nested_blocks.saturating_sub(1)
+            }
+            _ => {}
+        }
+    }
+    false
+}
@@ -0,0 +1,134 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use crate::parser::{Parser, ParserInput};
+use crate::rule_finder;
+use crate::tokenizer::SourceLocation;
+
+/// A rule that `concat_stylesheets` dropped or moved.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConcatenationChange {
+    /// What happened to the rule.
+    pub kind: ConcatenationChangeKind,
+    /// The index of the stylesheet that contains the rule.
+    pub stylesheet: usize,
+    /// The location of the rule in that stylesheet.
+    pub location: SourceLocation,
+}
+
+/// The kind of a `ConcatenationChange`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConcatenationChangeKind {
+    /// An `@charset` rule was dropped.
+    DroppedCharset,
+    /// An `@import` rule was moved before rules of an earlier stylesheet.
+    HoistedImport,
+    /// An `@layer` statement was moved before rules of an earlier stylesheet.
+    HoistedLayerStatement,
+}
+
+/// What kind of rule a slice of a stylesheet is.
+#[derive(Clone, Copy, PartialEq)]
+enum RuleKind {
+    Charset,
+    Import,
+    LayerStatement,
+    Other,
+}
+
+/// Concatenate stylesheets into one with the same rules,
+/// and return it together with the rules that had to be dropped or moved.
+///
+/// `@import` rules are only valid before any other rule except `@charset` and `@layer` statements,
+/// so the valid `@import` rules and leading `@layer` statements of every stylesheet
+/// are moved to the start of the result, in order.
+/// `@charset` rules are dropped, except at the start of the first stylesheet:
+/// the result is a Rust string, and is typically served as UTF-8.
+/// Comments before a rule are kept with it.
+///
+/// Other rules are copied as written, including `@import` rules that were already invalid.
+/// Note that `@namespace` rules apply to the whole stylesheet that contains them,
+/// so stylesheets that declare different namespaces can not be safely concatenated.
+pub fn concat_stylesheets(stylesheets: &[&str]) -> (String, Vec<ConcatenationChange>) {
+    let mut hoisted = Vec::new();
+    let mut rest = Vec::new();
+    let mut changes = Vec::new();
+    let mut any_other_rule = false;
+    for (index, &css) in stylesheets.iter().enumerate() {
+        let mut input = ParserInput::new(css);
+        let mut input = Parser::new(&mut input);
+        let mut imports_allowed = true;
+        let mut first_rule = true;
+        let mut sheet_rest = String::new();
+        let mut end = input.position();
+        while let Some((kind, slice, location)) = next_rule(&mut input) {
+            let change_kind = match kind {
+                RuleKind::Charset => {
+                    if index == 0 && first_rule {
+                        hoisted.push(slice.trim_start())
+                    } else {
+                        changes.push(ConcatenationChange {
+                            kind: ConcatenationChangeKind::DroppedCharset,
+                            stylesheet: index,
+                            location,
+                        })
+                    }
+                    None
+                }
+                RuleKind::Import if imports_allowed => Some(ConcatenationChangeKind::HoistedImport),
+                RuleKind::LayerStatement if imports_allowed => {
+                    Some(ConcatenationChangeKind::HoistedLayerStatement)
+                }
+                _ => {
+                    imports_allowed = false;
+                    any_other_rule = true;
+                    sheet_rest.push_str(slice);
+                    None
+                }
+            };
+            if let Some(kind) = change_kind {
+                hoisted.push(slice.trim_start());
+                if any_other_rule {
+                    changes.push(ConcatenationChange {
+                        kind,
+                        stylesheet: index,
+                        location,
+                    })
+                }
+            }
+            first_rule = false;
+            end = input.position();
+        }
+        // Whitespace and comments after the last rule.
+        sheet_rest.push_str(input.slice_from(end));
+        rest.push(sheet_rest)
+    }
+
+    let mut result = String::new();
+    let pieces = hoisted.into_iter().chain(rest.iter().map(|s| s.trim()));
+    for piece in pieces.filter(|piece| !piece.is_empty()) {
+        if !result.is_empty() {
+            result.push('\n')
+        }
+        result.push_str(piece)
+    }
+    (result, changes)
+}
+
+/// Consume the next top-level rule, and return its kind,
+/// its source text including preceding whitespace and comments, and its location.
+fn next_rule<'i, 't>(input: &mut Parser<'i, 't>) -> Option<(RuleKind, &'i str, SourceLocation)> {
+    let slice_start = input.position();
+    let rule = rule_finder::next_rule(input)?;
+    let kind = match rule.at_keyword {
+        Some(ref name) if name.eq_ignore_ascii_case("charset") => RuleKind::Charset,
+        Some(ref name) if name.eq_ignore_ascii_case("import") => RuleKind::Import,
+        // `@layer a { … }` is a block rule, not a statement.
+        Some(ref name) if name.eq_ignore_ascii_case("layer") && rule.block.is_none() => {
+            RuleKind::LayerStatement
+        }
+        _ => RuleKind::Other,
+    };
+    Some((kind, input.slice_from(slice_start), rule.location))
+}
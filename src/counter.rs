@@ -0,0 +1,88 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! https://drafts.csswg.org/css-lists-3/#counter-functions
+
+use crate::cow_rc_str::CowRcStr;
+use crate::custom_ident::parse_custom_ident;
+use crate::parser::{BasicParseError, FromCss, ParseError, Parser};
+use crate::serializer::{serialize_identifier, serialize_string, ToCss};
+use crate::tokenizer::Token;
+use std::fmt;
+
+/// A `counter()` or `counters()` function, as used in the `content` property.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Counter<'i> {
+    /// The name of the counter.
+    pub name: CowRcStr<'i>,
+    /// The string between the values of nested counters,
+    /// `Some(_)` for `counters()` and `None` for `counter()`.
+    pub separator: Option<CowRcStr<'i>>,
+    /// The name of the counter style, if specified.
+    ///
+    /// `symbols()` is not supported.
+    pub style: Option<CowRcStr<'i>>,
+}
+
+impl<'i> Counter<'i> {
+    /// Parse a `counter(<counter-name>, <counter-style>?)` or
+    /// `counters(<counter-name>, <string>, <counter-style>?)` function.
+    pub fn parse<'t>(input: &mut Parser<'i, 't>) -> Result<Self, BasicParseError<'i>> {
+        let location = input.current_source_location();
+        let is_counters = match *input.next()? {
+            Token::Function(ref name) if name.eq_ignore_ascii_case("counter") => false,
+            Token::Function(ref name) if name.eq_ignore_ascii_case("counters") => true,
+            ref token => return Err(location.new_basic_unexpected_token_error(token.clone())),
+        };
+        let result: Result<_, ParseError<()>> = input.parse_nested_block(|input| {
+            let name = parse_custom_ident(input, &["none"])?;
+            let separator = if is_counters {
+                input.expect_comma()?;
+                Some(input.expect_string()?.clone())
+            } else {
+                None
+            };
+            let style = if input.try_parse(|input| input.expect_comma()).is_ok() {
+                Some(parse_custom_ident(input, &["none"])?)
+            } else {
+                None
+            };
+            Ok(Counter {
+                name,
+                separator,
+                style,
+            })
+        });
+        result.map_err(ParseError::basic)
+    }
+}
+
+impl<'i, E> FromCss<'i, E> for Counter<'i> {
+    fn from_css<'t>(input: &mut Parser<'i, 't>) -> Result<Self, ParseError<'i, E>> {
+        Ok(Counter::parse(input)?)
+    }
+}
+
+impl<'i> ToCss for Counter<'i> {
+    fn to_css<W>(&self, dest: &mut W) -> fmt::Result
+    where
+        W: fmt::Write,
+    {
+        dest.write_str(if self.separator.is_some() {
+            "counters("
+        } else {
+            "counter("
+        })?;
+        serialize_identifier(&self.name, dest)?;
+        if let Some(ref separator) = self.separator {
+            dest.write_str(", ")?;
+            serialize_string(separator, dest)?;
+        }
+        if let Some(ref style) = self.style {
+            dest.write_str(", ")?;
+            serialize_identifier(style, dest)?;
+        }
+        dest.write_str(")")
+    }
+}
@@ -9,15 +9,23 @@ use std::hash;
 use std::marker::PhantomData;
 use std::mem;
 use std::ops::Deref;
+#[cfg(not(feature = "sync"))]
 use std::rc::Rc;
 use std::slice;
 use std::str;
+#[cfg(feature = "sync")]
+use std::sync::Arc as Rc;
 use std::usize;
 
 /// A string that is either shared (heap-allocated and reference-counted) or borrowed.
 ///
 /// Equivalent to `enum { Borrowed(&'a str), Shared(Rc<String>) }`, but stored more compactly.
 ///
+/// With the `sync` Cargo feature, `Arc<String>` is used instead of `Rc<String>`
+/// so that `CowRcStr` (and therefore `Token` and parse errors) is `Send` and `Sync`.
+/// This allows parsed results to be shared across threads, at the cost of atomic
+/// reference counting.
+///
 /// FIXME(https://github.com/rust-lang/rfcs/issues/1230): use an actual enum if/when
 /// the compiler can do this layout optimization.
 pub struct CowRcStr<'a> {
@@ -83,6 +91,23 @@ impl<'a> CowRcStr<'a> {
         }
     }
 
+    /// If this string is shared, return it with a lifetime that no longer
+    /// ties it to the borrowed input.
+    #[inline]
+    pub(crate) fn into_shared(self) -> Result<CowRcStr<'static>, Self> {
+        if self.borrowed_len_or_max == usize::MAX {
+            let shared = CowRcStr {
+                ptr: self.ptr,
+                borrowed_len_or_max: usize::MAX,
+                phantom: PhantomData,
+            };
+            mem::forget(self); // The strong reference is moved to `shared`
+            Ok(shared)
+        } else {
+            Err(self)
+        }
+    }
+
     #[inline]
     fn unpack(&self) -> Result<&'a str, *const String> {
         if self.borrowed_len_or_max == usize::MAX {
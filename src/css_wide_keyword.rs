@@ -0,0 +1,82 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! https://drafts.csswg.org/css-values-4/#common-keywords
+
+use crate::parser::{FromCss, ParseError, Parser};
+use crate::serializer::ToCss;
+use crate::tokenizer::Token;
+use std::fmt;
+
+/// A keyword that is valid as the value of any property.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CssWideKeyword {
+    /// `initial`
+    Initial,
+    /// `inherit`
+    Inherit,
+    /// `unset`
+    Unset,
+    /// `revert`
+    Revert,
+    /// `revert-layer`
+    RevertLayer,
+}
+
+impl CssWideKeyword {
+    /// Return the keyword as it is serialized, in lower case.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CssWideKeyword::Initial => "initial",
+            CssWideKeyword::Inherit => "inherit",
+            CssWideKeyword::Unset => "unset",
+            CssWideKeyword::Revert => "revert",
+            CssWideKeyword::RevertLayer => "revert-layer",
+        }
+    }
+
+    /// Return the keyword with the given name, matched case-insensitively in the ASCII range.
+    pub(crate) fn from_ident(ident: &str) -> Option<CssWideKeyword> {
+        Some(match_ignore_ascii_case! { ident,
+            "initial" => CssWideKeyword::Initial,
+            "inherit" => CssWideKeyword::Inherit,
+            "unset" => CssWideKeyword::Unset,
+            "revert" => CssWideKeyword::Revert,
+            "revert-layer" => CssWideKeyword::RevertLayer,
+            _ => return None,
+        })
+    }
+}
+
+impl ToCss for CssWideKeyword {
+    fn to_css<W>(&self, dest: &mut W) -> fmt::Result
+    where
+        W: fmt::Write,
+    {
+        dest.write_str(self.as_str())
+    }
+}
+
+impl<'i, E> FromCss<'i, E> for CssWideKeyword {
+    fn from_css<'t>(input: &mut Parser<'i, 't>) -> Result<Self, ParseError<'i, E>> {
+        let location = input.current_source_location();
+        let ident = input.expect_ident()?;
+        CssWideKeyword::from_ident(ident).ok_or_else(|| {
+            let token = Token::Ident(ident.clone());
+            location.new_unexpected_token_error(token)
+        })
+    }
+}
+
+/// If the entire remaining input, other than whitespace and comments, is a CSS-wide keyword,
+/// consume it and return that keyword.
+///
+/// Otherwise, return `None` and leave the input unchanged,
+/// to be parsed with the syntax specific to the property.
+/// Keywords are matched case-insensitively in the ASCII range.
+pub fn parse_css_wide_keyword<'i, 't>(input: &mut Parser<'i, 't>) -> Option<CssWideKeyword> {
+    let result: Result<_, ParseError<()>> =
+        input.try_parse(|input| input.parse_entirely(CssWideKeyword::from_css));
+    result.ok()
+}
@@ -0,0 +1,89 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! https://drafts.csswg.org/css-values-4/#custom-idents
+//! https://drafts.csswg.org/css-values-4/#dashed-idents
+
+use crate::cow_rc_str::CowRcStr;
+use crate::css_wide_keyword::CssWideKeyword;
+use crate::parser::{BasicParseError, FromCss, ParseError, Parser};
+use crate::serializer::{serialize_identifier, ToCss};
+use crate::tokenizer::Token;
+use std::fmt;
+
+/// Parse a `<custom-ident>`, such as an animation or counter name,
+/// and return it as written, since custom identifiers are case-sensitive.
+///
+/// The CSS-wide keywords and `default` are excluded, and so are the keywords in `excluded`,
+/// such as `none` for `animation-name`, all matched case-insensitively in the ASCII range.
+pub fn parse_custom_ident<'i, 't>(
+    input: &mut Parser<'i, 't>,
+    excluded: &[&str],
+) -> Result<CowRcStr<'i>, BasicParseError<'i>> {
+    let location = input.current_source_location();
+    let ident = input.expect_ident()?;
+    if is_valid_custom_ident(ident, excluded) {
+        Ok(ident.clone())
+    } else {
+        Err(location.new_basic_unexpected_token_error(Token::Ident(ident.clone())))
+    }
+}
+
+/// Parse a comma-separated list of one or more `<custom-ident>`s, with the same exclusions
+/// as `parse_custom_ident`, such as the value of `animation-name` or `will-change`.
+///
+/// An excluded keyword is an error at its location, even if it is not the first item.
+pub fn parse_custom_ident_list<'i, 't>(
+    input: &mut Parser<'i, 't>,
+    excluded: &[&str],
+) -> Result<Vec<CowRcStr<'i>>, BasicParseError<'i>> {
+    let result: Result<_, ParseError<()>> = input.parse_comma_separated(|input| {
+        input.skip_whitespace();
+        Ok(parse_custom_ident(input, excluded)?)
+    });
+    result.map_err(ParseError::basic)
+}
+
+fn is_valid_custom_ident(ident: &str, excluded: &[&str]) -> bool {
+    CssWideKeyword::from_ident(ident).is_none()
+        && !ident.eq_ignore_ascii_case("default")
+        && !excluded
+            .iter()
+            .any(|keyword| ident.eq_ignore_ascii_case(keyword))
+}
+
+/// A `<dashed-ident>`, such as the name of a custom property, an `@property` rule
+/// or a `@font-palette-values` rule: an identifier that starts with `--`.
+///
+/// The name is case-sensitive. `--` alone is reserved and is not a `<dashed-ident>`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DashedIdent<'i>(pub CowRcStr<'i>);
+
+impl<'i, E> FromCss<'i, E> for DashedIdent<'i> {
+    fn from_css<'t>(input: &mut Parser<'i, 't>) -> Result<Self, ParseError<'i, E>> {
+        Ok(DashedIdent(parse_dashed_ident(input)?))
+    }
+}
+
+impl<'i> ToCss for DashedIdent<'i> {
+    fn to_css<W>(&self, dest: &mut W) -> fmt::Result
+    where
+        W: fmt::Write,
+    {
+        serialize_identifier(&self.0, dest)
+    }
+}
+
+/// Parse a `<dashed-ident>` and return it as written, with its `--` prefix.
+pub fn parse_dashed_ident<'i, 't>(
+    input: &mut Parser<'i, 't>,
+) -> Result<CowRcStr<'i>, BasicParseError<'i>> {
+    let location = input.current_source_location();
+    let ident = input.expect_ident()?;
+    if ident.starts_with("--") && ident.len() > 2 {
+        Ok(ident.clone())
+    } else {
+        Err(location.new_basic_unexpected_token_error(Token::Ident(ident.clone())))
+    }
+}
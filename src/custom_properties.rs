@@ -0,0 +1,164 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! https://drafts.csswg.org/css-variables/
+
+use crate::parser::{BasicParseError, ParseError, Parser};
+use crate::tokenizer::{is_css_whitespace, SourceLocation, Token, Tokenizer};
+use std::collections::HashSet;
+
+/// A possible mistake with a custom property, found by `check_custom_properties`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CustomPropertyIssue {
+    /// What is wrong.
+    pub kind: CustomPropertyIssueKind,
+    /// The name of the custom property, including the leading `--`.
+    pub name: String,
+    /// The location of the name in the definition or reference.
+    pub location: SourceLocation,
+}
+
+/// The kind of a `CustomPropertyIssue`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CustomPropertyIssueKind {
+    /// A declaration such as `--x: 1px` whose name is not referenced by any `var()`.
+    Unused,
+    /// A reference such as `var(--x)` whose name is not defined by any declaration
+    /// or `@property` rule in the stylesheet.
+    Undefined,
+}
+
+/// Parse the value of a custom property declaration, such as `--x: 01.50e1 { a }`,
+/// and return it as written, without leading and trailing whitespace.
+///
+/// Custom property values are token sequences that `var()` substitutes elsewhere,
+/// so they should be kept as written: serializing their tokens instead would normalize
+/// numbers such as `01.50e1` to `15.0`, identifiers and strings with escapes, and comments.
+///
+/// The input should already stop before `!important`, as with `parse_declaration_value`.
+/// This is an error if the value contains a bad string, a bad URL
+/// or an unmatched `)`, `]` or `}`, which are not allowed in a `<declaration-value>`.
+/// An empty value is allowed.
+pub fn parse_custom_property_value<'i, 't>(
+    input: &mut Parser<'i, 't>,
+) -> Result<&'i str, BasicParseError<'i>> {
+    let start = input.position();
+    check_declaration_value(input)?;
+    Ok(input.slice_from(start).trim_matches(is_css_whitespace))
+}
+
+/// Consume the rest of `input`, checking that it is a valid `<declaration-value>`.
+fn check_declaration_value<'i, 't>(input: &mut Parser<'i, 't>) -> Result<(), BasicParseError<'i>> {
+    loop {
+        let location = input.current_source_location();
+        let token = match input.next_including_whitespace_and_comments() {
+            Ok(token) => token.clone(),
+            Err(_) => return Ok(()),
+        };
+        match token {
+            Token::BadString(_)
+            | Token::BadUrl(_)
+            | Token::CloseParenthesis
+            | Token::CloseSquareBracket
+            | Token::CloseCurlyBracket => {
+                return Err(location.new_basic_unexpected_token_error(token))
+            }
+            _ => {}
+        }
+        if token.is_block() {
+            let result: Result<(), ParseError<()>> =
+                input.parse_nested_block(|input| Ok(check_declaration_value(input)?));
+            result.map_err(ParseError::basic)?;
+        }
+    }
+}
+
+/// Cross-reference custom property definitions and `var()` references in a stylesheet,
+/// and return unused definitions and undefined references in source order.
+///
+/// Every occurrence is reported.
+/// Names are compared case-sensitively.
+/// References with a fallback, such as `var(--x, red)`, are reported too when undefined,
+/// since the fallback is then always used.
+///
+/// This only looks at the given stylesheet:
+/// custom properties can be defined or used by other stylesheets or by scripts,
+/// so issues are hints rather than errors.
+pub fn check_custom_properties(input: &str) -> Vec<CustomPropertyIssue> {
+    let mut definitions = Vec::new();
+    let mut references = Vec::new();
+
+    let mut tokenizer = Tokenizer::new(input);
+    // The closing token of each open block.
+    let mut blocks = Vec::new();
+    let mut previous = None;
+    let mut candidate = None;
+    loop {
+        let location = tokenizer.current_source_location();
+        let token = match tokenizer.next() {
+            Ok(Token::WhiteSpace(_)) | Ok(Token::Comment(_)) => continue,
+            Ok(token) => token,
+            Err(()) => break,
+        };
+        if let Some(definition) = candidate.take() {
+            if token == Token::Colon {
+                definitions.push(definition)
+            }
+        }
+        if let Token::Ident(ref name) = token {
+            if name.starts_with("--") {
+                let name = (name.to_string(), location);
+                match previous {
+                    Some(Token::Function(ref function)) if function.eq_ignore_ascii_case("var") => {
+                        references.push(name)
+                    }
+                    Some(Token::AtKeyword(ref at_keyword))
+                        if at_keyword.eq_ignore_ascii_case("property") =>
+                    {
+                        definitions.push(name)
+                    }
+                    Some(Token::CurlyBracketBlock) | Some(Token::Semicolon)
+                        if blocks.last() == Some(&Token::CloseCurlyBracket) =>
+                    {
+                        candidate = Some(name)
+                    }
+                    _ => {}
+                }
+            }
+        }
+        match token {
+            Token::Function(_) | Token::ParenthesisBlock => blocks.push(Token::CloseParenthesis),
+            Token::SquareBracketBlock => blocks.push(Token::CloseSquareBracket),
+            Token::CurlyBracketBlock => blocks.push(Token::CloseCurlyBracket),
+            Token::CloseParenthesis | Token::CloseSquareBracket | Token::CloseCurlyBracket
+                if blocks.last() == Some(&token) =>
+            {
+                blocks.pop();
+            }
+            _ => {}
+        }
+        previous = Some(token);
+    }
+
+    let defined: HashSet<&str> = definitions.iter().map(|d| &*d.0).collect();
+    let referenced: HashSet<&str> = references.iter().map(|r| &*r.0).collect();
+    let unused = definitions
+        .iter()
+        .filter(|d| !referenced.contains(&*d.0))
+        .map(|d| (CustomPropertyIssueKind::Unused, d));
+    let undefined = references
+        .iter()
+        .filter(|r| !defined.contains(&*r.0))
+        .map(|r| (CustomPropertyIssueKind::Undefined, r));
+    let mut issues: Vec<_> = unused
+        .chain(undefined)
+        .map(|(kind, &(ref name, location))| CustomPropertyIssue {
+            kind,
+            name: name.clone(),
+            location,
+        })
+        .collect();
+    issues.sort_by_key(|issue| (issue.location.line, issue.location.column));
+    issues
+}
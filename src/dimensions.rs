@@ -0,0 +1,595 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! https://drafts.csswg.org/css-values-4/#dimensions
+
+use crate::parser::{BasicParseError, FromCss, ParseError, Parser};
+use crate::serializer::{non_finite_keyword, serialize_percentage, ToCss};
+use crate::tokenizer::Token;
+use std::f32::consts::{E, PI};
+use std::f32::{INFINITY, NAN, NEG_INFINITY};
+use std::fmt;
+
+/// Parse a `<calc-constant>`: `e`, `pi`, `infinity`, `-infinity` or `NaN`,
+/// matched case-insensitively in the ASCII range.
+///
+/// These are only valid in math functions such as `calc()`:
+/// elsewhere, `infinity` is an identifier, and numbers too large for `f32` are infinite.
+/// Infinite and NaN values are serialized as `calc(infinity)` or `calc(NaN)`,
+/// and dimensions as `calc(infinity * 1px)`.
+pub fn parse_calc_constant<'i, 't>(input: &mut Parser<'i, 't>) -> Result<f32, BasicParseError<'i>> {
+    let location = input.current_source_location();
+    let token = input.next()?;
+    if let Token::Ident(ref ident) = *token {
+        let value = match_ignore_ascii_case! { ident,
+            "e" => Some(E),
+            "pi" => Some(PI),
+            "infinity" => Some(INFINITY),
+            "-infinity" => Some(NEG_INFINITY),
+            "nan" => Some(NAN),
+            _ => None,
+        };
+        if let Some(value) = value {
+            return Ok(value);
+        }
+    }
+    Err(location.new_basic_unexpected_token_error(token.clone()))
+}
+
+/// A `<length>` value, with its unit as written.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Length {
+    /// The numeric value.
+    pub value: f32,
+    /// The unit.
+    pub unit: LengthUnit,
+}
+
+/// A unit of `<length>`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LengthUnit {
+    /// `px`
+    Px,
+    /// `cm`
+    Cm,
+    /// `mm`
+    Mm,
+    /// `q`
+    Q,
+    /// `in`
+    In,
+    /// `pt`
+    Pt,
+    /// `pc`
+    Pc,
+    /// `em`
+    Em,
+    /// `rem`
+    Rem,
+    /// `ex`
+    Ex,
+    /// `ch`
+    Ch,
+    /// `vw`
+    Vw,
+    /// `vh`
+    Vh,
+    /// `vmin`
+    Vmin,
+    /// `vmax`
+    Vmax,
+}
+
+impl LengthUnit {
+    /// Return the unit for the given name, matched case-insensitively in the ASCII range.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match_ignore_ascii_case! { name,
+            "px" => LengthUnit::Px,
+            "cm" => LengthUnit::Cm,
+            "mm" => LengthUnit::Mm,
+            "q" => LengthUnit::Q,
+            "in" => LengthUnit::In,
+            "pt" => LengthUnit::Pt,
+            "pc" => LengthUnit::Pc,
+            "em" => LengthUnit::Em,
+            "rem" => LengthUnit::Rem,
+            "ex" => LengthUnit::Ex,
+            "ch" => LengthUnit::Ch,
+            "vw" => LengthUnit::Vw,
+            "vh" => LengthUnit::Vh,
+            "vmin" => LengthUnit::Vmin,
+            "vmax" => LengthUnit::Vmax,
+            _ => return None,
+        })
+    }
+
+    /// Return the name of the unit, in lower case.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LengthUnit::Px => "px",
+            LengthUnit::Cm => "cm",
+            LengthUnit::Mm => "mm",
+            LengthUnit::Q => "q",
+            LengthUnit::In => "in",
+            LengthUnit::Pt => "pt",
+            LengthUnit::Pc => "pc",
+            LengthUnit::Em => "em",
+            LengthUnit::Rem => "rem",
+            LengthUnit::Ex => "ex",
+            LengthUnit::Ch => "ch",
+            LengthUnit::Vw => "vw",
+            LengthUnit::Vh => "vh",
+            LengthUnit::Vmin => "vmin",
+            LengthUnit::Vmax => "vmax",
+        }
+    }
+
+    /// For absolute units, return the number of pixels in one of this unit.
+    ///
+    /// Return `None` for font-relative and viewport-percentage units.
+    pub fn px_per_unit(self) -> Option<f32> {
+        Some(match self {
+            LengthUnit::Px => 1.,
+            LengthUnit::Cm => 96. / 2.54,
+            LengthUnit::Mm => 96. / 25.4,
+            LengthUnit::Q => 96. / 101.6,
+            LengthUnit::In => 96.,
+            LengthUnit::Pt => 96. / 72.,
+            LengthUnit::Pc => 96. / 6.,
+            _ => return None,
+        })
+    }
+}
+
+impl Length {
+    /// Parse a `<length>`: a dimension with a length unit, or the number zero.
+    pub fn parse<'i, 't>(input: &mut Parser<'i, 't>) -> Result<Self, BasicParseError<'i>> {
+        let location = input.current_source_location();
+        let token = input.next()?;
+        match *token {
+            Token::Dimension {
+                value, ref unit, ..
+            } => {
+                if let Some(unit) = LengthUnit::from_name(unit) {
+                    return Ok(Length { value, unit });
+                }
+            }
+            Token::Number { value, .. } if value == 0. => {
+                return Ok(Length {
+                    value,
+                    unit: LengthUnit::Px,
+                })
+            }
+            _ => {}
+        }
+        Err(location.new_basic_unexpected_token_error(token.clone()))
+    }
+
+    /// For absolute units, return this length in pixels.
+    pub fn to_px(&self) -> Option<f32> {
+        self.unit.px_per_unit().map(|px| self.value * px)
+    }
+}
+
+/// A `<length-percentage>` value.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LengthPercentage {
+    /// `<length>`
+    Length(Length),
+    /// `<percentage>`
+    Percentage {
+        /// The value as a float, divided by 100 so that the nominal range is
+        /// 0.0 to 1.0.
+        unit_value: f32,
+    },
+}
+
+impl LengthPercentage {
+    /// Parse a `<length-percentage>`: a `<length>` or a percentage.
+    pub fn parse<'i, 't>(input: &mut Parser<'i, 't>) -> Result<Self, BasicParseError<'i>> {
+        if let Ok(length) = input.try_parse(Length::parse) {
+            return Ok(LengthPercentage::Length(length));
+        }
+        let location = input.current_source_location();
+        let token = input.next()?;
+        match *token {
+            Token::Percentage { unit_value, .. } => Ok(LengthPercentage::Percentage { unit_value }),
+            ref token => Err(location.new_basic_unexpected_token_error(token.clone())),
+        }
+    }
+}
+
+impl<'i, E> FromCss<'i, E> for LengthPercentage {
+    fn from_css<'t>(input: &mut Parser<'i, 't>) -> Result<Self, ParseError<'i, E>> {
+        Ok(LengthPercentage::parse(input)?)
+    }
+}
+
+impl ToCss for LengthPercentage {
+    fn to_css<W>(&self, dest: &mut W) -> fmt::Result
+    where
+        W: fmt::Write,
+    {
+        match *self {
+            LengthPercentage::Length(ref length) => length.to_css(dest),
+            LengthPercentage::Percentage { unit_value } => serialize_percentage(unit_value, dest),
+        }
+    }
+}
+
+/// An `<angle>` value, with its unit as written.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Angle {
+    /// The numeric value.
+    pub value: f32,
+    /// The unit.
+    pub unit: AngleUnit,
+}
+
+/// A unit of `<angle>`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AngleUnit {
+    /// `deg`
+    Deg,
+    /// `grad`
+    Grad,
+    /// `rad`
+    Rad,
+    /// `turn`
+    Turn,
+}
+
+impl AngleUnit {
+    /// Return the unit for the given name, matched case-insensitively in the ASCII range.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match_ignore_ascii_case! { name,
+            "deg" => AngleUnit::Deg,
+            "grad" => AngleUnit::Grad,
+            "rad" => AngleUnit::Rad,
+            "turn" => AngleUnit::Turn,
+            _ => return None,
+        })
+    }
+
+    /// Return the name of the unit, in lower case.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AngleUnit::Deg => "deg",
+            AngleUnit::Grad => "grad",
+            AngleUnit::Rad => "rad",
+            AngleUnit::Turn => "turn",
+        }
+    }
+}
+
+impl Angle {
+    /// Parse an `<angle>`: a dimension with an angle unit.
+    ///
+    /// The number zero is not accepted, since most properties that take an `<angle>` don’t.
+    pub fn parse<'i, 't>(input: &mut Parser<'i, 't>) -> Result<Self, BasicParseError<'i>> {
+        let location = input.current_source_location();
+        let token = input.next()?;
+        if let Token::Dimension {
+            value, ref unit, ..
+        } = *token
+        {
+            if let Some(unit) = AngleUnit::from_name(unit) {
+                return Ok(Angle { value, unit });
+            }
+        }
+        Err(location.new_basic_unexpected_token_error(token.clone()))
+    }
+
+    /// Return this angle in degrees.
+    pub fn to_degrees(&self) -> f32 {
+        match self.unit {
+            AngleUnit::Deg => self.value,
+            AngleUnit::Grad => self.value * 360. / 400.,
+            AngleUnit::Rad => self.value * 180. / PI,
+            AngleUnit::Turn => self.value * 360.,
+        }
+    }
+
+    /// Return this angle in degrees, the canonical unit,
+    /// such as for serializing a computed value.
+    pub fn to_canonical(&self) -> Self {
+        Angle {
+            value: self.to_degrees(),
+            unit: AngleUnit::Deg,
+        }
+    }
+
+    /// Return this angle in radians.
+    pub fn to_radians(&self) -> f32 {
+        match self.unit {
+            AngleUnit::Rad => self.value,
+            _ => self.to_degrees() * PI / 180.,
+        }
+    }
+}
+
+/// A `<time>` value, with its unit as written.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Time {
+    /// The numeric value.
+    pub value: f32,
+    /// The unit.
+    pub unit: TimeUnit,
+}
+
+/// A unit of `<time>`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TimeUnit {
+    /// `s`
+    S,
+    /// `ms`
+    Ms,
+}
+
+impl TimeUnit {
+    /// Return the unit for the given name, matched case-insensitively in the ASCII range.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match_ignore_ascii_case! { name,
+            "s" => TimeUnit::S,
+            "ms" => TimeUnit::Ms,
+            _ => return None,
+        })
+    }
+
+    /// Return the name of the unit, in lower case.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TimeUnit::S => "s",
+            TimeUnit::Ms => "ms",
+        }
+    }
+}
+
+impl Time {
+    /// Parse a `<time>`: a dimension with a time unit.
+    pub fn parse<'i, 't>(input: &mut Parser<'i, 't>) -> Result<Self, BasicParseError<'i>> {
+        let location = input.current_source_location();
+        let token = input.next()?;
+        if let Token::Dimension {
+            value, ref unit, ..
+        } = *token
+        {
+            if let Some(unit) = TimeUnit::from_name(unit) {
+                return Ok(Time { value, unit });
+            }
+        }
+        Err(location.new_basic_unexpected_token_error(token.clone()))
+    }
+
+    /// Return this time in seconds.
+    pub fn to_seconds(&self) -> f32 {
+        match self.unit {
+            TimeUnit::S => self.value,
+            TimeUnit::Ms => self.value / 1000.,
+        }
+    }
+
+    /// Return this time in milliseconds.
+    pub fn to_milliseconds(&self) -> f32 {
+        match self.unit {
+            TimeUnit::S => self.value * 1000.,
+            TimeUnit::Ms => self.value,
+        }
+    }
+
+    /// Return this time in seconds, the canonical unit,
+    /// such as for serializing a computed value: `250ms` becomes `0.25s`.
+    pub fn to_canonical(&self) -> Self {
+        Time {
+            value: self.to_seconds(),
+            unit: TimeUnit::S,
+        }
+    }
+}
+
+/// A `<frequency>` value, with its unit as written.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frequency {
+    /// The numeric value.
+    pub value: f32,
+    /// The unit.
+    pub unit: FrequencyUnit,
+}
+
+/// A unit of `<frequency>`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FrequencyUnit {
+    /// `hz`
+    Hz,
+    /// `khz`
+    KHz,
+}
+
+impl FrequencyUnit {
+    /// Return the unit for the given name, matched case-insensitively in the ASCII range.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match_ignore_ascii_case! { name,
+            "hz" => FrequencyUnit::Hz,
+            "khz" => FrequencyUnit::KHz,
+            _ => return None,
+        })
+    }
+
+    /// Return the name of the unit, in lower case.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FrequencyUnit::Hz => "hz",
+            FrequencyUnit::KHz => "khz",
+        }
+    }
+}
+
+impl Frequency {
+    /// Parse a `<frequency>`: a dimension with a frequency unit.
+    pub fn parse<'i, 't>(input: &mut Parser<'i, 't>) -> Result<Self, BasicParseError<'i>> {
+        let location = input.current_source_location();
+        let token = input.next()?;
+        if let Token::Dimension {
+            value, ref unit, ..
+        } = *token
+        {
+            if let Some(unit) = FrequencyUnit::from_name(unit) {
+                return Ok(Frequency { value, unit });
+            }
+        }
+        Err(location.new_basic_unexpected_token_error(token.clone()))
+    }
+
+    /// Return this frequency in hertz.
+    pub fn to_hertz(&self) -> f32 {
+        match self.unit {
+            FrequencyUnit::Hz => self.value,
+            FrequencyUnit::KHz => self.value * 1000.,
+        }
+    }
+
+    /// Return this frequency in kilohertz.
+    pub fn to_kilohertz(&self) -> f32 {
+        match self.unit {
+            FrequencyUnit::Hz => self.value / 1000.,
+            FrequencyUnit::KHz => self.value,
+        }
+    }
+
+    /// Return this frequency in hertz, the canonical unit,
+    /// such as for serializing a computed value.
+    pub fn to_canonical(&self) -> Self {
+        Frequency {
+            value: self.to_hertz(),
+            unit: FrequencyUnit::Hz,
+        }
+    }
+}
+
+/// A `<resolution>` value, with its unit as written.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Resolution {
+    /// The numeric value.
+    pub value: f32,
+    /// The unit.
+    pub unit: ResolutionUnit,
+}
+
+/// A unit of `<resolution>`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ResolutionUnit {
+    /// `dppx`, dots per CSS pixel.
+    Dppx,
+    /// `x`, an alias for `dppx`.
+    X,
+    /// `dpi`, dots per inch.
+    Dpi,
+    /// `dpcm`, dots per centimeter.
+    Dpcm,
+}
+
+impl ResolutionUnit {
+    /// Return the unit for the given name, matched case-insensitively in the ASCII range.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match_ignore_ascii_case! { name,
+            "dppx" => ResolutionUnit::Dppx,
+            "x" => ResolutionUnit::X,
+            "dpi" => ResolutionUnit::Dpi,
+            "dpcm" => ResolutionUnit::Dpcm,
+            _ => return None,
+        })
+    }
+
+    /// Return the name of the unit, in lower case.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ResolutionUnit::Dppx => "dppx",
+            ResolutionUnit::X => "x",
+            ResolutionUnit::Dpi => "dpi",
+            ResolutionUnit::Dpcm => "dpcm",
+        }
+    }
+}
+
+impl Resolution {
+    /// Parse a `<resolution>`: a dimension with a resolution unit.
+    pub fn parse<'i, 't>(input: &mut Parser<'i, 't>) -> Result<Self, BasicParseError<'i>> {
+        let location = input.current_source_location();
+        let token = input.next()?;
+        if let Token::Dimension {
+            value, ref unit, ..
+        } = *token
+        {
+            if let Some(unit) = ResolutionUnit::from_name(unit) {
+                return Ok(Resolution { value, unit });
+            }
+        }
+        Err(location.new_basic_unexpected_token_error(token.clone()))
+    }
+
+    /// Return this resolution in dots per CSS pixel, with 96 CSS pixels per inch.
+    pub fn to_dppx(&self) -> f32 {
+        match self.unit {
+            ResolutionUnit::Dppx | ResolutionUnit::X => self.value,
+            ResolutionUnit::Dpi => self.value / 96.,
+            ResolutionUnit::Dpcm => self.value * 2.54 / 96.,
+        }
+    }
+
+    /// Return this resolution in dots per inch.
+    pub fn to_dpi(&self) -> f32 {
+        match self.unit {
+            ResolutionUnit::Dpi => self.value,
+            _ => self.to_dppx() * 96.,
+        }
+    }
+
+    /// Return this resolution in `dppx`, the canonical unit,
+    /// such as for serializing a computed value: `192dpi` and `2x` become `2dppx`.
+    pub fn to_canonical(&self) -> Self {
+        Resolution {
+            value: self.to_dppx(),
+            unit: ResolutionUnit::Dppx,
+        }
+    }
+}
+
+macro_rules! dimension_impls {
+    ($($name: ident,)+) => {
+        $(
+            impl<'i, E> FromCss<'i, E> for $name {
+                fn from_css<'t>(input: &mut Parser<'i, 't>) -> Result<Self, ParseError<'i, E>> {
+                    Ok($name::parse(input)?)
+                }
+            }
+
+            /// Serializes the value followed by the unit in lower case,
+            /// or as `calc(infinity * 1px)` if the value is infinite or NaN.
+            impl ToCss for $name {
+                fn to_css<W>(&self, dest: &mut W) -> fmt::Result
+                where
+                    W: fmt::Write,
+                {
+                    if !self.value.is_finite() {
+                        dest.write_str("calc(")?;
+                        dest.write_str(non_finite_keyword(self.value))?;
+                        dest.write_str(" * 1")?;
+                        dest.write_str(self.unit.as_str())?;
+                        return dest.write_str(")");
+                    }
+                    self.value.to_css(dest)?;
+                    dest.write_str(self.unit.as_str())
+                }
+            }
+        )+
+    };
+}
+
+dimension_impls! {
+    Length,
+    Angle,
+    Time,
+    Frequency,
+    Resolution,
+}
@@ -0,0 +1,287 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! https://drafts.csswg.org/css-easing-2/#easing-functions
+
+use crate::parser::{BasicParseError, FromCss, ParseError, Parser};
+use crate::serializer::{serialize_percentage, ToCss};
+use crate::tokenizer::Token;
+use std::fmt;
+
+/// An `<easing-function>`, such as of `transition-timing-function`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EasingFunction {
+    /// `linear`
+    Linear,
+    /// `linear()`, with at least two stops.
+    LinearStops(Vec<LinearStop>),
+    /// `ease`
+    Ease,
+    /// `ease-in`
+    EaseIn,
+    /// `ease-out`
+    EaseOut,
+    /// `ease-in-out`
+    EaseInOut,
+    /// `cubic-bezier()`, whose `x1` and `x2` are in the range 0 to 1.
+    CubicBezier {
+        /// The x coordinate of the first control point.
+        x1: f32,
+        /// The y coordinate of the first control point.
+        y1: f32,
+        /// The x coordinate of the second control point.
+        x2: f32,
+        /// The y coordinate of the second control point.
+        y2: f32,
+    },
+    /// `step-start`
+    StepStart,
+    /// `step-end`
+    StepEnd,
+    /// `steps()`, with the number of intervals and the step position if specified.
+    Steps(i32, Option<StepPosition>),
+}
+
+/// A stop of `linear()`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LinearStop {
+    /// The output progress.
+    pub output: f32,
+    /// The zero to two input progress percentages,
+    /// as floats divided by 100 so that the nominal range is 0.0 to 1.0.
+    pub input: (Option<f32>, Option<f32>),
+}
+
+/// The `<step-position>` of `steps()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StepPosition {
+    /// `jump-start`
+    JumpStart,
+    /// `jump-end`
+    JumpEnd,
+    /// `jump-none`
+    JumpNone,
+    /// `jump-both`
+    JumpBoth,
+    /// `start`, the same as `jump-start`.
+    Start,
+    /// `end`, the same as `jump-end`.
+    End,
+}
+
+impl EasingFunction {
+    /// Parse an `<easing-function>`.
+    ///
+    /// Keywords and function names are matched case-insensitively in the ASCII range.
+    pub fn parse<'i, 't>(input: &mut Parser<'i, 't>) -> Result<Self, BasicParseError<'i>> {
+        let location = input.current_source_location();
+        let token = input.next()?.clone();
+        match token {
+            Token::Ident(ref ident) => Ok(match_ignore_ascii_case! { ident,
+                "linear" => EasingFunction::Linear,
+                "ease" => EasingFunction::Ease,
+                "ease-in" => EasingFunction::EaseIn,
+                "ease-out" => EasingFunction::EaseOut,
+                "ease-in-out" => EasingFunction::EaseInOut,
+                "step-start" => EasingFunction::StepStart,
+                "step-end" => EasingFunction::StepEnd,
+                _ => return Err(location.new_basic_unexpected_token_error(token.clone())),
+            }),
+            Token::Function(ref name) => {
+                let result: Result<_, ParseError<()>> = input.parse_nested_block(|input| {
+                    Ok(match_ignore_ascii_case! { name,
+                        "linear" => {
+                            let stops = parse_linear_stops(input)?;
+                            if stops.len() < 2 {
+                                return Err(location.new_unexpected_token_error(token.clone()));
+                            }
+                            EasingFunction::LinearStops(stops)
+                        },
+                        "cubic-bezier" => parse_cubic_bezier(input)?,
+                        "steps" => parse_steps(input)?,
+                        _ => return Err(location.new_unexpected_token_error(token.clone())),
+                    })
+                });
+                result.map_err(ParseError::basic)
+            }
+            _ => Err(location.new_basic_unexpected_token_error(token)),
+        }
+    }
+}
+
+fn parse_linear_stops<'i, 't>(
+    input: &mut Parser<'i, 't>,
+) -> Result<Vec<LinearStop>, BasicParseError<'i>> {
+    let stops = input.parse_comma_separated(|input| {
+        // `<number> && <percentage>{0,2}`: the percentages are before or after the number.
+        let mut percentages = parse_linear_stop_percentages(input);
+        let output = input.expect_number()?;
+        if percentages.0.is_none() {
+            percentages = parse_linear_stop_percentages(input)
+        }
+        Ok(LinearStop {
+            output,
+            input: percentages,
+        })
+    });
+    stops.map_err(ParseError::<()>::basic)
+}
+
+fn parse_linear_stop_percentages<'i, 't>(input: &mut Parser<'i, 't>) -> (Option<f32>, Option<f32>) {
+    let start = input.try_parse(|input| input.expect_percentage()).ok();
+    let end = match start {
+        Some(_) => input.try_parse(|input| input.expect_percentage()).ok(),
+        None => None,
+    };
+    (start, end)
+}
+
+fn parse_cubic_bezier<'i, 't>(
+    input: &mut Parser<'i, 't>,
+) -> Result<EasingFunction, BasicParseError<'i>> {
+    let parse_x = |input: &mut Parser<'i, 't>| {
+        input.skip_whitespace();
+        let location = input.current_source_location();
+        let x = input.expect_number()?;
+        if !(0. ..=1.).contains(&x) {
+            let token = Token::Number {
+                has_sign: false,
+                value: x,
+                int_value: None,
+            };
+            return Err(location.new_basic_unexpected_token_error(token));
+        }
+        Ok(x)
+    };
+    let x1 = parse_x(input)?;
+    input.expect_comma()?;
+    let y1 = input.expect_number()?;
+    input.expect_comma()?;
+    let x2 = parse_x(input)?;
+    input.expect_comma()?;
+    let y2 = input.expect_number()?;
+    Ok(EasingFunction::CubicBezier { x1, y1, x2, y2 })
+}
+
+fn parse_steps<'i, 't>(input: &mut Parser<'i, 't>) -> Result<EasingFunction, BasicParseError<'i>> {
+    let location = input.current_source_location();
+    let intervals = input.expect_integer()?;
+    let position = if input.try_parse(|input| input.expect_comma()).is_ok() {
+        let location = input.current_source_location();
+        let ident = input.expect_ident()?;
+        Some(match_ignore_ascii_case! { ident,
+            "jump-start" => StepPosition::JumpStart,
+            "jump-end" => StepPosition::JumpEnd,
+            "jump-none" => StepPosition::JumpNone,
+            "jump-both" => StepPosition::JumpBoth,
+            "start" => StepPosition::Start,
+            "end" => StepPosition::End,
+            _ => {
+                let token = Token::Ident(ident.clone());
+                return Err(location.new_basic_unexpected_token_error(token))
+            }
+        })
+    } else {
+        None
+    };
+    // `jump-none` has one fewer step than intervals.
+    let min_intervals = if position == Some(StepPosition::JumpNone) {
+        2
+    } else {
+        1
+    };
+    if intervals < min_intervals {
+        let token = Token::Number {
+            has_sign: false,
+            value: intervals as f32,
+            int_value: Some(intervals),
+        };
+        return Err(location.new_basic_unexpected_token_error(token));
+    }
+    Ok(EasingFunction::Steps(intervals, position))
+}
+
+impl<'i, E> FromCss<'i, E> for EasingFunction {
+    fn from_css<'t>(input: &mut Parser<'i, 't>) -> Result<Self, ParseError<'i, E>> {
+        Ok(EasingFunction::parse(input)?)
+    }
+}
+
+impl ToCss for EasingFunction {
+    fn to_css<W>(&self, dest: &mut W) -> fmt::Result
+    where
+        W: fmt::Write,
+    {
+        match *self {
+            EasingFunction::Linear => dest.write_str("linear"),
+            EasingFunction::LinearStops(ref stops) => {
+                dest.write_str("linear(")?;
+                for (i, stop) in stops.iter().enumerate() {
+                    if i > 0 {
+                        dest.write_str(", ")?;
+                    }
+                    stop.to_css(dest)?;
+                }
+                dest.write_str(")")
+            }
+            EasingFunction::Ease => dest.write_str("ease"),
+            EasingFunction::EaseIn => dest.write_str("ease-in"),
+            EasingFunction::EaseOut => dest.write_str("ease-out"),
+            EasingFunction::EaseInOut => dest.write_str("ease-in-out"),
+            EasingFunction::CubicBezier { x1, y1, x2, y2 } => {
+                dest.write_str("cubic-bezier(")?;
+                for (i, value) in [x1, y1, x2, y2].iter().enumerate() {
+                    if i > 0 {
+                        dest.write_str(", ")?;
+                    }
+                    value.to_css(dest)?;
+                }
+                dest.write_str(")")
+            }
+            EasingFunction::StepStart => dest.write_str("step-start"),
+            EasingFunction::StepEnd => dest.write_str("step-end"),
+            EasingFunction::Steps(intervals, position) => {
+                write!(dest, "steps({}", intervals)?;
+                if let Some(position) = position {
+                    dest.write_str(", ")?;
+                    position.to_css(dest)?;
+                }
+                dest.write_str(")")
+            }
+        }
+    }
+}
+
+/// Serializes the number followed by the percentages, such as `0.5 25% 75%`.
+impl ToCss for LinearStop {
+    fn to_css<W>(&self, dest: &mut W) -> fmt::Result
+    where
+        W: fmt::Write,
+    {
+        self.output.to_css(dest)?;
+        for &percentage in [self.input.0, self.input.1].iter() {
+            if let Some(percentage) = percentage {
+                dest.write_str(" ")?;
+                serialize_percentage(percentage, dest)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ToCss for StepPosition {
+    fn to_css<W>(&self, dest: &mut W) -> fmt::Result
+    where
+        W: fmt::Write,
+    {
+        dest.write_str(match *self {
+            StepPosition::JumpStart => "jump-start",
+            StepPosition::JumpEnd => "jump-end",
+            StepPosition::JumpNone => "jump-none",
+            StepPosition::JumpBoth => "jump-both",
+            StepPosition::Start => "start",
+            StepPosition::End => "end",
+        })
+    }
+}
@@ -0,0 +1,156 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use crate::parser::{ParseError, ParseErrorKind};
+use crate::tokenizer::{SourceLocation, Tokenizer};
+use matches::matches;
+use std::fmt;
+
+/// Receives the errors that rule and declaration list parsing recovers from,
+/// for example to log them to a console.
+///
+/// See `ParserInput::set_error_reporter`.
+/// The errors are still returned by `RuleListParser` and `DeclarationListParser`.
+pub trait ParseErrorReporter {
+    /// Report an error at the given location of the stylesheet at `url`,
+    /// with a message such as “invalid declaration: unexpected token ‘;’”.
+    fn report_error(&self, url: Option<&str>, location: SourceLocation, message: &str);
+}
+
+impl<F> ParseErrorReporter for F
+where
+    F: Fn(Option<&str>, SourceLocation, &str),
+{
+    fn report_error(&self, url: Option<&str>, location: SourceLocation, message: &str) {
+        self(url, location, message)
+    }
+}
+
+pub(crate) struct ErrorReporting {
+    url: Option<String>,
+    reporter: Box<dyn ParseErrorReporter>,
+}
+
+impl ErrorReporting {
+    pub fn new(url: Option<String>, reporter: Box<dyn ParseErrorReporter>) -> ErrorReporting {
+        ErrorReporting { url, reporter }
+    }
+
+    /// `what` is the kind of construct that was invalid, such as "declaration".
+    pub fn report<E>(&self, error: &ParseError<E>, what: &str) {
+        self.reporter.report_error(
+            self.url.as_ref().map(|url| &**url),
+            error.location,
+            &error_message(error, what),
+        )
+    }
+}
+
+/// A message such as “invalid declaration: unexpected token ‘;’”,
+/// where `what` is the kind of construct that was invalid.
+pub(crate) fn error_message<E>(error: &ParseError<E>, what: &str) -> String {
+    match error.kind {
+        ParseErrorKind::Basic(ref kind) => format!("invalid {}: {}", what, kind),
+        ParseErrorKind::Custom(_) => format!("invalid {}", what),
+    }
+}
+
+impl<'i, E> ParseError<'i, E> {
+    /// Render this error with `render_error`, with a message such as “invalid declaration”
+    /// where `what` is the kind of construct that was invalid,
+    /// and the kind of a basic error as label.
+    pub fn render(&self, source: &str, url: Option<&str>, what: &str) -> String {
+        let mut rendered = String::new();
+        let message = format!("invalid {}", what);
+        let label = match self.kind {
+            ParseErrorKind::Basic(ref kind) => kind.to_string(),
+            ParseErrorKind::Custom(_) => String::new(),
+        };
+        render_error(source, url, self.location, &message, &label, &mut rendered).unwrap();
+        rendered
+    }
+}
+
+/// Write a diagnostic for an error at the given location of `source`,
+/// with the line of the error and the token there underlined, such as:
+///
+/// ```text
+/// error: invalid declaration
+///  --> style.css:1:12
+///   |
+/// 1 | a { color: }
+///   |            ^ unexpected ‘}’
+/// ```
+///
+/// The label is omitted when it is empty, and so is the URL when it is `None`.
+/// The location is expected with the default `ColumnUnit` and no tab width,
+/// and with line numbers starting at 0 for the first line of `source`.
+pub fn render_error<W>(
+    source: &str,
+    url: Option<&str>,
+    location: SourceLocation,
+    message: &str,
+    label: &str,
+    dest: &mut W,
+) -> fmt::Result
+where
+    W: fmt::Write,
+{
+    let line_number = (location.line + 1).to_string();
+    let gutter = " ".repeat(line_number.len());
+    writeln!(dest, "error: {}", message)?;
+    match url {
+        Some(url) => writeln!(dest, "{}--> {}:{}", gutter, url, location)?,
+        None => writeln!(dest, "{}--> {}", gutter, location)?,
+    }
+    let line = match source_line(source, location.line) {
+        Some(line) => line,
+        None => return Ok(()),
+    };
+    writeln!(dest, "{} |", gutter)?;
+    writeln!(dest, "{} | {}", line_number, line)?;
+
+    // The byte index of the column, counted in UTF-16 code units.
+    let mut column = 1;
+    let mut start = line.len();
+    for (index, c) in line.char_indices() {
+        if column >= location.column {
+            start = index;
+            break;
+        }
+        column += c.len_utf16() as u32;
+    }
+    let mut tokenizer = Tokenizer::new(&line[start..]);
+    let tokenizer_start = tokenizer.position();
+    let _ = tokenizer.next();
+    let width = tokenizer.slice_from(tokenizer_start).chars().count().max(1);
+    write!(dest, "{} | ", gutter)?;
+    for c in line[..start].chars() {
+        // Keep tabs so that the carets line up with the line.
+        dest.write_char(if c == '\t' { '\t' } else { ' ' })?;
+    }
+    dest.write_str(&"^".repeat(width))?;
+    if !label.is_empty() {
+        write!(dest, " {}", label)?;
+    }
+    writeln!(dest)
+}
+
+/// The line with the given 0-based number, without its newline.
+fn source_line(source: &str, line: u32) -> Option<&str> {
+    let mut rest = source;
+    for _ in 0..line {
+        let end = rest.find(|c| matches!(c, '\n' | '\r' | '\x0C'))?;
+        let newline_len = if rest[end..].starts_with("\r\n") {
+            2
+        } else {
+            1
+        };
+        rest = &rest[end + newline_len..];
+    }
+    let end = rest
+        .find(|c| matches!(c, '\n' | '\r' | '\x0C'))
+        .unwrap_or(rest.len());
+    Some(&rest[..end])
+}
@@ -37,10 +37,58 @@ pub fn stylesheet_encoding<E>(
 where
     E: EncodingSupport,
 {
+    stylesheet_encoding_and_source::<E>(css, protocol_encoding_label, environment_encoding).0
+}
+
+/// Where the encoding returned by `stylesheet_encoding_and_source` comes from.
+///
+/// Decoding is left to the caller's encoding library, so this does not count
+/// the invalid byte sequences that decoding replaces with U+FFFD.
+/// Decoders such as `encoding_rs` report whether there were any.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EncodingSource {
+    /// A UTF-8, UTF-16BE or UTF-16LE BOM (Byte Order Mark),
+    /// which takes precedence over everything else and which decoding removes.
+    Bom,
+    /// The protocol encoding label, such as from a `Content-Type` header.
+    Protocol,
+    /// An `@charset` rule at the start of the stylesheet.
+    /// UTF-16 labels are replaced with UTF-8.
+    Charset,
+    /// The environment encoding, such as of the referring document.
+    Environment,
+    /// None of the above: UTF-8.
+    Default,
+}
+
+/// Same as `stylesheet_encoding`, but also returns where the encoding comes from,
+/// for diagnostics.
+pub fn stylesheet_encoding_and_source<E>(
+    css: &[u8],
+    protocol_encoding_label: Option<&[u8]>,
+    environment_encoding: Option<E::Encoding>,
+) -> (E::Encoding, EncodingSource)
+where
+    E: EncodingSupport,
+{
+    // https://encoding.spec.whatwg.org/#decode
+    let bom_label: Option<&[u8]> = if css.starts_with(b"\xEF\xBB\xBF") {
+        Some(b"utf-8")
+    } else if css.starts_with(b"\xFE\xFF") {
+        Some(b"utf-16be")
+    } else if css.starts_with(b"\xFF\xFE") {
+        Some(b"utf-16le")
+    } else {
+        None
+    };
+    if let Some(bom_encoding) = bom_label.and_then(E::from_label) {
+        return (bom_encoding, EncodingSource::Bom);
+    }
+
     // https://drafts.csswg.org/css-syntax/#the-input-byte-stream
     if let Some(label) = protocol_encoding_label {
         if let Some(protocol_encoding) = E::from_label(label) {
-            return protocol_encoding;
+            return (protocol_encoding, EncodingSource::Protocol);
         };
     };
 
@@ -52,13 +100,16 @@ where
                 let label = &rest[..label_length];
                 if let Some(charset_encoding) = E::from_label(label) {
                     if E::is_utf16_be_or_le(&charset_encoding) {
-                        return E::utf8();
+                        return (E::utf8(), EncodingSource::Charset);
                     } else {
-                        return charset_encoding;
+                        return (charset_encoding, EncodingSource::Charset);
                     }
                 }
             }
         }
     }
-    environment_encoding.unwrap_or_else(E::utf8)
+    match environment_encoding {
+        Some(environment_encoding) => (environment_encoding, EncodingSource::Environment),
+        None => (E::utf8(), EncodingSource::Default),
+    }
 }
@@ -0,0 +1,109 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use crate::cow_rc_str::CowRcStr;
+use crate::parser::{BasicParseErrorKind, ParseError, Parser};
+use crate::serializer::ToCss;
+use crate::tokenizer::Token;
+use std::fmt;
+
+/// Parse a whitespace-separated list of one or more functions, such as the value of `transform`.
+///
+/// `parse_function` is called with the name of each function
+/// and a parser for its arguments, which it must consume entirely.
+/// Any other token, such as an ident between two functions, is an unexpected token error
+/// at its location.
+pub fn parse_function_list<'i, 't, T, E, F>(
+    input: &mut Parser<'i, 't>,
+    mut parse_function: F,
+) -> Result<Vec<T>, ParseError<'i, E>>
+where
+    F: for<'tt> FnMut(CowRcStr<'i>, &mut Parser<'i, 'tt>) -> Result<T, ParseError<'i, E>>,
+{
+    let mut functions = Vec::new();
+    loop {
+        input.skip_whitespace();
+        let location = input.current_source_location();
+        let name = match input.next() {
+            Ok(token) => match *token {
+                Token::Function(ref name) => name.clone(),
+                ref token => return Err(location.new_unexpected_token_error(token.clone())),
+            },
+            Err(_) if functions.is_empty() => {
+                return Err(input.new_error(BasicParseErrorKind::EndOfInput))
+            }
+            Err(_) => return Ok(functions),
+        };
+        functions.push(input.parse_nested_block(|input| parse_function(name, input))?);
+    }
+}
+
+/// A function together with its source text, see `parse_raw_function`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RawFunction<'i, T> {
+    /// The name of the function, without the `(` marker.
+    pub name: CowRcStr<'i>,
+    /// What `parse_arguments` returned for the arguments.
+    pub arguments: T,
+    /// The source text of the function, from its name to its closing parenthesis.
+    pub source: &'i str,
+}
+
+/// Serializes the source text as written.
+impl<'i, T> ToCss for RawFunction<'i, T> {
+    fn to_css<W>(&self, dest: &mut W) -> fmt::Result
+    where
+        W: fmt::Write,
+    {
+        dest.write_str(self.source)
+    }
+}
+
+/// Parse a function, and keep its source text alongside its parsed arguments.
+///
+/// This is for functions that can not be serialized back from their arguments without loss,
+/// such as unknown or vendor-prefixed functions like `-moz-element(#a)`,
+/// whose arguments may have been parsed as a list of tokens.
+/// `parse_arguments` is called with the name of the function
+/// and a parser for its arguments, which it must consume entirely.
+pub fn parse_raw_function<'i, 't, T, E, F>(
+    input: &mut Parser<'i, 't>,
+    parse_arguments: F,
+) -> Result<RawFunction<'i, T>, ParseError<'i, E>>
+where
+    F: for<'tt> FnOnce(CowRcStr<'i>, &mut Parser<'i, 'tt>) -> Result<T, ParseError<'i, E>>,
+{
+    input.skip_whitespace();
+    let start = input.position();
+    let location = input.current_source_location();
+    let name = match *input.next()? {
+        Token::Function(ref name) => name.clone(),
+        ref token => return Err(location.new_unexpected_token_error(token.clone())),
+    };
+    let arguments = input.parse_nested_block(|input| parse_arguments(name.clone(), input))?;
+    Ok(RawFunction {
+        name,
+        arguments,
+        source: input.slice_from(start),
+    })
+}
+
+/// Like `parse_function_list`, but keep the source text of each function,
+/// see `parse_raw_function`.
+pub fn parse_raw_function_list<'i, 't, T, E, F>(
+    input: &mut Parser<'i, 't>,
+    mut parse_function: F,
+) -> Result<Vec<RawFunction<'i, T>>, ParseError<'i, E>>
+where
+    F: for<'tt> FnMut(CowRcStr<'i>, &mut Parser<'i, 'tt>) -> Result<T, ParseError<'i, E>>,
+{
+    let mut functions = Vec::new();
+    loop {
+        input.skip_whitespace();
+        if !functions.is_empty() && input.is_exhausted() {
+            return Ok(functions);
+        }
+        functions.push(parse_raw_function(input, &mut parse_function)?);
+    }
+}
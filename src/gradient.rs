@@ -0,0 +1,126 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! https://drafts.csswg.org/css-images-4/#gradients
+
+use crate::color::Color;
+use crate::parser::{ParseError, Parser, ParserState};
+
+/// The arguments of a gradient function such as `linear-gradient()` or `radial-gradient()`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GradientArguments<D, P> {
+    /// The direction, shape, or other first argument that is not a color stop, as parsed by the caller.
+    pub direction: Option<D>,
+    /// The color stops and color hints, in order.
+    pub stops: Vec<ColorStop<P>>,
+}
+
+/// A color stop, or a color hint, of a gradient.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColorStop<P> {
+    /// The color, or `None` for a color hint.
+    pub color: Option<Color>,
+    /// The positions as parsed by the caller: zero to two for a color stop,
+    /// exactly one for a color hint.
+    pub positions: Vec<P>,
+}
+
+/// Parse the arguments of a gradient function, i.e. the contents of its block.
+///
+/// If the first comma-separated argument is not a color stop,
+/// it is parsed with `parse_direction`.
+/// `parse_position` parses one position of a color stop or color hint,
+/// typically a `<length-percentage>` or (for `conic-gradient()`) an `<angle-percentage>`.
+///
+/// Color hints must be between two color stops.
+pub fn parse_gradient_arguments<'i, 't, D, P, E, FD, FP>(
+    input: &mut Parser<'i, 't>,
+    mut parse_direction: FD,
+    mut parse_position: FP,
+) -> Result<GradientArguments<D, P>, ParseError<'i, E>>
+where
+    FD: for<'tt> FnMut(&mut Parser<'i, 'tt>) -> Result<D, ParseError<'i, E>>,
+    FP: for<'tt> FnMut(&mut Parser<'i, 'tt>) -> Result<P, ParseError<'i, E>>,
+{
+    let mut direction = None;
+    let mut stops: Vec<ColorStop<P>> = Vec::new();
+    let mut first = true;
+    // The start of the last argument, if it is a color hint.
+    let mut last_hint = None;
+    input.parse_comma_separated(|input| {
+        input.skip_whitespace();
+        let start = input.state();
+        if first {
+            first = false;
+            // A color hint can not be first, so this would be a direction.
+            let stop = input.try_parse(|input| {
+                let stop =
+                    input.parse_entirely(|input| parse_color_stop(input, &mut parse_position))?;
+                match stop.color {
+                    Some(_) => Ok(stop),
+                    None => Err(unexpected_hint(input, &start)),
+                }
+            });
+            match stop {
+                Ok(stop) => stops.push(stop),
+                Err(_) => direction = Some(parse_direction(input)?),
+            }
+            return Ok(());
+        }
+        let stop = parse_color_stop(input, &mut parse_position)?;
+        let after_hint = match stops.last() {
+            Some(previous) => previous.color.is_none(),
+            None => true,
+        };
+        if stop.color.is_none() {
+            if after_hint {
+                return Err(unexpected_hint(input, &start));
+            }
+            last_hint = Some(start)
+        } else {
+            last_hint = None
+        }
+        stops.push(stop);
+        Ok(())
+    })?;
+    if let Some(start) = last_hint {
+        return Err(unexpected_hint(input, &start));
+    }
+    Ok(GradientArguments { direction, stops })
+}
+
+/// An error for the color hint that starts at `start`, which is not between two color stops.
+fn unexpected_hint<'i, 't, E>(
+    input: &mut Parser<'i, 't>,
+    start: &ParserState,
+) -> ParseError<'i, E> {
+    input.reset(start);
+    let location = input.current_source_location();
+    match input.next() {
+        Ok(token) => location.new_unexpected_token_error(token.clone()),
+        Err(error) => error.into(),
+    }
+}
+
+fn parse_color_stop<'i, 't, P, E, FP>(
+    input: &mut Parser<'i, 't>,
+    parse_position: &mut FP,
+) -> Result<ColorStop<P>, ParseError<'i, E>>
+where
+    FP: for<'tt> FnMut(&mut Parser<'i, 'tt>) -> Result<P, ParseError<'i, E>>,
+{
+    let color = input.try_parse(Color::parse).ok();
+    let mut positions = Vec::new();
+    if color.is_none() {
+        positions.push(parse_position(input)?);
+    } else {
+        while positions.len() < 2 {
+            match input.try_parse(|input| parse_position(input)) {
+                Ok(position) => positions.push(position),
+                Err(_) => break,
+            }
+        }
+    }
+    Ok(ColorStop { color, positions })
+}
@@ -0,0 +1,81 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! https://drafts.csswg.org/css-grid-2/#track-sizing
+
+use crate::cow_rc_str::CowRcStr;
+use crate::custom_ident::parse_custom_ident;
+use crate::parser::{BasicParseError, ParseError, Parser};
+use crate::tokenizer::Token;
+
+/// An item of a grid track list, returned by `parse_grid_track_list_items`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GridTrackListItem<'i> {
+    /// A `<line-names>` block, such as `[header-start main]`, with the names as written.
+    LineNames(Vec<CowRcStr<'i>>),
+    /// The tokens between line names, as written without surrounding whitespace,
+    /// such as `1fr minmax(10px, auto)` or `repeat(2, [a] 1fr)`,
+    /// to be parsed as track sizes by the caller.
+    Tracks(&'i str),
+}
+
+/// Split the rest of the input, such as the value of `grid-template-columns`,
+/// into line names in `[]` blocks and the runs of tokens between them.
+///
+/// Line names are `<custom-ident>`s other than `span` and `auto`, and an error is returned
+/// for anything else in brackets.
+/// The contents of functions such as `repeat()` are not looked into:
+/// they can be given to this function again with `Parser::parse_nested_block`.
+/// Adjacent `[]` blocks are returned as separate items,
+/// since only some grammars such as `subgrid` line name lists accept them.
+pub fn parse_grid_track_list_items<'i, 't>(
+    input: &mut Parser<'i, 't>,
+) -> Result<Vec<GridTrackListItem<'i>>, BasicParseError<'i>> {
+    let mut items = Vec::new();
+    loop {
+        input.skip_whitespace();
+        let start = input.state();
+        match input.next() {
+            Ok(&Token::SquareBracketBlock) => {
+                let result: Result<_, ParseError<()>> = input.parse_nested_block(|input| {
+                    let mut names = Vec::new();
+                    while !input.is_exhausted() {
+                        names.push(parse_custom_ident(input, &["span", "auto"])?)
+                    }
+                    Ok(names)
+                });
+                items.push(GridTrackListItem::LineNames(
+                    result.map_err(ParseError::basic)?,
+                ))
+            }
+            Ok(_) => {
+                input.reset(&start);
+                let run_start = input.position();
+                let mut run_end = run_start;
+                loop {
+                    let state = input.state();
+                    match input.next() {
+                        Ok(&Token::SquareBracketBlock) | Err(_) => {
+                            input.reset(&state);
+                            break;
+                        }
+                        Ok(&Token::Function(_))
+                        | Ok(&Token::ParenthesisBlock)
+                        | Ok(&Token::CurlyBracketBlock) => {
+                            // Include the closing token of the block.
+                            let _: Result<_, ParseError<()>> = input.parse_nested_block(|input| {
+                                while input.next().is_ok() {}
+                                Ok(())
+                            });
+                        }
+                        Ok(_) => {}
+                    }
+                    run_end = input.position();
+                }
+                items.push(GridTrackListItem::Tracks(input.slice(run_start..run_end)))
+            }
+            Err(_) => return Ok(items),
+        }
+    }
+}
@@ -0,0 +1,85 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! https://drafts.csswg.org/css-images-4/#image-set-notation
+
+use crate::cow_rc_str::CowRcStr;
+use crate::parser::{BasicParseError, ParseError, Parser};
+use crate::tokenizer::Token;
+
+/// One of the comma-separated options of an `image-set()` function.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageSetOption<'i, I> {
+    /// The image, as parsed by the caller.
+    pub image: I,
+    /// The resolution in `dppx`, if specified.
+    ///
+    /// `x` is an alias for `dppx`, and `dpi` and `dpcm` are converted.
+    pub resolution: Option<f32>,
+    /// The MIME type from `type("…")`, if specified.
+    pub mime_type: Option<CowRcStr<'i>>,
+}
+
+/// Parse the arguments of an `image-set()` or `-webkit-image-set()` function,
+/// i.e. the contents of its block.
+///
+/// `parse_image` parses the image of one option, typically a `url()` or a string.
+/// It is followed by a resolution and a `type()` function in either order, both optional.
+pub fn parse_image_set<'i, 't, I, E, F>(
+    input: &mut Parser<'i, 't>,
+    mut parse_image: F,
+) -> Result<Vec<ImageSetOption<'i, I>>, ParseError<'i, E>>
+where
+    F: for<'tt> FnMut(&mut Parser<'i, 'tt>) -> Result<I, ParseError<'i, E>>,
+{
+    input.parse_comma_separated(|input| {
+        let image = parse_image(input)?;
+        let mut resolution = None;
+        let mut mime_type = None;
+        loop {
+            if resolution.is_none() {
+                if let Ok(value) = input.try_parse(parse_resolution) {
+                    resolution = Some(value);
+                    continue;
+                }
+            }
+            if mime_type.is_none() {
+                if let Ok(value) = input.try_parse(parse_type_function) {
+                    mime_type = Some(value);
+                    continue;
+                }
+            }
+            break;
+        }
+        Ok(ImageSetOption {
+            image,
+            resolution,
+            mime_type,
+        })
+    })
+}
+
+fn parse_resolution<'i, 't>(input: &mut Parser<'i, 't>) -> Result<f32, BasicParseError<'i>> {
+    let location = input.current_source_location();
+    let token = input.next()?.clone();
+    if let Token::Dimension {
+        value, ref unit, ..
+    } = token
+    {
+        match_ignore_ascii_case! { unit,
+            "x" | "dppx" => return Ok(value),
+            "dpi" => return Ok(value / 96.),
+            "dpcm" => return Ok(value * 2.54 / 96.),
+            _ => {}
+        }
+    }
+    Err(location.new_basic_unexpected_token_error(token))
+}
+
+fn parse_type_function<'i, 't>(
+    input: &mut Parser<'i, 't>,
+) -> Result<CowRcStr<'i>, ParseError<'i, ()>> {
+    input.expect_function_matching("type")?;
+    input.parse_nested_block(|input| Ok(input.expect_string()?.clone()))
+}
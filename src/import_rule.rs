@@ -0,0 +1,210 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! https://drafts.csswg.org/css-cascade-5/#at-import
+
+use crate::cow_rc_str::CowRcStr;
+use crate::parser::{BasicParseError, ParseError, Parser};
+use crate::rules_and_declarations::{AtRuleParser, AtRuleType, QualifiedRuleParser};
+use crate::tokenizer::{SourceLocation, Token};
+
+/// The prelude of an `@import` rule.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportPrelude<'i> {
+    /// The URL of the imported stylesheet, as written.
+    pub url: CowRcStr<'i>,
+    /// The cascade layer from `layer` or `layer(…)`, if any.
+    pub layer: Option<ImportLayer<'i>>,
+    /// The condition in `supports(…)`, if any, as written.
+    pub supports: Option<&'i str>,
+    /// The media query list, as written. Empty if there is none.
+    pub media: &'i str,
+}
+
+/// The cascade layer of an `@import` rule.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImportLayer<'i> {
+    /// `layer`
+    Anonymous,
+    /// `layer(…)`, with the layer name as written, such as `base.reset`.
+    Named(&'i str),
+}
+
+impl<'i> ImportPrelude<'i> {
+    /// Parse the prelude of an `@import` rule, after the at-keyword.
+    ///
+    /// The supports condition and media query list are not checked,
+    /// other than `supports(…)` being a block.
+    pub fn parse<'t>(input: &mut Parser<'i, 't>) -> Result<Self, BasicParseError<'i>> {
+        let url = input.expect_url_or_string()?;
+
+        let mut layer = None;
+        let mut supports = None;
+        let state = input.state();
+        let location = input.current_source_location();
+        match input.next().ok().cloned() {
+            Some(Token::Ident(ref name)) if name.eq_ignore_ascii_case("layer") => {
+                layer = Some(ImportLayer::Anonymous)
+            }
+            Some(Token::Function(ref name)) if name.eq_ignore_ascii_case("layer") => {
+                let name = block_contents(input)?;
+                if name.is_empty() {
+                    return Err(
+                        location.new_basic_unexpected_token_error(Token::Function("layer".into()))
+                    );
+                }
+                layer = Some(ImportLayer::Named(name))
+            }
+            _ => input.reset(&state),
+        }
+        let state = input.state();
+        match input.next().ok().cloned() {
+            Some(Token::Function(ref name)) if name.eq_ignore_ascii_case("supports") => {
+                supports = Some(block_contents(input)?)
+            }
+            _ => input.reset(&state),
+        }
+
+        input.skip_whitespace();
+        let start = input.position();
+        while input.next().is_ok() {}
+        Ok(ImportPrelude {
+            url,
+            layer,
+            supports,
+            media: input.slice_from(start).trim_end(),
+        })
+    }
+}
+
+/// The source text in the block that was just opened, without surrounding whitespace.
+fn block_contents<'i, 't>(input: &mut Parser<'i, 't>) -> Result<&'i str, BasicParseError<'i>> {
+    let result: Result<_, ParseError<()>> = input.parse_nested_block(|input| {
+        input.skip_whitespace();
+        let start = input.position();
+        while input.next().is_ok() {}
+        Ok(input.slice_from(start).trim_end())
+    });
+    result.map_err(ParseError::basic)
+}
+
+/// Wraps a rule parser to call `on_import` with the prelude of each valid `@import` rule,
+/// as soon as the wrapped parser has parsed the rule.
+///
+/// This lets a stylesheet loader start fetching imported stylesheets
+/// while the rest of the stylesheet is still being parsed by `RuleListParser`.
+/// Rules are otherwise parsed by the wrapped parser, including `@import` rules,
+/// and `on_import` is not called for those that it rejects.
+/// `@import` rules after any other valid rule than `@charset` and `@layer` statements
+/// are not passed to `on_import`.
+pub struct ImportObserver<'i, P, F> {
+    /// The wrapped parser.
+    pub parser: P,
+    on_import: F,
+    imports_allowed: bool,
+    /// The prelude of the `@import` rule being parsed, until the wrapped parser accepts it.
+    pending_import: Option<ImportPrelude<'i>>,
+}
+
+impl<'i, P, F> ImportObserver<'i, P, F> {
+    /// Wrap the given parser.
+    pub fn new(parser: P, on_import: F) -> Self {
+        ImportObserver {
+            parser,
+            on_import,
+            imports_allowed: true,
+            pending_import: None,
+        }
+    }
+}
+
+impl<'i, P, F> AtRuleParser<'i> for ImportObserver<'i, P, F>
+where
+    P: AtRuleParser<'i>,
+    F: FnMut(ImportPrelude<'i>),
+{
+    type PreludeNoBlock = P::PreludeNoBlock;
+    type PreludeBlock = P::PreludeBlock;
+    type AtRule = P::AtRule;
+    type Error = P::Error;
+
+    fn parse_prelude<'t>(
+        &mut self,
+        name: CowRcStr<'i>,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<AtRuleType<Self::PreludeNoBlock, Self::PreludeBlock>, ParseError<'i, Self::Error>>
+    {
+        self.pending_import = None;
+        let is_import = name.eq_ignore_ascii_case("import");
+        let mut import = None;
+        if is_import && self.imports_allowed {
+            let start = input.state();
+            let result: Result<_, ParseError<()>> =
+                input.parse_entirely(|input| Ok(ImportPrelude::parse(input)?));
+            import = result.ok();
+            input.reset(&start);
+        }
+        let is_layer = name.eq_ignore_ascii_case("layer");
+        let result = self.parser.parse_prelude(name, input);
+        match result {
+            Ok(AtRuleType::WithoutBlock(_)) if is_import || is_layer => {
+                self.pending_import = import
+            }
+            Ok(_) => self.imports_allowed = false,
+            Err(_) => {}
+        }
+        result
+    }
+
+    fn rule_without_block(
+        &mut self,
+        prelude: Self::PreludeNoBlock,
+        location: SourceLocation,
+    ) -> Self::AtRule {
+        let rule = self.parser.rule_without_block(prelude, location);
+        if let Some(import) = self.pending_import.take() {
+            (self.on_import)(import)
+        }
+        rule
+    }
+
+    fn parse_block<'t>(
+        &mut self,
+        prelude: Self::PreludeBlock,
+        location: SourceLocation,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self::AtRule, ParseError<'i, Self::Error>> {
+        self.parser.parse_block(prelude, location, input)
+    }
+}
+
+impl<'i, P, F> QualifiedRuleParser<'i> for ImportObserver<'i, P, F>
+where
+    P: QualifiedRuleParser<'i>,
+{
+    type Prelude = P::Prelude;
+    type QualifiedRule = P::QualifiedRule;
+    type Error = P::Error;
+
+    fn parse_prelude<'t>(
+        &mut self,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self::Prelude, ParseError<'i, Self::Error>> {
+        self.pending_import = None;
+        let result = self.parser.parse_prelude(input);
+        if result.is_ok() {
+            self.imports_allowed = false
+        }
+        result
+    }
+
+    fn parse_block<'t>(
+        &mut self,
+        prelude: Self::Prelude,
+        location: SourceLocation,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self::QualifiedRule, ParseError<'i, Self::Error>> {
+        self.parser.parse_block(prelude, location, input)
+    }
+}
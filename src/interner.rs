@@ -0,0 +1,93 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use crate::cow_rc_str::CowRcStr;
+use crate::tokenizer::Token;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::Rc;
+
+/// Deduplicates the values of tokens that the tokenizer had to allocate.
+///
+/// Most idents and strings borrow from the input and never allocate.
+/// Those that contain escape sequences (or that are otherwise not a slice of the input)
+/// are heap-allocated, and an interner lets identical values share one allocation.
+///
+/// See [`ParserInput::new_with_interner`](struct.ParserInput.html#method.new_with_interner).
+pub trait StringInterner {
+    /// Return a string equal to `value`,
+    /// possibly sharing its allocation with a previously seen equal string.
+    fn intern(&mut self, value: CowRcStr<'static>) -> CowRcStr<'static>;
+}
+
+impl<T: StringInterner + ?Sized> StringInterner for Rc<RefCell<T>> {
+    fn intern(&mut self, value: CowRcStr<'static>) -> CowRcStr<'static> {
+        self.borrow_mut().intern(value)
+    }
+}
+
+/// A `StringInterner` that keeps one copy of every distinct value it has seen.
+#[derive(Default)]
+pub struct Interner {
+    strings: HashSet<CowRcStr<'static>>,
+}
+
+impl Interner {
+    /// Create an empty interner.
+    pub fn new() -> Interner {
+        Interner::default()
+    }
+
+    /// Return the number of distinct strings interned so far.
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    /// Return whether no string was interned yet.
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+}
+
+impl StringInterner for Interner {
+    fn intern(&mut self, value: CowRcStr<'static>) -> CowRcStr<'static> {
+        if let Some(existing) = self.strings.get(&*value) {
+            return existing.clone();
+        }
+        self.strings.insert(value.clone());
+        value
+    }
+}
+
+fn intern_str<'i>(value: CowRcStr<'i>, interner: &mut dyn StringInterner) -> CowRcStr<'i> {
+    match value.into_shared() {
+        Ok(shared) => interner.intern(shared),
+        Err(borrowed) => borrowed,
+    }
+}
+
+/// Pass the heap-allocated name or value of this token, if any, through the interner.
+pub(crate) fn intern_token<'i>(token: Token<'i>, interner: &mut dyn StringInterner) -> Token<'i> {
+    match token {
+        Token::Ident(value) => Token::Ident(intern_str(value, interner)),
+        Token::AtKeyword(value) => Token::AtKeyword(intern_str(value, interner)),
+        Token::Hash(value) => Token::Hash(intern_str(value, interner)),
+        Token::IDHash(value) => Token::IDHash(intern_str(value, interner)),
+        Token::QuotedString(value) => Token::QuotedString(intern_str(value, interner)),
+        Token::UnquotedUrl(value) => Token::UnquotedUrl(intern_str(value, interner)),
+        Token::Function(name) => Token::Function(intern_str(name, interner)),
+        Token::Dimension {
+            has_sign,
+            value,
+            int_value,
+            unit,
+        } => Token::Dimension {
+            has_sign,
+            value,
+            int_value,
+            unit: intern_str(unit, interner),
+        },
+        token => token,
+    }
+}
@@ -67,28 +67,126 @@ fn parse_border_spacing(_context: &ParserContext, input: &mut Parser)
 
 #![recursion_limit = "200"] // For color::parse_color_keyword
 
-pub use crate::color::{
-    parse_color_keyword, AngleOrNumber, Color, ColorComponentParser, NumberOrPercentage, RGBA,
-};
+pub use crate::at_rules::{classify_at_rule, AtRuleClassifier, AtRuleContents};
+#[cfg(feature = "values")]
+pub use crate::basic_shape::{BasicShape, BorderRadius, FillRule, ShapeRadius};
+pub use crate::chunked_tokenizer::{tokenize_bytes_lossy, ChunkedTokenizer};
+#[cfg(feature = "color")]
+pub use crate::color::{parse_color_keyword, AngleOrNumber, Color, ColorComponentParser};
+#[cfg(feature = "color")]
+pub use crate::color::{ColorDetails, Hsla, Hwba, NumberOrPercentage, RgbComponents, RGBA};
+pub use crate::completion::{complete_input, completion_context, CompletionContext};
+pub use crate::completion::{Incomplete, IncompleteKind, InputCompletion};
+pub use crate::concatenation::ConcatenationChangeKind;
+pub use crate::concatenation::{concat_stylesheets, ConcatenationChange};
+#[cfg(feature = "values")]
+pub use crate::counter::Counter;
 pub use crate::cow_rc_str::CowRcStr;
+#[cfg(feature = "values")]
+pub use crate::css_wide_keyword::{parse_css_wide_keyword, CssWideKeyword};
+#[cfg(feature = "values")]
+pub use crate::custom_ident::{parse_custom_ident, parse_custom_ident_list};
+#[cfg(feature = "values")]
+pub use crate::custom_ident::{parse_dashed_ident, DashedIdent};
+pub use crate::custom_properties::CustomPropertyIssue;
+pub use crate::custom_properties::CustomPropertyIssueKind;
+pub use crate::custom_properties::{check_custom_properties, parse_custom_property_value};
+#[cfg(feature = "values")]
+pub use crate::dimensions::parse_calc_constant;
+#[cfg(feature = "values")]
+pub use crate::dimensions::LengthPercentage;
+#[cfg(feature = "values")]
+pub use crate::dimensions::{Angle, AngleUnit, Length, LengthUnit, Time, TimeUnit};
+#[cfg(feature = "values")]
+pub use crate::dimensions::{Frequency, FrequencyUnit, Resolution, ResolutionUnit};
+#[cfg(feature = "values")]
+pub use crate::easing::{EasingFunction, LinearStop, StepPosition};
+pub use crate::error_reporting::{render_error, ParseErrorReporter};
 pub use crate::from_bytes::{stylesheet_encoding, EncodingSupport};
+pub use crate::from_bytes::{stylesheet_encoding_and_source, EncodingSource};
+#[cfg(feature = "values")]
+pub use crate::function_list::{parse_function_list, parse_raw_function};
+#[cfg(feature = "values")]
+pub use crate::function_list::{parse_raw_function_list, RawFunction};
+#[cfg(feature = "values")]
+pub use crate::gradient::{parse_gradient_arguments, ColorStop, GradientArguments};
+#[cfg(feature = "values")]
+pub use crate::grid::{parse_grid_track_list_items, GridTrackListItem};
+#[cfg(feature = "values")]
+pub use crate::image_set::{parse_image_set, ImageSetOption};
+pub use crate::import_rule::{ImportLayer, ImportObserver, ImportPrelude};
+pub use crate::interner::{Interner, StringInterner};
+pub use crate::line_index::LineIndex;
+#[cfg(feature = "lint")]
+pub use crate::lint::{lint_stylesheet, LintDiagnostic, LintKind};
 #[doc(hidden)]
 pub use crate::macros::_cssparser_internal_to_lowercase;
+#[cfg(feature = "values")]
+pub use crate::media_query::{media_query_list_matches, parse_media_query_list};
+#[cfg(feature = "values")]
+pub use crate::media_query::{MediaComparison, MediaCondition, MediaDevice, MediaFeature};
+#[cfg(feature = "values")]
+pub use crate::media_query::{MediaFeatureValue, MediaQualifier, MediaQuery};
+pub use crate::minify::MinifyOptions;
+pub use crate::minify::{serialize_minified, serialize_minified_number, serialize_minified_with};
+#[cfg(feature = "nth")]
 pub use crate::nth::parse_nth;
+pub use crate::outline::{document_symbols, DocumentSymbol};
+pub use crate::outline::{folding_ranges, FoldingRange, FoldingRangeKind};
+pub use crate::parser::UnclosedBlock;
 pub use crate::parser::{BasicParseError, BasicParseErrorKind, ParseError, ParseErrorKind};
-pub use crate::parser::{Delimiter, Delimiters, Parser, ParserInput, ParserState};
-pub use crate::rules_and_declarations::{parse_important, parse_one_declaration};
-pub use crate::rules_and_declarations::{parse_one_rule, RuleListParser};
+pub use crate::parser::{Delimiter, Delimiters, FromCss, Parser, ParserInput, ParserState};
+#[cfg(feature = "values")]
+pub use crate::position::{HorizontalPositionKeyword, VerticalPositionKeyword};
+#[cfg(feature = "values")]
+pub use crate::position::{Position, PositionComponent};
+#[cfg(feature = "values")]
+pub use crate::ratio::Ratio;
+pub use crate::rule_finder::{find_rules, FindRules, FoundRule};
+pub use crate::rule_serialization::TrailingSemicolon;
+pub use crate::rule_serialization::{rule_list_len_estimate, serialize_rule_list};
+pub use crate::rule_serialization::{serialize_rule_list_into, SerializableAtRule};
+pub use crate::rule_serialization::{SerializableDeclaration, SerializableDeclarationListItem};
+pub use crate::rule_serialization::{SerializableQualifiedRule, SerializableRule};
+pub use crate::rules_and_declarations::RecoveredErrors;
+pub use crate::rules_and_declarations::RuleListParser;
+pub use crate::rules_and_declarations::{add_vendor_prefixes, remove_vendor_prefixes};
+pub use crate::rules_and_declarations::{parse_declaration_value, parse_nested_rules};
+pub use crate::rules_and_declarations::{parse_importance, parse_important, parse_one_declaration};
+pub use crate::rules_and_declarations::{
+    parse_one_declaration_with_errors, parse_one_rule_with_errors,
+};
+pub use crate::rules_and_declarations::{parse_one_rule, remove_overridden_declarations};
 pub use crate::rules_and_declarations::{AtRuleParser, AtRuleType, QualifiedRuleParser};
 pub use crate::rules_and_declarations::{DeclarationListParser, DeclarationParser};
-pub use crate::serializer::{serialize_identifier, serialize_name, serialize_string};
+pub use crate::serializer::{round_trip_check, Mismatch};
+pub use crate::serializer::{serialize_identifier, serialize_name, serialize_normalized_value};
+pub use crate::serializer::{serialize_identifier_with, serialize_rewritten, IdentifierEscaping};
+pub use crate::serializer::{serialize_string, serialize_string_with, QuoteStyle};
 pub use crate::serializer::{CssStringWriter, ToCss, TokenSerializationType};
-pub use crate::tokenizer::{SourceLocation, SourcePosition, Token};
+pub use crate::sniffing::{sniff_stylesheet, SniffedContent};
+pub use crate::specificity::{estimate_specificity, Specificity};
+pub use crate::supports::{SupportsCondition, SupportsEvaluator};
+pub use crate::token_list::TokenList;
+pub use crate::token_stream::{diff_rules, RuleChange};
+pub use crate::token_stream::{hash_token_stream, token_stream_eq};
+pub use crate::token_stream::{merge_adjacent_rules, remove_duplicate_rules};
+pub use crate::token_stream::{stable_hash_token_stream, STABLE_HASH_VERSION};
+pub use crate::tokenizer::{is_ident_start, starts_with_valid_escape};
+pub use crate::tokenizer::{ColumnUnit, SourceLocation, SourcePosition, Token, TokenKind};
 pub use crate::unicode_range::UnicodeRange;
+pub use crate::url_extraction::{extract_urls, ExtractedUrl, UrlRole};
+pub use crate::url_rewriting::{UnresolvedUrl, UrlResolver};
 pub use cssparser_macros::*;
 #[doc(hidden)]
 pub use phf as _cssparser_internal_phf;
 
+/// A type with no values, so that the `__Nonexhaustive` variants of public enums
+/// can not be constructed.
+#[doc(hidden)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Void {}
+
 #[macro_use]
 mod macros;
 
@@ -101,14 +199,66 @@ mod tokenizer;
 mod tokenizer {
     include!(concat!(env!("OUT_DIR"), "/tokenizer.rs"));
 }
+mod at_rules;
+#[cfg(feature = "values")]
+mod basic_shape;
+mod chunked_tokenizer;
+#[cfg(feature = "color")]
 mod color;
+mod completion;
+mod concatenation;
+#[cfg(feature = "values")]
+mod counter;
 mod cow_rc_str;
+#[cfg(feature = "values")]
+mod css_wide_keyword;
+#[cfg(feature = "values")]
+mod custom_ident;
+mod custom_properties;
+#[cfg(feature = "values")]
+mod dimensions;
+#[cfg(feature = "values")]
+mod easing;
+mod error_reporting;
 mod from_bytes;
+#[cfg(feature = "values")]
+mod function_list;
+#[cfg(feature = "values")]
+mod gradient;
+#[cfg(feature = "values")]
+mod grid;
+#[cfg(feature = "values")]
+mod image_set;
+mod import_rule;
+mod interner;
+mod line_index;
+#[cfg(feature = "lint")]
+mod lint;
+#[cfg(feature = "values")]
+mod media_query;
+mod minify;
+#[cfg(feature = "nth")]
 mod nth;
+mod outline;
 mod parser;
+#[cfg(feature = "values")]
+mod position;
+#[cfg(feature = "values")]
+mod ratio;
+mod rule_finder;
+mod rule_serialization;
 mod serializer;
+mod sniffing;
+mod specificity;
+mod supports;
+mod token_list;
+mod token_stream;
 mod unicode_range;
+mod url_extraction;
+mod url_rewriting;
 
+#[cfg(any(test, feature = "bench"))]
+pub mod bench_corpora;
 #[cfg(test)]
 mod size_of_tests;
 #[cfg(test)]
@@ -0,0 +1,137 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use crate::tokenizer::{column_in_unit, ColumnUnit, SourceLocation, SourcePosition};
+
+const CHECKPOINT_INTERVAL: usize = 256;
+
+/// The start of each line of a stylesheet, to compute source locations on demand.
+///
+/// This is for consumers that keep byte offsets, such as from `Parser::position`,
+/// and only need line and column numbers for some of them, such as to report errors.
+/// It is built in one pass over the input, after which each lookup is a binary search
+/// followed by counting the column units of a few hundred bytes at most,
+/// except with a tab width, see `set_tab_width`, where the line is scanned from its start.
+///
+/// Lines are separated as by the tokenizer: by `\n`, `\r\n`, `\r` or `\x0C`,
+/// so locations are the same as `Parser::current_source_location` would give
+/// with the same column unit and tab width.
+#[derive(Clone, Debug)]
+pub struct LineIndex<'i> {
+    input: &'i str,
+    /// The byte index of the start of each line.
+    line_starts: Vec<usize>,
+    /// The number of UTF-16 code units and of code points before every
+    /// `CHECKPOINT_INTERVAL` bytes of the input.
+    checkpoints: Vec<(usize, usize)>,
+    column_unit: ColumnUnit,
+    /// 0 if tabs are one column wide.
+    tab_width: u8,
+}
+
+impl<'i> LineIndex<'i> {
+    /// Find the lines of the given input.
+    pub fn new(input: &'i str) -> Self {
+        let bytes = input.as_bytes();
+        let mut line_starts = vec![0];
+        let mut i = 0;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'\r' if bytes.get(i + 1) == Some(&b'\n') => {
+                    i += 1;
+                    line_starts.push(i + 1)
+                }
+                b'\n' | b'\r' | b'\x0C' => line_starts.push(i + 1),
+                _ => {}
+            }
+            i += 1;
+        }
+        let mut checkpoints = Vec::with_capacity(bytes.len() / CHECKPOINT_INTERVAL + 1);
+        let (mut utf16_code_units, mut code_points) = (0, 0);
+        for chunk in bytes.chunks(CHECKPOINT_INTERVAL) {
+            checkpoints.push((utf16_code_units, code_points));
+            utf16_code_units += units_in(chunk, ColumnUnit::Utf16CodeUnits);
+            code_points += units_in(chunk, ColumnUnit::CodePoints);
+        }
+        checkpoints.push((utf16_code_units, code_points));
+        LineIndex {
+            input,
+            line_starts,
+            checkpoints,
+            column_unit: ColumnUnit::Utf16CodeUnits,
+            tab_width: 0,
+        }
+    }
+
+    /// Count the column numbers of source locations in the given unit,
+    /// as with `ParserInput::set_column_unit`.
+    pub fn set_column_unit(&mut self, unit: ColumnUnit) {
+        self.column_unit = unit
+    }
+
+    /// Count tabs as several columns, as with `ParserInput::set_tab_width`.
+    pub fn set_tab_width(&mut self, width: u8) {
+        self.tab_width = width
+    }
+
+    /// Return the number of lines, which is one more than the number of line breaks.
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Return the position of the start of the given 0-based line, if there is such a line.
+    pub fn line_start(&self, line: u32) -> Option<SourcePosition> {
+        self.line_starts
+            .get(line as usize)
+            .map(|&start| SourcePosition(start))
+    }
+
+    /// Return the line and column number of the given position,
+    /// which must be in the input this index was built for.
+    pub fn location(&self, position: SourcePosition) -> SourceLocation {
+        self.location_of_byte_index(position.byte_index())
+    }
+
+    /// Same as `location`, but with a byte index in the input.
+    ///
+    /// Panics if `byte_index` is past the end of the input.
+    pub fn location_of_byte_index(&self, byte_index: usize) -> SourceLocation {
+        assert!(byte_index <= self.input.len());
+        let line = match self.line_starts.binary_search(&byte_index) {
+            Ok(line) => line,
+            Err(next_line) => next_line - 1,
+        };
+        let line_start = self.line_starts[line];
+        let column = if self.tab_width == 0 {
+            (self.units_before(byte_index) - self.units_before(line_start) + 1) as u32
+        } else {
+            let before = &self.input.as_bytes()[line_start..byte_index];
+            column_in_unit(before, self.column_unit, self.tab_width)
+        };
+        SourceLocation {
+            line: line as u32,
+            column,
+        }
+    }
+
+    /// The number of column units in the input before the given byte index.
+    fn units_before(&self, byte_index: usize) -> usize {
+        if self.column_unit == ColumnUnit::Utf8Bytes {
+            return byte_index;
+        }
+        let checkpoint = byte_index / CHECKPOINT_INTERVAL;
+        let (utf16_code_units, code_points) = self.checkpoints[checkpoint];
+        let start = checkpoint * CHECKPOINT_INTERVAL;
+        let rest = units_in(&self.input.as_bytes()[start..byte_index], self.column_unit);
+        match self.column_unit {
+            ColumnUnit::Utf16CodeUnits => utf16_code_units + rest,
+            _ => code_points + rest,
+        }
+    }
+}
+
+/// The number of column units in some bytes, which need not start or end at a character boundary.
+fn units_in(bytes: &[u8], unit: ColumnUnit) -> usize {
+    column_in_unit(bytes, unit, 0) as usize - 1
+}
@@ -0,0 +1,233 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use crate::at_rules::{classify_at_rule, AtRuleContents};
+use crate::cow_rc_str::CowRcStr;
+use crate::parser::{Delimiter, ParseError, Parser, ParserInput};
+use crate::rules_and_declarations::RuleListParser;
+use crate::rules_and_declarations::{parse_important, parse_nested_rules};
+use crate::rules_and_declarations::{AtRuleParser, AtRuleType, QualifiedRuleParser};
+use crate::tokenizer::{SourceLocation, Token};
+use std::collections::HashMap;
+
+/// A declaration that has no effect, found by `lint_stylesheet`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LintDiagnostic {
+    /// Why the declaration has no effect.
+    pub kind: LintKind,
+    /// The property name of the declaration, in lower case unless it is a custom property.
+    pub property: String,
+    /// The location of the property name of the declaration.
+    pub location: SourceLocation,
+    /// The location of the property name of the declaration that overrides it.
+    pub overridden_by: SourceLocation,
+}
+
+/// The kind of a `LintDiagnostic`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LintKind {
+    /// Another declaration of the same property in the same block overrides this one.
+    DuplicateDeclaration,
+    /// A declaration of a shorthand of this property in the same block overrides this one,
+    /// such as `margin-top: 1px; margin: 0`.
+    OverriddenByShorthand,
+}
+
+/// Report declarations of a stylesheet that are overridden by another declaration
+/// of the same block.
+///
+/// `shorthands` maps shorthand property names to their longhands,
+/// such as `("margin", &["margin-top", "margin-right", "margin-bottom", "margin-left"])`.
+/// Names are compared ASCII case-insensitively, except for custom properties (`--*`).
+/// Importance is taken into account: a declaration is overridden
+/// by a later one with the same or higher importance,
+/// or by an earlier `!important` one if it is not `!important` itself.
+///
+/// Blocks of qualified rules and of at-rules that contain declarations
+/// per `classify_at_rule` are checked, recursing into at-rules that contain rules.
+/// Invalid declarations are ignored.
+/// As with `remove_overridden_declarations`, “fallback” declarations
+/// such as `display: -webkit-box` followed by `display: flex` are reported.
+pub fn lint_stylesheet(input: &str, shorthands: &[(&str, &[&str])]) -> Vec<LintDiagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut input = ParserInput::new(input);
+    let mut input = Parser::new(&mut input);
+    let rules = LintRules {
+        shorthands,
+        diagnostics: &mut diagnostics,
+    };
+    for _ in RuleListParser::new_for_stylesheet(&mut input, rules) {}
+    diagnostics.sort_by_key(|d| (d.location.line, d.location.column));
+    diagnostics
+}
+
+struct LintRules<'a, 'b> {
+    shorthands: &'a [(&'a str, &'a [&'a str])],
+    diagnostics: &'b mut Vec<LintDiagnostic>,
+}
+
+impl<'a, 'b, 'i> QualifiedRuleParser<'i> for LintRules<'a, 'b> {
+    type Prelude = ();
+    type QualifiedRule = ();
+    type Error = ();
+
+    fn parse_prelude<'t>(&mut self, input: &mut Parser<'i, 't>) -> Result<(), ParseError<'i, ()>> {
+        while input.next().is_ok() {}
+        Ok(())
+    }
+
+    fn parse_block<'t>(
+        &mut self,
+        _: (),
+        _: SourceLocation,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<(), ParseError<'i, ()>> {
+        self.lint_declarations(input);
+        Ok(())
+    }
+}
+
+impl<'a, 'b, 'i> AtRuleParser<'i> for LintRules<'a, 'b> {
+    type PreludeNoBlock = ();
+    type PreludeBlock = AtRuleContents;
+    type AtRule = ();
+    type Error = ();
+
+    fn parse_prelude<'t>(
+        &mut self,
+        name: CowRcStr<'i>,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<AtRuleType<(), AtRuleContents>, ParseError<'i, ()>> {
+        while input.next().is_ok() {}
+        match classify_at_rule(&name) {
+            Some(AtRuleContents::PreludeOnly) => Ok(AtRuleType::WithoutBlock(())),
+            Some(contents) => Ok(AtRuleType::WithBlock(contents)),
+            None => Err(input.new_custom_error(())),
+        }
+    }
+
+    fn parse_block<'t>(
+        &mut self,
+        contents: AtRuleContents,
+        _: SourceLocation,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<(), ParseError<'i, ()>> {
+        match contents {
+            AtRuleContents::Rules => {
+                let rules = LintRules {
+                    shorthands: self.shorthands,
+                    diagnostics: &mut *self.diagnostics,
+                };
+                parse_nested_rules(input, rules, |_, _| {});
+            }
+            AtRuleContents::Declarations => self.lint_declarations(input),
+            AtRuleContents::PreludeOnly => {}
+        }
+        Ok(())
+    }
+}
+
+/// A valid declaration in a block.
+struct Declaration {
+    name: String,
+    important: bool,
+    location: SourceLocation,
+}
+
+impl<'a, 'b> LintRules<'a, 'b> {
+    fn lint_declarations<'i, 't>(&mut self, input: &mut Parser<'i, 't>) {
+        let declarations = parse_declarations(input);
+
+        // The index of the declaration that wins for each property.
+        let mut winners: HashMap<&str, usize> = HashMap::new();
+        for (index, declaration) in declarations.iter().enumerate() {
+            let winner = winners.entry(&declaration.name).or_insert(index);
+            if declaration.important || !declarations[*winner].important {
+                *winner = index
+            }
+        }
+
+        for (index, declaration) in declarations.iter().enumerate() {
+            let winner = winners[&*declaration.name];
+            let overridden_by = if winner != index {
+                Some((LintKind::DuplicateDeclaration, winner))
+            } else {
+                self.overriding_shorthand(&declarations, index)
+                    .map(|shorthand| (LintKind::OverriddenByShorthand, shorthand))
+            };
+            if let Some((kind, other)) = overridden_by {
+                self.diagnostics.push(LintDiagnostic {
+                    kind,
+                    property: declaration.name.clone(),
+                    location: declaration.location,
+                    overridden_by: declarations[other].location,
+                })
+            }
+        }
+    }
+
+    /// The index of the last shorthand declaration that overrides the one at `index`, if any.
+    fn overriding_shorthand(&self, declarations: &[Declaration], index: usize) -> Option<usize> {
+        let longhand = &declarations[index];
+        let overrides = |other: usize| {
+            let shorthand = &declarations[other];
+            if other > index {
+                shorthand.important || !longhand.important
+            } else {
+                shorthand.important && !longhand.important
+            }
+        };
+        declarations
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|&(other, shorthand)| {
+                self.shorthands.iter().any(|&(name, longhands)| {
+                    name.eq_ignore_ascii_case(&shorthand.name)
+                        && longhands
+                            .iter()
+                            .any(|l| l.eq_ignore_ascii_case(&longhand.name))
+                }) && overrides(other)
+            })
+            .map(|(other, _)| other)
+    }
+}
+
+/// Parse the declarations of a block, skipping invalid ones and nested rules.
+fn parse_declarations<'i, 't>(input: &mut Parser<'i, 't>) -> Vec<Declaration> {
+    let mut declarations = Vec::new();
+    loop {
+        input.skip_whitespace();
+        let location = input.current_source_location();
+        let name = match input.next().ok().cloned() {
+            Some(Token::Ident(name)) => Some(name),
+            Some(_) => None,
+            None => break,
+        };
+        let result: Result<_, ParseError<()>> =
+            input.parse_until_after(Delimiter::Semicolon, |input| {
+                let name = name.ok_or_else(|| input.new_custom_error(()))?;
+                input.expect_colon()?;
+                input.parse_until_before(Delimiter::Bang, |input| {
+                    while input.next().is_ok() {}
+                    Ok(())
+                })?;
+                let important = input.try_parse(parse_important).is_ok();
+                Ok((name, important))
+            });
+        if let Ok((name, important)) = result {
+            let name = if name.starts_with("--") {
+                name.to_string()
+            } else {
+                name.to_ascii_lowercase()
+            };
+            declarations.push(Declaration {
+                name,
+                important,
+                location,
+            })
+        }
+    }
+    declarations
+}
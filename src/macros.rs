@@ -52,7 +52,7 @@ macro_rules! match_ignore_ascii_case {
                     $( $( $pattern )+ )+
                 }
             }
-            _cssparser_internal_to_lowercase!($input, cssparser_internal::MAX_LENGTH => lowercase);
+            $crate::_cssparser_internal_to_lowercase!($input, cssparser_internal::MAX_LENGTH => lowercase);
             // "A" is a short string that we know is different for every string pattern,
             // since we’ve verified that none of them include ASCII upper case letters.
             match lowercase.unwrap_or("A") {
@@ -109,12 +109,122 @@ macro_rules! ascii_case_insensitive_phf_map {
                     $key => $value,
                 )*
             };
-            _cssparser_internal_to_lowercase!(input, _cssparser_internal::MAX_LENGTH => lowercase);
+            $crate::_cssparser_internal_to_lowercase!(input, _cssparser_internal::MAX_LENGTH => lowercase);
             lowercase.and_then(|s| MAP.get(s))
         }
     }
 }
 
+/// Define an enum for a keyword-valued property, with one variant per keyword.
+///
+/// The enum gets `from_ident`, `as_str`, and `parse` methods,
+/// and implementations of `FromCss` and `ToCss`.
+/// Keywords are matched case-insensitively in the ASCII range,
+/// and must be written in lower case.
+///
+/// ## Example:
+///
+/// ```rust
+/// #[macro_use] extern crate cssparser;
+/// use cssparser::{Parser, ParserInput, ToCss};
+///
+/// define_css_keyword_enum! {
+///     /// A side of a box.
+///     pub enum Side {
+///         "left" => Left,
+///         "right" => Right,
+///     }
+/// }
+///
+/// # fn main() {
+/// let mut input = ParserInput::new("Right");
+/// let side = Side::parse(&mut Parser::new(&mut input)).unwrap();
+/// assert_eq!(side, Side::Right);
+/// assert_eq!(side.to_css_string(), "right");
+/// # }
+/// ```
+///
+/// The macro can also be imported on its own:
+///
+/// ```rust
+/// use cssparser::define_css_keyword_enum;
+///
+/// define_css_keyword_enum! {
+///     pub enum Direction {
+///         "ltr" => Ltr,
+///         "rtl" => Rtl,
+///     }
+/// }
+///
+/// assert_eq!(Direction::from_ident("RTL"), Some(Direction::Rtl));
+/// ```
+#[macro_export]
+macro_rules! define_css_keyword_enum {
+    (
+        $( #[$meta: meta] )*
+        $vis: vis enum $name: ident {
+            $( $( #[$variant_meta: meta] )* $css: tt => $variant: ident ),+
+            $(,)?
+        }
+    ) => {
+        $( #[$meta] )*
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        $vis enum $name {
+            $(
+                $( #[$variant_meta] )*
+                $variant,
+            )+
+        }
+
+        impl $name {
+            /// Return the value for the given keyword, matched case-insensitively.
+            pub fn from_ident(ident: &str) -> Option<Self> {
+                $crate::match_ignore_ascii_case! { ident,
+                    $( $css => Some($name::$variant), )+
+                    _ => None,
+                }
+            }
+
+            /// Return the keyword, in lower case.
+            pub fn as_str(&self) -> &'static str {
+                match *self {
+                    $( $name::$variant => $css, )+
+                }
+            }
+
+            /// Parse one of the keywords.
+            pub fn parse<'i, 't>(
+                input: &mut $crate::Parser<'i, 't>,
+            ) -> Result<Self, $crate::BasicParseError<'i>> {
+                let location = input.current_source_location();
+                let ident = input.expect_ident()?;
+                match $name::from_ident(ident) {
+                    Some(value) => Ok(value),
+                    None => Err(location
+                        .new_basic_unexpected_token_error($crate::Token::Ident(ident.clone()))),
+                }
+            }
+        }
+
+        impl<'i, E> $crate::FromCss<'i, E> for $name {
+            fn from_css<'t>(
+                input: &mut $crate::Parser<'i, 't>,
+            ) -> Result<Self, $crate::ParseError<'i, E>> {
+                Ok($name::parse(input)?)
+            }
+        }
+
+        impl $crate::ToCss for $name {
+            fn to_css<W>(&self, dest: &mut W) -> ::std::fmt::Result
+            where
+                W: ::std::fmt::Write,
+            {
+                dest.write_str(self.as_str())
+            }
+        }
+    };
+}
+
 /// Implementation detail of match_ignore_ascii_case! and ascii_case_insensitive_phf_map! macros.
 ///
 /// **This macro is not part of the public API. It can change or be removed between any versions.**
@@ -187,3 +297,21 @@ macro_rules! match_byte {
         }
     };
 }
+
+/// Emit a trace event about a parser decision through the `log` facade,
+/// with the `log` feature.
+#[cfg(feature = "log")]
+macro_rules! trace_event {
+    ($($arg: tt)+) => {
+        log::trace!(target: "cssparser", $($arg)+)
+    };
+}
+
+#[cfg(not(feature = "log"))]
+macro_rules! trace_event {
+    ($($arg: tt)+) => {
+        if false {
+            let _ = format_args!($($arg)+);
+        }
+    };
+}
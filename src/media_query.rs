@@ -0,0 +1,567 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! https://drafts.csswg.org/mediaqueries-4/
+
+use crate::dimensions::{Length, LengthUnit, Resolution, ResolutionUnit};
+use crate::parser::{BasicParseError, ParseError, Parser};
+use crate::ratio::Ratio;
+use crate::tokenizer::Token;
+
+/// A media query, such as `screen and (min-width: 600px)`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MediaQuery {
+    /// `only` or `not` before the media type, if any.
+    pub qualifier: Option<MediaQualifier>,
+    /// The media type in lower case, such as `screen`, or `None` for a query
+    /// that only has a condition, such as `(color)`.
+    pub media_type: Option<String>,
+    /// The condition, if any.
+    pub condition: Option<MediaCondition>,
+}
+
+/// A keyword before the media type of a `MediaQuery`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MediaQualifier {
+    /// `only`, which hides the query from legacy user agents but has no effect otherwise.
+    Only,
+    /// `not`, which negates the whole query.
+    Not,
+}
+
+/// A `<media-condition>`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MediaCondition {
+    /// A media feature in parentheses, such as `(width >= 600px)`.
+    Feature(MediaFeature),
+    /// `not …`
+    Not(Box<MediaCondition>),
+    /// `… and …`
+    And(Vec<MediaCondition>),
+    /// `… or …`
+    Or(Vec<MediaCondition>),
+    /// Parentheses or a function with contents of unknown syntax, as written,
+    /// which is reserved for future extensions and evaluates to unknown.
+    GeneralEnclosed(String),
+}
+
+/// A media feature in parentheses, such as `(color)`, `(min-width: 600px)` or `(400px < width < 800px)`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MediaFeature {
+    /// The name of the feature in lower case, without any `min-` or `max-` prefix.
+    pub name: String,
+    /// What the value of the feature is compared to, such as `(GreaterThanOrEqual, 600px)`
+    /// for both `(min-width: 600px)` and `(width >= 600px)`.
+    ///
+    /// This is empty when the feature is evaluated in a boolean context, such as `(color)`,
+    /// and has two comparisons for a range such as `(400px < width < 800px)`.
+    pub comparisons: Vec<(MediaComparison, MediaFeatureValue)>,
+}
+
+/// How the value of a `MediaFeature` is compared.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MediaComparison {
+    /// `=`, or a colon without prefix.
+    Equal,
+    /// `<`
+    LessThan,
+    /// `<=`, or `max-`.
+    LessThanOrEqual,
+    /// `>`
+    GreaterThan,
+    /// `>=`, or `min-`.
+    GreaterThanOrEqual,
+}
+
+impl MediaComparison {
+    /// The comparison with the operands swapped, such that `a < b` is `b > a`.
+    fn flipped(self) -> Self {
+        match self {
+            MediaComparison::Equal => MediaComparison::Equal,
+            MediaComparison::LessThan => MediaComparison::GreaterThan,
+            MediaComparison::LessThanOrEqual => MediaComparison::GreaterThanOrEqual,
+            MediaComparison::GreaterThan => MediaComparison::LessThan,
+            MediaComparison::GreaterThanOrEqual => MediaComparison::LessThanOrEqual,
+        }
+    }
+
+    /// Whether `a` compares to `b` this way.
+    fn holds(self, a: f32, b: f32) -> bool {
+        match self {
+            MediaComparison::Equal => a == b,
+            MediaComparison::LessThan => a < b,
+            MediaComparison::LessThanOrEqual => a <= b,
+            MediaComparison::GreaterThan => a > b,
+            MediaComparison::GreaterThanOrEqual => a >= b,
+        }
+    }
+}
+
+/// The value of a `MediaFeature`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MediaFeatureValue {
+    /// A number, such as `8` in `(min-color: 8)`.
+    Number(f32),
+    /// A length, such as `600px`.
+    Length(Length),
+    /// A ratio with a slash, such as `16 / 9`.
+    Ratio(Ratio),
+    /// A resolution, in dots per CSS pixel: `2dppx`, `2x` and `192dpi` are all 2.
+    Resolution(f32),
+    /// A keyword in lower case, such as `portrait`.
+    Ident(String),
+}
+
+/// The device that media queries are evaluated against, see `MediaQuery::matches`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MediaDevice<'a> {
+    /// The media type in lower case, such as `screen` or `print`.
+    pub media_type: &'a str,
+    /// The width of the viewport or page area, in CSS pixels.
+    pub width: f32,
+    /// The height of the viewport or page area, in CSS pixels.
+    pub height: f32,
+    /// The resolution, in dots per CSS pixel.
+    pub resolution: f32,
+}
+
+/// Parse a comma-separated `<media-query-list>`, such as the prelude of an `@media` rule.
+///
+/// Invalid queries are replaced with `not all`, which never matches,
+/// so that the other queries of the list still apply.
+/// An empty list, which always matches, is returned for empty input.
+pub fn parse_media_query_list<'i, 't>(input: &mut Parser<'i, 't>) -> Vec<MediaQuery> {
+    input.skip_whitespace();
+    if input.is_exhausted() {
+        return Vec::new();
+    }
+    let result: Result<_, ParseError<()>> = input.parse_comma_separated(|input| {
+        let result: Result<_, ParseError<()>> =
+            input.parse_entirely(|input| Ok(MediaQuery::parse(input)?));
+        Ok(result.unwrap_or_else(|_| {
+            while input.next().is_ok() {}
+            MediaQuery {
+                qualifier: Some(MediaQualifier::Not),
+                media_type: Some("all".to_owned()),
+                condition: None,
+            }
+        }))
+    });
+    result.unwrap_or_default()
+}
+
+/// Whether a media query list matches the given device: whether any of its queries does,
+/// or whether it is empty.
+///
+/// See `MediaQuery::matches` for `features`.
+pub fn media_query_list_matches<F>(
+    queries: &[MediaQuery],
+    device: &MediaDevice,
+    mut features: F,
+) -> bool
+where
+    F: FnMut(&MediaFeature) -> bool,
+{
+    queries.is_empty()
+        || queries
+            .iter()
+            .any(|query| query.matches(device, &mut features))
+}
+
+impl MediaQuery {
+    /// Parse a `<media-query>`.
+    pub fn parse<'i, 't>(input: &mut Parser<'i, 't>) -> Result<Self, BasicParseError<'i>> {
+        let start = input.state();
+        let mut qualifier = None;
+        let mut media_type = None;
+        if let Ok(ident) = input.expect_ident_cloned() {
+            let ident = ident.to_ascii_lowercase();
+            qualifier = match &*ident {
+                "only" => Some(MediaQualifier::Only),
+                "not" => Some(MediaQualifier::Not),
+                _ => None,
+            };
+            media_type = match qualifier {
+                Some(_) => input
+                    .try_parse(|input| input.expect_ident_cloned())
+                    .ok()
+                    .map(|ident| ident.to_ascii_lowercase()),
+                None => Some(ident),
+            };
+        }
+        match media_type {
+            // `not (color)` is a condition.
+            None => {
+                input.reset(&start);
+                Ok(MediaQuery {
+                    qualifier: None,
+                    media_type: None,
+                    condition: Some(parse_condition(input, true)?),
+                })
+            }
+            Some(ref media_type) if is_reserved(media_type) => Err(start
+                .source_location()
+                .new_basic_unexpected_token_error(Token::Ident(media_type.clone().into()))),
+            Some(media_type) => {
+                let condition = if input
+                    .try_parse(|input| input.expect_ident_matching("and"))
+                    .is_ok()
+                {
+                    Some(parse_condition(input, false)?)
+                } else {
+                    None
+                };
+                Ok(MediaQuery {
+                    qualifier,
+                    media_type: Some(media_type),
+                    condition,
+                })
+            }
+        }
+    }
+
+    /// Whether this query matches the given device.
+    ///
+    /// The media types `all` and `device.media_type` match.
+    /// The `width`, `height`, `aspect-ratio`, `orientation` and `resolution` features
+    /// are evaluated from the device, where `em` and `rem` are 16px
+    /// and viewport-percentage lengths are relative to the device size.
+    /// Other features are evaluated by `features`.
+    /// Values of the wrong type never match.
+    /// A query whose condition is unknown, see `MediaCondition::evaluate`, does not match
+    /// with or without `not`.
+    pub fn matches<F>(&self, device: &MediaDevice, mut features: F) -> bool
+    where
+        F: FnMut(&MediaFeature) -> bool,
+    {
+        let type_matches = match self.media_type {
+            Some(ref media_type) => media_type == "all" || *media_type == *device.media_type,
+            None => true,
+        };
+        let matches = if type_matches {
+            match self.condition {
+                Some(ref condition) => condition.evaluate(device, &mut features),
+                None => Some(true),
+            }
+        } else {
+            Some(false)
+        };
+        // An unknown result does not match, even when negated.
+        matches.map_or(false, |matches| {
+            matches != (self.qualifier == Some(MediaQualifier::Not))
+        })
+    }
+}
+
+impl MediaCondition {
+    /// Whether this condition matches the given device, see `MediaQuery::matches`.
+    ///
+    /// This is false when the result is unknown, see `evaluate`.
+    pub fn matches<F>(&self, device: &MediaDevice, features: &mut F) -> bool
+    where
+        F: FnMut(&MediaFeature) -> bool,
+    {
+        self.evaluate(device, features) == Some(true)
+    }
+
+    /// Whether this condition matches the given device,
+    /// or `None` if the result is unknown because of `GeneralEnclosed`.
+    ///
+    /// Unknown results propagate with three-valued logic:
+    /// `not` keeps them unknown, `and` is false if any condition is false,
+    /// and `or` is true if any condition is true.
+    pub fn evaluate<F>(&self, device: &MediaDevice, features: &mut F) -> Option<bool>
+    where
+        F: FnMut(&MediaFeature) -> bool,
+    {
+        match *self {
+            MediaCondition::Feature(ref feature) => Some(feature.matches(device, features)),
+            MediaCondition::Not(ref condition) => condition.evaluate(device, features).map(|b| !b),
+            MediaCondition::And(ref conditions) => {
+                let mut result = Some(true);
+                for condition in conditions {
+                    match condition.evaluate(device, features) {
+                        Some(true) => {}
+                        Some(false) => return Some(false),
+                        None => result = None,
+                    }
+                }
+                result
+            }
+            MediaCondition::Or(ref conditions) => {
+                let mut result = Some(false);
+                for condition in conditions {
+                    match condition.evaluate(device, features) {
+                        Some(true) => return Some(true),
+                        Some(false) => {}
+                        None => result = None,
+                    }
+                }
+                result
+            }
+            MediaCondition::GeneralEnclosed(_) => None,
+        }
+    }
+}
+
+impl MediaFeature {
+    fn matches<F>(&self, device: &MediaDevice, features: &mut F) -> bool
+    where
+        F: FnMut(&MediaFeature) -> bool,
+    {
+        let length = |value: &MediaFeatureValue| match *value {
+            MediaFeatureValue::Length(length) => length_to_px(length, device),
+            MediaFeatureValue::Number(value) => Some(value).filter(|&value| value == 0.),
+            _ => None,
+        };
+        let ratio = |value: &MediaFeatureValue| match *value {
+            MediaFeatureValue::Ratio(ratio) if !ratio.is_degenerate() => {
+                Some(ratio.numerator / ratio.denominator)
+            }
+            MediaFeatureValue::Number(value) if value != 0. => Some(value),
+            _ => None,
+        };
+        let resolution = |value: &MediaFeatureValue| match *value {
+            MediaFeatureValue::Resolution(value) => Some(value),
+            _ => None,
+        };
+        let compare = |actual: f32, value: &dyn Fn(&MediaFeatureValue) -> Option<f32>| {
+            self.comparisons.iter().all(|&(comparison, ref expected)| {
+                value(expected).map_or(false, |expected| comparison.holds(actual, expected))
+            })
+        };
+        match &*self.name {
+            "width" => compare(device.width, &length),
+            "height" => compare(device.height, &length),
+            "aspect-ratio" if device.height != 0. => compare(device.width / device.height, &ratio),
+            "aspect-ratio" => false,
+            "resolution" => compare(device.resolution, &resolution),
+            "orientation" => match self.comparisons.first() {
+                None => true,
+                Some(&(MediaComparison::Equal, MediaFeatureValue::Ident(ref orientation))) => {
+                    let portrait = device.height >= device.width;
+                    self.comparisons.len() == 1
+                        && match &**orientation {
+                            "portrait" => portrait,
+                            "landscape" => !portrait,
+                            _ => false,
+                        }
+                }
+                Some(_) => false,
+            },
+            _ => features(self),
+        }
+    }
+}
+
+fn length_to_px(length: Length, device: &MediaDevice) -> Option<f32> {
+    let px_per_unit = match length.unit {
+        LengthUnit::Em | LengthUnit::Rem => 16.,
+        LengthUnit::Vw => device.width / 100.,
+        LengthUnit::Vh => device.height / 100.,
+        LengthUnit::Vmin => device.width.min(device.height) / 100.,
+        LengthUnit::Vmax => device.width.max(device.height) / 100.,
+        unit => unit.px_per_unit()?,
+    };
+    Some(length.value * px_per_unit)
+}
+
+/// Keywords that are not valid media types.
+fn is_reserved(media_type: &str) -> bool {
+    match media_type {
+        "only" | "not" | "and" | "or" | "layer" => true,
+        _ => false,
+    }
+}
+
+/// Parse a `<media-condition>`, or a `<media-condition-without-or>` if `allow_or` is false.
+fn parse_condition<'i, 't>(
+    input: &mut Parser<'i, 't>,
+    allow_or: bool,
+) -> Result<MediaCondition, BasicParseError<'i>> {
+    if input
+        .try_parse(|input| input.expect_ident_matching("not"))
+        .is_ok()
+    {
+        return Ok(MediaCondition::Not(Box::new(parse_in_parens(input)?)));
+    }
+    let first = parse_in_parens(input)?;
+    let state = input.state();
+    let is_and = match input.next().ok().cloned() {
+        Some(Token::Ident(ref ident)) if ident.eq_ignore_ascii_case("and") => true,
+        Some(Token::Ident(ref ident)) if allow_or && ident.eq_ignore_ascii_case("or") => false,
+        _ => {
+            input.reset(&state);
+            return Ok(first);
+        }
+    };
+    let operator = if is_and { "and" } else { "or" };
+    let mut conditions = vec![first, parse_in_parens(input)?];
+    while input
+        .try_parse(|input| input.expect_ident_matching(operator))
+        .is_ok()
+    {
+        conditions.push(parse_in_parens(input)?)
+    }
+    Ok(if is_and {
+        MediaCondition::And(conditions)
+    } else {
+        MediaCondition::Or(conditions)
+    })
+}
+
+/// Parse a `<media-in-parens>`.
+fn parse_in_parens<'i, 't>(
+    input: &mut Parser<'i, 't>,
+) -> Result<MediaCondition, BasicParseError<'i>> {
+    input.skip_whitespace();
+    let start = input.position();
+    let location = input.current_source_location();
+    let is_function = match *input.next()? {
+        Token::ParenthesisBlock => false,
+        Token::Function(_) => true,
+        ref token => return Err(location.new_basic_unexpected_token_error(token.clone())),
+    };
+    let result: Result<_, ParseError<()>> = input.parse_nested_block(|input| {
+        if !is_function {
+            if let Ok(condition) = input.try_parse(|input| {
+                let condition = parse_condition(input, true)?;
+                input.expect_exhausted()?;
+                Ok::<_, BasicParseError>(condition)
+            }) {
+                return Ok(Some(condition));
+            }
+            if let Ok(feature) = input.try_parse(|input| {
+                let feature = parse_feature(input)?;
+                input.expect_exhausted()?;
+                Ok::<_, BasicParseError>(feature)
+            }) {
+                return Ok(Some(MediaCondition::Feature(feature)));
+            }
+        }
+        while input.next().is_ok() {}
+        Ok(None)
+    });
+    Ok(result
+        .map_err(ParseError::basic)?
+        .unwrap_or_else(|| MediaCondition::GeneralEnclosed(input.slice_from(start).to_owned())))
+}
+
+/// Parse the contents of a `<media-feature>`.
+fn parse_feature<'i, 't>(input: &mut Parser<'i, 't>) -> Result<MediaFeature, BasicParseError<'i>> {
+    // `(name)`, `(name: value)` or `(name < value)`.
+    let start = input.state();
+    if let Ok(name) = input.expect_ident_cloned() {
+        let name = name.to_ascii_lowercase();
+        if input.is_exhausted() {
+            return Ok(MediaFeature {
+                name,
+                comparisons: Vec::new(),
+            });
+        }
+        if input.try_parse(|input| input.expect_colon()).is_ok() {
+            let value = parse_value(input)?;
+            let (comparison, name) = if name.starts_with("min-") {
+                (MediaComparison::GreaterThanOrEqual, name[4..].to_owned())
+            } else if name.starts_with("max-") {
+                (MediaComparison::LessThanOrEqual, name[4..].to_owned())
+            } else {
+                (MediaComparison::Equal, name)
+            };
+            return Ok(MediaFeature {
+                name,
+                comparisons: vec![(comparison, value)],
+            });
+        }
+        if let Ok(comparison) = input.try_parse(parse_comparison) {
+            let value = parse_value(input)?;
+            return Ok(MediaFeature {
+                name,
+                comparisons: vec![(comparison, value)],
+            });
+        }
+    }
+    input.reset(&start);
+
+    // `(value < name)` or `(value < name < value)`.
+    let first = parse_value(input)?;
+    let first_comparison = parse_comparison(input)?;
+    let name = input.expect_ident()?.to_ascii_lowercase();
+    let mut comparisons = vec![(first_comparison.flipped(), first)];
+    if !input.is_exhausted() {
+        let location = input.current_source_location();
+        let second_comparison = parse_comparison(input)?;
+        let is_less = |c| c == MediaComparison::LessThan || c == MediaComparison::LessThanOrEqual;
+        let is_greater =
+            |c| c == MediaComparison::GreaterThan || c == MediaComparison::GreaterThanOrEqual;
+        let same_direction = (is_less(first_comparison) && is_less(second_comparison))
+            || (is_greater(first_comparison) && is_greater(second_comparison));
+        if !same_direction {
+            return Err(location.new_basic_unexpected_token_error(Token::Delim('=')));
+        }
+        comparisons.push((second_comparison, parse_value(input)?))
+    }
+    Ok(MediaFeature { name, comparisons })
+}
+
+/// Parse `=`, `<`, `<=`, `>` or `>=`, without whitespace between the two characters.
+fn parse_comparison<'i, 't>(
+    input: &mut Parser<'i, 't>,
+) -> Result<MediaComparison, BasicParseError<'i>> {
+    let location = input.current_source_location();
+    let (less, greater) = match *input.next()? {
+        Token::Delim('=') => return Ok(MediaComparison::Equal),
+        Token::Delim('<') => (MediaComparison::LessThan, MediaComparison::LessThanOrEqual),
+        Token::Delim('>') => (
+            MediaComparison::GreaterThan,
+            MediaComparison::GreaterThanOrEqual,
+        ),
+        ref token => return Err(location.new_basic_unexpected_token_error(token.clone())),
+    };
+    let state = input.state();
+    match input.next_including_whitespace() {
+        Ok(&Token::Delim('=')) => Ok(greater),
+        _ => {
+            input.reset(&state);
+            Ok(less)
+        }
+    }
+}
+
+/// Parse a `<mf-value>`.
+fn parse_value<'i, 't>(
+    input: &mut Parser<'i, 't>,
+) -> Result<MediaFeatureValue, BasicParseError<'i>> {
+    let location = input.current_source_location();
+    let token = input.next()?.clone();
+    match token {
+        Token::Number { value, .. } => {
+            let denominator = input.try_parse(|input| {
+                input.expect_delim('/')?;
+                input.expect_number()
+            });
+            return Ok(match denominator {
+                Ok(denominator) => MediaFeatureValue::Ratio(Ratio {
+                    numerator: value,
+                    denominator,
+                }),
+                Err(_) => MediaFeatureValue::Number(value),
+            });
+        }
+        Token::Dimension {
+            value, ref unit, ..
+        } => {
+            if let Some(unit) = LengthUnit::from_name(unit) {
+                return Ok(MediaFeatureValue::Length(Length { value, unit }));
+            }
+            if let Some(unit) = ResolutionUnit::from_name(unit) {
+                let resolution = Resolution { value, unit };
+                return Ok(MediaFeatureValue::Resolution(resolution.to_dppx()));
+            }
+        }
+        Token::Ident(ref ident) => return Ok(MediaFeatureValue::Ident(ident.to_ascii_lowercase())),
+        _ => {}
+    }
+    Err(location.new_basic_unexpected_token_error(token))
+}
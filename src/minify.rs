@@ -0,0 +1,239 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use crate::parser::{ParseError, Parser};
+use crate::serializer::{serialize_string_with, serialize_unit, QuoteStyle};
+use crate::serializer::{ToCss, TokenSerializationType};
+use crate::tokenizer::{SourceLocation, Token};
+use matches::matches;
+use std::fmt;
+
+/// Options for `serialize_minified`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MinifyOptions {
+    /// The quotes of strings. Defaults to `QuoteStyle::Auto`.
+    pub quote_style: QuoteStyle,
+    /// Whether to write numbers with `serialize_minified_number`,
+    /// and without a `+` sign where possible. Defaults to `true`.
+    ///
+    /// Note that this writes `2.0` as `2`, which is an `<integer>` rather than a `<number>`,
+    /// so this can make an invalid declaration such as `z-index: 2.0` valid.
+    pub trim_numbers: bool,
+}
+
+impl Default for MinifyOptions {
+    fn default() -> Self {
+        MinifyOptions {
+            quote_style: QuoteStyle::Auto,
+            trim_numbers: true,
+        }
+    }
+}
+
+/// Write the rest of `input`, such as a stylesheet or the value of a declaration, minified.
+///
+/// Whitespace and comments are normalized as by `serialize_normalized_value`,
+/// except that whitespace next to `,`, `;`, `{` and `}` is removed,
+/// and tokens are written as configured by `options`.
+/// Other whitespace is kept, since it is significant in selectors and in some values.
+pub fn serialize_minified<'i, 't, W>(
+    input: &mut Parser<'i, 't>,
+    options: &MinifyOptions,
+    dest: &mut W,
+) -> fmt::Result
+where
+    W: fmt::Write,
+{
+    serialize_minified_with(input, options, |_, _| false, dest)
+}
+
+/// Like `serialize_minified`, but write zero dimensions and percentages such as `0px` as `0`
+/// when `can_collapse_zero` returns true for them.
+///
+/// Whether this is safe depends on the grammar of the value:
+/// `margin: 0px` can be `margin: 0`, but `flex: 1 0px` can not be `flex: 1 0`
+/// and `0s` is not a valid `<time>` as `0`, so this is left to the caller,
+/// which is given each zero dimension or percentage with its location.
+pub fn serialize_minified_with<'i, 't, W, F>(
+    input: &mut Parser<'i, 't>,
+    options: &MinifyOptions,
+    mut can_collapse_zero: F,
+    dest: &mut W,
+) -> fmt::Result
+where
+    W: fmt::Write,
+    F: FnMut(&Token<'i>, SourceLocation) -> bool,
+{
+    minify(input, options, &mut can_collapse_zero, dest)
+}
+
+fn minify<'i, 't, W, F>(
+    input: &mut Parser<'i, 't>,
+    options: &MinifyOptions,
+    can_collapse_zero: &mut F,
+    dest: &mut W,
+) -> fmt::Result
+where
+    W: fmt::Write,
+    F: FnMut(&Token<'i>, SourceLocation) -> bool,
+{
+    let mut previous_token = TokenSerializationType::nothing();
+    let mut previous_is_separator = false;
+    let mut whitespace_before = false;
+    let mut comment_before = false;
+    loop {
+        let location = input.current_source_location();
+        let token = match input.next_including_whitespace_and_comments() {
+            Ok(token) => token,
+            Err(_) => return Ok(()),
+        };
+        let token = match *token {
+            Token::WhiteSpace(_) => {
+                whitespace_before = true;
+                continue;
+            }
+            Token::Comment(_) => {
+                comment_before = true;
+                continue;
+            }
+            ref token => token.clone(),
+        };
+        let token_type = token.serialization_type();
+        let is_separator = matches!(
+            token,
+            Token::Comma | Token::Semicolon | Token::CurlyBracketBlock
+        );
+        let mut separated = false;
+        let needs_separator = previous_token.needs_separator_when_before(token_type);
+        if previous_token != TokenSerializationType::nothing() {
+            if whitespace_before && !previous_is_separator && !is_separator {
+                dest.write_str(" ")?;
+                separated = true;
+            } else if (whitespace_before || comment_before) && needs_separator {
+                dest.write_str("/**/")?;
+                separated = true;
+            }
+        }
+        whitespace_before = false;
+        comment_before = false;
+        previous_token = token_type;
+        previous_is_separator = is_separator;
+        // `2n+1` would be `2n1` without the sign.
+        let keep_sign = !separated && needs_separator;
+        let is_zero = match token {
+            Token::Dimension { value, .. } => value == 0.0,
+            Token::Percentage { unit_value, .. } => unit_value == 0.0,
+            _ => false,
+        };
+        if is_zero && can_collapse_zero(&token, location) {
+            dest.write_str("0")?;
+            continue;
+        }
+        write_minified_token(&token, options, keep_sign, dest)?;
+
+        let closing_token = match token {
+            Token::Function(_) | Token::ParenthesisBlock => Token::CloseParenthesis,
+            Token::SquareBracketBlock => Token::CloseSquareBracket,
+            Token::CurlyBracketBlock => Token::CloseCurlyBracket,
+            _ => continue,
+        };
+        let result: Result<(), ParseError<()>> = input.parse_nested_block(|input| {
+            minify(input, options, can_collapse_zero, dest)
+                .map_err(|fmt::Error| input.new_custom_error(()))
+        });
+        result.map_err(|_| fmt::Error)?;
+        closing_token.to_css(dest)?;
+        previous_token = closing_token.serialization_type();
+        previous_is_separator = closing_token == Token::CloseCurlyBracket;
+    }
+}
+
+fn write_minified_token<W>(
+    token: &Token,
+    options: &MinifyOptions,
+    keep_sign: bool,
+    dest: &mut W,
+) -> fmt::Result
+where
+    W: fmt::Write,
+{
+    let write_sign = |has_sign: bool, value: f32, dest: &mut W| {
+        if keep_sign && has_sign && value.is_sign_positive() {
+            dest.write_str("+")
+        } else {
+            Ok(())
+        }
+    };
+    match *token {
+        Token::QuotedString(ref value) => serialize_string_with(value, options.quote_style, dest),
+        Token::Number {
+            value,
+            has_sign,
+            int_value,
+        } if options.trim_numbers => {
+            write_sign(has_sign, value, dest)?;
+            // A number with an exponent is not an `<integer>`, such as for `z-index`.
+            write_minified_number(value, int_value.is_none(), dest)
+        }
+        Token::Percentage {
+            unit_value,
+            has_sign,
+            ..
+        } if options.trim_numbers => {
+            write_sign(has_sign, unit_value, dest)?;
+            serialize_minified_number(unit_value * 100., dest)?;
+            dest.write_str("%")
+        }
+        Token::Dimension {
+            value,
+            has_sign,
+            ref unit,
+            ..
+        } if options.trim_numbers => {
+            write_sign(has_sign, value, dest)?;
+            serialize_minified_number(value, dest)?;
+            serialize_unit(unit, dest)
+        }
+        ref token => token.to_css(dest),
+    }
+}
+
+/// Write a number in its shortest form, such as `.5` for `0.5`, `-.5` for `-0.5`
+/// `2` for `2.0` and `1e20` for `100000000000000000000`, never with a `+` sign.
+///
+/// Unlike the serialization of `Token::Number`, this does not preserve
+/// whether the number was written as an integer,
+/// so it is not suitable for `<integer>` values such as `1000`.
+/// `serialize_minified` does not use the exponent form for those.
+pub fn serialize_minified_number<W>(value: f32, dest: &mut W) -> fmt::Result
+where
+    W: fmt::Write,
+{
+    write_minified_number(value, true, dest)
+}
+
+fn write_minified_number<W>(value: f32, allow_exponent: bool, dest: &mut W) -> fmt::Result
+where
+    W: fmt::Write,
+{
+    if value == 0.0 {
+        // Negative zero. Work around #20596.
+        return dest.write_str(if value.is_sign_negative() { "-0" } else { "0" });
+    }
+    let mut number = String::new();
+    dtoa_short::write(&mut number, value)?;
+    let zeros = number.len() - number.trim_end_matches('0').len();
+    if allow_exponent && zeros >= 3 && !number.contains('.') && !number.contains('e') {
+        // `1e3` rather than `1000`.
+        dest.write_str(&number[..number.len() - zeros])?;
+        write!(dest, "e{}", zeros)
+    } else if number.starts_with("0.") {
+        dest.write_str(&number[1..])
+    } else if number.starts_with("-0.") {
+        dest.write_str("-")?;
+        dest.write_str(&number[2..])
+    } else {
+        dest.write_str(&number)
+    }
+}
@@ -0,0 +1,198 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use crate::at_rules::{classify_at_rule, AtRuleContents};
+use crate::cow_rc_str::CowRcStr;
+use crate::parser::{Delimiter, ParseError, Parser, ParserInput};
+use crate::tokenizer::{SourceLocation, Token, Tokenizer};
+
+/// A rule of a stylesheet, found by `document_symbols`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DocumentSymbol<'i> {
+    /// The name of an at-rule, without the `@` marker, or `None` for a qualified rule.
+    pub at_keyword: Option<CowRcStr<'i>>,
+    /// The prelude as written, without surrounding whitespace,
+    /// such as a selector or the media query list of `@media`.
+    pub prelude: &'i str,
+    /// The number of rules that this rule is nested in.
+    pub depth: usize,
+    /// The location of the start of the rule.
+    pub start: SourceLocation,
+    /// The location just after the end of the rule, after its block or `;`.
+    pub end: SourceLocation,
+    /// The location of the prelude.
+    pub prelude_location: SourceLocation,
+}
+
+/// Return an outline of a stylesheet: its rules, including nested ones, in source order.
+///
+/// Rules are nested in the blocks of at-rules that contain rules, such as `@media`,
+/// and in declaration blocks, such as nested style rules or the margin rules of `@page`.
+/// The blocks of at-rules of unknown syntax are not looked into.
+/// The outline of a rule is given by the symbols that follow it with a larger `depth`,
+/// as with `documentSymbol` in the Language Server Protocol.
+pub fn document_symbols(css: &str) -> Vec<DocumentSymbol<'_>> {
+    let mut input = ParserInput::new(css);
+    let mut input = Parser::new(&mut input);
+    let mut symbols = Vec::new();
+    collect_symbols(&mut input, 0, false, &mut symbols);
+    symbols
+}
+
+fn collect_symbols<'i, 't>(
+    input: &mut Parser<'i, 't>,
+    depth: usize,
+    in_declarations: bool,
+    symbols: &mut Vec<DocumentSymbol<'i>>,
+) {
+    loop {
+        input.skip_whitespace();
+        let start = input.state();
+        let at_keyword = match input.next() {
+            Ok(&Token::CDO) | Ok(&Token::CDC) if !in_declarations => continue,
+            Ok(Token::AtKeyword(name)) => Some(name.clone()),
+            Ok(Token::Ident(name)) if in_declarations && name.starts_with("--") => {
+                // A custom property, whose value can contain `{}` blocks.
+                let _: Result<_, ParseError<()>> =
+                    input.parse_until_after(Delimiter::Semicolon, |input| {
+                        while input.next().is_ok() {}
+                        Ok(())
+                    });
+                continue;
+            }
+            Ok(_) => None,
+            Err(_) => return,
+        };
+        if at_keyword.is_none() {
+            input.reset(&start);
+        }
+        let delimiters = if at_keyword.is_some() || in_declarations {
+            Delimiter::Semicolon | Delimiter::CurlyBracketBlock
+        } else {
+            Delimiter::CurlyBracketBlock
+        };
+        let result: Result<_, ParseError<()>> = input.parse_until_before(delimiters, |input| {
+            input.skip_whitespace();
+            let prelude_start = input.position();
+            let location = input.current_source_location();
+            while input.next().is_ok() {}
+            Ok((input.slice_from(prelude_start).trim_end(), location))
+        });
+        let (prelude, prelude_location) = result.unwrap();
+        let has_block = match input.next() {
+            Ok(&Token::CurlyBracketBlock) => true,
+            // A declaration.
+            _ if at_keyword.is_none() => continue,
+            _ => false,
+        };
+        let index = symbols.len();
+        let location = input.source_location_of(&start);
+        symbols.push(DocumentSymbol {
+            at_keyword,
+            prelude,
+            depth,
+            start: location,
+            end: location,
+            prelude_location,
+        });
+        if has_block {
+            let contents = match symbols[index].at_keyword {
+                Some(ref name) => classify_at_rule(name),
+                None => Some(AtRuleContents::Declarations),
+            };
+            let in_declarations = match contents {
+                // Nested group rules such as `@media` contain declarations.
+                Some(AtRuleContents::Rules) => in_declarations,
+                Some(AtRuleContents::Declarations) => true,
+                _ => false,
+            };
+            let recurse = contents.is_some() && contents != Some(AtRuleContents::PreludeOnly);
+            let _: Result<_, ParseError<()>> = input.parse_nested_block(|input| {
+                if recurse {
+                    collect_symbols(input, depth + 1, in_declarations, symbols)
+                }
+                while input.next().is_ok() {}
+                Ok(())
+            });
+        }
+        symbols[index].end = input.current_source_location();
+    }
+}
+
+/// A range of lines that an editor can fold, found by `folding_ranges`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FoldingRange {
+    /// What the lines are.
+    pub kind: FoldingRangeKind,
+    /// The first line, 0-based like `SourceLocation::line`, with the `{` or the start of the comment.
+    pub start_line: u32,
+    /// The last line, with the `}` or the end of the comment.
+    pub end_line: u32,
+}
+
+/// The kind of a `FoldingRange`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FoldingRangeKind {
+    /// A `{}` block, such as of a rule.
+    Block,
+    /// A comment.
+    Comment,
+}
+
+/// Return the `{}` blocks and comments of a stylesheet that span several lines,
+/// in the order of their start, in a single pass of the tokenizer.
+///
+/// A block that is not closed ends at the last line of the input.
+pub fn folding_ranges(css: &str) -> Vec<FoldingRange> {
+    let mut tokenizer = Tokenizer::new(css);
+    let mut ranges = Vec::new();
+    // The `()`, `[]` and `{}` blocks, with the index in `ranges` of each `{}` block.
+    let mut blocks = Vec::new();
+    loop {
+        let start_line = tokenizer.current_source_location().line;
+        let token = match tokenizer.next() {
+            Ok(token) => token,
+            Err(()) => break,
+        };
+        let end_line = tokenizer.current_source_location().line;
+        match token {
+            Token::Comment(_) if end_line > start_line => ranges.push(FoldingRange {
+                kind: FoldingRangeKind::Comment,
+                start_line,
+                end_line,
+            }),
+            Token::Function(_) | Token::ParenthesisBlock => blocks.push((')', None)),
+            Token::SquareBracketBlock => blocks.push((']', None)),
+            Token::CurlyBracketBlock => {
+                blocks.push(('}', Some(ranges.len())));
+                ranges.push(FoldingRange {
+                    kind: FoldingRangeKind::Block,
+                    start_line,
+                    end_line: start_line,
+                })
+            }
+            Token::CloseParenthesis | Token::CloseSquareBracket | Token::CloseCurlyBracket => {
+                let closing = match token {
+                    Token::CloseParenthesis => ')',
+                    Token::CloseSquareBracket => ']',
+                    _ => '}',
+                };
+                if blocks.last().map(|&(c, _)| c) == Some(closing) {
+                    if let Some((_, Some(index))) = blocks.pop() {
+                        ranges[index].end_line = end_line
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    let last_line = tokenizer.current_source_location().line;
+    for &(_, index) in &blocks {
+        if let Some(index) = index {
+            ranges[index].end_line = last_line
+        }
+    }
+    ranges.retain(|range| range.end_line > range.start_line);
+    ranges
+}
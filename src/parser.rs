@@ -3,8 +3,20 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use crate::cow_rc_str::CowRcStr;
-use crate::tokenizer::{SourceLocation, SourcePosition, Token, Tokenizer};
+use crate::error_reporting::{ErrorReporting, ParseErrorReporter};
+use crate::interner::{intern_token, StringInterner};
+use crate::serializer::{serialize_identifier, ToCss};
+use crate::tokenizer::{
+    is_css_whitespace, ColumnUnit, SourceLocation, SourcePosition, Token, Tokenizer,
+};
+use crate::url_rewriting::{UnresolvedUrl, UrlResolver, UrlRewriter};
+use crate::Void;
+use matches::matches;
 use smallvec::SmallVec;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::mem;
 use std::ops::BitOr;
 use std::ops::Range;
 
@@ -29,6 +41,9 @@ impl ParserState {
     }
 
     /// The line number and column number
+    ///
+    /// Column numbers are always counted in UTF-16 code units here.
+    /// `Parser::source_location_of` takes the parser’s input options into account.
     #[inline]
     pub fn source_location(&self) -> SourceLocation {
         SourceLocation {
@@ -51,6 +66,19 @@ pub enum BasicParseErrorKind<'i> {
     AtRuleBodyInvalid,
     /// A qualified rule was encountered that was invalid.
     QualifiedRuleInvalid,
+    /// A `!` delimiter was followed by an identifier other than `important`,
+    /// such as `!ie` in some legacy stylesheets.
+    InvalidImportant(CowRcStr<'i>),
+    /// An `@charset` rule in a stylesheet, other than exactly `@charset "…";`
+    /// as the first rule.
+    ///
+    /// `@charset` is only used to determine the encoding, see `stylesheet_encoding`,
+    /// and is otherwise not a valid rule.
+    InvalidCharsetRule,
+    /// Never returned. Matches on `BasicParseErrorKind` need a wildcard arm,
+    /// so that new variants can be added without a breaking change.
+    #[doc(hidden)]
+    __Nonexhaustive(Void),
 }
 
 /// The funamental parsing errors that can be triggered by built-in parsing routines.
@@ -62,6 +90,57 @@ pub struct BasicParseError<'i> {
     pub location: SourceLocation,
 }
 
+impl<'i> fmt::Display for BasicParseErrorKind<'i> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BasicParseErrorKind::UnexpectedToken(ref token) => {
+                f.write_str("unexpected ")?;
+                match *token {
+                    Token::WhiteSpace(_)
+                    | Token::Comment(_)
+                    | Token::BadUrl(_)
+                    | Token::BadString(_) => f.write_str(token.kind().description()),
+                    _ => write!(f, "‘{}’", token.to_css_string()),
+                }
+            }
+            BasicParseErrorKind::EndOfInput => f.write_str("unexpected end of input"),
+            BasicParseErrorKind::AtRuleInvalid(ref name) => {
+                f.write_str("invalid at-rule ‘@")?;
+                serialize_identifier(name, f)?;
+                f.write_str("’")
+            }
+            BasicParseErrorKind::AtRuleBodyInvalid => f.write_str("invalid at-rule body"),
+            BasicParseErrorKind::QualifiedRuleInvalid => f.write_str("invalid qualified rule"),
+            BasicParseErrorKind::InvalidImportant(ref name) => {
+                f.write_str("invalid importance ‘!")?;
+                serialize_identifier(name, f)?;
+                f.write_str("’")
+            }
+            BasicParseErrorKind::InvalidCharsetRule => f.write_str(
+                "‘@charset’ is only valid as ‘@charset \"…\";’ at the start of a stylesheet",
+            ),
+            BasicParseErrorKind::__Nonexhaustive(void) => match void {},
+        }
+    }
+}
+
+impl<'i> BasicParseError<'i> {
+    /// Whether the two errors are of the same kind, wherever they occurred.
+    pub fn eq_ignoring_location(&self, other: &Self) -> bool {
+        self.kind == other.kind
+    }
+}
+
+/// Formats as the kind of error followed by its location,
+/// for example “unexpected ‘}’ at 3:14”.
+impl<'i> fmt::Display for BasicParseError<'i> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at {}", self.kind, self.location)
+    }
+}
+
+impl<'i> Error for BasicParseError<'i> {}
+
 impl<'i, T> From<BasicParseError<'i>> for ParseError<'i, T> {
     #[inline]
     fn from(this: BasicParseError<'i>) -> ParseError<'i, T> {
@@ -110,6 +189,15 @@ pub enum ParseErrorKind<'i, T: 'i> {
     Custom(T),
 }
 
+impl<'i, T: fmt::Display> fmt::Display for ParseErrorKind<'i, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseErrorKind::Basic(ref basic) => basic.fmt(f),
+            ParseErrorKind::Custom(ref custom) => custom.fmt(f),
+        }
+    }
+}
+
 impl<'i, T> ParseErrorKind<'i, T> {
     /// Like `std::convert::Into::into`
     pub fn into<U>(self) -> ParseErrorKind<'i, U>
@@ -154,12 +242,68 @@ impl<'i, T> ParseError<'i, T> {
             location: self.location,
         }
     }
+
+    /// Whether the two errors are of the same kind, wherever they occurred.
+    pub fn eq_ignoring_location(&self, other: &Self) -> bool
+    where
+        T: PartialEq,
+    {
+        self.kind == other.kind
+    }
+}
+
+/// Formats as the kind of error followed by its location,
+/// for example “unexpected ‘}’ at 3:14”.
+impl<'i, T: fmt::Display> fmt::Display for ParseError<'i, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at {}", self.kind, self.location)
+    }
+}
+
+impl<'i, T: fmt::Debug + fmt::Display> Error for ParseError<'i, T> {}
+
+/// A value that can be parsed from CSS, the counterpart of `ToCss`.
+///
+/// `E` is the custom error type of the parser that this value is used in.
+/// Values that only return basic errors implement this for any `E`,
+/// so that for example `input.parse_comma_separated(Color::from_css)`
+/// works in parsers with their own error type.
+pub trait FromCss<'i, E = ()>: Sized {
+    /// Parse one value, consuming the tokens that make it up.
+    fn from_css<'t>(input: &mut Parser<'i, 't>) -> Result<Self, ParseError<'i, E>>;
+}
+
+/// A block or function that was closed by the end of the input,
+/// see `ParserInput::take_unclosed_blocks`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UnclosedBlock {
+    /// The location of the `(`, `[` or `{` that opens the block,
+    /// which is the last character of a `Function` token.
+    pub location: SourceLocation,
+    /// The character that would close the block: `)`, `]` or `}`.
+    pub closing: char,
 }
 
 /// The owned input for a parser.
+///
+/// A `ParserInput` is not `Send`, even with the `sync` Cargo feature:
+/// the interner, URL rewriter and error reporter that it can hold are not required to be,
+/// so that they can share state with `Rc`.
+/// Parse each stylesheet on the thread that created its input.
 pub struct ParserInput<'i> {
     tokenizer: Tokenizer<'i>,
     cached_token: Option<CachedToken<'i>>,
+    interner: Option<Box<dyn StringInterner>>,
+    url_rewriter: Option<Box<UrlRewriter>>,
+    error_reporting: Option<Box<ErrorReporting>>,
+    unclosed_blocks: Option<Box<UnclosedBlocks>>,
+}
+
+#[derive(Default)]
+struct UnclosedBlocks {
+    blocks: Vec<UnclosedBlock>,
+    /// The positions of the opening tokens of all blocks recorded so far.
+    positions: HashSet<usize>,
 }
 
 struct CachedToken<'i> {
@@ -174,6 +318,10 @@ impl<'i> ParserInput<'i> {
         ParserInput {
             tokenizer: Tokenizer::new(input),
             cached_token: None,
+            interner: None,
+            url_rewriter: None,
+            error_reporting: None,
+            unclosed_blocks: None,
         }
     }
 
@@ -183,7 +331,157 @@ impl<'i> ParserInput<'i> {
         ParserInput {
             tokenizer: Tokenizer::with_first_line_number(input, first_line_number),
             cached_token: None,
+            interner: None,
+            url_rewriter: None,
+            error_reporting: None,
+            unclosed_blocks: None,
+        }
+    }
+
+    /// Create a new input for a parser whose tokens have their heap-allocated
+    /// values passed through the given interner.
+    ///
+    /// Pass a `Rc<RefCell<Interner>>` to share the same interner between several inputs
+    /// on the same thread. The interner does not need to be `Send`, see `ParserInput`.
+    pub fn new_with_interner(input: &'i str, interner: Box<dyn StringInterner>) -> ParserInput<'i> {
+        ParserInput {
+            tokenizer: Tokenizer::new(input),
+            cached_token: None,
+            interner: Some(interner),
+            url_rewriter: None,
+            error_reporting: None,
+            unclosed_blocks: None,
+        }
+    }
+
+    /// Have URLs pass through the given callback before they are returned in tokens.
+    ///
+    /// The callback is given the URL of each `UnquotedUrl` token
+    /// and of each `QuotedString` token that is the argument of a `url()` function,
+    /// with the location of the token.
+    /// It can return a new URL, for example resolved against the stylesheet’s base URL,
+    /// or `None` to keep the URL as is.
+    /// It may be called again for the same token if the parser backtracks with `Parser::reset`.
+    pub fn set_url_rewriter<F>(&mut self, rewrite: F)
+    where
+        F: FnMut(&str, SourceLocation) -> Option<String> + 'static,
+    {
+        self.url_rewriter = Some(Box::new(UrlRewriter::new(rewrite)))
+    }
+
+    /// Have URLs resolved against the given base URL before they are returned in tokens,
+    /// for example to make them absolute.
+    ///
+    /// This applies to the same URLs as `set_url_rewriter`.
+    /// URLs that could not be resolved are left unchanged and recorded,
+    /// see `ParserInput::take_unresolved_urls`.
+    pub fn set_base_url<R>(&mut self, base_url: &str, resolver: R)
+    where
+        R: UrlResolver + 'static,
+    {
+        self.url_rewriter = Some(Box::new(UrlRewriter::with_base_url(
+            base_url.to_owned(),
+            resolver,
+        )))
+    }
+
+    /// Return the URLs that could not be resolved against the base URL so far,
+    /// and forget them.
+    pub fn take_unresolved_urls(&mut self) -> Vec<UnresolvedUrl> {
+        match self.url_rewriter {
+            Some(ref mut url_rewriter) => url_rewriter.take_unresolved_urls(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Record the blocks and functions that are closed by the end of the input,
+    /// rather than by their closing token, see `ParserInput::take_unclosed_blocks`.
+    ///
+    /// This is disabled by default, since it makes consuming blocks slower.
+    /// Disabling it forgets the blocks recorded so far.
+    pub fn set_record_unclosed_blocks(&mut self, enabled: bool) {
+        if !enabled {
+            self.unclosed_blocks = None
+        } else if self.unclosed_blocks.is_none() {
+            self.unclosed_blocks = Some(Box::new(UnclosedBlocks::default()))
+        }
+    }
+
+    /// Return the blocks and functions that were closed by the end of the input so far,
+    /// rather than by their closing token, and forget them.
+    ///
+    /// This is always empty unless enabled with `ParserInput::set_record_unclosed_blocks`.
+    ///
+    /// The parser consumes such blocks as if they were closed, as the CSS Syntax specification requires.
+    /// This lets editors show where the unclosed blocks start,
+    /// and serializers append the missing closing characters, in the order they are returned.
+    pub fn take_unclosed_blocks(&mut self) -> Vec<UnclosedBlock> {
+        match self.unclosed_blocks {
+            Some(ref mut unclosed_blocks) => mem::replace(&mut unclosed_blocks.blocks, Vec::new()),
+            None => Vec::new(),
+        }
+    }
+
+    /// Have the errors that `RuleListParser` and `DeclarationListParser` recover from
+    /// passed to the given reporter, with `url` as the location of the stylesheet.
+    ///
+    /// This includes nested rule and declaration lists parsed from the same input.
+    pub fn set_error_reporter<R>(&mut self, url: Option<&str>, reporter: R)
+    where
+        R: ParseErrorReporter + 'static,
+    {
+        self.error_reporting = Some(Box::new(ErrorReporting::new(
+            url.map(str::to_owned),
+            Box::new(reporter),
+        )))
+    }
+
+    /// Count the column numbers of source locations in the given unit.
+    pub fn set_column_unit(&mut self, unit: ColumnUnit) {
+        self.tokenizer.set_column_unit(unit)
+    }
+
+    /// Count a tab as advancing the column number to the next multiple of `width`
+    /// (plus one, since columns start at 1), instead of as one character.
+    ///
+    /// A `width` of 0 restores the default.
+    pub fn set_tab_width(&mut self, width: u8) {
+        self.tokenizer.set_tab_width(width)
+    }
+
+    /// Enable tokenization quirks of legacy browsers that compatibility layers need:
+    /// `progid:` filters are `Token::ProgId` and `expression()` values are `Token::Expression`,
+    /// with their source text kept as written.
+    ///
+    /// These are tokenized as usual by default, per the CSS Syntax specification.
+    /// The two token variants are new in version 0.28.0 and never returned unless this is enabled,
+    /// so existing tokenizer users see the same tokens as before.
+    pub fn set_compat_quirks(&mut self, enabled: bool) {
+        self.tokenizer.set_compat_quirks(enabled)
+    }
+
+    fn record_unclosed_block(&mut self, block_type: BlockType, opened: &ParserState) {
+        let unclosed_blocks = match self.unclosed_blocks {
+            Some(ref mut unclosed_blocks) => unclosed_blocks,
+            None => return,
+        };
+        // The same block is consumed again if the parser backtracks.
+        if !unclosed_blocks.positions.insert(opened.position) {
+            return;
         }
+        let mut location = self.tokenizer.source_location_of(opened);
+        // The last character of the opening token.
+        location.column -= 1;
+        let block = UnclosedBlock {
+            location,
+            closing: match block_type {
+                BlockType::Parenthesis => ')',
+                BlockType::SquareBracket => ']',
+                BlockType::CurlyBracket => '}',
+            },
+        };
+        trace_event!("unclosed block at {}", location);
+        unclosed_blocks.blocks.push(block)
     }
 
     #[inline]
@@ -201,6 +499,8 @@ pub struct Parser<'i, 't> {
     at_start_of: Option<BlockType>,
     /// For parsers from `parse_until` or `parse_nested_block`
     stop_before: Delimiters,
+    /// For parsers from `split_top_level`
+    stop_before_delims: DelimSet,
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
@@ -300,6 +600,47 @@ impl Delimiters {
     }
 }
 
+/// A set of the ASCII punctuation characters that can be `Delim` tokens,
+/// for parsers from `split_top_level`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+struct DelimSet {
+    bits: u32,
+}
+
+impl DelimSet {
+    const NONE: DelimSet = DelimSet { bits: 0 };
+
+    /// The set of only the given byte, or an empty set if it is not ASCII punctuation.
+    #[inline]
+    fn from_byte(byte: Option<u8>) -> DelimSet {
+        // The 32 ASCII punctuation characters, in four ranges.
+        let bits = match byte {
+            Some(byte @ b'!'..=b'/') => 1 << (byte - b'!'),
+            Some(byte @ b':'..=b'@') => 1 << (byte - b':' + 15),
+            Some(byte @ b'['..=b'`') => 1 << (byte - b'[' + 22),
+            Some(byte @ b'{'..=b'~') => 1 << (byte - b'{' + 28),
+            _ => 0,
+        };
+        DelimSet { bits }
+    }
+
+    #[inline]
+    fn contains(self, other: DelimSet) -> bool {
+        (self.bits & other.bits) != 0
+    }
+}
+
+impl BitOr<DelimSet> for DelimSet {
+    type Output = DelimSet;
+
+    #[inline]
+    fn bitor(self, other: DelimSet) -> DelimSet {
+        DelimSet {
+            bits: self.bits | other.bits,
+        }
+    }
+}
+
 /// Used in some `fn expect_*` methods
 macro_rules! expect {
     ($parser: ident, $($branches: tt)+) => {
@@ -323,6 +664,7 @@ impl<'i: 't, 't> Parser<'i, 't> {
             input: input,
             at_start_of: None,
             stop_before: Delimiter::None,
+            stop_before_delims: DelimSet::NONE,
         }
     }
 
@@ -346,15 +688,14 @@ impl<'i: 't, 't> Parser<'i, 't> {
     #[inline]
     pub fn expect_exhausted(&mut self) -> Result<(), BasicParseError<'i>> {
         let start = self.state();
+        let start_location = self.current_source_location();
         let result = match self.next() {
             Err(BasicParseError {
                 kind: BasicParseErrorKind::EndOfInput,
                 ..
             }) => Ok(()),
             Err(e) => unreachable!("Unexpected error encountered: {:?}", e),
-            Ok(t) => Err(start
-                .source_location()
-                .new_basic_unexpected_token_error(t.clone())),
+            Ok(t) => Err(start_location.new_basic_unexpected_token_error(t.clone())),
         };
         self.reset(&start);
         result
@@ -368,12 +709,26 @@ impl<'i: 't, 't> Parser<'i, 't> {
         self.input.tokenizer.position()
     }
 
+    /// Pass an error to the reporter set with `ParserInput::set_error_reporter`, if any.
+    pub(crate) fn report_error<E>(&self, error: &ParseError<'i, E>, what: &str) {
+        if let Some(ref error_reporting) = self.input.error_reporting {
+            error_reporting.report(error, what)
+        }
+    }
+
     /// The current line number and column number.
     #[inline]
     pub fn current_source_location(&self) -> SourceLocation {
         self.input.tokenizer.current_source_location()
     }
 
+    /// The line number and column number of a state previously obtained
+    /// from the `Parser::state` method.
+    #[inline]
+    pub fn source_location_of(&self, state: &ParserState) -> SourceLocation {
+        self.input.tokenizer.source_location_of(state)
+    }
+
     /// The source map URL, if known.
     ///
     /// The source map URL is extracted from a specially formatted
@@ -453,7 +808,7 @@ impl<'i: 't, 't> Parser<'i, 't> {
     #[inline]
     pub fn skip_whitespace(&mut self) {
         if let Some(block_type) = self.at_start_of.take() {
-            consume_until_end_of_block(block_type, &mut self.input.tokenizer);
+            consume_until_end_of_block(block_type, self.input);
         }
 
         self.input.tokenizer.skip_whitespace()
@@ -462,7 +817,7 @@ impl<'i: 't, 't> Parser<'i, 't> {
     #[inline]
     pub(crate) fn skip_cdc_and_cdo(&mut self) {
         if let Some(block_type) = self.at_start_of.take() {
-            consume_until_end_of_block(block_type, &mut self.input.tokenizer);
+            consume_until_end_of_block(block_type, self.input);
         }
 
         self.input.tokenizer.skip_cdc_and_cdo()
@@ -578,13 +933,18 @@ impl<'i: 't, 't> Parser<'i, 't> {
         &mut self,
     ) -> Result<&Token<'i>, BasicParseError<'i>> {
         if let Some(block_type) = self.at_start_of.take() {
-            consume_until_end_of_block(block_type, &mut self.input.tokenizer);
+            consume_until_end_of_block(block_type, self.input);
         }
 
         let byte = self.input.tokenizer.next_byte();
         if self.stop_before.contains(Delimiters::from_byte(byte)) {
             return Err(self.new_basic_error(BasicParseErrorKind::EndOfInput));
         }
+        if self.stop_before_delims.contains(DelimSet::from_byte(byte))
+            && at_delim(&mut self.input.tokenizer)
+        {
+            return Err(self.new_basic_error(BasicParseErrorKind::EndOfInput));
+        }
 
         let token_start_position = self.input.tokenizer.position();
         let using_cached_token = self
@@ -601,13 +961,29 @@ impl<'i: 't, 't> Parser<'i, 't> {
                 Token::Function(ref name) => self.input.tokenizer.see_function(name),
                 _ => {}
             }
+            if let Some(ref mut url_rewriter) = self.input.url_rewriter {
+                url_rewriter.see_token(&cached_token.token, cached_token.end_state.position());
+            }
             &cached_token.token
         } else {
-            let new_token = self
+            let token_start_location = match self.input.url_rewriter {
+                Some(_) => Some(self.input.tokenizer.current_source_location()),
+                None => None,
+            };
+            let mut new_token = self
                 .input
                 .tokenizer
                 .next()
                 .map_err(|()| self.new_basic_error(BasicParseErrorKind::EndOfInput))?;
+            if let Some(ref mut interner) = self.input.interner {
+                new_token = intern_token(new_token, &mut **interner);
+            }
+            if let Some(ref mut url_rewriter) = self.input.url_rewriter {
+                let tokenizer = &self.input.tokenizer;
+                let start = (token_start_position, token_start_location.unwrap());
+                new_token = url_rewriter.rewrite_token(new_token, start, tokenizer);
+                url_rewriter.see_token(&new_token, tokenizer.position());
+            }
             self.input.cached_token = Some(CachedToken {
                 token: new_token,
                 start_position: token_start_position,
@@ -636,6 +1012,28 @@ impl<'i: 't, 't> Parser<'i, 't> {
         Ok(result)
     }
 
+    /// Like `parse_entirely`, but also return the source text of the input,
+    /// without leading and trailing whitespace. Comments are included.
+    ///
+    /// In `QualifiedRuleParser::parse_prelude` or `AtRuleParser::parse_prelude`,
+    /// this is the prelude exactly as written,
+    /// e.g. for comparing selectors textually or quoting them in error messages.
+    #[inline]
+    pub fn parse_entirely_with_source<F, T, E>(
+        &mut self,
+        parse: F,
+    ) -> Result<(T, &'i str), ParseError<'i, E>>
+    where
+        F: FnOnce(&mut Parser<'i, 't>) -> Result<T, ParseError<'i, E>>,
+    {
+        let start = self.position();
+        let result = self.parse_entirely(parse)?;
+        // Include trailing comments, as for the rest of the input.
+        self.skip_whitespace();
+        let source = self.slice_from(start).trim_matches(is_css_whitespace);
+        Ok((result, source))
+    }
+
     /// Parse a list of comma-separated values, all with the same syntax.
     ///
     /// The given closure is called repeatedly with a "delimited" parser
@@ -670,6 +1068,47 @@ impl<'i: 't, 't> Parser<'i, 't> {
         }
     }
 
+    /// Parse a list of values separated by a given `Delim` character, e.g. `/` in
+    /// `font: 12px/1.5 serif` or `grid-area: 1 / 2 / 3`.
+    ///
+    /// Like `parse_comma_separated`, the given closure is called repeatedly with a "delimited"
+    /// parser that stops before the delimiter at this block/function nesting level.
+    /// Delimiters in strings, comments, and nested blocks or functions are not separators.
+    /// The delimited parser also stops before the delimiters of enclosing `split_top_level`
+    /// calls, so `1 / 2 + 3 / 4` can be split on `+` and then on `/`.
+    ///
+    /// This method returns an error the first time that a closure call does,
+    /// or if a closure call leaves some input before the next delimiter or the end of the input.
+    ///
+    /// Panics if the delimiter is not ASCII punctuation that can be a `Delim` token:
+    /// non-ASCII characters and `_` start identifiers, and `, ; : ( ) [ ] { } " '`
+    /// are other tokens.
+    #[inline]
+    pub fn split_top_level<F, T, E>(
+        &mut self,
+        delimiter: char,
+        mut parse_one: F,
+    ) -> Result<Vec<T>, ParseError<'i, E>>
+    where
+        F: for<'tt> FnMut(&mut Parser<'i, 'tt>) -> Result<T, ParseError<'i, E>>,
+    {
+        assert!(
+            delimiter.is_ascii_punctuation() && !"_,;:()[]{}\"'".contains(delimiter),
+            "not a delimiter: {:?}",
+            delimiter
+        );
+        let mut values = Vec::with_capacity(1);
+        loop {
+            self.skip_whitespace();
+            values.push(parse_until_before_delim(self, delimiter, &mut parse_one)?);
+            match self.next() {
+                Err(_) => return Ok(values),
+                Ok(&Token::Delim(c)) if c == delimiter => continue,
+                Ok(_) => unreachable!(),
+            }
+        }
+    }
+
     /// Parse the content of a block or function.
     ///
     /// This method panics if the last token yielded by this parser
@@ -784,6 +1223,23 @@ impl<'i: 't, 't> Parser<'i, 't> {
         }
     }
 
+    /// Parse either a <string-token> or one or more <ident-token>s,
+    /// such as a family name of `font-family`, and return the unescaped value,
+    /// with the identifiers separated by single spaces whatever the whitespace between them.
+    pub fn expect_string_or_ident_sequence(&mut self) -> Result<CowRcStr<'i>, BasicParseError<'i>> {
+        let first = expect! {self,
+            Token::Ident(ref value) => value.clone(),
+            Token::QuotedString(ref value) => return Ok(value.clone()),
+        };
+        let mut sequence: Option<String> = None;
+        while let Ok(ident) = self.try_parse(|input| input.expect_ident_cloned()) {
+            let sequence = sequence.get_or_insert_with(|| first.to_string());
+            sequence.push(' ');
+            sequence.push_str(&ident);
+        }
+        Ok(sequence.map_or(first, CowRcStr::from))
+    }
+
     /// Parse a <url-token> and return the unescaped value.
     #[inline]
     pub fn expect_url(&mut self) -> Result<CowRcStr<'i>, BasicParseError<'i>> {
@@ -966,25 +1422,40 @@ where
             input: parser.input,
             at_start_of: parser.at_start_of.take(),
             stop_before: delimiters,
+            stop_before_delims: parser.stop_before_delims,
         };
         result = delimited_parser.parse_entirely(parse);
         if let Some(block_type) = delimited_parser.at_start_of {
-            consume_until_end_of_block(block_type, &mut delimited_parser.input.tokenizer);
+            consume_until_end_of_block(block_type, delimited_parser.input);
         }
     }
     // FIXME: have a special-purpose tokenizer method for this that does less work.
+    let skipped_from = parser.current_source_location();
+    let mut skipped = 0;
     loop {
-        if delimiters.contains(Delimiters::from_byte(parser.input.tokenizer.next_byte())) {
+        let byte = parser.input.tokenizer.next_byte();
+        if delimiters.contains(Delimiters::from_byte(byte)) {
+            break;
+        }
+        if parser
+            .stop_before_delims
+            .contains(DelimSet::from_byte(byte))
+            && at_delim(&mut parser.input.tokenizer)
+        {
             break;
         }
         if let Ok(token) = parser.input.tokenizer.next() {
+            skipped += 1;
             if let Some(block_type) = BlockType::opening(&token) {
-                consume_until_end_of_block(block_type, &mut parser.input.tokenizer);
+                consume_until_end_of_block(block_type, parser.input);
             }
         } else {
             break;
         }
     }
+    if skipped > 0 {
+        trace_event!("skipped {} tokens from {}", skipped, skipped_from);
+    }
     result
 }
 
@@ -1007,12 +1478,43 @@ where
         // We know this byte is ASCII.
         parser.input.tokenizer.advance(1);
         if next_byte == Some(b'{') {
-            consume_until_end_of_block(BlockType::CurlyBracket, &mut parser.input.tokenizer);
+            consume_until_end_of_block(BlockType::CurlyBracket, parser.input);
         }
     }
     result
 }
 
+fn parse_until_before_delim<'i: 't, 't, F, T, E>(
+    parser: &mut Parser<'i, 't>,
+    delimiter: char,
+    parse: F,
+) -> Result<T, ParseError<'i, E>>
+where
+    F: for<'tt> FnOnce(&mut Parser<'i, 'tt>) -> Result<T, ParseError<'i, E>>,
+{
+    let mut delimited_parser = Parser {
+        input: parser.input,
+        at_start_of: parser.at_start_of.take(),
+        stop_before: parser.stop_before,
+        stop_before_delims: parser.stop_before_delims | DelimSet::from_byte(Some(delimiter as u8)),
+    };
+    let result = delimited_parser.parse_entirely(parse);
+    while delimited_parser
+        .next_including_whitespace_and_comments()
+        .is_ok()
+    {}
+    result
+}
+
+/// Return whether the next token is a `Delim`, rather than e.g. a comment or a number
+/// that starts with the same byte.
+fn at_delim(tokenizer: &mut Tokenizer) -> bool {
+    let state = tokenizer.state();
+    let result = matches!(tokenizer.next(), Ok(Token::Delim(_)));
+    tokenizer.reset(&state);
+    result
+}
+
 pub fn parse_nested_block<'i: 't, 't, F, T, E>(
     parser: &mut Parser<'i, 't>,
     parse: F,
@@ -1032,6 +1534,11 @@ where
         BlockType::SquareBracket => ClosingDelimiter::CloseSquareBracket,
         BlockType::Parenthesis => ClosingDelimiter::CloseParenthesis,
     };
+    let opened = if parser.input.unclosed_blocks.is_some() {
+        Some(parser.input.tokenizer.state())
+    } else {
+        None
+    };
     let result;
     // Introduce a new scope to limit duration of nested_parser’s borrow
     {
@@ -1039,19 +1546,35 @@ where
             input: parser.input,
             at_start_of: None,
             stop_before: closing_delimiter,
+            stop_before_delims: DelimSet::NONE,
         };
         result = nested_parser.parse_entirely(parse);
         if let Some(block_type) = nested_parser.at_start_of {
-            consume_until_end_of_block(block_type, &mut nested_parser.input.tokenizer);
+            consume_until_end_of_block(block_type, nested_parser.input);
         }
     }
-    consume_until_end_of_block(block_type, &mut parser.input.tokenizer);
+    match opened {
+        Some(opened) => consume_rest_of_block(block_type, opened, parser.input),
+        None => skip_until_end_of_block(block_type, &mut parser.input.tokenizer),
+    }
     result
 }
 
 #[inline(never)]
 #[cold]
-fn consume_until_end_of_block(block_type: BlockType, tokenizer: &mut Tokenizer) {
+fn consume_until_end_of_block(block_type: BlockType, input: &mut ParserInput) {
+    if input.unclosed_blocks.is_some() {
+        let opened = input.tokenizer.state();
+        consume_rest_of_block(block_type, opened, input)
+    } else {
+        skip_until_end_of_block(block_type, &mut input.tokenizer)
+    }
+}
+
+/// Like `consume_until_end_of_block`, without recording unclosed blocks.
+#[inline(never)]
+#[cold]
+fn skip_until_end_of_block(block_type: BlockType, tokenizer: &mut Tokenizer) {
     let mut stack = SmallVec::<[BlockType; 16]>::new();
     stack.push(block_type);
 
@@ -1071,3 +1594,31 @@ fn consume_until_end_of_block(block_type: BlockType, tokenizer: &mut Tokenizer)
         }
     }
 }
+
+/// Like `consume_until_end_of_block`, for a block whose opening token ended at `opened`,
+/// recording the blocks that the end of the input closes.
+fn consume_rest_of_block(block_type: BlockType, opened: ParserState, input: &mut ParserInput) {
+    let mut stack = SmallVec::<[(BlockType, ParserState); 16]>::new();
+    stack.push((block_type, opened));
+
+    // FIXME: have a special-purpose tokenizer method for this that does less work.
+    while let Ok(ref token) = input.tokenizer.next() {
+        if let Some(b) = BlockType::closing(token) {
+            if stack.last().unwrap().0 == b {
+                stack.pop();
+                if stack.is_empty() {
+                    return;
+                }
+            }
+        }
+
+        if let Some(block_type) = BlockType::opening(token) {
+            stack.push((block_type, input.tokenizer.state()));
+        }
+    }
+
+    // The end of the input closed the blocks that are still open.
+    for &(block_type, ref opened) in stack.iter().rev() {
+        input.record_unclosed_block(block_type, opened)
+    }
+}
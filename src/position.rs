@@ -0,0 +1,258 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! https://drafts.csswg.org/css-values-4/#position
+
+use crate::dimensions::LengthPercentage;
+use crate::parser::{BasicParseError, FromCss, ParseError, Parser, ParserState};
+use crate::serializer::ToCss;
+use crate::tokenizer::Token;
+use std::fmt;
+
+/// A `<position>` value, such as of `object-position` or `transform-origin`,
+/// with its components as written.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Position {
+    /// The horizontal component.
+    pub horizontal: PositionComponent<HorizontalPositionKeyword>,
+    /// The vertical component.
+    pub vertical: PositionComponent<VerticalPositionKeyword>,
+}
+
+/// A component of a `Position`, along the axis of its side keywords `S`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PositionComponent<S> {
+    /// `center`, or a component that is omitted, such as the vertical one of `left`.
+    Center,
+    /// An offset from the left or top edge.
+    Length(LengthPercentage),
+    /// A side keyword, with an optional offset from that side, such as `right 10px`.
+    Side(S, Option<LengthPercentage>),
+}
+
+/// A side keyword of the horizontal component of a `Position`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HorizontalPositionKeyword {
+    /// `left`
+    Left,
+    /// `right`
+    Right,
+}
+
+/// A side keyword of the vertical component of a `Position`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum VerticalPositionKeyword {
+    /// `top`
+    Top,
+    /// `bottom`
+    Bottom,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Keyword {
+    Left,
+    Right,
+    Top,
+    Bottom,
+    Center,
+}
+
+#[derive(Clone, Copy)]
+enum Item {
+    Keyword(Keyword),
+    Length(LengthPercentage),
+}
+
+impl Position {
+    /// Parse a `<position>`, with one to four components,
+    /// such as `left`, `10% 20%`, `center top` or `right 10px bottom 20px`.
+    ///
+    /// The components are consumed as long as they make a valid position,
+    /// so `left 10px top` is parsed as `left 10px`, leaving `top`.
+    pub fn parse<'i, 't>(input: &mut Parser<'i, 't>) -> Result<Self, BasicParseError<'i>> {
+        parse_position(input, false)
+    }
+
+    /// Parse a `<bg-position>`, the value of `background-position`,
+    /// which also accepts three components, such as `left 10px top`.
+    pub fn parse_bg_position<'i, 't>(
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self, BasicParseError<'i>> {
+        parse_position(input, true)
+    }
+}
+
+fn parse_position<'i, 't>(
+    input: &mut Parser<'i, 't>,
+    allow_three_components: bool,
+) -> Result<Position, BasicParseError<'i>> {
+    let mut items: Vec<(Item, ParserState)> = Vec::new();
+    while items.len() < 4 {
+        let item = input.try_parse(|input| {
+            if let Ok(length) = input.try_parse(LengthPercentage::parse) {
+                return Ok(Item::Length(length));
+            }
+            let location = input.current_source_location();
+            let ident = input.expect_ident()?;
+            Ok(Item::Keyword(match_ignore_ascii_case! { ident,
+                "left" => Keyword::Left,
+                "right" => Keyword::Right,
+                "top" => Keyword::Top,
+                "bottom" => Keyword::Bottom,
+                "center" => Keyword::Center,
+                _ => {
+                    let token = Token::Ident(ident.clone());
+                    return Err(location.new_basic_unexpected_token_error(token))
+                }
+            }))
+        });
+        match item {
+            Ok(item) => items.push((item, input.state())),
+            Err(error) if items.is_empty() => return Err(error),
+            Err(_) => break,
+        }
+    }
+    // The longest valid position.
+    for len in (1..=items.len()).rev() {
+        if len == 3 && !allow_three_components {
+            continue;
+        }
+        let components = items[..len]
+            .iter()
+            .map(|&(item, _)| item)
+            .collect::<Vec<_>>();
+        if let Some(position) = position_from_items(&components) {
+            input.reset(&items[len - 1].1);
+            return Ok(position);
+        }
+    }
+    // A lone side keyword or length is always valid.
+    unreachable!()
+}
+
+fn position_from_items(items: &[Item]) -> Option<Position> {
+    use self::Item::{Keyword as K, Length as L};
+    match *items {
+        [K(keyword)] => sides((keyword, None), (Keyword::Center, None)),
+        [L(length)] => Some(Position {
+            horizontal: PositionComponent::Length(length),
+            vertical: PositionComponent::Center,
+        }),
+        [K(a), K(b)] => sides((a, None), (b, None)),
+        [K(keyword), L(length)] => Some(Position {
+            horizontal: horizontal((keyword, None))?,
+            vertical: PositionComponent::Length(length),
+        }),
+        [L(length), K(keyword)] => Some(Position {
+            horizontal: PositionComponent::Length(length),
+            vertical: vertical((keyword, None))?,
+        }),
+        [L(x), L(y)] => Some(Position {
+            horizontal: PositionComponent::Length(x),
+            vertical: PositionComponent::Length(y),
+        }),
+        [K(a), L(offset), K(b)] => sides((a, Some(offset)), (b, None)),
+        [K(a), K(b), L(offset)] => sides((a, None), (b, Some(offset))),
+        [K(a), L(a_offset), K(b), L(b_offset)] => sides((a, Some(a_offset)), (b, Some(b_offset))),
+        _ => None,
+    }
+}
+
+/// A position from two keywords with optional offsets, in either order.
+fn sides(
+    a: (Keyword, Option<LengthPercentage>),
+    b: (Keyword, Option<LengthPercentage>),
+) -> Option<Position> {
+    let position = |a, b| {
+        Some(Position {
+            horizontal: horizontal(a)?,
+            vertical: vertical(b)?,
+        })
+    };
+    position(a, b).or_else(|| position(b, a))
+}
+
+fn horizontal(
+    (keyword, offset): (Keyword, Option<LengthPercentage>),
+) -> Option<PositionComponent<HorizontalPositionKeyword>> {
+    Some(match keyword {
+        Keyword::Center if offset.is_none() => PositionComponent::Center,
+        Keyword::Left => PositionComponent::Side(HorizontalPositionKeyword::Left, offset),
+        Keyword::Right => PositionComponent::Side(HorizontalPositionKeyword::Right, offset),
+        _ => return None,
+    })
+}
+
+fn vertical(
+    (keyword, offset): (Keyword, Option<LengthPercentage>),
+) -> Option<PositionComponent<VerticalPositionKeyword>> {
+    Some(match keyword {
+        Keyword::Center if offset.is_none() => PositionComponent::Center,
+        Keyword::Top => PositionComponent::Side(VerticalPositionKeyword::Top, offset),
+        Keyword::Bottom => PositionComponent::Side(VerticalPositionKeyword::Bottom, offset),
+        _ => return None,
+    })
+}
+
+impl<'i, E> FromCss<'i, E> for Position {
+    fn from_css<'t>(input: &mut Parser<'i, 't>) -> Result<Self, ParseError<'i, E>> {
+        Ok(Position::parse(input)?)
+    }
+}
+
+/// Serializes both components, such as `left center` for `left`.
+impl ToCss for Position {
+    fn to_css<W>(&self, dest: &mut W) -> fmt::Result
+    where
+        W: fmt::Write,
+    {
+        self.horizontal.to_css(dest)?;
+        dest.write_str(" ")?;
+        self.vertical.to_css(dest)
+    }
+}
+
+impl<S: ToCss> ToCss for PositionComponent<S> {
+    fn to_css<W>(&self, dest: &mut W) -> fmt::Result
+    where
+        W: fmt::Write,
+    {
+        match *self {
+            PositionComponent::Center => dest.write_str("center"),
+            PositionComponent::Length(ref length) => length.to_css(dest),
+            PositionComponent::Side(ref side, ref offset) => {
+                side.to_css(dest)?;
+                if let Some(ref offset) = *offset {
+                    dest.write_str(" ")?;
+                    offset.to_css(dest)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl ToCss for HorizontalPositionKeyword {
+    fn to_css<W>(&self, dest: &mut W) -> fmt::Result
+    where
+        W: fmt::Write,
+    {
+        dest.write_str(match *self {
+            HorizontalPositionKeyword::Left => "left",
+            HorizontalPositionKeyword::Right => "right",
+        })
+    }
+}
+
+impl ToCss for VerticalPositionKeyword {
+    fn to_css<W>(&self, dest: &mut W) -> fmt::Result
+    where
+        W: fmt::Write,
+    {
+        dest.write_str(match *self {
+            VerticalPositionKeyword::Top => "top",
+            VerticalPositionKeyword::Bottom => "bottom",
+        })
+    }
+}
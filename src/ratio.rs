@@ -0,0 +1,71 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! https://drafts.csswg.org/css-values-4/#ratios
+
+use crate::parser::{BasicParseError, FromCss, ParseError, Parser};
+use crate::serializer::ToCss;
+use crate::tokenizer::Token;
+use std::fmt;
+
+/// A `<ratio>` value, as used in the `aspect-ratio` property and media feature.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ratio {
+    /// The number before the slash.
+    pub numerator: f32,
+    /// The number after the slash, or 1 if there is no slash.
+    pub denominator: f32,
+}
+
+impl Ratio {
+    /// Parse a `<number [0,∞]> [ / <number [0,∞]> ]?`.
+    ///
+    /// Whitespace and comments are allowed on either side of the slash, but not required.
+    pub fn parse<'i, 't>(input: &mut Parser<'i, 't>) -> Result<Self, BasicParseError<'i>> {
+        let numerator = parse_non_negative_number(input)?;
+        let denominator = if input.try_parse(|input| input.expect_delim('/')).is_ok() {
+            parse_non_negative_number(input)?
+        } else {
+            1.
+        };
+        Ok(Ratio {
+            numerator,
+            denominator,
+        })
+    }
+
+    /// Whether either number is zero, in which case the ratio has no meaningful value.
+    pub fn is_degenerate(&self) -> bool {
+        self.numerator == 0. || self.denominator == 0.
+    }
+}
+
+fn parse_non_negative_number<'i, 't>(
+    input: &mut Parser<'i, 't>,
+) -> Result<f32, BasicParseError<'i>> {
+    let location = input.current_source_location();
+    let token = input.next()?;
+    match *token {
+        Token::Number { value, .. } if value >= 0. => Ok(value),
+        ref token => Err(location.new_basic_unexpected_token_error(token.clone())),
+    }
+}
+
+impl<'i, E> FromCss<'i, E> for Ratio {
+    fn from_css<'t>(input: &mut Parser<'i, 't>) -> Result<Self, ParseError<'i, E>> {
+        Ok(Ratio::parse(input)?)
+    }
+}
+
+/// Always serializes both numbers, as `16 / 9` or `2 / 1`.
+impl ToCss for Ratio {
+    fn to_css<W>(&self, dest: &mut W) -> fmt::Result
+    where
+        W: fmt::Write,
+    {
+        self.numerator.to_css(dest)?;
+        dest.write_str(" / ")?;
+        self.denominator.to_css(dest)
+    }
+}
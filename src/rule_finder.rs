@@ -0,0 +1,191 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use crate::cow_rc_str::CowRcStr;
+use crate::parser::{Delimiter, ParseError, Parser, ParserInput};
+use crate::tokenizer::{SourceLocation, Token};
+use std::iter::FusedIterator;
+
+/// A rule found by `find_rules`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FoundRule<'i> {
+    /// The preludes of the enclosing at-rules, outermost first,
+    /// such as `print` and `(display: grid)` for the path `["@media", "@supports"]`.
+    pub conditions: Vec<&'i str>,
+    /// The source text of the rule, including its block.
+    pub source: &'i str,
+    /// The location of the start of the rule.
+    pub location: SourceLocation,
+}
+
+/// Return an iterator over the rules of a stylesheet that are nested in at-rules
+/// with the given names, such as `["@media", "@supports"]`
+/// for the rules in `@supports` rules that are directly in top-level `@media` rules.
+///
+/// Names are compared ASCII case-insensitively, and the `@` is optional.
+/// The rules in the innermost at-rule are returned as written, including nested at-rules,
+/// and with an empty path these are the top-level rules.
+///
+/// The stylesheet is only parsed as far as needed for each call to `next`,
+/// and blocks that do not match the path are skipped without looking at their rules.
+pub fn find_rules<'i, 'a>(css: &'i str, path: &'a [&'a str]) -> FindRules<'i, 'a> {
+    FindRules {
+        path,
+        blocks: vec![RuleList {
+            css,
+            start: SourceLocation { line: 0, column: 1 },
+            conditions: Vec::new(),
+        }],
+    }
+}
+
+/// The iterator returned by `find_rules`.
+pub struct FindRules<'i, 'a> {
+    path: &'a [&'a str],
+    /// The rest of each block that is being searched, outermost first.
+    blocks: Vec<RuleList<'i>>,
+}
+
+struct RuleList<'i> {
+    css: &'i str,
+    /// The location of the start of `css` in the stylesheet.
+    start: SourceLocation,
+    conditions: Vec<&'i str>,
+}
+
+/// A rule, with locations relative to the start of the input it was parsed from.
+pub(crate) struct Rule<'i> {
+    pub(crate) at_keyword: Option<CowRcStr<'i>>,
+    pub(crate) prelude: &'i str,
+    pub(crate) source: &'i str,
+    pub(crate) location: SourceLocation,
+    /// The contents of the block, and their location.
+    pub(crate) block: Option<(&'i str, SourceLocation)>,
+}
+
+impl<'i, 'a> Iterator for FindRules<'i, 'a> {
+    type Item = FoundRule<'i>;
+
+    fn next(&mut self) -> Option<FoundRule<'i>> {
+        loop {
+            // `None` once all the blocks have been searched.
+            let depth = self.blocks.len().checked_sub(1)?;
+            let (rule, rest, rest_start) = {
+                let rules = self.blocks.last()?;
+                let mut input =
+                    ParserInput::new_with_line_number_offset(rules.css, rules.start.line);
+                let mut input = Parser::new(&mut input);
+                let rule = next_rule(&mut input);
+                let rest = &rules.css[input.position().byte_index()..];
+                (rule, rest, input.current_source_location())
+            };
+            let rule = match rule {
+                Some(rule) => rule,
+                None => {
+                    self.blocks.pop();
+                    if self.blocks.is_empty() {
+                        return None;
+                    }
+                    continue;
+                }
+            };
+
+            let rules = self.blocks.last_mut().unwrap();
+            let start = rules.start;
+            let absolute = |location: SourceLocation| {
+                if location.line == start.line {
+                    SourceLocation {
+                        line: location.line,
+                        column: start.column - 1 + location.column,
+                    }
+                } else {
+                    location
+                }
+            };
+            rules.css = rest;
+            rules.start = absolute(rest_start);
+
+            if depth == self.path.len() {
+                return Some(FoundRule {
+                    conditions: rules.conditions.clone(),
+                    source: rule.source,
+                    location: absolute(rule.location),
+                });
+            }
+            let name = self.path[depth];
+            let name = if name.starts_with('@') {
+                &name[1..]
+            } else {
+                name
+            };
+            match (rule.at_keyword, rule.block) {
+                (Some(ref at_keyword), Some((css, location)))
+                    if at_keyword.eq_ignore_ascii_case(name) =>
+                {
+                    let mut conditions = rules.conditions.clone();
+                    conditions.push(rule.prelude);
+                    self.blocks.push(RuleList {
+                        css,
+                        start: absolute(location),
+                        conditions,
+                    })
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<'i, 'a> FusedIterator for FindRules<'i, 'a> {}
+
+/// Consume the next rule, if any, without checking its prelude or block.
+pub(crate) fn next_rule<'i, 't>(input: &mut Parser<'i, 't>) -> Option<Rule<'i>> {
+    let (start, token) = loop {
+        input.skip_whitespace();
+        let start = input.state();
+        match input.next().ok().cloned() {
+            Some(Token::CDO) | Some(Token::CDC) => continue,
+            Some(token) => break (start, token),
+            None => return None,
+        }
+    };
+    let at_keyword = match token {
+        Token::AtKeyword(name) => Some(name),
+        _ => {
+            input.reset(&start);
+            None
+        }
+    };
+    let delimiters = if at_keyword.is_some() {
+        Delimiter::Semicolon | Delimiter::CurlyBracketBlock
+    } else {
+        Delimiter::CurlyBracketBlock
+    };
+    let result: Result<_, ParseError<()>> = input.parse_until_before(delimiters, |input| {
+        input.skip_whitespace();
+        let prelude_start = input.position();
+        while input.next().is_ok() {}
+        Ok(input.slice_from(prelude_start).trim_end())
+    });
+    let prelude = result.unwrap_or("");
+    let block = match input.next() {
+        Ok(&Token::CurlyBracketBlock) => {
+            let result: Result<_, ParseError<()>> = input.parse_nested_block(|input| {
+                let block_start = input.position();
+                let location = input.current_source_location();
+                while input.next_including_whitespace_and_comments().is_ok() {}
+                Ok((input.slice_from(block_start), location))
+            });
+            result.ok()
+        }
+        _ => None,
+    };
+    Some(Rule {
+        at_keyword,
+        prelude,
+        source: input.slice_from(start.position()),
+        location: input.source_location_of(&start),
+        block,
+    })
+}
@@ -0,0 +1,417 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use crate::parser::{Parser, ParserInput};
+use crate::serializer::{serialize_identifier, serialize_normalized_value, ToCss};
+use crate::tokenizer::is_css_whitespace;
+use std::fmt;
+
+/// Whether to write a semicolon after the last item of a declaration block,
+/// where it is optional.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrailingSemicolon {
+    /// `a { color: red; }`, as in CSSOM serialization.
+    Always,
+    /// `a { color: red }`, which is shorter.
+    Omit,
+}
+
+/// A declaration to serialize, such as `color: red !important`.
+///
+/// This does not include the semicolon that separates it from the next item of a block.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SerializableDeclaration<'a> {
+    /// The property name, unescaped. It is escaped as needed when serialized.
+    pub name: &'a str,
+    /// The value, as written, without `!important`.
+    pub value: &'a str,
+    /// Whether the declaration is `!important`.
+    pub important: bool,
+}
+
+/// An item of a declaration block to serialize.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SerializableDeclarationListItem<'a> {
+    /// A declaration, followed by a semicolon.
+    Declaration(SerializableDeclaration<'a>),
+    /// A nested at-rule, such as `@media` in CSS Nesting.
+    AtRule(SerializableAtRule<'a>),
+    /// A nested style rule, as in CSS Nesting.
+    QualifiedRule(SerializableQualifiedRule<'a>),
+}
+
+/// A qualified rule to serialize, such as a style rule.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SerializableQualifiedRule<'a> {
+    /// The prelude, such as a selector list, as written.
+    pub prelude: &'a str,
+    /// The items of the block.
+    pub block: Vec<SerializableDeclarationListItem<'a>>,
+}
+
+/// An at-rule to serialize.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SerializableAtRule<'a> {
+    /// The name, unescaped and without `@`. It is escaped as needed when serialized.
+    pub name: &'a str,
+    /// The prelude, as written. It is omitted if empty.
+    pub prelude: &'a str,
+    /// The items of the block, or `None` for a statement such as `@import`,
+    /// which is followed by a semicolon.
+    ///
+    /// Rules in the block of a rule such as `@media` are
+    /// `SerializableDeclarationListItem::QualifiedRule`
+    /// and `SerializableDeclarationListItem::AtRule` items.
+    pub block: Option<Vec<SerializableDeclarationListItem<'a>>>,
+}
+
+/// A rule of a rule list to serialize, such as a stylesheet.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SerializableRule<'a> {
+    /// An at-rule.
+    AtRule(SerializableAtRule<'a>),
+    /// A qualified rule.
+    QualifiedRule(SerializableQualifiedRule<'a>),
+}
+
+impl<'a> ToCss for SerializableDeclaration<'a> {
+    fn to_css<W>(&self, dest: &mut W) -> fmt::Result
+    where
+        W: fmt::Write,
+    {
+        serialize_identifier(self.name, dest)?;
+        dest.write_str(": ")?;
+        dest.write_str(self.value)?;
+        if self.important {
+            dest.write_str(" !important")?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> SerializableDeclaration<'a> {
+    /// Serialize this declaration as in CSSOM, such as `color: red !important;`.
+    ///
+    /// Unlike `to_css`, which keeps the value as written,
+    /// this is the canonical form that CSSOM APIs such as `cssText` expect:
+    /// the name is ASCII-lowercased unless it is a custom property name,
+    /// the value is serialized with `serialize_cssom_value`, and the declaration ends with `;`.
+    pub fn serialize_cssom<W>(&self, dest: &mut W) -> fmt::Result
+    where
+        W: fmt::Write,
+    {
+        if self.is_custom_property() {
+            serialize_identifier(self.name, dest)?;
+        } else {
+            serialize_identifier(&self.name.to_ascii_lowercase(), dest)?;
+        }
+        dest.write_str(": ")?;
+        self.serialize_cssom_value(dest)?;
+        if self.important {
+            dest.write_str(" !important")?;
+        }
+        dest.write_str(";")
+    }
+
+    /// Serialize the value of this declaration as returned by CSSOM `getPropertyValue`,
+    /// without `!important`.
+    ///
+    /// Whitespace and comments are normalized as by `serialize_normalized_value`,
+    /// except in the values of custom properties, which are only trimmed.
+    pub fn serialize_cssom_value<W>(&self, dest: &mut W) -> fmt::Result
+    where
+        W: fmt::Write,
+    {
+        if self.is_custom_property() {
+            let value = self.value.trim_matches(is_css_whitespace);
+            return dest.write_str(value);
+        }
+        let mut input = ParserInput::new(self.value);
+        serialize_normalized_value(&mut Parser::new(&mut input), dest)
+    }
+
+    /// Return the length of `to_css`, or of `serialize_into`,
+    /// unless the name needs escaping.
+    pub fn serialized_len_estimate(&self) -> usize {
+        let important = if self.important {
+            " !important".len()
+        } else {
+            0
+        };
+        self.name.len() + ": ".len() + self.value.len() + important
+    }
+
+    fn is_custom_property(&self) -> bool {
+        self.name.starts_with("--")
+    }
+}
+
+impl<'a> SerializableDeclarationListItem<'a> {
+    /// Return the length of `to_css`, or of `serialize_into`,
+    /// unless names need escaping.
+    pub fn serialized_len_estimate(&self) -> usize {
+        match *self {
+            SerializableDeclarationListItem::Declaration(ref declaration) => {
+                declaration.serialized_len_estimate() + ";".len()
+            }
+            SerializableDeclarationListItem::AtRule(ref rule) => rule.serialized_len_estimate(),
+            SerializableDeclarationListItem::QualifiedRule(ref rule) => {
+                rule.serialized_len_estimate()
+            }
+        }
+    }
+
+    /// Serialize this item, with the given policy for the blocks it contains.
+    ///
+    /// A declaration or an at-rule statement is always followed by a semicolon here:
+    /// `trailing_semicolon` only applies when this is the last item of a block.
+    pub fn serialize<W>(&self, trailing_semicolon: TrailingSemicolon, dest: &mut W) -> fmt::Result
+    where
+        W: fmt::Write,
+    {
+        self.serialize_item(trailing_semicolon, true, dest)
+    }
+
+    fn serialize_item<W>(
+        &self,
+        trailing_semicolon: TrailingSemicolon,
+        semicolon: bool,
+        dest: &mut W,
+    ) -> fmt::Result
+    where
+        W: fmt::Write,
+    {
+        match *self {
+            SerializableDeclarationListItem::Declaration(ref declaration) => {
+                declaration.to_css(dest)?;
+                if semicolon {
+                    dest.write_str(";")?;
+                }
+                Ok(())
+            }
+            SerializableDeclarationListItem::AtRule(ref rule) => {
+                rule.serialize_rule(trailing_semicolon, semicolon, dest)
+            }
+            SerializableDeclarationListItem::QualifiedRule(ref rule) => {
+                rule.serialize(trailing_semicolon, dest)
+            }
+        }
+    }
+}
+
+impl<'a> SerializableQualifiedRule<'a> {
+    /// Return the length of `to_css`, or of `serialize_into`,
+    /// unless names need escaping.
+    pub fn serialized_len_estimate(&self) -> usize {
+        self.prelude.len() + " ".len() + block_len_estimate(&self.block)
+    }
+
+    /// Serialize this rule, with the given policy for its block and the blocks it contains.
+    pub fn serialize<W>(&self, trailing_semicolon: TrailingSemicolon, dest: &mut W) -> fmt::Result
+    where
+        W: fmt::Write,
+    {
+        dest.write_str(self.prelude)?;
+        dest.write_str(" ")?;
+        serialize_block(&self.block, trailing_semicolon, dest)
+    }
+}
+
+impl<'a> SerializableAtRule<'a> {
+    /// Return the length of `to_css`, or of `serialize_into`,
+    /// unless names need escaping.
+    pub fn serialized_len_estimate(&self) -> usize {
+        let prelude = if self.prelude.is_empty() {
+            0
+        } else {
+            " ".len() + self.prelude.len()
+        };
+        let block = match self.block {
+            Some(ref block) => " ".len() + block_len_estimate(block),
+            None => ";".len(),
+        };
+        "@".len() + self.name.len() + prelude + block
+    }
+
+    /// Serialize this rule, with the given policy for its block and the blocks it contains.
+    ///
+    /// A statement is always followed by a semicolon here.
+    pub fn serialize<W>(&self, trailing_semicolon: TrailingSemicolon, dest: &mut W) -> fmt::Result
+    where
+        W: fmt::Write,
+    {
+        self.serialize_rule(trailing_semicolon, true, dest)
+    }
+
+    fn serialize_rule<W>(
+        &self,
+        trailing_semicolon: TrailingSemicolon,
+        semicolon: bool,
+        dest: &mut W,
+    ) -> fmt::Result
+    where
+        W: fmt::Write,
+    {
+        dest.write_str("@")?;
+        serialize_identifier(self.name, dest)?;
+        if !self.prelude.is_empty() {
+            dest.write_str(" ")?;
+            dest.write_str(self.prelude)?;
+        }
+        match self.block {
+            Some(ref block) => {
+                dest.write_str(" ")?;
+                serialize_block(block, trailing_semicolon, dest)
+            }
+            None if semicolon => dest.write_str(";"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<'a> SerializableRule<'a> {
+    /// Return the length of `to_css`, or of `serialize_into`,
+    /// unless names need escaping.
+    pub fn serialized_len_estimate(&self) -> usize {
+        match *self {
+            SerializableRule::AtRule(ref rule) => rule.serialized_len_estimate(),
+            SerializableRule::QualifiedRule(ref rule) => rule.serialized_len_estimate(),
+        }
+    }
+
+    /// Serialize this rule, with the given policy for the blocks it contains.
+    pub fn serialize<W>(&self, trailing_semicolon: TrailingSemicolon, dest: &mut W) -> fmt::Result
+    where
+        W: fmt::Write,
+    {
+        match *self {
+            SerializableRule::AtRule(ref rule) => rule.serialize(trailing_semicolon, dest),
+            SerializableRule::QualifiedRule(ref rule) => rule.serialize(trailing_semicolon, dest),
+        }
+    }
+}
+
+macro_rules! to_css_with_trailing_semicolons {
+    ($($name: ident),+) => {
+        $(
+            impl<'a> ToCss for $name<'a> {
+                fn to_css<W>(&self, dest: &mut W) -> fmt::Result
+                where
+                    W: fmt::Write,
+                {
+                    self.serialize(TrailingSemicolon::Always, dest)
+                }
+            }
+        )+
+    };
+}
+
+to_css_with_trailing_semicolons!(
+    SerializableDeclarationListItem,
+    SerializableQualifiedRule,
+    SerializableAtRule,
+    SerializableRule
+);
+
+macro_rules! serialize_into {
+    ($($name: ident),+) => {
+        $(
+            impl<'a> $name<'a> {
+                /// Append `to_css` to `dest`, after reserving `serialized_len_estimate` bytes,
+                /// so that a large output is allocated once.
+                ///
+                /// Estimating the length takes a pass over the items, so this is not
+                /// necessarily faster than letting `dest` grow.
+                pub fn serialize_into(&self, dest: &mut String) {
+                    dest.reserve(self.serialized_len_estimate());
+                    self.to_css(dest).unwrap()
+                }
+            }
+        )+
+    };
+}
+
+serialize_into!(
+    SerializableDeclaration,
+    SerializableDeclarationListItem,
+    SerializableQualifiedRule,
+    SerializableAtRule,
+    SerializableRule
+);
+
+/// The length of `serialize_block`, with trailing semicolons.
+fn block_len_estimate(items: &[SerializableDeclarationListItem]) -> usize {
+    let items: usize = items
+        .iter()
+        .map(|item| " ".len() + item.serialized_len_estimate())
+        .sum();
+    "{".len() + items + " }".len()
+}
+
+/// Write a block with the given items on one line, such as `{ color: red; }`.
+fn serialize_block<W>(
+    items: &[SerializableDeclarationListItem],
+    trailing_semicolon: TrailingSemicolon,
+    dest: &mut W,
+) -> fmt::Result
+where
+    W: fmt::Write,
+{
+    dest.write_str("{")?;
+    for (i, item) in items.iter().enumerate() {
+        let is_last = i == items.len() - 1;
+        dest.write_str(" ")?;
+        item.serialize_item(
+            trailing_semicolon,
+            !is_last || trailing_semicolon == TrailingSemicolon::Always,
+            dest,
+        )?;
+    }
+    dest.write_str(" }")
+}
+
+/// Write a rule list, such as a stylesheet, with one rule per line,
+/// and with the given policy for the blocks of the rules.
+pub fn serialize_rule_list<W>(
+    rules: &[SerializableRule],
+    trailing_semicolon: TrailingSemicolon,
+    dest: &mut W,
+) -> fmt::Result
+where
+    W: fmt::Write,
+{
+    for (i, rule) in rules.iter().enumerate() {
+        if i > 0 {
+            dest.write_str("\n")?;
+        }
+        rule.serialize(trailing_semicolon, dest)?;
+    }
+    Ok(())
+}
+
+/// Return the length of `serialize_rule_list` with trailing semicolons,
+/// unless names need escaping.
+///
+/// Omitting trailing semicolons makes the result shorter than this estimate.
+pub fn rule_list_len_estimate(rules: &[SerializableRule]) -> usize {
+    let rules_len: usize = rules
+        .iter()
+        .map(SerializableRule::serialized_len_estimate)
+        .sum();
+    rules_len + rules.len().saturating_sub(1)
+}
+
+/// Append `serialize_rule_list` to `dest`, after reserving `rule_list_len_estimate` bytes,
+/// so that a large stylesheet is allocated once.
+///
+/// Estimating the length takes a pass over the rules, so this is not necessarily faster
+/// than letting `dest` grow, as the `serialize_rule_list_*` benchmarks show.
+pub fn serialize_rule_list_into(
+    rules: &[SerializableRule],
+    trailing_semicolon: TrailingSemicolon,
+    dest: &mut String,
+) {
+    dest.reserve(rule_list_len_estimate(rules));
+    serialize_rule_list(rules, trailing_semicolon, dest).unwrap()
+}
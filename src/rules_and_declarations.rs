@@ -7,15 +7,145 @@
 use super::{BasicParseError, BasicParseErrorKind, Delimiter};
 use super::{ParseError, Parser, SourceLocation, Token};
 use crate::cow_rc_str::CowRcStr;
+use crate::error_reporting::error_message;
 use crate::parser::{parse_nested_block, parse_until_after, parse_until_before, ParserState};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 
 /// Parse `!important`.
 ///
 /// Typical usage is `input.try_parse(parse_important).is_ok()`
 /// at the end of a `DeclarationParser::parse_value` implementation.
+///
+/// A `!` followed by another identifier is an `InvalidImportant` error.
 pub fn parse_important<'i, 't>(input: &mut Parser<'i, 't>) -> Result<(), BasicParseError<'i>> {
+    parse_importance(input, false).map(|_| ())
+}
+
+/// Parse `!important` and return `true`, or with `accept_invalid`
+/// a `!` followed by any other identifier and return `false`.
+///
+/// This is for compatibility with legacy stylesheets that use e.g. `!ie`:
+/// a declaration that ends with it can then be kept, but without importance.
+pub fn parse_importance<'i, 't>(
+    input: &mut Parser<'i, 't>,
+    accept_invalid: bool,
+) -> Result<bool, BasicParseError<'i>> {
     input.expect_delim('!')?;
-    input.expect_ident_matching("important")
+    input.skip_whitespace();
+    let location = input.current_source_location();
+    let ident = input.expect_ident()?;
+    if ident.eq_ignore_ascii_case("important") {
+        Ok(true)
+    } else if accept_invalid {
+        Ok(false)
+    } else {
+        let kind = BasicParseErrorKind::InvalidImportant(ident.clone());
+        Err(BasicParseError { kind, location })
+    }
+}
+
+/// Remove declarations that are overridden by another declaration of the same block,
+/// keeping the remaining ones in order.
+///
+/// `name_and_importance` returns the property name of a declaration
+/// and whether it was marked `!important`.
+/// Names are compared ASCII case-insensitively, except for custom properties (`--*`).
+/// For each name, the last `!important` declaration is kept if there is any,
+/// otherwise the last declaration.
+///
+/// Note that this also removes “fallback” declarations such as `display: -webkit-box`
+/// followed by `display: flex`, which only matter to user agents
+/// that drop the later value as invalid.
+pub fn remove_overridden_declarations<D, F>(declarations: &mut Vec<D>, name_and_importance: F)
+where
+    F: Fn(&D) -> (&str, bool),
+{
+    let mut winners: HashMap<Cow<str>, (usize, bool)> = HashMap::new();
+    for (index, declaration) in declarations.iter().enumerate() {
+        let (name, important) = name_and_importance(declaration);
+        let name = if name.starts_with("--") {
+            Cow::Borrowed(name)
+        } else {
+            Cow::Owned(name.to_ascii_lowercase())
+        };
+        let winner = winners.entry(name).or_insert((index, important));
+        if important || !winner.1 {
+            *winner = (index, important)
+        }
+    }
+    let mut keep = vec![false; declarations.len()];
+    for &(index, _) in winners.values() {
+        keep[index] = true
+    }
+    let mut keep = keep.into_iter();
+    declarations.retain(|_| keep.next().unwrap())
+}
+
+/// For each declaration of a property in `table`, insert a copy with each of its vendor prefixes
+/// just before it, unless a declaration with that prefixed name is already in the block.
+///
+/// `table` maps unprefixed property names to prefixes, such as `("user-select", &["-webkit-"])`.
+/// `name` returns the property name of a declaration,
+/// and `with_name` returns a copy of a declaration with another name,
+/// the same value and the same importance.
+/// Names are compared ASCII case-insensitively.
+pub fn add_vendor_prefixes<D, F, G>(
+    declarations: &mut Vec<D>,
+    table: &[(&str, &[&str])],
+    name: F,
+    with_name: G,
+) where
+    F: Fn(&D) -> &str,
+    G: Fn(&D, &str) -> D,
+{
+    let existing: HashSet<String> = declarations
+        .iter()
+        .map(|declaration| name(declaration).to_ascii_lowercase())
+        .collect();
+    let mut result = Vec::with_capacity(declarations.len());
+    for declaration in declarations.drain(..) {
+        let entry = table
+            .iter()
+            .find(|&&(unprefixed, _)| unprefixed.eq_ignore_ascii_case(name(&declaration)));
+        if let Some(&(unprefixed, prefixes)) = entry {
+            for prefix in prefixes {
+                let prefixed = format!("{}{}", prefix, unprefixed).to_ascii_lowercase();
+                if !existing.contains(&prefixed) {
+                    result.push(with_name(&declaration, &prefixed))
+                }
+            }
+        }
+        result.push(declaration)
+    }
+    *declarations = result
+}
+
+/// Remove declarations of a vendor-prefixed property in `table`
+/// if the block also has a declaration of the unprefixed property,
+/// keeping the remaining ones in order.
+///
+/// `table` and `name` are as in `add_vendor_prefixes`.
+/// Prefixed declarations without an unprefixed counterpart are kept,
+/// since they may be the only way some user agents get the value.
+pub fn remove_vendor_prefixes<D, F>(declarations: &mut Vec<D>, table: &[(&str, &[&str])], name: F)
+where
+    F: Fn(&D) -> &str,
+{
+    let existing: HashSet<String> = declarations
+        .iter()
+        .map(|declaration| name(declaration).to_ascii_lowercase())
+        .collect();
+    let redundant: HashSet<String> = table
+        .iter()
+        .filter(|&&(unprefixed, _)| existing.contains(&unprefixed.to_ascii_lowercase()))
+        .flat_map(|&(unprefixed, prefixes)| {
+            prefixes
+                .iter()
+                .map(move |prefix| format!("{}{}", prefix, unprefixed).to_ascii_lowercase())
+        })
+        .collect();
+    declarations.retain(|declaration| !redundant.contains(&name(declaration).to_ascii_lowercase()))
 }
 
 /// The return value for `AtRuleParser::parse_prelude`.
@@ -272,6 +402,11 @@ where
                 }
                 Ok(&Token::Ident(ref name)) => {
                     let name = name.clone();
+                    trace_event!(
+                        "declaration start at {}: {}",
+                        self.input.source_location_of(&start),
+                        name
+                    );
                     let result = {
                         let parser = &mut self.parser;
                         // FIXME: https://github.com/servo/rust-cssparser/issues/254
@@ -281,18 +416,27 @@ where
                         };
                         parse_until_after(self.input, Delimiter::Semicolon, callback)
                     };
-                    return Some(result.map_err(|e| (e, self.input.slice_from(start.position()))));
+                    let result = result.map_err(|e| (e, self.input.slice_from(start.position())));
+                    return Some(reported(self.input, &start, result, "declaration"));
                 }
                 Ok(&Token::AtKeyword(ref name)) => {
                     let name = name.clone();
-                    return Some(parse_at_rule(&start, name, self.input, &mut self.parser));
+                    trace_event!(
+                        "at-rule start at {}: @{}",
+                        self.input.source_location_of(&start),
+                        name
+                    );
+                    let result = parse_at_rule(&start, name, self.input, &mut self.parser);
+                    return Some(reported(self.input, &start, result, "at-rule"));
                 }
                 Ok(token) => {
                     let token = token.clone();
+                    let location = self.input.source_location_of(&start);
                     let result = self.input.parse_until_after(Delimiter::Semicolon, |_| {
-                        Err(start.source_location().new_unexpected_token_error(token))
+                        Err(location.new_unexpected_token_error(token))
                     });
-                    return Some(result.map_err(|e| (e, self.input.slice_from(start.position()))));
+                    let result = result.map_err(|e| (e, self.input.slice_from(start.position())));
+                    return Some(reported(self.input, &start, result, "declaration"));
                 }
                 Err(..) => return None,
             }
@@ -381,29 +525,108 @@ where
             };
 
             if let Some(name) = at_keyword {
-                let first_stylesheet_rule = self.is_stylesheet && !self.any_rule_so_far;
+                let at_stylesheet_start =
+                    !self.any_rule_so_far && start.position().byte_index() == 0;
                 self.any_rule_so_far = true;
-                if first_stylesheet_rule && name.eq_ignore_ascii_case("charset") {
+                if self.is_stylesheet && name.eq_ignore_ascii_case("charset") {
                     let delimiters = Delimiter::Semicolon | Delimiter::CurlyBracketBlock;
                     let _: Result<(), ParseError<()>> =
                         self.input.parse_until_after(delimiters, |_| Ok(()));
+                    let rule = self.input.slice_from(start.position());
+                    if !at_stylesheet_start || !is_charset_rule(rule) {
+                        // Only meaningful at the first byte of a stylesheet, in the exact form
+                        // that determines its encoding.
+                        let error = BasicParseError {
+                            kind: BasicParseErrorKind::InvalidCharsetRule,
+                            location: self.input.source_location_of(&start),
+                        };
+                        let result = Err((error.into(), rule));
+                        return Some(reported(self.input, &start, result, "at-rule"));
+                    }
                 } else {
-                    return Some(parse_at_rule(
-                        &start,
-                        name.clone(),
-                        self.input,
-                        &mut self.parser,
-                    ));
+                    trace_event!(
+                        "at-rule start at {}: @{}",
+                        self.input.source_location_of(&start),
+                        name
+                    );
+                    let result = parse_at_rule(&start, name.clone(), self.input, &mut self.parser);
+                    return Some(reported(self.input, &start, result, "at-rule"));
                 }
             } else {
                 self.any_rule_so_far = true;
+                trace_event!("rule start at {}", self.input.source_location_of(&start));
                 let result = parse_qualified_rule(self.input, &mut self.parser);
-                return Some(result.map_err(|e| (e, self.input.slice_from(start.position()))));
+                let result = result.map_err(|e| (e, self.input.slice_from(start.position())));
+                return Some(reported(self.input, &start, result, "rule"));
             }
         }
     }
 }
 
+/// Whether a rule is exactly `@charset "…";`, the only form that `stylesheet_encoding` reads.
+fn is_charset_rule(rule: &str) -> bool {
+    let prefix = "@charset \"";
+    rule.starts_with(prefix)
+        && rule.len() >= prefix.len() + 2
+        && rule.ends_with("\";")
+        && !rule[prefix.len()..rule.len() - 2].contains('"')
+}
+
+/// Pass the error of an invalid rule or declaration to the error reporter, if any.
+fn reported<'i, T, E>(
+    input: &Parser<'i, '_>,
+    start: &ParserState,
+    result: Result<T, (ParseError<'i, E>, &'i str)>,
+    what: &str,
+) -> Result<T, (ParseError<'i, E>, &'i str)> {
+    let start = input.source_location_of(start);
+    match result {
+        Ok(_) => trace_event!("{} end, from {}", what, start),
+        Err((ref error, slice)) => {
+            trace_event!(
+                "{} from {} dropped, skipping {} bytes: {} at {}",
+                what,
+                start,
+                slice.len(),
+                error_message(error, what),
+                error.location
+            );
+            input.report_error(error, what)
+        }
+    }
+    result
+}
+
+/// Parse the contents of a block that contains rules, such as the body of `@media` or `@supports`,
+/// and collect them.
+///
+/// This is typically called from `AtRuleParser::parse_block`
+/// with the same parser that is used for the enclosing rule list,
+/// so that nested at-rules recurse the same way.
+/// Invalid rules are skipped after being passed to `on_error`
+/// together with the slice of the input they span.
+pub fn parse_nested_rules<'i, 't, R, P, E, F>(
+    input: &mut Parser<'i, 't>,
+    parser: P,
+    mut on_error: F,
+) -> Vec<R>
+where
+    P: QualifiedRuleParser<'i, QualifiedRule = R, Error = E>
+        + AtRuleParser<'i, AtRule = R, Error = E>,
+    E: 'i,
+    F: FnMut(ParseError<'i, E>, &'i str),
+{
+    RuleListParser::new_for_nested_rule(input, parser)
+        .filter_map(|result| match result {
+            Ok(rule) => Some(rule),
+            Err((error, slice)) => {
+                on_error(error, slice);
+                None
+            }
+        })
+        .collect()
+}
+
 /// Parse a single declaration, such as an `( /* ... */ )` parenthesis in an `@supports` prelude.
 pub fn parse_one_declaration<'i, 't, P, E>(
     input: &mut Parser<'i, 't>,
@@ -414,14 +637,91 @@ where
 {
     let start_position = input.position();
     input
-        .parse_entirely(|input| {
-            let name = input.expect_ident()?.clone();
-            input.expect_colon()?;
-            parser.parse_value(name, input)
-        })
+        .parse_entirely(|input| parse_declaration(input, parser))
         .map_err(|e| (e, input.slice_from(start_position)))
 }
 
+/// The errors of `parse_one_declaration_with_errors` and `parse_one_rule_with_errors`,
+/// each with the slice of input it is about.
+pub type RecoveredErrors<'i, E> = Vec<(ParseError<'i, E>, &'i str)>;
+
+/// Parse a single declaration like `parse_one_declaration`, but without stopping at the first error,
+/// such as for an editor that applies changes from partially-typed input.
+///
+/// Return the declaration if one could be parsed, together with every error
+/// and the slice of input it is about: an invalid declaration,
+/// and input that remains after the declaration, which is consumed.
+pub fn parse_one_declaration_with_errors<'i, 't, P, E>(
+    input: &mut Parser<'i, 't>,
+    parser: &mut P,
+) -> (
+    Option<<P as DeclarationParser<'i>>::Declaration>,
+    RecoveredErrors<'i, E>,
+)
+where
+    P: DeclarationParser<'i, Error = E>,
+{
+    let mut errors = Vec::new();
+    input.skip_whitespace();
+    let start_position = input.position();
+    let declaration = match parse_declaration(input, parser) {
+        Ok(declaration) => Some(declaration),
+        Err(error) => {
+            errors.push((error, input.slice_from(start_position)));
+            None
+        }
+    };
+    consume_extra_input(input, &mut errors);
+    (declaration, errors)
+}
+
+fn parse_declaration<'i, 't, P, E>(
+    input: &mut Parser<'i, 't>,
+    parser: &mut P,
+) -> Result<<P as DeclarationParser<'i>>::Declaration, ParseError<'i, E>>
+where
+    P: DeclarationParser<'i, Error = E>,
+{
+    let name = input.expect_ident()?.clone();
+    input.expect_colon()?;
+    parser.parse_value(name, input)
+}
+
+/// Consume the rest of `input`, and report it as an error unless it is whitespace and comments.
+fn consume_extra_input<'i, 't, E>(input: &mut Parser<'i, 't>, errors: &mut RecoveredErrors<'i, E>) {
+    input.skip_whitespace();
+    let start = input.state();
+    let location = input.current_source_location();
+    if let Ok(token) = input.next() {
+        let error = location.new_unexpected_token_error(token.clone());
+        while input.next().is_ok() {}
+        errors.push((error, input.slice_from(start.position())))
+    }
+}
+
+/// Parse a declaration value on its own, such as for CSSOM’s
+/// `CSSStyleDeclaration.setProperty(name, value, priority)`,
+/// and return it together with whether it is followed by `!important`.
+///
+/// The given closure is called with a "delimited" parser
+/// that stops before a `!` or a `;` semicolon at the top level.
+/// The result is an error if a semicolon or any other input remains after the value
+/// and the optional `!important`.
+pub fn parse_declaration_value<'i, 't, T, E, F>(
+    input: &mut Parser<'i, 't>,
+    parse_value: F,
+) -> Result<(T, bool), ParseError<'i, E>>
+where
+    F: for<'tt> FnOnce(&mut Parser<'i, 'tt>) -> Result<T, ParseError<'i, E>>,
+{
+    input.parse_entirely(|input| {
+        let delimiters = Delimiter::Bang | Delimiter::Semicolon;
+        let value = input.parse_until_before(delimiters, parse_value)?;
+        let important = input.try_parse(parse_important).is_ok();
+        Ok((value, important))
+    })
+}
+
 /// Parse a single rule, such as for CSSOM’s `CSSStyleSheet.insertRule`.
 pub fn parse_one_rule<'i, 't, R, P, E>(
     input: &mut Parser<'i, 't>,
@@ -431,27 +731,64 @@ where
     P: QualifiedRuleParser<'i, QualifiedRule = R, Error = E>
         + AtRuleParser<'i, AtRule = R, Error = E>,
 {
-    input.parse_entirely(|input| {
-        input.skip_whitespace();
-        let start = input.state();
-        let at_keyword = if input.next_byte() == Some(b'@') {
-            match *input.next_including_whitespace_and_comments()? {
-                Token::AtKeyword(ref name) => Some(name.clone()),
-                _ => {
-                    input.reset(&start);
-                    None
-                }
-            }
-        } else {
+    input.parse_entirely(|input| parse_rule(input, parser))
+}
+
+/// Parse a single rule like `parse_one_rule`, but without stopping at the first error,
+/// such as for an editor that applies changes from partially-typed input.
+///
+/// Return the rule if one could be parsed, together with every error
+/// and the slice of input it is about: an invalid rule,
+/// and input that remains after the rule, which is consumed.
+pub fn parse_one_rule_with_errors<'i, 't, R, P, E>(
+    input: &mut Parser<'i, 't>,
+    parser: &mut P,
+) -> (Option<R>, RecoveredErrors<'i, E>)
+where
+    P: QualifiedRuleParser<'i, QualifiedRule = R, Error = E>
+        + AtRuleParser<'i, AtRule = R, Error = E>,
+{
+    let mut errors = Vec::new();
+    input.skip_whitespace();
+    let start_position = input.position();
+    let rule = match parse_rule(input, parser) {
+        Ok(rule) => Some(rule),
+        Err(error) => {
+            errors.push((error, input.slice_from(start_position)));
             None
-        };
+        }
+    };
+    consume_extra_input(input, &mut errors);
+    (rule, errors)
+}
 
-        if let Some(name) = at_keyword {
-            parse_at_rule(&start, name, input, parser).map_err(|e| e.0)
-        } else {
-            parse_qualified_rule(input, parser)
+fn parse_rule<'i, 't, R, P, E>(
+    input: &mut Parser<'i, 't>,
+    parser: &mut P,
+) -> Result<R, ParseError<'i, E>>
+where
+    P: QualifiedRuleParser<'i, QualifiedRule = R, Error = E>
+        + AtRuleParser<'i, AtRule = R, Error = E>,
+{
+    input.skip_whitespace();
+    let start = input.state();
+    let at_keyword = if input.next_byte() == Some(b'@') {
+        match *input.next_including_whitespace_and_comments()? {
+            Token::AtKeyword(ref name) => Some(name.clone()),
+            _ => {
+                input.reset(&start);
+                None
+            }
         }
-    })
+    } else {
+        None
+    };
+
+    if let Some(name) = at_keyword {
+        parse_at_rule(&start, name, input, parser).map_err(|e| e.0)
+    } else {
+        parse_qualified_rule(input, parser)
+    }
 }
 
 fn parse_at_rule<'i, 't, P, E>(
@@ -9,7 +9,8 @@ use std::fmt::{self, Write};
 use std::io;
 use std::str;
 
-use super::Token;
+use super::{ParseError, Parser, Token};
+use crate::tokenizer::{SourceLocation, TokenKind, Tokenizer};
 
 /// Trait for things the can serialize themselves in CSS syntax.
 pub trait ToCss {
@@ -46,6 +47,13 @@ where
             decimal_point: false,
             scientific: false,
         }
+    } else if value.is_infinite() {
+        // A number too large for `f32`, which is infinite again when tokenized.
+        dest.write_str(if value > 0. { "1e39" } else { "-1e39" })?;
+        Notation {
+            decimal_point: false,
+            scientific: true,
+        }
     } else {
         dtoa_short::write(dest, value)?
     };
@@ -105,14 +113,7 @@ impl<'a> ToCss for Token<'a> {
                 ref unit,
             } => {
                 write_numeric(value, int_value, has_sign, dest)?;
-                // Disambiguate with scientific notation.
-                let unit = &**unit;
-                if unit == "e" || unit == "E" || unit.starts_with("e-") || unit.starts_with("E-") {
-                    dest.write_str("\\65 ")?;
-                    serialize_name(&unit[1..], dest)?;
-                } else {
-                    serialize_identifier(unit, dest)?;
-                }
+                serialize_unit(unit, dest)?;
             }
 
             Token::WhiteSpace(content) => dest.write_str(content)?,
@@ -157,11 +158,35 @@ impl<'a> ToCss for Token<'a> {
             Token::CloseParenthesis => dest.write_str(")")?,
             Token::CloseSquareBracket => dest.write_str("]")?,
             Token::CloseCurlyBracket => dest.write_str("}")?,
+            Token::ProgId(value) => {
+                dest.write_str("progid:")?;
+                dest.write_str(value)?
+            }
+            Token::Expression(value) => {
+                dest.write_str("expression(")?;
+                dest.write_str(value)?;
+                dest.write_char(')')?
+            }
+            Token::__Nonexhaustive(void) => match void {},
         }
         Ok(())
     }
 }
 
+/// Write the unit of a dimension, escaping it as necessary.
+pub(crate) fn serialize_unit<W>(unit: &str, dest: &mut W) -> fmt::Result
+where
+    W: fmt::Write,
+{
+    // Disambiguate with scientific notation.
+    if unit == "e" || unit == "E" || unit.starts_with("e-") || unit.starts_with("E-") {
+        dest.write_str("\\65 ")?;
+        serialize_name(&unit[1..], dest)
+    } else {
+        serialize_identifier(unit, dest)
+    }
+}
+
 fn hex_escape<W>(ascii_byte: u8, dest: &mut W) -> fmt::Result
 where
     W: fmt::Write,
@@ -189,8 +214,37 @@ where
     dest.write_str(unsafe { str::from_utf8_unchecked(&bytes) })
 }
 
+/// How `serialize_identifier_with` escapes characters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IdentifierEscaping {
+    /// Exactly as CSSOM "serialize an identifier", which always ends hex escapes with a space,
+    /// such as `\31 23` or `\31 a`.
+    Cssom,
+    /// The shortest escapes that tokenize the same, such as `\31 23` but `\31x`:
+    /// the space after a hex escape is only kept before a hex digit or at the end,
+    /// where the next character is unknown.
+    Minimal,
+}
+
 /// Write a CSS identifier, escaping characters as necessary.
-pub fn serialize_identifier<W>(mut value: &str, dest: &mut W) -> fmt::Result
+///
+/// This is `serialize_identifier_with` with `IdentifierEscaping::Cssom`.
+pub fn serialize_identifier<W>(value: &str, dest: &mut W) -> fmt::Result
+where
+    W: fmt::Write,
+{
+    serialize_identifier_with(value, IdentifierEscaping::Cssom, dest)
+}
+
+/// Write a CSS identifier, escaping characters as necessary in the given way.
+///
+/// Either way, a leading digit (after an optional `-`) and a lone `-` are the only characters
+/// escaped because of their position, and non-ASCII characters are never escaped.
+pub fn serialize_identifier_with<W>(
+    mut value: &str,
+    escaping: IdentifierEscaping,
+    dest: &mut W,
+) -> fmt::Result
 where
     W: fmt::Write,
 {
@@ -200,7 +254,7 @@ where
 
     if value.starts_with("--") {
         dest.write_str("--")?;
-        serialize_name(&value[2..], dest)
+        serialize_name_with(&value[2..], escaping, dest)
     } else if value == "-" {
         dest.write_str("\\-")
     } else {
@@ -209,10 +263,10 @@ where
             value = &value[1..];
         }
         if let digit @ b'0'..=b'9' = value.as_bytes()[0] {
-            hex_escape(digit, dest)?;
+            identifier_hex_escape(digit, value.as_bytes().get(1).cloned(), escaping, dest)?;
             value = &value[1..];
         }
-        serialize_name(value, dest)
+        serialize_name_with(value, escaping, dest)
     }
 }
 
@@ -221,6 +275,13 @@ where
 /// You should only use this when you know what you're doing, when in doubt,
 /// consider using `serialize_identifier`.
 pub fn serialize_name<W>(value: &str, dest: &mut W) -> fmt::Result
+where
+    W: fmt::Write,
+{
+    serialize_name_with(value, IdentifierEscaping::Cssom, dest)
+}
+
+fn serialize_name_with<W>(value: &str, escaping: IdentifierEscaping, dest: &mut W) -> fmt::Result
 where
     W: fmt::Write,
 {
@@ -236,7 +297,7 @@ where
         if let Some(escaped) = escaped {
             dest.write_str(escaped)?;
         } else if (b >= b'\x01' && b <= b'\x1F') || b == b'\x7F' {
-            hex_escape(b, dest)?;
+            identifier_hex_escape(b, value.as_bytes().get(i + 1).cloned(), escaping, dest)?;
         } else {
             char_escape(b, dest)?;
         }
@@ -245,6 +306,24 @@ where
     dest.write_str(&value[chunk_start..])
 }
 
+/// Write a hex escape in an identifier, followed by the given byte, if any.
+fn identifier_hex_escape<W>(
+    ascii_byte: u8,
+    next: Option<u8>,
+    escaping: IdentifierEscaping,
+    dest: &mut W,
+) -> fmt::Result
+where
+    W: fmt::Write,
+{
+    match (escaping, next) {
+        (IdentifierEscaping::Minimal, Some(next)) if !next.is_ascii_hexdigit() => {
+            write!(dest, "\\{:x}", ascii_byte)
+        }
+        _ => hex_escape(ascii_byte, dest),
+    }
+}
+
 fn serialize_unquoted_url<W>(value: &str, dest: &mut W) -> fmt::Result
 where
     W: fmt::Write,
@@ -278,7 +357,42 @@ where
     Ok(())
 }
 
-/// A `fmt::Write` adapter that escapes text for writing as a double-quoted CSS string.
+/// Which quotes `serialize_string_with` uses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuoteStyle {
+    /// Double quotes, as `serialize_string` and CSSOM.
+    Double,
+    /// Single quotes.
+    Single,
+    /// The quotes that need the fewest escapes, which are double quotes
+    /// unless the string contains more double quotes than single quotes.
+    Auto,
+}
+
+/// Write a quoted CSS string token with the given quotes, escaping content as necessary.
+pub fn serialize_string_with<W>(value: &str, quote_style: QuoteStyle, dest: &mut W) -> fmt::Result
+where
+    W: fmt::Write,
+{
+    let single = match quote_style {
+        QuoteStyle::Double => false,
+        QuoteStyle::Single => true,
+        QuoteStyle::Auto => {
+            let count = |quote| value.bytes().filter(|&b| b == quote).count();
+            count(b'"') > count(b'\'')
+        }
+    };
+    if !single {
+        return serialize_string(value, dest);
+    }
+    dest.write_str("'")?;
+    CssStringWriter::new_single_quoted(dest).write_str(value)?;
+    dest.write_str("'")?;
+    Ok(())
+}
+
+/// A `fmt::Write` adapter that escapes text for writing as a double-quoted CSS string,
+/// or as a single-quoted one with `new_single_quoted`.
 /// Quotes are not included.
 ///
 /// Typical usage:
@@ -296,6 +410,7 @@ where
 /// ```
 pub struct CssStringWriter<'a, W> {
     inner: &'a mut W,
+    quote: u8,
 }
 
 impl<'a, W> CssStringWriter<'a, W>
@@ -304,7 +419,16 @@ where
 {
     /// Wrap a text writer to create a `CssStringWriter`.
     pub fn new(inner: &'a mut W) -> CssStringWriter<'a, W> {
-        CssStringWriter { inner: inner }
+        CssStringWriter { inner, quote: b'"' }
+    }
+
+    /// Wrap a text writer to create a `CssStringWriter` for a single-quoted CSS string,
+    /// which escapes single quotes rather than double quotes.
+    pub fn new_single_quoted(inner: &'a mut W) -> CssStringWriter<'a, W> {
+        CssStringWriter {
+            inner,
+            quote: b'\'',
+        }
     }
 }
 
@@ -316,7 +440,8 @@ where
         let mut chunk_start = 0;
         for (i, b) in s.bytes().enumerate() {
             let escaped = match b {
-                b'"' => Some("\\\""),
+                b'"' if self.quote == b'"' => Some("\\\""),
+                b'\'' if self.quote == b'\'' => Some("\\'"),
                 b'\\' => Some("\\\\"),
                 b'\0' => Some("\u{FFFD}"),
                 b'\x01'..=b'\x1F' | b'\x7F' => None,
@@ -389,6 +514,11 @@ macro_rules! impl_tocss_for_float {
             where
                 W: fmt::Write,
             {
+                if !self.is_finite() {
+                    dest.write_str("calc(")?;
+                    dest.write_str(non_finite_keyword(*self as f32))?;
+                    return dest.write_str(")");
+                }
                 dtoa_short::write(dest, *self).map(|_| ())
             }
         }
@@ -398,6 +528,35 @@ macro_rules! impl_tocss_for_float {
 impl_tocss_for_float!(f32);
 impl_tocss_for_float!(f64);
 
+/// Write a percentage given as a fraction, such as `50%` for 0.5,
+/// or `calc(infinity * 1%)` if the percentage is infinite or NaN.
+#[cfg(feature = "values")]
+pub(crate) fn serialize_percentage<W>(unit_value: f32, dest: &mut W) -> fmt::Result
+where
+    W: fmt::Write,
+{
+    let value = unit_value * 100.;
+    if !value.is_finite() {
+        dest.write_str("calc(")?;
+        dest.write_str(non_finite_keyword(value))?;
+        return dest.write_str(" * 1%)");
+    }
+    value.to_css(dest)?;
+    dest.write_str("%")
+}
+
+/// The `<calc-constant>` for an infinite or NaN value, per
+/// https://drafts.csswg.org/css-values-4/#calc-serialize
+pub(crate) fn non_finite_keyword(value: f32) -> &'static str {
+    if value.is_nan() {
+        "NaN"
+    } else if value > 0. {
+        "infinity"
+    } else {
+        "-infinity"
+    }
+}
+
 /// A category of token. See the `needs_separator_when_before` method.
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub struct TokenSerializationType(TokenSerializationTypeVariants);
@@ -508,7 +667,7 @@ impl<'a> Token<'a> {
     pub fn serialization_type(&self) -> TokenSerializationType {
         use self::TokenSerializationTypeVariants::*;
         TokenSerializationType(match *self {
-            Token::Ident(_) => Ident,
+            Token::Ident(_) | Token::ProgId(_) | Token::Expression(_) => Ident,
             Token::AtKeyword(_) | Token::Hash(_) | Token::IDHash(_) => AtKeywordOrHash,
             Token::UnquotedUrl(_) | Token::BadUrl(_) => UrlOrBadUrl,
             Token::Delim('#') => DelimHash,
@@ -546,7 +705,190 @@ impl<'a> Token<'a> {
             | Token::CDO
             | Token::IncludeMatch
             | Token::PrefixMatch
-            | Token::SuffixMatch => Other,
+            | Token::SuffixMatch
+            | Token::__Nonexhaustive(_) => Other,
         })
     }
 }
+
+/// Write the rest of `input`, typically the value of a declaration,
+/// with whitespace and comments normalized.
+///
+/// Whitespace and comments are removed at the start and end of the value and of each block,
+/// and other runs of them are replaced with a single space.
+/// A comment that separates two tokens without whitespace is replaced with `/**/` only when needed
+/// for the result to tokenize the same way.
+/// Other tokens are preserved, so this is suitable for the values of custom properties,
+/// and for comparing or hashing values that only differ in formatting.
+pub fn serialize_normalized_value<'i, 't, W>(
+    input: &mut Parser<'i, 't>,
+    dest: &mut W,
+) -> fmt::Result
+where
+    W: fmt::Write,
+{
+    let mut previous_token = TokenSerializationType::nothing();
+    let mut whitespace_before = false;
+    let mut comment_before = false;
+    while let Ok(token) = input.next_including_whitespace_and_comments() {
+        let token = match *token {
+            Token::WhiteSpace(_) => {
+                whitespace_before = true;
+                continue;
+            }
+            Token::Comment(_) => {
+                comment_before = true;
+                continue;
+            }
+            ref token => token.clone(),
+        };
+        let token_type = token.serialization_type();
+        if previous_token != TokenSerializationType::nothing() {
+            if whitespace_before {
+                dest.write_str(" ")?;
+            } else if comment_before && previous_token.needs_separator_when_before(token_type) {
+                dest.write_str("/**/")?;
+            }
+        }
+        whitespace_before = false;
+        comment_before = false;
+        previous_token = token_type;
+        token.to_css(dest)?;
+
+        let closing_token = match token {
+            Token::Function(_) | Token::ParenthesisBlock => Token::CloseParenthesis,
+            Token::SquareBracketBlock => Token::CloseSquareBracket,
+            Token::CurlyBracketBlock => Token::CloseCurlyBracket,
+            _ => continue,
+        };
+        let result: Result<(), ParseError<()>> = input.parse_nested_block(|input| {
+            serialize_normalized_value(input, dest).map_err(|fmt::Error| input.new_custom_error(()))
+        });
+        result.map_err(|_| fmt::Error)?;
+        closing_token.to_css(dest)?;
+        previous_token = closing_token.serialization_type();
+    }
+    Ok(())
+}
+
+/// Write the rest of `input` as written, except that tokens of the given kinds
+/// are passed to `rewrite` with their location, and replaced with the text it returns, if any.
+///
+/// This rewrites and serializes in a single pass, for example to resolve every URL
+/// or to rename every identifier. Tokens in nested blocks are rewritten too.
+/// Replacing a `Function` or another block-opening token only replaces the opening token:
+/// the contents and closing token of the block are still written.
+/// The replacement text is written as is, so it must be valid CSS in that context.
+pub fn serialize_rewritten<'i, 't, W, F>(
+    input: &mut Parser<'i, 't>,
+    kinds: &[TokenKind],
+    mut rewrite: F,
+    dest: &mut W,
+) -> fmt::Result
+where
+    W: fmt::Write,
+    F: FnMut(&Token<'i>, SourceLocation) -> Option<String>,
+{
+    serialize_rewritten_with(input, kinds, &mut rewrite, dest)
+}
+
+fn serialize_rewritten_with<'i, 't, W, F>(
+    input: &mut Parser<'i, 't>,
+    kinds: &[TokenKind],
+    rewrite: &mut F,
+    dest: &mut W,
+) -> fmt::Result
+where
+    W: fmt::Write,
+    F: FnMut(&Token<'i>, SourceLocation) -> Option<String>,
+{
+    loop {
+        let start = input.position();
+        let location = input.current_source_location();
+        let token = match input.next_including_whitespace_and_comments() {
+            Ok(token) => token.clone(),
+            Err(_) => return Ok(()),
+        };
+        let replacement = if kinds.contains(&token.kind()) {
+            rewrite(&token, location)
+        } else {
+            None
+        };
+        match replacement {
+            Some(text) => dest.write_str(&text)?,
+            None => dest.write_str(input.slice_from(start))?,
+        }
+        if !token.is_block() {
+            continue;
+        }
+        let result: Result<_, ParseError<()>> = input.parse_nested_block(|input| {
+            serialize_rewritten_with(input, kinds, rewrite, dest)
+                .map_err(|fmt::Error| input.new_custom_error(()))?;
+            Ok(input.position())
+        });
+        // The closing token, unless the block was closed by the end of the input.
+        let contents_end = result.map_err(|_| fmt::Error)?;
+        dest.write_str(input.slice_from(contents_end))?;
+    }
+}
+
+/// The first difference found by `round_trip_check`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mismatch<'a> {
+    /// The index of the first token that did not round-trip.
+    pub index: usize,
+    /// The given token at that index, or `None` if the serialization has extra tokens.
+    pub expected: Option<Token<'a>>,
+    /// The serialization of the token that was found instead,
+    /// or `None` if the serialization has fewer tokens.
+    pub found: Option<String>,
+    /// The serialization of all the given tokens.
+    pub serialization: String,
+}
+
+/// Serialize a sequence of tokens, tokenize the result again,
+/// and check that this gives exactly the same tokens.
+///
+/// Blocks are given as flat tokens, for example `Function`, the tokens of its arguments,
+/// and `CloseParenthesis`.
+/// `/**/` is inserted between tokens that would otherwise tokenize differently,
+/// and the corresponding comment tokens are ignored.
+///
+/// This is intended for tests of `ToCss` implementations and of code that creates tokens.
+pub fn round_trip_check<'a>(tokens: &[Token<'a>]) -> Result<(), Mismatch<'a>> {
+    let mut serialization = String::new();
+    let mut previous_token = TokenSerializationType::nothing();
+    for token in tokens {
+        let token_type = token.serialization_type();
+        if previous_token.needs_separator_when_before(token_type) {
+            serialization.push_str("/**/")
+        }
+        previous_token = token_type;
+        token.to_css(&mut serialization).unwrap();
+    }
+
+    let mut tokenizer = Tokenizer::new(&serialization);
+    let mut index = 0;
+    loop {
+        let found = match tokenizer.next() {
+            Ok(Token::Comment("")) if tokens.get(index) != Some(&Token::Comment("")) => continue,
+            Ok(token) => Some(token),
+            Err(()) => None,
+        };
+        let expected = tokens.get(index);
+        if expected == found.as_ref() {
+            if found.is_none() {
+                return Ok(());
+            }
+            index += 1;
+            continue;
+        }
+        let found = found.map(|token| token.to_css_string());
+        return Err(Mismatch {
+            index,
+            expected: expected.cloned(),
+            found,
+            serialization,
+        });
+    }
+}
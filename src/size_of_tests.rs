@@ -40,12 +40,13 @@ macro_rules! size_of_test {
 size_of_test!(token, Token, 32);
 size_of_test!(std_cow_str, Cow<'static, str>, 32);
 size_of_test!(cow_rc_str, CowRcStr, 16);
+size_of_test!(token_list_entry, crate::token_list::Entry, 8);
 
-size_of_test!(tokenizer, crate::tokenizer::Tokenizer, 72);
+size_of_test!(tokenizer, crate::tokenizer::Tokenizer, 80);
 size_of_test!(
     parser_input,
     crate::parser::ParserInput,
-    if cfg!(rustc_has_pr45225) { 136 } else { 144 }
+    if cfg!(rustc_has_pr45225) { 184 } else { 192 }
 );
 size_of_test!(parser, crate::parser::Parser, 16);
 size_of_test!(source_position, crate::SourcePosition, 8);
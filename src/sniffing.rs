@@ -0,0 +1,92 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use crate::tokenizer::{is_css_whitespace, Token, Tokenizer};
+use matches::matches;
+
+/// What the start of a resource served as a stylesheet looks like, see `sniff_stylesheet`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SniffedContent {
+    /// Nothing in the scanned tokens is out of place in a stylesheet.
+    Css,
+    /// The resource starts with an HTML or XML tag, such as `<!DOCTYPE html>`.
+    Html,
+    /// The scanned tokens contain JavaScript syntax, such as `function(` or `var x =`.
+    JavaScript,
+}
+
+/// Guess whether `input` is plausibly CSS, by scanning its first `max_tokens` tokens.
+///
+/// This is a cheap heuristic for loaders that check the MIME type of stylesheets
+/// and want to tell a misconfigured server apart from a page served instead of a stylesheet.
+/// It does not validate the CSS: anything that doesn’t look like HTML or JavaScript is `Css`.
+pub fn sniff_stylesheet(input: &str, max_tokens: usize) -> SniffedContent {
+    let input = input.trim_start_matches('\u{FEFF}');
+    let after_whitespace = input.trim_start_matches(is_css_whitespace);
+    if starts_with_tag(after_whitespace) {
+        return SniffedContent::Html;
+    }
+
+    let mut tokenizer = Tokenizer::new(input);
+    let mut depth = 0_usize;
+    let mut count = 0;
+    let mut first_is_string = false;
+    let mut previous_is_function_keyword = false;
+    for _ in 0..max_tokens {
+        let token = match tokenizer.next() {
+            Ok(Token::WhiteSpace(_)) | Ok(Token::Comment(_)) => continue,
+            Ok(token) => token,
+            Err(()) => break,
+        };
+        count += 1;
+        match token {
+            // `function(` or `function name(`
+            Token::Function(ref name)
+                if previous_is_function_keyword || name.eq_ignore_ascii_case("function") =>
+            {
+                return SniffedContent::JavaScript
+            }
+            // `=` is only valid in CSS in attribute selectors and at-rule preludes.
+            Token::Delim('=') if depth == 0 => return SniffedContent::JavaScript,
+            // A directive prologue such as `"use strict";`.
+            Token::Semicolon if count == 2 && first_is_string => return SniffedContent::JavaScript,
+            _ => {}
+        }
+        if token.is_block() {
+            depth += 1
+        } else if matches_close_block(&token) {
+            depth = depth.saturating_sub(1)
+        }
+        if count == 1 {
+            first_is_string = matches!(token, Token::QuotedString(_));
+        }
+        previous_is_function_keyword = match token {
+            Token::Ident(ref name) => name.eq_ignore_ascii_case("function"),
+            _ => false,
+        };
+    }
+    SniffedContent::Css
+}
+
+fn matches_close_block(token: &Token) -> bool {
+    matches!(
+        *token,
+        Token::CloseParenthesis | Token::CloseSquareBracket | Token::CloseCurlyBracket
+    )
+}
+
+/// Whether `input` starts with `<!DOCTYPE`, `<?xml`, or a tag such as `<html`,
+/// but not with `<!--`, which is allowed in stylesheets.
+fn starts_with_tag(input: &str) -> bool {
+    let bytes = input.as_bytes();
+    if bytes.first() != Some(&b'<') {
+        return false;
+    }
+    match bytes.get(1) {
+        Some(&b'!') => bytes.len() >= 9 && bytes[2..9].eq_ignore_ascii_case(b"doctype"),
+        Some(&b'?') => true,
+        Some(&b) => b.is_ascii_alphabetic(),
+        None => false,
+    }
+}
@@ -0,0 +1,141 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! https://drafts.csswg.org/selectors/#specificity-rules
+
+use crate::parser::{Delimiter, ParseError, Parser};
+use crate::tokenizer::Token;
+use std::ops::Add;
+
+/// The specificity of a selector.
+///
+/// The derived ordering compares specificities as the cascade does.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Specificity {
+    /// The number of ID selectors.
+    pub ids: u32,
+    /// The number of class selectors, attribute selectors and pseudo-classes.
+    pub classes: u32,
+    /// The number of type selectors and pseudo-elements.
+    pub elements: u32,
+}
+
+impl Add for Specificity {
+    type Output = Specificity;
+
+    fn add(self, other: Specificity) -> Specificity {
+        Specificity {
+            ids: self.ids + other.ids,
+            classes: self.classes + other.classes,
+            elements: self.elements + other.elements,
+        }
+    }
+}
+
+/// The previous token, as far as it matters for the meaning of the next one.
+#[derive(Clone, Copy, PartialEq)]
+enum Previous {
+    Dot,
+    Colon,
+    DoubleColon,
+    TypeSelector,
+    Other,
+}
+
+/// Estimate the specificity of each selector in the rest of the input,
+/// such as the prelude of a style rule, without parsing selectors.
+///
+/// The input is consumed, and a specificity is returned for each comma-separated selector.
+/// Tokens are counted per the specificity rules of Selectors level 4,
+/// including `:is()`, `:not()`, `:has()`, `:where()` and `:nth-child(… of …)`,
+/// but the selectors are not validated: invalid selectors get a specificity too.
+/// This is intended for sorting or linting, where an approximation is good enough.
+pub fn estimate_specificity<'i, 't>(input: &mut Parser<'i, 't>) -> Vec<Specificity> {
+    let mut specificities = Vec::new();
+    loop {
+        let result: Result<_, ParseError<()>> =
+            input.parse_until_before(Delimiter::Comma, |input| Ok(complex_selector(input)));
+        specificities.push(result.unwrap_or_default());
+        if input.next().is_err() {
+            return specificities;
+        }
+    }
+}
+
+fn complex_selector<'i, 't>(input: &mut Parser<'i, 't>) -> Specificity {
+    let mut specificity = Specificity::default();
+    let mut previous = Previous::Other;
+    while let Some(token) = input.next().ok().cloned() {
+        let mut next_previous = Previous::Other;
+        match token {
+            Token::IDHash(_) => specificity.ids += 1,
+            Token::SquareBracketBlock => specificity.classes += 1,
+            Token::Delim('.') => next_previous = Previous::Dot,
+            Token::Colon if previous == Previous::Colon => next_previous = Previous::DoubleColon,
+            Token::Colon => next_previous = Previous::Colon,
+            // `ns|E`: the namespace prefix is not a type selector.
+            Token::Delim('|') if previous == Previous::TypeSelector => specificity.elements -= 1,
+            Token::Ident(ref name) => match previous {
+                Previous::Dot => specificity.classes += 1,
+                Previous::Colon if is_legacy_pseudo_element(name) => specificity.elements += 1,
+                Previous::Colon => specificity.classes += 1,
+                Previous::DoubleColon => specificity.elements += 1,
+                Previous::TypeSelector | Previous::Other => {
+                    specificity.elements += 1;
+                    next_previous = Previous::TypeSelector
+                }
+            },
+            Token::Function(ref name) => match previous {
+                Previous::Colon => specificity = specificity + functional_pseudo_class(name, input),
+                Previous::DoubleColon => specificity.elements += 1,
+                _ => {}
+            },
+            _ => {}
+        }
+        previous = next_previous;
+    }
+    specificity
+}
+
+/// The specificity of a functional pseudo-class, whose block was just opened.
+fn functional_pseudo_class<'i, 't>(name: &str, input: &mut Parser<'i, 't>) -> Specificity {
+    let result: Result<_, ParseError<()>> = input.parse_nested_block(|input| {
+        let most_specific = |input: &mut Parser| {
+            let specificities = estimate_specificity(input);
+            specificities.into_iter().max().unwrap_or_default()
+        };
+        let class = Specificity {
+            classes: 1,
+            ..Specificity::default()
+        };
+        let specificity = match_ignore_ascii_case! { name,
+            "is" | "matches" | "not" | "has" => most_specific(input),
+            "where" => Specificity::default(),
+            "nth-child" | "nth-last-child" => {
+                let mut specificity = class;
+                while let Ok(token) = input.next() {
+                    if let Token::Ident(ref ident) = *token {
+                        if ident.eq_ignore_ascii_case("of") {
+                            specificity = class + most_specific(input);
+                            break;
+                        }
+                    }
+                }
+                specificity
+            },
+            _ => class,
+        };
+        while input.next().is_ok() {}
+        Ok(specificity)
+    });
+    result.unwrap_or_default()
+}
+
+/// Pseudo-elements that can be written with a single colon.
+fn is_legacy_pseudo_element(name: &str) -> bool {
+    match_ignore_ascii_case! { name,
+        "before" | "after" | "first-line" | "first-letter" => true,
+        _ => false,
+    }
+}
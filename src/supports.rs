@@ -0,0 +1,202 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! https://drafts.csswg.org/css-conditional-4/#at-supports
+
+use crate::cow_rc_str::CowRcStr;
+use crate::parser::{BasicParseError, ParseError, Parser, ParserInput};
+use crate::tokenizer::Token;
+use std::collections::HashMap;
+
+/// A `<supports-condition>`, such as the prelude of an `@supports` rule.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SupportsCondition<'i> {
+    /// `not …`
+    Not(Box<SupportsCondition<'i>>),
+    /// `… and …`
+    And(Vec<SupportsCondition<'i>>),
+    /// `… or …`
+    Or(Vec<SupportsCondition<'i>>),
+    /// A declaration in parentheses, such as `(display: grid)`.
+    Declaration {
+        /// The property name.
+        name: CowRcStr<'i>,
+        /// The value as written, without surrounding whitespace.
+        value: &'i str,
+    },
+    /// `selector(…)`, with the selector as written, without surrounding whitespace.
+    ///
+    /// This is only checked to be a single selector, rather than an empty or comma-separated list:
+    /// `selector(a, b)` is `GeneralEnclosed`.
+    Selector(&'i str),
+    /// Parentheses or a function with contents of unknown syntax, as written,
+    /// which is reserved for future extensions and is never supported.
+    GeneralEnclosed(&'i str),
+}
+
+impl<'i> SupportsCondition<'i> {
+    /// Parse a `<supports-condition>`.
+    pub fn parse<'t>(input: &mut Parser<'i, 't>) -> Result<Self, BasicParseError<'i>> {
+        if input
+            .try_parse(|input| input.expect_ident_matching("not"))
+            .is_ok()
+        {
+            return Ok(SupportsCondition::Not(Box::new(parse_in_parens(input)?)));
+        }
+        let first = parse_in_parens(input)?;
+        let state = input.state();
+        let is_and = match input.next().ok().cloned() {
+            Some(Token::Ident(ref ident)) if ident.eq_ignore_ascii_case("and") => true,
+            Some(Token::Ident(ref ident)) if ident.eq_ignore_ascii_case("or") => false,
+            _ => {
+                input.reset(&state);
+                return Ok(first);
+            }
+        };
+        let operator = if is_and { "and" } else { "or" };
+        let mut conditions = vec![first, parse_in_parens(input)?];
+        while input
+            .try_parse(|input| input.expect_ident_matching(operator))
+            .is_ok()
+        {
+            conditions.push(parse_in_parens(input)?)
+        }
+        Ok(if is_and {
+            SupportsCondition::And(conditions)
+        } else {
+            SupportsCondition::Or(conditions)
+        })
+    }
+}
+
+/// Parse a `<supports-in-parens>`.
+fn parse_in_parens<'i, 't>(
+    input: &mut Parser<'i, 't>,
+) -> Result<SupportsCondition<'i>, BasicParseError<'i>> {
+    input.skip_whitespace();
+    let start = input.position();
+    let location = input.current_source_location();
+    let function = match *input.next()? {
+        Token::ParenthesisBlock => None,
+        Token::Function(ref name) => Some(name.clone()),
+        ref token => return Err(location.new_basic_unexpected_token_error(token.clone())),
+    };
+    let result: Result<_, ParseError<()>> = input.parse_nested_block(|input| {
+        match function {
+            Some(ref name) if name.eq_ignore_ascii_case("selector") => {
+                input.skip_whitespace();
+                let selector_start = input.position();
+                // A `<complex-selector>`, not a selector list.
+                let mut is_complex_selector = !input.is_exhausted();
+                while let Ok(token) = input.next() {
+                    is_complex_selector &= *token != Token::Comma
+                }
+                if is_complex_selector {
+                    let selector = input.slice_from(selector_start).trim_end();
+                    return Ok(Some(SupportsCondition::Selector(selector)));
+                }
+            }
+            Some(_) => {}
+            None => {
+                let condition: Result<_, ParseError<()>> = input.try_parse(|input| {
+                    input.parse_entirely(|input| Ok(SupportsCondition::parse(input)?))
+                });
+                if let Ok(condition) = condition {
+                    return Ok(Some(condition));
+                }
+                if let Ok(declaration) = input.try_parse(parse_declaration) {
+                    return Ok(Some(declaration));
+                }
+            }
+        }
+        while input.next().is_ok() {}
+        Ok(None)
+    });
+    Ok(result
+        .map_err(ParseError::basic)?
+        .unwrap_or_else(|| SupportsCondition::GeneralEnclosed(input.slice_from(start))))
+}
+
+/// Parse the contents of a `<supports-decl>`.
+fn parse_declaration<'i, 't>(
+    input: &mut Parser<'i, 't>,
+) -> Result<SupportsCondition<'i>, ParseError<'i, ()>> {
+    let name = input.expect_ident_cloned()?;
+    input.expect_colon()?;
+    input.skip_whitespace();
+    let value_start = input.position();
+    while input.next().is_ok() {}
+    Ok(SupportsCondition::Declaration {
+        name,
+        value: input.slice_from(value_start).trim_end(),
+    })
+}
+
+/// Evaluates `SupportsCondition`s, remembering the result for each declaration and selector.
+///
+/// Evaluation short-circuits: once the result of `and` or `or` is known,
+/// the remaining conditions are not evaluated.
+/// Declarations are remembered by their name and value as written,
+/// so a cache can be kept for all the `@supports` rules of a document,
+/// which often repeat the same declarations.
+pub struct SupportsEvaluator<F, G> {
+    supports_declaration: F,
+    supports_selector: G,
+    cache: HashMap<String, bool>,
+}
+
+impl<F, G> SupportsEvaluator<F, G>
+where
+    F: for<'i, 't> FnMut(&str, &mut Parser<'i, 't>) -> bool,
+    G: for<'i, 't> FnMut(&mut Parser<'i, 't>) -> bool,
+{
+    /// Create an evaluator with the given callbacks, which return whether
+    /// a declaration or a selector is supported.
+    ///
+    /// `supports_declaration` is given the property name and a parser for the value,
+    /// and `supports_selector` a parser for the selector in `selector()`,
+    /// which it should consume entirely for the selector to be supported.
+    pub fn new(supports_declaration: F, supports_selector: G) -> Self {
+        SupportsEvaluator {
+            supports_declaration,
+            supports_selector,
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Return whether the given condition is supported.
+    pub fn evaluate(&mut self, condition: &SupportsCondition) -> bool {
+        match *condition {
+            SupportsCondition::Not(ref condition) => !self.evaluate(condition),
+            SupportsCondition::And(ref conditions) => conditions.iter().all(|c| self.evaluate(c)),
+            SupportsCondition::Or(ref conditions) => conditions.iter().any(|c| self.evaluate(c)),
+            SupportsCondition::Declaration { ref name, value } => {
+                let key = format!("{}:{}", name, value);
+                if let Some(&supported) = self.cache.get(&key) {
+                    return supported;
+                }
+                let mut input = ParserInput::new(value);
+                let supported = (self.supports_declaration)(name, &mut Parser::new(&mut input));
+                self.cache.insert(key, supported);
+                supported
+            }
+            SupportsCondition::Selector(selector) => {
+                let key = format!("selector({})", selector);
+                if let Some(&supported) = self.cache.get(&key) {
+                    return supported;
+                }
+                let mut input = ParserInput::new(selector);
+                let supported = (self.supports_selector)(&mut Parser::new(&mut input));
+                self.cache.insert(key, supported);
+                supported
+            }
+            SupportsCondition::GeneralEnclosed(_) => false,
+        }
+    }
+
+    /// Forget the remembered results, for example after a browser feature flag changed.
+    pub fn clear_cache(&mut self) {
+        self.cache.clear()
+    }
+}
@@ -8,16 +8,58 @@ extern crate test;
 use encoding_rs;
 use matches::matches;
 use serde_json::{self, json, Map, Value};
+use std::cell::{Cell, RefCell};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
+use std::rc::Rc;
 
 #[cfg(feature = "bench")]
 use self::test::Bencher;
 
 use super::{
-    parse_important, parse_nth, parse_one_declaration, parse_one_rule, stylesheet_encoding,
-    AtRuleParser, AtRuleType, BasicParseError, BasicParseErrorKind, Color, CowRcStr,
-    DeclarationListParser, DeclarationParser, Delimiter, EncodingSupport, ParseError,
-    ParseErrorKind, Parser, ParserInput, QualifiedRuleParser, RuleListParser, SourceLocation,
-    ToCss, Token, TokenSerializationType, UnicodeRange, RGBA,
+    add_vendor_prefixes, check_custom_properties, classify_at_rule, complete_input,
+    completion_context, concat_stylesheets, diff_rules, document_symbols, estimate_specificity,
+    extract_urls, find_rules, folding_ranges, hash_token_stream, is_ident_start,
+    merge_adjacent_rules, parse_custom_property_value, parse_declaration_value, parse_importance,
+    parse_important, parse_nested_rules, parse_one_declaration, parse_one_declaration_with_errors,
+    parse_one_rule, parse_one_rule_with_errors, remove_duplicate_rules,
+    remove_overridden_declarations, remove_vendor_prefixes, render_error, round_trip_check,
+    rule_list_len_estimate, serialize_identifier_with, serialize_minified,
+    serialize_minified_number, serialize_minified_with, serialize_normalized_value,
+    serialize_rewritten, serialize_rule_list, serialize_rule_list_into, serialize_string_with,
+    sniff_stylesheet, stable_hash_token_stream, starts_with_valid_escape, stylesheet_encoding,
+    stylesheet_encoding_and_source, token_stream_eq, tokenize_bytes_lossy, AtRuleClassifier,
+    AtRuleContents, AtRuleParser, AtRuleType, BasicParseError, BasicParseErrorKind,
+    ChunkedTokenizer, ColumnUnit, CompletionContext, ConcatenationChangeKind, CowRcStr,
+    CustomPropertyIssueKind, DeclarationListParser, DeclarationParser, Delimiter, EncodingSource,
+    EncodingSupport, FoldingRange, FoldingRangeKind, FromCss, IdentifierEscaping, ImportLayer,
+    ImportObserver, ImportPrelude, IncompleteKind, Interner, LineIndex, MinifyOptions, Mismatch,
+    ParseError, ParseErrorKind, Parser, ParserInput, QualifiedRuleParser, QuoteStyle, RuleChange,
+    RuleListParser, SerializableAtRule, SerializableDeclaration, SerializableDeclarationListItem,
+    SerializableQualifiedRule, SerializableRule, SniffedContent, SourceLocation, SupportsCondition,
+    SupportsEvaluator, ToCss, Token, TokenKind, TokenList, TokenSerializationType,
+    TrailingSemicolon, UnclosedBlock, UnicodeRange, UnresolvedUrl, UrlRole, STABLE_HASH_VERSION,
+};
+
+#[cfg(feature = "color")]
+use super::{Color, ColorComponentParser, Hsla, Hwba, NumberOrPercentage, RgbComponents, RGBA};
+
+#[cfg(feature = "lint")]
+use super::{lint_stylesheet, LintKind};
+
+#[cfg(feature = "nth")]
+use super::parse_nth;
+
+#[cfg(feature = "values")]
+use super::{
+    media_query_list_matches, parse_calc_constant, parse_css_wide_keyword, parse_custom_ident,
+    parse_custom_ident_list, parse_dashed_ident, parse_function_list, parse_gradient_arguments,
+    parse_grid_track_list_items, parse_image_set, parse_media_query_list, parse_raw_function,
+    parse_raw_function_list, Angle, AngleUnit, BasicShape, ColorStop, Counter, CssWideKeyword,
+    DashedIdent, EasingFunction, Frequency, FrequencyUnit, GradientArguments, GridTrackListItem,
+    ImageSetOption, Length, LengthPercentage, LengthUnit, LinearStop, MediaComparison,
+    MediaCondition, MediaDevice, MediaFeature, MediaFeatureValue, MediaQualifier, MediaQuery,
+    Position, Ratio, Resolution, ResolutionUnit, StepPosition, Time, TimeUnit,
 };
 
 macro_rules! JArray {
@@ -168,12 +210,29 @@ fn stylesheet() {
     run_json_tests(include_str!("css-parsing-tests/stylesheet.json"), |input| {
         Value::Array(
             RuleListParser::new_for_stylesheet(input, JsonParser)
-                .map(|result| result.unwrap_or(JArray!["error", "invalid"]))
+                .enumerate()
+                .filter(|&(i, ref result)| i > 0 || !is_charset_error(result))
+                .map(|(_, result)| result.unwrap_or(JArray!["error", "invalid"]))
                 .collect(),
         )
     });
 }
 
+/// The shared tests ignore a first `@charset` rule, which is reported here when it is malformed
+/// or not at the first byte. See `invalid_charset_rule` for the forms that are reported.
+fn is_charset_error<T, E>(result: &Result<T, (ParseError<E>, &str)>) -> bool {
+    matches!(
+        *result,
+        Err((
+            ParseError {
+                kind: ParseErrorKind::Basic(BasicParseErrorKind::InvalidCharsetRule),
+                ..
+            },
+            _
+        ))
+    )
+}
+
 #[test]
 fn one_rule() {
     run_json_tests(include_str!("css-parsing-tests/one_rule.json"), |input| {
@@ -233,7 +292,9 @@ fn stylesheet_from_bytes() {
                 let mut input = ParserInput::new(&css_unicode);
                 let input = &mut Parser::new(&mut input);
                 let rules = RuleListParser::new_for_stylesheet(input, JsonParser)
-                    .map(|result| result.unwrap_or(JArray!["error", "invalid"]))
+                    .enumerate()
+                    .filter(|&(i, ref result)| i > 0 || !is_charset_error(result))
+                    .map(|(_, result)| result.unwrap_or(JArray!["error", "invalid"]))
                     .collect::<Vec<_>>();
                 JArray![rules, used_encoding.name().to_lowercase()]
             };
@@ -249,6 +310,43 @@ fn stylesheet_from_bytes() {
             _ => panic!("Unexpected JSON"),
         }
     }
+
+    let source = |css: &[u8], protocol: Option<&[u8]>, environment| {
+        stylesheet_encoding_and_source::<EncodingRs>(css, protocol, environment)
+    };
+    let css = b"@charset \"utf-16le\"; a {}";
+    assert_eq!(
+        source(css, Some(b"latin1"), None),
+        (encoding_rs::WINDOWS_1252, EncodingSource::Protocol)
+    );
+    assert_eq!(
+        source(css, Some(b"unknown"), None),
+        (encoding_rs::UTF_8, EncodingSource::Charset)
+    );
+    assert_eq!(
+        source(b"@charset \"ISO-8859-2\";", None, None),
+        (encoding_rs::ISO_8859_2, EncodingSource::Charset)
+    );
+    assert_eq!(
+        source(b"a {}", None, Some(encoding_rs::SHIFT_JIS)),
+        (encoding_rs::SHIFT_JIS, EncodingSource::Environment)
+    );
+    assert_eq!(
+        source(b"a {}", None, None),
+        (encoding_rs::UTF_8, EncodingSource::Default)
+    );
+    assert_eq!(
+        source(b"\xEF\xBB\xBF@charset \"latin1\";", Some(b"latin1"), None),
+        (encoding_rs::UTF_8, EncodingSource::Bom)
+    );
+    assert_eq!(
+        source(b"\xFE\xFFa {}", None, None),
+        (encoding_rs::UTF_16BE, EncodingSource::Bom)
+    );
+    assert_eq!(
+        source(b"\xFF\xFEa {}", None, None),
+        (encoding_rs::UTF_16LE, EncodingSource::Bom)
+    );
 }
 
 #[test]
@@ -354,6 +452,7 @@ fn test_expect_url() {
     assert!(parse(&mut input).is_err());
 }
 
+#[cfg(feature = "color")]
 fn run_color_tests<F: Fn(Result<Color, ()>) -> Value>(json_data: &str, to_json: F) {
     run_json_tests(json_data, |input| {
         let result: Result<_, ParseError<()>> =
@@ -362,6 +461,7 @@ fn run_color_tests<F: Fn(Result<Color, ()>) -> Value>(json_data: &str, to_json:
     });
 }
 
+#[cfg(feature = "color")]
 #[test]
 fn color3() {
     run_color_tests(include_str!("css-parsing-tests/color3.json"), |c| {
@@ -369,6 +469,7 @@ fn color3() {
     })
 }
 
+#[cfg(feature = "color")]
 #[test]
 fn color3_hsl() {
     run_color_tests(include_str!("css-parsing-tests/color3_hsl.json"), |c| {
@@ -377,6 +478,7 @@ fn color3_hsl() {
 }
 
 /// color3_keywords.json is different: R, G and B are in 0..255 rather than 0..1
+#[cfg(feature = "color")]
 #[test]
 fn color3_keywords() {
     run_color_tests(
@@ -385,6 +487,7 @@ fn color3_keywords() {
     )
 }
 
+#[cfg(feature = "nth")]
 #[test]
 fn nth() {
     run_json_tests(include_str!("css-parsing-tests/An+B.json"), |input| {
@@ -512,918 +615,4843 @@ fn serialize_bad_tokens() {
     assert!(parser.next().is_err());
 }
 
-#[test]
-fn serialize_current_color() {
-    let c = Color::CurrentColor;
-    assert!(c.to_css_string() == "currentcolor");
-}
-
-#[test]
-fn serialize_rgb_full_alpha() {
-    let c = Color::RGBA(RGBA::new(255, 230, 204, 255));
-    assert_eq!(c.to_css_string(), "rgb(255, 230, 204)");
-}
-
-#[test]
-fn serialize_rgba() {
-    let c = Color::RGBA(RGBA::new(26, 51, 77, 32));
-    assert_eq!(c.to_css_string(), "rgba(26, 51, 77, 0.125)");
-}
+#[cfg(feature = "color")]
+struct DefaultComponentParser;
 
-#[test]
-fn serialize_rgba_two_digit_float_if_roundtrips() {
-    let c = Color::RGBA(RGBA::from_floats(0., 0., 0., 0.5));
-    assert_eq!(c.to_css_string(), "rgba(0, 0, 0, 0.5)");
+#[cfg(feature = "color")]
+impl<'i> ColorComponentParser<'i> for DefaultComponentParser {
+    type Error = ();
 }
 
+#[cfg(feature = "color")]
 #[test]
-fn line_numbers() {
-    let mut input = ParserInput::new(concat!(
-        "fo\\30\r\n",
-        "0o bar/*\n",
-        "*/baz\r\n",
-        "\n",
-        "url(\r\n",
-        "  u \r\n",
-        ")\"a\\\r\n",
-        "b\""
-    ));
-    let mut input = Parser::new(&mut input);
-    assert_eq!(
-        input.current_source_location(),
-        SourceLocation { line: 0, column: 1 }
-    );
+fn color_out_of_range() {
+    fn parse(css: &str) -> (RGBA, bool) {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        let result: Result<_, ParseError<()>> =
+            Color::parse_with_details(&DefaultComponentParser, &mut parser);
+        match result.unwrap() {
+            (Color::RGBA(rgba), details) => (rgba, details.out_of_range),
+            _ => unreachable!(),
+        }
+    }
     assert_eq!(
-        input.next_including_whitespace(),
-        Ok(&Token::Ident("fo00o".into()))
+        parse("rgb(300, -20, 50)"),
+        (RGBA::new(255, 0, 50, 255), true)
     );
     assert_eq!(
-        input.current_source_location(),
-        SourceLocation { line: 1, column: 3 }
+        parse("rgb(255 0 50.4)"),
+        (RGBA::new(255, 0, 50, 255), false)
     );
     assert_eq!(
-        input.next_including_whitespace(),
-        Ok(&Token::WhiteSpace(" "))
+        parse("rgb(0% 100% 101%)"),
+        (RGBA::new(0, 255, 255, 255), true)
     );
+    assert_eq!(parse("rgba(0, 0, 0, 1.5)"), (RGBA::new(0, 0, 0, 255), true));
     assert_eq!(
-        input.current_source_location(),
-        SourceLocation { line: 1, column: 4 }
+        parse("rgba(0, 0, 0, 50%)"),
+        (RGBA::new(0, 0, 0, 128), false)
     );
     assert_eq!(
-        input.next_including_whitespace(),
-        Ok(&Token::Ident("bar".into()))
+        parse("hsl(120, 100%, 25%)"),
+        (RGBA::new(0, 128, 0, 255), false)
     );
     assert_eq!(
-        input.current_source_location(),
-        SourceLocation { line: 1, column: 7 }
+        parse("hsl(480, 100%, 25%)"),
+        (RGBA::new(0, 128, 0, 255), true)
     );
     assert_eq!(
-        input.next_including_whitespace_and_comments(),
-        Ok(&Token::Comment("\n"))
+        parse("hsl(-240, 100%, 25%)"),
+        (RGBA::new(0, 128, 0, 255), true)
     );
     assert_eq!(
-        input.current_source_location(),
-        SourceLocation { line: 2, column: 3 }
+        parse("hsl(0, 150%, 50%)"),
+        (RGBA::new(255, 0, 0, 255), true)
     );
+    assert_eq!(parse("#f00"), (RGBA::new(255, 0, 0, 255), false));
+}
+
+#[cfg(feature = "color")]
+#[test]
+fn color_components_as_written() {
+    fn parse(css: &str) -> (Option<RgbComponents>, Option<NumberOrPercentage>) {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        let result: Result<_, ParseError<()>> =
+            Color::parse_with_details(&DefaultComponentParser, &mut parser);
+        let details = result.unwrap().1;
+        (details.rgb_components, details.alpha)
+    }
     assert_eq!(
-        input.next_including_whitespace(),
-        Ok(&Token::Ident("baz".into()))
+        parse("rgb(100%, 0%, 0%)"),
+        (Some(RgbComponents::Percentages([1., 0., 0.])), None)
     );
     assert_eq!(
-        input.current_source_location(),
-        SourceLocation { line: 2, column: 6 }
+        parse("rgb(255, 0, 0)"),
+        (Some(RgbComponents::Numbers([255., 0., 0.])), None)
     );
-    let state = input.state();
-
     assert_eq!(
-        input.next_including_whitespace(),
-        Ok(&Token::WhiteSpace("\r\n\n"))
+        parse("rgba(300 20.5 -1 / 50%)"),
+        (
+            Some(RgbComponents::Numbers([300., 20.5, -1.])),
+            Some(NumberOrPercentage::Percentage { unit_value: 0.5 })
+        )
     );
     assert_eq!(
-        input.current_source_location(),
-        SourceLocation { line: 4, column: 1 }
+        parse("hsla(0, 0%, 0%, 0.25)"),
+        (None, Some(NumberOrPercentage::Number { value: 0.25 }))
     );
+    assert_eq!(parse("red"), (None, None));
+}
 
-    assert_eq!(
-        state.source_location(),
-        SourceLocation { line: 2, column: 6 }
-    );
+#[cfg(feature = "color")]
+#[test]
+fn color_keyword_as_written() {
+    fn keyword(css: &str) -> Option<String> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        let result: Result<_, ParseError<()>> =
+            Color::parse_with_details(&DefaultComponentParser, &mut parser);
+        result.unwrap().1.keyword
+    }
+    assert_eq!(keyword("Red"), Some("red".to_owned()));
+    assert_eq!(keyword("TRANSPARENT"), Some("transparent".to_owned()));
+    assert_eq!(keyword("currentColor"), Some("currentcolor".to_owned()));
+    assert_eq!(keyword("#f00"), None);
+    assert_eq!(keyword("rgb(255, 0, 0)"), None);
+}
+
+#[cfg(feature = "color")]
+#[test]
+fn preserve_hsl_and_hwb() {
+    struct PreservingComponentParser;
+    impl<'i> ColorComponentParser<'i> for PreservingComponentParser {
+        type Error = ();
+        fn preserve_hsl_and_hwb(&self) -> bool {
+            true
+        }
+    }
+    fn parse<P: for<'i> ColorComponentParser<'i, Error = ()>>(
+        component_parser: &P,
+        css: &str,
+    ) -> Result<Color, ()> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        Color::parse_with(component_parser, &mut parser).map_err(|_| ())
+    }
 
+    let color = parse(&PreservingComponentParser, "hsla(120deg, 100%, 25%, 0.5)").unwrap();
     assert_eq!(
-        input.next_including_whitespace(),
-        Ok(&Token::UnquotedUrl("u".into()))
+        color,
+        Color::Hsl(Hsla {
+            hue: 120.,
+            saturation: 1.,
+            lightness: 0.25,
+            alpha: 0.5,
+        })
     );
+    assert_eq!(color.to_css_string(), "hsla(120, 100%, 25%, 0.5)");
     assert_eq!(
-        input.current_source_location(),
-        SourceLocation { line: 6, column: 2 }
+        color.to_rgba(),
+        parse(&DefaultComponentParser, "hsla(120deg, 100%, 25%, 0.5)")
+            .unwrap()
+            .to_rgba()
     );
 
+    let color = parse(&PreservingComponentParser, "hwb(-90 20% 30% / 25%)").unwrap();
     assert_eq!(
-        input.next_including_whitespace(),
-        Ok(&Token::QuotedString("ab".into()))
+        color,
+        Color::Hwb(Hwba {
+            hue: 270.,
+            whiteness: 0.2,
+            blackness: 0.3,
+            alpha: 0.25,
+        })
     );
+    assert_eq!(color.to_css_string(), "hwb(270 20% 30% / 0.25)");
+    assert_eq!(color.to_rgba(), Some(RGBA::new(115, 51, 179, 64)));
     assert_eq!(
-        input.current_source_location(),
-        SourceLocation { line: 7, column: 3 }
+        parse(&PreservingComponentParser, "hwb(0 60% 60%)").map(|color| color.to_rgba()),
+        Ok(Some(RGBA::new(128, 128, 128, 255)))
     );
-    assert!(input.next_including_whitespace().is_err());
+    // Only parsed by parsers that opt in.
+    assert_eq!(
+        parse(&DefaultComponentParser, "hwb(270 20% 30% / 0.25)"),
+        Err(())
+    );
+    assert_eq!(Color::CurrentColor.to_rgba(), None);
 }
 
-#[test]
-fn overflow() {
-    use std::f32;
-    use std::iter::repeat;
-
-    let css = r"
-         2147483646
-         2147483647
-         2147483648
-         10000000000000
-         1000000000000000000000000000000000000000
-         1{309 zeros}
+define_css_keyword_enum! {
+    enum Side {
+        "left" => Left,
+        "right" => Right,
+        "inline-start" => InlineStart,
+    }
+}
 
-         -2147483647
-         -2147483648
-         -2147483649
-         -10000000000000
-         -1000000000000000000000000000000000000000
-         -1{309 zeros}
+#[test]
+fn keyword_enum() {
+    fn parse(css: &str) -> Result<Side, BasicParseError<'_>> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        Side::parse(&mut parser)
+    }
+    assert_eq!(parse("LEFT"), Ok(Side::Left));
+    assert_eq!(parse("inline-Start"), Ok(Side::InlineStart));
+    assert_eq!(Side::InlineStart.to_css_string(), "inline-start");
+    assert_eq!(Side::from_ident("top"), None);
+    let error = parse("top").unwrap_err();
+    assert_eq!(
+        error.kind,
+        BasicParseErrorKind::UnexpectedToken(Token::Ident("top".into()))
+    );
 
-         3.30282347e+38
-         3.40282347e+38
-         3.402824e+38
+    let mut input = ParserInput::new("left, right");
+    let mut parser = Parser::new(&mut input);
+    let result: Result<_, ParseError<()>> = parser.parse_comma_separated(Side::from_css);
+    assert_eq!(result, Ok(vec![Side::Left, Side::Right]));
+}
 
-         -3.30282347e+38
-         -3.40282347e+38
-         -3.402824e+38
+#[cfg(feature = "values")]
+#[test]
+fn dimensions() {
+    fn parse<'i, T: FromCss<'i>>(css: &'i str) -> Result<T, ParseError<'i, ()>> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        parser.parse_entirely(T::from_css)
+    }
 
-    "
-    .replace("{309 zeros}", &repeat('0').take(309).collect::<String>());
-    let mut input = ParserInput::new(&css);
-    let mut input = Parser::new(&mut input);
+    let length: Length = parse("2.5IN").unwrap();
+    assert_eq!(length.unit, LengthUnit::In);
+    assert_eq!(length.to_px(), Some(240.));
+    assert_eq!(length.to_css_string(), "2.5in");
+    let zero: Length = parse("0").unwrap();
+    assert_eq!(zero.to_css_string(), "0px");
+    assert_eq!(parse::<Length>("3em").unwrap().to_px(), None);
+    assert!(parse::<Length>("1").is_err());
+    assert!(parse::<Length>("1deg").is_err());
 
-    assert_eq!(input.expect_integer(), Ok(2147483646));
-    assert_eq!(input.expect_integer(), Ok(2147483647));
-    assert_eq!(input.expect_integer(), Ok(2147483647)); // Clamp on overflow
-    assert_eq!(input.expect_integer(), Ok(2147483647));
-    assert_eq!(input.expect_integer(), Ok(2147483647));
-    assert_eq!(input.expect_integer(), Ok(2147483647));
+    let angle: Angle = parse("0.25turn").unwrap();
+    assert_eq!(angle.unit, AngleUnit::Turn);
+    assert_eq!(angle.to_degrees(), 90.);
+    assert_eq!(angle.to_css_string(), "0.25turn");
+    assert_eq!(parse::<Angle>("200grad").unwrap().to_degrees(), 180.);
+    assert!(parse::<Angle>("0").is_err());
 
-    assert_eq!(input.expect_integer(), Ok(-2147483647));
-    assert_eq!(input.expect_integer(), Ok(-2147483648));
-    assert_eq!(input.expect_integer(), Ok(-2147483648)); // Clamp on overflow
-    assert_eq!(input.expect_integer(), Ok(-2147483648));
-    assert_eq!(input.expect_integer(), Ok(-2147483648));
-    assert_eq!(input.expect_integer(), Ok(-2147483648));
+    let time: Time = parse("250Ms").unwrap();
+    assert_eq!(time.unit, TimeUnit::Ms);
+    assert_eq!(time.to_seconds(), 0.25);
+    assert_eq!(time.to_css_string(), "250ms");
+    assert!(parse::<Time>("1px").is_err());
+    assert_eq!(time.to_canonical().to_css_string(), "0.25s");
+    assert_eq!(parse::<Time>("1.5s").unwrap().to_milliseconds(), 1500.);
+    assert_eq!(angle.to_canonical().to_css_string(), "90deg");
 
-    assert_eq!(input.expect_number(), Ok(3.30282347e+38));
-    assert_eq!(input.expect_number(), Ok(f32::MAX));
-    assert_eq!(input.expect_number(), Ok(f32::INFINITY));
-    assert!(f32::MAX != f32::INFINITY);
+    let frequency: Frequency = parse("1.5kHz").unwrap();
+    assert_eq!(frequency.unit, FrequencyUnit::KHz);
+    assert_eq!(frequency.to_hertz(), 1500.);
+    assert_eq!(frequency.to_css_string(), "1.5khz");
+    assert_eq!(frequency.to_canonical().to_css_string(), "1500hz");
+    assert_eq!(parse::<Frequency>("250hz").unwrap().to_kilohertz(), 0.25);
+    assert!(parse::<Frequency>("1s").is_err());
 
-    assert_eq!(input.expect_number(), Ok(-3.30282347e+38));
-    assert_eq!(input.expect_number(), Ok(f32::MIN));
-    assert_eq!(input.expect_number(), Ok(f32::NEG_INFINITY));
-    assert!(f32::MIN != f32::NEG_INFINITY);
+    let resolution: Resolution = parse("192DPI").unwrap();
+    assert_eq!(resolution.unit, ResolutionUnit::Dpi);
+    assert_eq!(resolution.to_dppx(), 2.);
+    assert_eq!(resolution.to_css_string(), "192dpi");
+    assert_eq!(resolution.to_canonical().to_css_string(), "2dppx");
+    let resolution: Resolution = parse("2x").unwrap();
+    assert_eq!(resolution.to_dpi(), 192.);
+    assert_eq!(resolution.to_canonical().to_css_string(), "2dppx");
+    assert!(parse::<Resolution>("2").is_err());
 }
 
+#[cfg(feature = "values")]
 #[test]
-fn line_delimited() {
-    let mut input = ParserInput::new(" { foo ; bar } baz;,");
-    let mut input = Parser::new(&mut input);
-    assert_eq!(input.next(), Ok(&Token::CurlyBracketBlock));
-    assert!({
-        let result: Result<_, ParseError<()>> =
-            input.parse_until_after(Delimiter::Semicolon, |_| Ok(42));
-        result
+fn ratios() {
+    fn parse(css: &str) -> Result<Ratio, ParseError<'_, ()>> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        parser.parse_entirely(Ratio::from_css)
     }
-    .is_err());
-    assert_eq!(input.next(), Ok(&Token::Comma));
-    assert!(input.next().is_err());
+    let ratio = parse("16/9").unwrap();
+    assert_eq!(
+        ratio,
+        Ratio {
+            numerator: 16.,
+            denominator: 9.
+        }
+    );
+    assert_eq!(ratio.to_css_string(), "16 / 9");
+    assert_eq!(parse(" 4 /* x */ / 3 ").unwrap().to_css_string(), "4 / 3");
+    assert_eq!(parse("1.5").unwrap().to_css_string(), "1.5 / 1");
+    assert!(parse("0 / 1").unwrap().is_degenerate());
+    assert!(parse("-1 / 2").is_err());
+    assert!(parse("16 /").is_err());
+    assert!(parse("16 9").is_err());
+    assert!(parse("16px / 9").is_err());
 }
 
+#[cfg(feature = "values")]
 #[test]
-fn identifier_serialization() {
-    // Null bytes
-    assert_eq!(Token::Ident("\0".into()).to_css_string(), "\u{FFFD}");
-    assert_eq!(Token::Ident("a\0".into()).to_css_string(), "a\u{FFFD}");
-    assert_eq!(Token::Ident("\0b".into()).to_css_string(), "\u{FFFD}b");
-    assert_eq!(Token::Ident("a\0b".into()).to_css_string(), "a\u{FFFD}b");
+fn media_queries() {
+    fn parse(css: &str) -> Vec<MediaQuery> {
+        let mut input = ParserInput::new(css);
+        parse_media_query_list(&mut Parser::new(&mut input))
+    }
+    let px = |value| {
+        MediaFeatureValue::Length(Length {
+            value,
+            unit: LengthUnit::Px,
+        })
+    };
+    assert_eq!(
+        parse("(400px < WIDTH < 800px), only screen and (min-width: 600px)"),
+        [
+            MediaQuery {
+                qualifier: None,
+                media_type: None,
+                condition: Some(MediaCondition::Feature(MediaFeature {
+                    name: "width".into(),
+                    comparisons: vec![
+                        (MediaComparison::GreaterThan, px(400.)),
+                        (MediaComparison::LessThan, px(800.)),
+                    ],
+                })),
+            },
+            MediaQuery {
+                qualifier: Some(MediaQualifier::Only),
+                media_type: Some("screen".into()),
+                condition: Some(MediaCondition::Feature(MediaFeature {
+                    name: "width".into(),
+                    comparisons: vec![(MediaComparison::GreaterThanOrEqual, px(600.))],
+                })),
+            },
+        ]
+    );
 
-    // Replacement character
-    assert_eq!(Token::Ident("\u{FFFD}".into()).to_css_string(), "\u{FFFD}");
+    let device = MediaDevice {
+        media_type: "screen",
+        width: 800.,
+        height: 600.,
+        resolution: 2.,
+    };
+    let matches =
+        |css| media_query_list_matches(&parse(css), &device, |feature| feature.name == "color");
+    assert!(matches(""));
+    assert!(matches("screen"));
+    assert!(!matches("print"));
+    assert!(matches("not print"));
+    assert!(matches("only screen and (min-width: 600px)"));
+    assert!(matches("all and (max-width: 50em)"));
+    assert!(!matches("(width > 800px)"));
+    assert!(matches("(400px < width <= 800px)"));
+    assert!(!matches("(400px < width > 800px)"));
+    assert!(matches("(orientation: landscape)"));
+    assert!(matches("(aspect-ratio: 4/3)"));
+    assert!(matches("(min-resolution: 192dpi)"));
+    assert!(!matches("(resolution: 3x)"));
+    assert!(!matches("(width: 3)"));
+    assert!(matches("(color)"));
+    assert!(!matches("(hover)"));
+    assert!(!matches("not (color)"));
+    assert!(matches("(monochrome) or ((color) and (width))"));
+    assert!(!matches("(width < 100px) and (color)"));
+    assert!(matches("print, (min-width: 100vw)"));
+    assert!(!matches("foo(bar)"));
+    assert!(!matches("(foo bar)"));
+    assert!(!matches("not (foo bar)"));
+    assert!(!matches("not (color) and (foo bar)"));
+    assert!(matches("(color) or (foo bar)"));
+    assert!(!matches("not ((color) and (foo bar))"));
+    assert!(matches("not print and (foo bar)"));
+    assert!(!matches("not screen and (foo bar)"));
+    assert!(!matches("screen and"));
+    assert!(!matches("screen and (color) or (hover)"));
+    assert_eq!(parse("print, screen and, (color)").len(), 3);
     assert_eq!(
-        Token::Ident("a\u{FFFD}".into()).to_css_string(),
-        "a\u{FFFD}"
+        parse("screen and foo(bar)")[0].condition,
+        Some(MediaCondition::GeneralEnclosed("foo(bar)".into()))
     );
+}
+
+#[test]
+fn supports_conditions() {
+    fn parse(css: &str) -> Result<SupportsCondition<'_>, String> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        let result: Result<_, ParseError<()>> =
+            parser.parse_entirely(|input| Ok(SupportsCondition::parse(input)?));
+        result.map_err(|error| error.basic().to_string())
+    }
+    let declaration = |name: &'static str, value| SupportsCondition::Declaration {
+        name: name.into(),
+        value,
+    };
     assert_eq!(
-        Token::Ident("\u{FFFD}b".into()).to_css_string(),
-        "\u{FFFD}b"
+        parse("(display: grid) and (not (display:inline-grid)) AND selector( a > b )"),
+        Ok(SupportsCondition::And(vec![
+            declaration("display", "grid"),
+            SupportsCondition::Not(Box::new(declaration("display", "inline-grid"))),
+            SupportsCondition::Selector("a > b"),
+        ]))
     );
     assert_eq!(
-        Token::Ident("a\u{FFFD}b".into()).to_css_string(),
-        "a\u{FFFD}b"
+        parse("(foo bar) or font-tech(color-COLRv1)"),
+        Ok(SupportsCondition::Or(vec![
+            SupportsCondition::GeneralEnclosed("(foo bar)"),
+            SupportsCondition::GeneralEnclosed("font-tech(color-COLRv1)"),
+        ]))
     );
+    assert_eq!(
+        parse("selector(a, b) or selector( )"),
+        Ok(SupportsCondition::Or(vec![
+            SupportsCondition::GeneralEnclosed("selector(a, b)"),
+            SupportsCondition::GeneralEnclosed("selector( )"),
+        ]))
+    );
+    assert!(parse("(a: b) and (c: d) or (e: f)").is_err());
+    assert!(parse("display: grid").is_err());
 
-    // Number prefix
-    assert_eq!(Token::Ident("0a".into()).to_css_string(), "\\30 a");
-    assert_eq!(Token::Ident("1a".into()).to_css_string(), "\\31 a");
-    assert_eq!(Token::Ident("2a".into()).to_css_string(), "\\32 a");
-    assert_eq!(Token::Ident("3a".into()).to_css_string(), "\\33 a");
-    assert_eq!(Token::Ident("4a".into()).to_css_string(), "\\34 a");
-    assert_eq!(Token::Ident("5a".into()).to_css_string(), "\\35 a");
-    assert_eq!(Token::Ident("6a".into()).to_css_string(), "\\36 a");
-    assert_eq!(Token::Ident("7a".into()).to_css_string(), "\\37 a");
-    assert_eq!(Token::Ident("8a".into()).to_css_string(), "\\38 a");
-    assert_eq!(Token::Ident("9a".into()).to_css_string(), "\\39 a");
-
-    // Letter number prefix
-    assert_eq!(Token::Ident("a0b".into()).to_css_string(), "a0b");
-    assert_eq!(Token::Ident("a1b".into()).to_css_string(), "a1b");
-    assert_eq!(Token::Ident("a2b".into()).to_css_string(), "a2b");
-    assert_eq!(Token::Ident("a3b".into()).to_css_string(), "a3b");
-    assert_eq!(Token::Ident("a4b".into()).to_css_string(), "a4b");
-    assert_eq!(Token::Ident("a5b".into()).to_css_string(), "a5b");
-    assert_eq!(Token::Ident("a6b".into()).to_css_string(), "a6b");
-    assert_eq!(Token::Ident("a7b".into()).to_css_string(), "a7b");
-    assert_eq!(Token::Ident("a8b".into()).to_css_string(), "a8b");
-    assert_eq!(Token::Ident("a9b".into()).to_css_string(), "a9b");
+    let calls = Cell::new(0);
+    let mut evaluator = SupportsEvaluator::new(
+        |name, input| {
+            calls.set(calls.get() + 1);
+            name == "display"
+                && input
+                    .expect_ident()
+                    .map_or(false, |value| *value == "grid" || *value == "flex")
+                && input.is_exhausted()
+        },
+        |input| {
+            let mut compounds = 0;
+            while let Ok(token) = input.next() {
+                match *token {
+                    Token::Ident(_) => compounds += 1,
+                    Token::Delim('>') => {}
+                    _ => return false,
+                }
+            }
+            compounds > 0
+        },
+    );
+    let mut evaluate = |css| evaluator.evaluate(&parse(css).unwrap());
+    assert!(evaluate(
+        "(display: grid) and (not (display:inline-grid)) and selector(a > b)"
+    ));
+    assert_eq!(calls.get(), 2);
+    assert!(evaluate("(display: flex) or (display: grid)"));
+    assert_eq!(calls.get(), 3);
+    assert!(!evaluate("(display: inline-grid) and (display: flex)"));
+    assert!(!evaluate("not (display: grid)"));
+    assert!(!evaluate(
+        "(display: grid red) or (foo bar) or selector(a:hover)"
+    ));
+    assert_eq!(calls.get(), 4);
+    assert!(evaluate("selector(a   >b)"));
+    assert!(!evaluate("selector(a, b)"));
+}
 
-    // Dash number prefix
-    assert_eq!(Token::Ident("-0a".into()).to_css_string(), "-\\30 a");
-    assert_eq!(Token::Ident("-1a".into()).to_css_string(), "-\\31 a");
-    assert_eq!(Token::Ident("-2a".into()).to_css_string(), "-\\32 a");
-    assert_eq!(Token::Ident("-3a".into()).to_css_string(), "-\\33 a");
-    assert_eq!(Token::Ident("-4a".into()).to_css_string(), "-\\34 a");
-    assert_eq!(Token::Ident("-5a".into()).to_css_string(), "-\\35 a");
-    assert_eq!(Token::Ident("-6a".into()).to_css_string(), "-\\36 a");
-    assert_eq!(Token::Ident("-7a".into()).to_css_string(), "-\\37 a");
-    assert_eq!(Token::Ident("-8a".into()).to_css_string(), "-\\38 a");
-    assert_eq!(Token::Ident("-9a".into()).to_css_string(), "-\\39 a");
+#[cfg(feature = "values")]
+#[test]
+fn from_css() {
+    #[derive(Debug, PartialEq)]
+    struct CustomError;
 
-    // Double dash prefix
-    assert_eq!(Token::Ident("--a".into()).to_css_string(), "--a");
+    fn parse<'i, T: FromCss<'i, CustomError>>(
+        input: &mut Parser<'i, '_>,
+    ) -> Result<Vec<T>, ParseError<'i, CustomError>> {
+        input.parse_comma_separated(T::from_css)
+    }
 
-    // Various tests
-    assert_eq!(
-        Token::Ident("\x01\x02\x1E\x1F".into()).to_css_string(),
-        "\\1 \\2 \\1e \\1f "
-    );
-    assert_eq!(
-        Token::Ident("\u{0080}\x2D\x5F\u{00A9}".into()).to_css_string(),
-        "\u{0080}\x2D\x5F\u{00A9}"
-    );
-    assert_eq!(Token::Ident("\x7F\u{0080}\u{0081}\u{0082}\u{0083}\u{0084}\u{0085}\u{0086}\u{0087}\u{0088}\u{0089}\
-        \u{008A}\u{008B}\u{008C}\u{008D}\u{008E}\u{008F}\u{0090}\u{0091}\u{0092}\u{0093}\u{0094}\u{0095}\u{0096}\
-        \u{0097}\u{0098}\u{0099}\u{009A}\u{009B}\u{009C}\u{009D}\u{009E}\u{009F}".into()).to_css_string(),
-        "\\7f \u{0080}\u{0081}\u{0082}\u{0083}\u{0084}\u{0085}\u{0086}\u{0087}\u{0088}\u{0089}\u{008A}\u{008B}\u{008C}\
-        \u{008D}\u{008E}\u{008F}\u{0090}\u{0091}\u{0092}\u{0093}\u{0094}\u{0095}\u{0096}\u{0097}\u{0098}\u{0099}\
-        \u{009A}\u{009B}\u{009C}\u{009D}\u{009E}\u{009F}");
-    assert_eq!(
-        Token::Ident("\u{00A0}\u{00A1}\u{00A2}".into()).to_css_string(),
-        "\u{00A0}\u{00A1}\u{00A2}"
-    );
-    assert_eq!(
-        Token::Ident("a0123456789b".into()).to_css_string(),
-        "a0123456789b"
-    );
+    let mut input = ParserInput::new("red, #00f");
+    let mut parser = Parser::new(&mut input);
     assert_eq!(
-        Token::Ident("abcdefghijklmnopqrstuvwxyz".into()).to_css_string(),
-        "abcdefghijklmnopqrstuvwxyz"
+        parse::<Color>(&mut parser),
+        Ok(vec![
+            Color::RGBA(RGBA::new(255, 0, 0, 255)),
+            Color::RGBA(RGBA::new(0, 0, 255, 255)),
+        ])
     );
+
+    let mut input = ParserInput::new("U+26, u+0-7F");
+    let mut parser = Parser::new(&mut input);
     assert_eq!(
-        Token::Ident("ABCDEFGHIJKLMNOPQRSTUVWXYZ".into()).to_css_string(),
-        "ABCDEFGHIJKLMNOPQRSTUVWXYZ"
+        parse::<UnicodeRange>(&mut parser),
+        Ok(vec![
+            UnicodeRange {
+                start: 0x26,
+                end: 0x26
+            },
+            UnicodeRange {
+                start: 0x0,
+                end: 0x7F
+            },
+        ])
     );
+
+    let mut input = ParserInput::new("unset");
+    let mut parser = Parser::new(&mut input);
+    let result: Result<_, ParseError<()>> = CssWideKeyword::from_css(&mut parser);
+    assert_eq!(result, Ok(CssWideKeyword::Unset));
+
+    let mut input = ParserInput::new("counter(x)");
+    let mut parser = Parser::new(&mut input);
+    let result: Result<_, ParseError<()>> = Counter::from_css(&mut parser);
+    assert_eq!(result.unwrap().name, "x");
+}
+
+#[cfg(feature = "color")]
+#[test]
+fn color_parse_str() {
     assert_eq!(
-        Token::Ident("\x20\x21\x78\x79".into()).to_css_string(),
-        "\\ \\!xy"
+        Color::parse_str(" rgb(1, 2, 3) /* comment */ "),
+        Ok(Color::RGBA(RGBA::new(1, 2, 3, 255)))
     );
+    assert_eq!(Color::parse_str("currentcolor"), Ok(Color::CurrentColor));
 
-    // astral symbol (U+1D306 TETRAGRAM FOR CENTRE)
+    let error = Color::parse_str("red blue").unwrap_err();
     assert_eq!(
-        Token::Ident("\u{1D306}".into()).to_css_string(),
-        "\u{1D306}"
+        error.kind,
+        BasicParseErrorKind::UnexpectedToken(Token::Ident("blue".into()))
     );
-}
+    assert_eq!(error.location, SourceLocation { line: 0, column: 4 });
 
-trait ToJson {
-    fn to_json(&self) -> Value;
+    let error = Color::parse_str("").unwrap_err();
+    assert_eq!(error.kind, BasicParseErrorKind::EndOfInput);
 }
 
-impl<T> ToJson for T
-where
-    T: Clone,
-    Value: From<T>,
-{
-    fn to_json(&self) -> Value {
-        Value::from(self.clone())
-    }
+#[cfg(feature = "color")]
+#[test]
+fn rgba_packed_conversions() {
+    let color = RGBA::new(0x12, 0x34, 0x56, 0x78);
+    assert_eq!(color.to_rgba_u32(), 0x12345678);
+    assert_eq!(color.to_argb_u32(), 0x78123456);
+    assert_eq!(RGBA::from_rgba_u32(0x12345678), color);
+    assert_eq!(RGBA::from_argb_u32(0x78123456), color);
+    assert_eq!(<[u8; 4]>::from(color), [0x12, 0x34, 0x56, 0x78]);
+    assert_eq!(RGBA::from([0x12, 0x34, 0x56, 0x78]), color);
 }
 
-impl ToJson for Color {
-    fn to_json(&self) -> Value {
-        match *self {
-            Color::RGBA(ref rgba) => json!([rgba.red, rgba.green, rgba.blue, rgba.alpha]),
-            Color::CurrentColor => "currentcolor".to_json(),
-        }
-    }
+#[cfg(feature = "color")]
+#[test]
+fn serialize_current_color() {
+    let c = Color::CurrentColor;
+    assert!(c.to_css_string() == "currentcolor");
 }
 
-impl<'a> ToJson for CowRcStr<'a> {
-    fn to_json(&self) -> Value {
-        let s: &str = &*self;
-        s.to_json()
-    }
+#[cfg(feature = "color")]
+#[test]
+fn serialize_rgb_full_alpha() {
+    let c = Color::RGBA(RGBA::new(255, 230, 204, 255));
+    assert_eq!(c.to_css_string(), "rgb(255, 230, 204)");
 }
 
-#[cfg(feature = "bench")]
-const BACKGROUND_IMAGE: &'static str = include_str!("big-data-url.css");
-
-#[cfg(feature = "bench")]
-#[bench]
-fn unquoted_url(b: &mut Bencher) {
-    b.iter(|| {
-        let mut input = ParserInput::new(BACKGROUND_IMAGE);
-        let mut input = Parser::new(&mut input);
-        input.look_for_var_or_env_functions();
-
-        let result = input.try_parse(|input| input.expect_url());
-
-        assert!(result.is_ok());
-
-        input.seen_var_or_env_functions();
-        (result.is_ok(), input.seen_var_or_env_functions())
-    })
+#[cfg(feature = "color")]
+#[test]
+fn serialize_rgba() {
+    let c = Color::RGBA(RGBA::new(26, 51, 77, 32));
+    assert_eq!(c.to_css_string(), "rgba(26, 51, 77, 0.125)");
 }
 
-#[cfg(feature = "bench")]
-#[bench]
-fn numeric(b: &mut Bencher) {
-    b.iter(|| {
-        for _ in 0..1000000 {
-            let mut input = ParserInput::new("10px");
-            let mut input = Parser::new(&mut input);
-            let _ = test::black_box(input.next());
-        }
-    })
+#[cfg(feature = "color")]
+#[test]
+fn serialize_rgba_two_digit_float_if_roundtrips() {
+    let c = Color::RGBA(RGBA::from_floats(0., 0., 0., 0.5));
+    assert_eq!(c.to_css_string(), "rgba(0, 0, 0, 0.5)");
 }
 
-struct JsonParser;
-
+#[cfg(feature = "color")]
 #[test]
-fn no_stack_overflow_multiple_nested_blocks() {
-    let mut input: String = "{{".into();
-    for _ in 0..20 {
-        let dup = input.clone();
-        input.push_str(&dup);
+fn serialize_shortest_color() {
+    fn shortest(css: &str) -> String {
+        let mut css_string = String::new();
+        Color::parse_str(css)
+            .unwrap()
+            .to_css_shortest(&mut css_string)
+            .unwrap();
+        css_string
+    }
+    assert_eq!(shortest("#FF0000"), "red");
+    assert_eq!(shortest("rgb(0, 0, 255)"), "#00f");
+    assert_eq!(shortest("#000080"), "navy");
+    assert_eq!(shortest("#c0c0c0"), "silver");
+    assert_eq!(shortest("#ffffff"), "#fff");
+    assert_eq!(shortest("fuchsia"), "#f0f");
+    assert_eq!(shortest("#123457"), "#123457");
+    assert_eq!(shortest("hsl(120, 100%, 25%)"), "green");
+    assert_eq!(shortest("rgba(255, 0, 0, 0.5)"), "rgba(255, 0, 0, 0.5)");
+    assert_eq!(shortest("transparent"), "rgba(0, 0, 0, 0)");
+    assert_eq!(shortest("currentColor"), "currentcolor");
+    let keywords = [
+        "tan", "gold", "gray", "peru", "pink", "plum", "snow", "teal", "azure", "beige", "brown",
+        "coral", "ivory", "khaki", "linen", "olive", "wheat", "bisque", "indigo", "maroon",
+        "orange", "orchid", "purple", "salmon", "sienna", "tomato", "violet",
+    ];
+    for &keyword in &keywords {
+        assert_eq!(shortest(keyword), keyword);
     }
-    let mut input = ParserInput::new(&input);
-    let mut input = Parser::new(&mut input);
-    while let Ok(..) = input.next() {}
 }
 
-impl<'i> DeclarationParser<'i> for JsonParser {
-    type Declaration = Value;
-    type Error = ();
+#[cfg(feature = "color")]
+#[test]
+fn serialize_alpha_cssom_rounding() {
+    fn serialize(css: &str) -> String {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        Color::parse(&mut parser).unwrap().to_css_string()
+    }
+    assert_eq!(serialize("rgba(0, 0, 0, 0.3)"), "rgba(0, 0, 0, 0.3)");
+    assert_eq!(serialize("rgba(0, 0, 0, 0.299999)"), "rgba(0, 0, 0, 0.298)");
+    assert_eq!(serialize("rgba(0, 0, 0, 0.001)"), "rgba(0, 0, 0, 0)");
+    assert_eq!(serialize("rgba(0, 0, 0, 0.003)"), "rgba(0, 0, 0, 0.004)");
+    assert_eq!(serialize("rgba(0, 0, 0, 0.999)"), "rgb(0, 0, 0)");
 
-    fn parse_value<'t>(
-        &mut self,
-        name: CowRcStr<'i>,
-        input: &mut Parser<'i, 't>,
-    ) -> Result<Value, ParseError<'i, ()>> {
-        let mut value = vec![];
-        let mut important = false;
-        loop {
-            let start = input.state();
-            if let Ok(mut token) = input.next_including_whitespace().map(|t| t.clone()) {
-                // Hack to deal with css-parsing-tests assuming that
-                // `!important` in the middle of a declaration value is OK.
-                // This can never happen per spec
-                // (even CSS Variables forbid top-level `!`)
-                if token == Token::Delim('!') {
-                    input.reset(&start);
-                    if parse_important(input).is_ok() {
-                        if input.is_exhausted() {
-                            important = true;
-                            break;
-                        }
-                    }
-                    input.reset(&start);
-                    token = input.next_including_whitespace().unwrap().clone();
-                }
-                value.push(one_component_value_to_json(token, input));
-            } else {
-                break;
-            }
-        }
-        Ok(JArray!["declaration", name, value, important,])
+    let hsla = Hsla {
+        hue: 0.,
+        saturation: 0.,
+        lightness: 0.,
+        alpha: 0.299999,
+    };
+    assert_eq!(hsla.to_css_string(), "hsla(0, 0%, 0%, 0.298)");
+
+    // Every alpha value survives a round trip through its serialization.
+    for alpha in 0..=255 {
+        let serialized = RGBA::new(0, 0, 0, alpha).to_css_string();
+        let mut input = ParserInput::new(&serialized);
+        let mut parser = Parser::new(&mut input);
+        assert_eq!(
+            Color::parse(&mut parser),
+            Ok(Color::RGBA(RGBA::new(0, 0, 0, alpha))),
+            "{}",
+            serialized
+        );
     }
 }
 
-impl<'i> AtRuleParser<'i> for JsonParser {
-    type PreludeNoBlock = Vec<Value>;
-    type PreludeBlock = Vec<Value>;
-    type AtRule = Value;
-    type Error = ();
-
-    fn parse_prelude<'t>(
-        &mut self,
-        name: CowRcStr<'i>,
-        input: &mut Parser<'i, 't>,
-    ) -> Result<AtRuleType<Vec<Value>, Vec<Value>>, ParseError<'i, ()>> {
-        let prelude = vec![
-            "at-rule".to_json(),
-            name.to_json(),
-            Value::Array(component_values_to_json(input)),
-        ];
-        match_ignore_ascii_case! { &*name,
-            "media" | "foo-with-block" => Ok(AtRuleType::WithBlock(prelude)),
-            "charset" => {
-                Err(input.new_error(BasicParseErrorKind::AtRuleInvalid(name.clone()).into()))
-            },
-            _ => Ok(AtRuleType::WithoutBlock(prelude)),
-        }
+#[test]
+fn tokenize_bytes() {
+    fn tokenize(css: &[u8]) -> Vec<(String, SourceLocation)> {
+        let mut tokens = Vec::new();
+        tokenize_bytes_lossy(css, |token, location| {
+            tokens.push((token.to_css_string(), location))
+        });
+        tokens
+    }
+    fn tokenize_str(css: &str) -> Vec<(String, SourceLocation)> {
+        let mut tokens = Vec::new();
+        let mut tokenizer = ChunkedTokenizer::new();
+        tokenizer.push_str(css, |token, location| {
+            tokens.push((token.to_css_string(), location))
+        });
+        tokenizer.finish(|token, location| tokens.push((token.to_css_string(), location)));
+        tokens
     }
 
-    fn rule_without_block(&mut self, mut prelude: Vec<Value>, _location: SourceLocation) -> Value {
-        prelude.push(Value::Null);
-        Value::Array(prelude)
+    let css = "a { content: \"h\u{e9}llo\" }\n@media (min-width: 1e+1px) { b { c: url( 'x') } }";
+    assert_eq!(tokenize(css.as_bytes()), tokenize_str(css));
+    assert_eq!(tokenize(b"\xEF\xBB\xBFa{}"), tokenize_str("a{}"));
+    assert_eq!(
+        tokenize(b"\xFFa { b\xC3: \"c\xE2\x82\" }\n.d\xE9e{} \xF0\x9F\x98"),
+        tokenize_str("\u{FFFD}a { b\u{FFFD}: \"c\u{FFFD}\" }\n.d\u{FFFD}e{} \u{FFFD}")
+    );
+    assert_eq!(tokenize(b""), vec![]);
+
+    for i in 0..=css.len() {
+        let mut bytes = css.as_bytes().to_vec();
+        bytes.insert(i, b'\xFF');
+        let lossy = String::from_utf8_lossy(&bytes);
+        assert_eq!(tokenize(&bytes), tokenize_str(&lossy), "{}", lossy);
     }
 
-    fn parse_block<'t>(
-        &mut self,
-        mut prelude: Vec<Value>,
-        _location: SourceLocation,
-        input: &mut Parser<'i, 't>,
-    ) -> Result<Value, ParseError<'i, ()>> {
-        prelude.push(Value::Array(component_values_to_json(input)));
-        Ok(Value::Array(prelude))
+    // Long tokens across invalid bytes, and long valid input between them.
+    let mut bytes = b"a{b:\"".to_vec();
+    for _ in 0..1000 {
+        bytes.extend_from_slice(b"c\xFF");
     }
+    bytes.extend_from_slice(b"\" d\xFF");
+    bytes.extend(b"e f{} ".iter().cycle().take(3000));
+    bytes.extend_from_slice(b"g\xFFh\xC3");
+    let lossy = String::from_utf8_lossy(&bytes);
+    assert_eq!(tokenize(&bytes), tokenize_str(&lossy));
 }
 
-impl<'i> QualifiedRuleParser<'i> for JsonParser {
-    type Prelude = Vec<Value>;
-    type QualifiedRule = Value;
-    type Error = ();
+#[test]
+fn chunked_tokenizer() {
+    fn tokenize(chunks: &[&str]) -> Vec<(String, SourceLocation)> {
+        let mut tokens = Vec::new();
+        let mut tokenizer = ChunkedTokenizer::new();
+        for chunk in chunks {
+            tokenizer.push_str(chunk, |token, location| {
+                tokens.push((token.to_css_string(), location))
+            });
+        }
+        tokenizer.finish(|token, location| tokens.push((token.to_css_string(), location)));
+        tokens
+    }
 
-    fn parse_prelude<'t>(
-        &mut self,
-        input: &mut Parser<'i, 't>,
+    let css = "a { content: \"hello\" }\n@media (min-width: 1e+1px) { b { c: url( 'x') } }";
+    let expected = tokenize(&[css]);
+    assert_eq!(expected.len(), 37);
+    assert_eq!(
+        expected[7],
+        (
+            "\"hello\"".to_owned(),
+            SourceLocation {
+                line: 0,
+                column: 14
+            }
+        )
+    );
+    assert_eq!(
+        expected[17],
+        (
+            "10.0px".to_owned(),
+            SourceLocation {
+                line: 1,
+                column: 20
+            }
+        )
+    );
+
+    // Every split into two or three chunks gives the same tokens.
+    for i in 0..=css.len() {
+        assert_eq!(
+            tokenize(&[&css[..i], &css[i..]]),
+            expected,
+            "split at {}",
+            i
+        );
+        for j in i..=css.len() {
+            assert_eq!(tokenize(&[&css[..i], &css[i..j], &css[j..]]), expected);
+        }
+    }
+    let bytes: Vec<String> = css.chars().map(|c| c.to_string()).collect();
+    let bytes: Vec<&str> = bytes.iter().map(|c| &**c).collect();
+    assert_eq!(tokenize(&bytes), expected);
+}
+
+#[test]
+fn stylesheets_concatenated() {
+    let (css, changes) = concat_stylesheets(&[
+        "@charset \"utf-8\";\n@import 'a.css';\nbody { margin: 0 }\n",
+        "@charset \"utf-8\"; /* b */ @layer base, theme;\n@import url(c.css) layer(base);\
+         \np { color: red }\n@import 'late.css';\n/* end */",
+        "@layer theme { p { color: blue } }\n",
+    ]);
+    assert_eq!(
+        css,
+        "@charset \"utf-8\";\n\
+         @import 'a.css';\n\
+         /* b */ @layer base, theme;\n\
+         @import url(c.css) layer(base);\n\
+         body { margin: 0 }\n\
+         p { color: red }\n@import 'late.css';\n/* end */\n\
+         @layer theme { p { color: blue } }"
+    );
+    let changes: Vec<_> = changes
+        .into_iter()
+        .map(|c| (c.kind, c.stylesheet, c.location.line, c.location.column))
+        .collect();
+    use self::ConcatenationChangeKind::*;
+    assert_eq!(
+        changes,
+        [
+            (DroppedCharset, 1, 0, 1),
+            (HoistedLayerStatement, 1, 0, 27),
+            (HoistedImport, 1, 1, 1),
+        ]
+    );
+}
+
+#[test]
+fn custom_property_values_as_written() {
+    fn parse(css: &str) -> Result<(&str, bool), ParseError<'_, ()>> {
+        let mut input = ParserInput::new(css);
+        let mut input = Parser::new(&mut input);
+        parse_declaration_value(&mut input, |input| Ok(parse_custom_property_value(input)?))
+    }
+    assert_eq!(
+        parse("  01.50e1 +.5E+1 \\61 [a] {b: c;} /* c */ !important"),
+        Ok(("01.50e1 +.5E+1 \\61 [a] {b: c;} /* c */", true))
+    );
+    assert_eq!(parse(" "), Ok(("", false)));
+    assert!(parse("a )").is_err());
+    assert!(parse("f(\"a\n)").is_err());
+    assert!(parse("url(a b)").is_err());
+
+    let mut input = ParserInput::new("01.50e1");
+    let mut normalized = String::new();
+    serialize_normalized_value(&mut Parser::new(&mut input), &mut normalized).unwrap();
+    assert_eq!(normalized, "15.0");
+}
+
+#[test]
+fn custom_property_issues() {
+    let css = "\
+        :root { --used: 1px; --unused: 2px; --Used: 3px }\n\
+        a { margin: var(--used) var(--missing, 0); }\n\
+        @property --registered { syntax: '*'; inherits: false }\n\
+        b { color: var(--registered); --x: var(--unused-fallback, var(--used)) }\n\
+        --not-a-declaration: 1 {}\n\
+        c { width: calc(var( --missing ) * 2) }";
+    let issues: Vec<_> = check_custom_properties(css)
+        .into_iter()
+        .map(|issue| {
+            let location = issue.location;
+            (issue.kind, issue.name, location.line, location.column)
+        })
+        .collect();
+    use self::CustomPropertyIssueKind::*;
+    assert_eq!(
+        issues,
+        [
+            (Unused, "--unused".to_owned(), 0, 22),
+            (Unused, "--Used".to_owned(), 0, 37),
+            (Undefined, "--missing".to_owned(), 1, 29),
+            (Unused, "--x".to_owned(), 3, 31),
+            (Undefined, "--unused-fallback".to_owned(), 3, 40),
+            (Undefined, "--missing".to_owned(), 5, 22),
+        ]
+    );
+}
+
+#[cfg(feature = "lint")]
+#[test]
+fn lint_overridden_declarations() {
+    let shorthands: &[(&str, &[&str])] = &[
+        (
+            "margin",
+            &["margin-top", "margin-right", "margin-bottom", "margin-left"],
+        ),
+        ("background", &["background-color", "background-image"]),
+    ];
+    let css = "\
+        a { color: red; Color: blue; margin-top: 1px; margin: 0 }\n\
+        @media print { b { background: none !important; background-color: red } }\n\
+        c { --x: 1; --X: 2; --x: 3; invalid; d { } margin-left: 1px !important; margin: 0 }\n\
+        @font-face { src: url(a); src: url(b) !important; src: url(c) }\n\
+        @unknown { color: red; color: blue }";
+    let diagnostics: Vec<_> = lint_stylesheet(css, shorthands)
+        .into_iter()
+        .map(|d| {
+            (
+                d.kind,
+                d.property,
+                (d.location.line, d.location.column),
+                (d.overridden_by.line, d.overridden_by.column),
+            )
+        })
+        .collect();
+    use self::LintKind::*;
+    assert_eq!(
+        diagnostics,
+        [
+            (DuplicateDeclaration, "color".to_owned(), (0, 5), (0, 17)),
+            (
+                OverriddenByShorthand,
+                "margin-top".to_owned(),
+                (0, 30),
+                (0, 47)
+            ),
+            (
+                OverriddenByShorthand,
+                "background-color".to_owned(),
+                (1, 49),
+                (1, 20)
+            ),
+            (DuplicateDeclaration, "--x".to_owned(), (2, 5), (2, 21)),
+            (DuplicateDeclaration, "src".to_owned(), (3, 14), (3, 27)),
+            (DuplicateDeclaration, "src".to_owned(), (3, 51), (3, 27)),
+        ]
+    );
+}
+
+#[test]
+fn specificity_estimates() {
+    fn estimate(css: &str) -> Vec<(u32, u32, u32)> {
+        let mut input = ParserInput::new(css);
+        estimate_specificity(&mut Parser::new(&mut input))
+            .into_iter()
+            .map(|s| (s.ids, s.classes, s.elements))
+            .collect()
+    }
+    assert_eq!(estimate("*"), [(0, 0, 0)]);
+    assert_eq!(estimate("ul li.a, #x > p:hover"), [(0, 1, 2), (1, 1, 1)]);
+    assert_eq!(estimate("a[href^='http']::before"), [(0, 1, 2)]);
+    assert_eq!(
+        estimate("p:first-line, svg|rect, *|a"),
+        [(0, 0, 2), (0, 0, 1), (0, 0, 1)]
+    );
+    assert_eq!(estimate(":is(#a, .b) c"), [(1, 0, 1)]);
+    assert_eq!(estimate(":where(#a, .b) c:not(.d.e)"), [(0, 2, 1)]);
+    assert_eq!(estimate("li:nth-child(2n + 1 of .x, #y)"), [(1, 1, 1)]);
+    assert_eq!(estimate("li:nth-child(odd):lang(en)"), [(0, 2, 1)]);
+
+    let mut input = ParserInput::new(".a .b, #c");
+    let mut specificities = estimate_specificity(&mut Parser::new(&mut input));
+    specificities.sort();
+    assert!(specificities[0] < specificities[1]);
+    assert_eq!(specificities[1].ids, 1);
+}
+
+#[test]
+fn stylesheet_sniffing() {
+    let sniff = |input| sniff_stylesheet(input, 50);
+    assert_eq!(sniff("a { color: red }"), SniffedContent::Css);
+    assert_eq!(sniff("<!-- a[href=x] { } -->"), SniffedContent::Css);
+    assert_eq!(sniff("@media (width = 1px) { a { } }"), SniffedContent::Css);
+    assert_eq!(
+        sniff("\u{FEFF}  <!DOCTYPE html><html>"),
+        SniffedContent::Html
+    );
+    assert_eq!(sniff("<?xml version=\"1.0\"?>"), SniffedContent::Html);
+    assert_eq!(sniff("\n<HTML>"), SniffedContent::Html);
+    assert_eq!(sniff("(function() { })()"), SniffedContent::JavaScript);
+    assert_eq!(sniff("function init(a) { }"), SniffedContent::JavaScript);
+    assert_eq!(sniff("var x = 1;"), SniffedContent::JavaScript);
+    assert_eq!(sniff("'use strict'; x()"), SniffedContent::JavaScript);
+    assert_eq!(
+        sniff_stylesheet("a { } b { } x = 1", 8),
+        SniffedContent::Css
+    );
+}
+
+#[test]
+fn line_numbers() {
+    let mut input = ParserInput::new(concat!(
+        "fo\\30\r\n",
+        "0o bar/*\n",
+        "*/baz\r\n",
+        "\n",
+        "url(\r\n",
+        "  u \r\n",
+        ")\"a\\\r\n",
+        "b\""
+    ));
+    let mut input = Parser::new(&mut input);
+    assert_eq!(
+        input.current_source_location(),
+        SourceLocation { line: 0, column: 1 }
+    );
+    assert_eq!(
+        input.next_including_whitespace(),
+        Ok(&Token::Ident("fo00o".into()))
+    );
+    assert_eq!(
+        input.current_source_location(),
+        SourceLocation { line: 1, column: 3 }
+    );
+    assert_eq!(
+        input.next_including_whitespace(),
+        Ok(&Token::WhiteSpace(" "))
+    );
+    assert_eq!(
+        input.current_source_location(),
+        SourceLocation { line: 1, column: 4 }
+    );
+    assert_eq!(
+        input.next_including_whitespace(),
+        Ok(&Token::Ident("bar".into()))
+    );
+    assert_eq!(
+        input.current_source_location(),
+        SourceLocation { line: 1, column: 7 }
+    );
+    assert_eq!(
+        input.next_including_whitespace_and_comments(),
+        Ok(&Token::Comment("\n"))
+    );
+    assert_eq!(
+        input.current_source_location(),
+        SourceLocation { line: 2, column: 3 }
+    );
+    assert_eq!(
+        input.next_including_whitespace(),
+        Ok(&Token::Ident("baz".into()))
+    );
+    assert_eq!(
+        input.current_source_location(),
+        SourceLocation { line: 2, column: 6 }
+    );
+    let state = input.state();
+
+    assert_eq!(
+        input.next_including_whitespace(),
+        Ok(&Token::WhiteSpace("\r\n\n"))
+    );
+    assert_eq!(
+        input.current_source_location(),
+        SourceLocation { line: 4, column: 1 }
+    );
+
+    assert_eq!(
+        state.source_location(),
+        SourceLocation { line: 2, column: 6 }
+    );
+
+    assert_eq!(
+        input.next_including_whitespace(),
+        Ok(&Token::UnquotedUrl("u".into()))
+    );
+    assert_eq!(
+        input.current_source_location(),
+        SourceLocation { line: 6, column: 2 }
+    );
+
+    assert_eq!(
+        input.next_including_whitespace(),
+        Ok(&Token::QuotedString("ab".into()))
+    );
+    assert_eq!(
+        input.current_source_location(),
+        SourceLocation { line: 7, column: 3 }
+    );
+    assert!(input.next_including_whitespace().is_err());
+}
+
+#[test]
+fn overflow() {
+    use std::f32;
+    use std::iter::repeat;
+
+    let css = r"
+         2147483646
+         2147483647
+         2147483648
+         10000000000000
+         1000000000000000000000000000000000000000
+         1{309 zeros}
+
+         -2147483647
+         -2147483648
+         -2147483649
+         -10000000000000
+         -1000000000000000000000000000000000000000
+         -1{309 zeros}
+
+         3.30282347e+38
+         3.40282347e+38
+         3.402824e+38
+
+         -3.30282347e+38
+         -3.40282347e+38
+         -3.402824e+38
+
+    "
+    .replace("{309 zeros}", &repeat('0').take(309).collect::<String>());
+    let mut input = ParserInput::new(&css);
+    let mut input = Parser::new(&mut input);
+
+    assert_eq!(input.expect_integer(), Ok(2147483646));
+    assert_eq!(input.expect_integer(), Ok(2147483647));
+    assert_eq!(input.expect_integer(), Ok(2147483647)); // Clamp on overflow
+    assert_eq!(input.expect_integer(), Ok(2147483647));
+    assert_eq!(input.expect_integer(), Ok(2147483647));
+    assert_eq!(input.expect_integer(), Ok(2147483647));
+
+    assert_eq!(input.expect_integer(), Ok(-2147483647));
+    assert_eq!(input.expect_integer(), Ok(-2147483648));
+    assert_eq!(input.expect_integer(), Ok(-2147483648)); // Clamp on overflow
+    assert_eq!(input.expect_integer(), Ok(-2147483648));
+    assert_eq!(input.expect_integer(), Ok(-2147483648));
+    assert_eq!(input.expect_integer(), Ok(-2147483648));
+
+    assert_eq!(input.expect_number(), Ok(3.30282347e+38));
+    assert_eq!(input.expect_number(), Ok(f32::MAX));
+    assert_eq!(input.expect_number(), Ok(f32::INFINITY));
+    assert!(f32::MAX != f32::INFINITY);
+
+    assert_eq!(input.expect_number(), Ok(-3.30282347e+38));
+    assert_eq!(input.expect_number(), Ok(f32::MIN));
+    assert_eq!(input.expect_number(), Ok(f32::NEG_INFINITY));
+    assert!(f32::MIN != f32::NEG_INFINITY);
+}
+
+#[cfg(feature = "values")]
+#[test]
+fn non_finite_values() {
+    let mut input = ParserInput::new("PI e -Infinity nan infinity calc");
+    let mut input = Parser::new(&mut input);
+    assert_eq!(parse_calc_constant(&mut input), Ok(std::f32::consts::PI));
+    assert_eq!(parse_calc_constant(&mut input), Ok(std::f32::consts::E));
+    assert_eq!(parse_calc_constant(&mut input), Ok(f32::NEG_INFINITY));
+    assert!(parse_calc_constant(&mut input).unwrap().is_nan());
+    assert_eq!(parse_calc_constant(&mut input), Ok(f32::INFINITY));
+    assert!(parse_calc_constant(&mut input).is_err());
+
+    assert_eq!(f32::INFINITY.to_css_string(), "calc(infinity)");
+    assert_eq!(f64::NEG_INFINITY.to_css_string(), "calc(-infinity)");
+    assert_eq!(f32::NAN.to_css_string(), "calc(NaN)");
+    let length = Length {
+        value: f32::NEG_INFINITY,
+        unit: LengthUnit::Px,
+    };
+    assert_eq!(length.to_css_string(), "calc(-infinity * 1px)");
+    let percentage = LengthPercentage::Percentage {
+        unit_value: f32::INFINITY,
+    };
+    assert_eq!(percentage.to_css_string(), "calc(infinity * 1%)");
+    // 1e37 is finite, but not once multiplied by 100 to serialize it as a percentage.
+    let stop = LinearStop {
+        output: 0.,
+        input: (Some(1e37), Some(f32::NAN)),
+    };
+    assert_eq!(stop.to_css_string(), "0 calc(infinity * 1%) calc(NaN * 1%)");
+
+    // Tokens too large for `f32` are still infinite once serialized and tokenized again.
+    let css = "1e40 -1e40px +1e40%";
+    let mut input = ParserInput::new(css);
+    let mut input = Parser::new(&mut input);
+    let mut serialized = String::new();
+    while let Ok(token) = input.next() {
+        serialized.push_str(&token.to_css_string());
+        serialized.push(' ');
+    }
+    assert_eq!(serialized, "1e39 -1e39px +1e39% ");
+    let mut input = ParserInput::new(&serialized);
+    let mut input = Parser::new(&mut input);
+    assert_eq!(input.expect_number(), Ok(f32::INFINITY));
+    match *input.next().unwrap() {
+        Token::Dimension { value, .. } => assert_eq!(value, f32::NEG_INFINITY),
+        ref token => panic!("{:?}", token),
+    }
+}
+
+#[test]
+fn line_delimited() {
+    let mut input = ParserInput::new(" { foo ; bar } baz;,");
+    let mut input = Parser::new(&mut input);
+    assert_eq!(input.next(), Ok(&Token::CurlyBracketBlock));
+    assert!({
+        let result: Result<_, ParseError<()>> =
+            input.parse_until_after(Delimiter::Semicolon, |_| Ok(42));
+        result
+    }
+    .is_err());
+    assert_eq!(input.next(), Ok(&Token::Comma));
+    assert!(input.next().is_err());
+}
+
+#[test]
+fn identifier_serialization() {
+    // Null bytes
+    assert_eq!(Token::Ident("\0".into()).to_css_string(), "\u{FFFD}");
+    assert_eq!(Token::Ident("a\0".into()).to_css_string(), "a\u{FFFD}");
+    assert_eq!(Token::Ident("\0b".into()).to_css_string(), "\u{FFFD}b");
+    assert_eq!(Token::Ident("a\0b".into()).to_css_string(), "a\u{FFFD}b");
+
+    // Replacement character
+    assert_eq!(Token::Ident("\u{FFFD}".into()).to_css_string(), "\u{FFFD}");
+    assert_eq!(
+        Token::Ident("a\u{FFFD}".into()).to_css_string(),
+        "a\u{FFFD}"
+    );
+    assert_eq!(
+        Token::Ident("\u{FFFD}b".into()).to_css_string(),
+        "\u{FFFD}b"
+    );
+    assert_eq!(
+        Token::Ident("a\u{FFFD}b".into()).to_css_string(),
+        "a\u{FFFD}b"
+    );
+
+    // Number prefix
+    assert_eq!(Token::Ident("0a".into()).to_css_string(), "\\30 a");
+    assert_eq!(Token::Ident("1a".into()).to_css_string(), "\\31 a");
+    assert_eq!(Token::Ident("2a".into()).to_css_string(), "\\32 a");
+    assert_eq!(Token::Ident("3a".into()).to_css_string(), "\\33 a");
+    assert_eq!(Token::Ident("4a".into()).to_css_string(), "\\34 a");
+    assert_eq!(Token::Ident("5a".into()).to_css_string(), "\\35 a");
+    assert_eq!(Token::Ident("6a".into()).to_css_string(), "\\36 a");
+    assert_eq!(Token::Ident("7a".into()).to_css_string(), "\\37 a");
+    assert_eq!(Token::Ident("8a".into()).to_css_string(), "\\38 a");
+    assert_eq!(Token::Ident("9a".into()).to_css_string(), "\\39 a");
+
+    // Letter number prefix
+    assert_eq!(Token::Ident("a0b".into()).to_css_string(), "a0b");
+    assert_eq!(Token::Ident("a1b".into()).to_css_string(), "a1b");
+    assert_eq!(Token::Ident("a2b".into()).to_css_string(), "a2b");
+    assert_eq!(Token::Ident("a3b".into()).to_css_string(), "a3b");
+    assert_eq!(Token::Ident("a4b".into()).to_css_string(), "a4b");
+    assert_eq!(Token::Ident("a5b".into()).to_css_string(), "a5b");
+    assert_eq!(Token::Ident("a6b".into()).to_css_string(), "a6b");
+    assert_eq!(Token::Ident("a7b".into()).to_css_string(), "a7b");
+    assert_eq!(Token::Ident("a8b".into()).to_css_string(), "a8b");
+    assert_eq!(Token::Ident("a9b".into()).to_css_string(), "a9b");
+
+    // Dash number prefix
+    assert_eq!(Token::Ident("-0a".into()).to_css_string(), "-\\30 a");
+    assert_eq!(Token::Ident("-1a".into()).to_css_string(), "-\\31 a");
+    assert_eq!(Token::Ident("-2a".into()).to_css_string(), "-\\32 a");
+    assert_eq!(Token::Ident("-3a".into()).to_css_string(), "-\\33 a");
+    assert_eq!(Token::Ident("-4a".into()).to_css_string(), "-\\34 a");
+    assert_eq!(Token::Ident("-5a".into()).to_css_string(), "-\\35 a");
+    assert_eq!(Token::Ident("-6a".into()).to_css_string(), "-\\36 a");
+    assert_eq!(Token::Ident("-7a".into()).to_css_string(), "-\\37 a");
+    assert_eq!(Token::Ident("-8a".into()).to_css_string(), "-\\38 a");
+    assert_eq!(Token::Ident("-9a".into()).to_css_string(), "-\\39 a");
+
+    // Double dash prefix
+    assert_eq!(Token::Ident("--a".into()).to_css_string(), "--a");
+
+    // Various tests
+    assert_eq!(
+        Token::Ident("\x01\x02\x1E\x1F".into()).to_css_string(),
+        "\\1 \\2 \\1e \\1f "
+    );
+    assert_eq!(
+        Token::Ident("\u{0080}\x2D\x5F\u{00A9}".into()).to_css_string(),
+        "\u{0080}\x2D\x5F\u{00A9}"
+    );
+    assert_eq!(Token::Ident("\x7F\u{0080}\u{0081}\u{0082}\u{0083}\u{0084}\u{0085}\u{0086}\u{0087}\u{0088}\u{0089}\
+        \u{008A}\u{008B}\u{008C}\u{008D}\u{008E}\u{008F}\u{0090}\u{0091}\u{0092}\u{0093}\u{0094}\u{0095}\u{0096}\
+        \u{0097}\u{0098}\u{0099}\u{009A}\u{009B}\u{009C}\u{009D}\u{009E}\u{009F}".into()).to_css_string(),
+        "\\7f \u{0080}\u{0081}\u{0082}\u{0083}\u{0084}\u{0085}\u{0086}\u{0087}\u{0088}\u{0089}\u{008A}\u{008B}\u{008C}\
+        \u{008D}\u{008E}\u{008F}\u{0090}\u{0091}\u{0092}\u{0093}\u{0094}\u{0095}\u{0096}\u{0097}\u{0098}\u{0099}\
+        \u{009A}\u{009B}\u{009C}\u{009D}\u{009E}\u{009F}");
+    assert_eq!(
+        Token::Ident("\u{00A0}\u{00A1}\u{00A2}".into()).to_css_string(),
+        "\u{00A0}\u{00A1}\u{00A2}"
+    );
+    assert_eq!(
+        Token::Ident("a0123456789b".into()).to_css_string(),
+        "a0123456789b"
+    );
+    assert_eq!(
+        Token::Ident("abcdefghijklmnopqrstuvwxyz".into()).to_css_string(),
+        "abcdefghijklmnopqrstuvwxyz"
+    );
+    assert_eq!(
+        Token::Ident("ABCDEFGHIJKLMNOPQRSTUVWXYZ".into()).to_css_string(),
+        "ABCDEFGHIJKLMNOPQRSTUVWXYZ"
+    );
+    assert_eq!(
+        Token::Ident("\x20\x21\x78\x79".into()).to_css_string(),
+        "\\ \\!xy"
+    );
+
+    // astral symbol (U+1D306 TETRAGRAM FOR CENTRE)
+    assert_eq!(
+        Token::Ident("\u{1D306}".into()).to_css_string(),
+        "\u{1D306}"
+    );
+}
+
+#[test]
+fn string_quote_styles() {
+    fn serialize(value: &str, quote_style: QuoteStyle) -> String {
+        let mut css = String::new();
+        serialize_string_with(value, quote_style, &mut css).unwrap();
+        {
+            let mut input = ParserInput::new(&css);
+            let mut input = Parser::new(&mut input);
+            assert_eq!(input.expect_string().map(|s| &**s), Ok(value));
+        }
+        css
+    }
+    let cases = [
+        ("a", "\"a\"", "'a'", "\"a\""),
+        ("it's", "\"it's\"", "'it\\'s'", "\"it's\""),
+        (
+            "say \"hi\"",
+            "\"say \\\"hi\\\"\"",
+            "'say \"hi\"'",
+            "'say \"hi\"'",
+        ),
+        ("'\"", "\"'\\\"\"", "'\\'\"'", "\"'\\\"\""),
+        ("a\\b\n", "\"a\\\\b\\a \"", "'a\\\\b\\a '", "\"a\\\\b\\a \""),
+    ];
+    for &(value, double, single, auto) in &cases {
+        assert_eq!(serialize(value, QuoteStyle::Double), double);
+        assert_eq!(serialize(value, QuoteStyle::Single), single);
+        assert_eq!(serialize(value, QuoteStyle::Auto), auto);
+    }
+}
+
+#[test]
+fn minimal_identifier_escaping() {
+    fn serialize(ident: &str, escaping: IdentifierEscaping) -> String {
+        let mut css = String::new();
+        serialize_identifier_with(ident, escaping, &mut css).unwrap();
+        if escaping == IdentifierEscaping::Minimal {
+            let mut input = ParserInput::new(&css);
+            let mut input = Parser::new(&mut input);
+            assert_eq!(input.expect_ident().map(|s| &**s), Ok(ident));
+            assert!(input.is_exhausted());
+        }
+        css
+    }
+    let cases = [
+        ("123", "\\31 23", "\\31 23"),
+        ("1a", "\\31 a", "\\31 a"),
+        ("1x", "\\31 x", "\\31x"),
+        ("-1x", "-\\31 x", "-\\31x"),
+        ("1", "\\31 ", "\\31 "),
+        ("1 x", "\\31 \\ x", "\\31\\ x"),
+        ("a\x01b\x01z", "a\\1 b\\1 z", "a\\1 b\\1z"),
+        ("-", "\\-", "\\-"),
+        ("--1x", "--1x", "--1x"),
+        ("a.b", "a\\.b", "a\\.b"),
+    ];
+    for &(ident, cssom, minimal) in &cases {
+        assert_eq!(serialize(ident, IdentifierEscaping::Cssom), cssom);
+        assert_eq!(serialize(ident, IdentifierEscaping::Minimal), minimal);
+    }
+}
+
+trait ToJson {
+    fn to_json(&self) -> Value;
+}
+
+impl<T> ToJson for T
+where
+    T: Clone,
+    Value: From<T>,
+{
+    fn to_json(&self) -> Value {
+        Value::from(self.clone())
+    }
+}
+
+#[cfg(feature = "color")]
+impl ToJson for Color {
+    fn to_json(&self) -> Value {
+        match *self {
+            Color::RGBA(ref rgba) => json!([rgba.red, rgba.green, rgba.blue, rgba.alpha]),
+            Color::CurrentColor => "currentcolor".to_json(),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<'a> ToJson for CowRcStr<'a> {
+    fn to_json(&self) -> Value {
+        let s: &str = &*self;
+        s.to_json()
+    }
+}
+
+#[cfg(feature = "bench")]
+const BACKGROUND_IMAGE: &'static str = include_str!("big-data-url.css");
+
+#[cfg(feature = "bench")]
+#[bench]
+fn unquoted_url(b: &mut Bencher) {
+    b.iter(|| {
+        let mut input = ParserInput::new(BACKGROUND_IMAGE);
+        let mut input = Parser::new(&mut input);
+        input.look_for_var_or_env_functions();
+
+        let result = input.try_parse(|input| input.expect_url());
+
+        assert!(result.is_ok());
+
+        input.seen_var_or_env_functions();
+        (result.is_ok(), input.seen_var_or_env_functions())
+    })
+}
+
+#[cfg(feature = "bench")]
+#[bench]
+fn numeric(b: &mut Bencher) {
+    b.iter(|| {
+        for _ in 0..1000000 {
+            let mut input = ParserInput::new("10px");
+            let mut input = Parser::new(&mut input);
+            let _ = test::black_box(input.next());
+        }
+    })
+}
+
+#[cfg(feature = "bench")]
+macro_rules! corpus_benches {
+    ($( $corpus: ident: $tokenize: ident, $parse: ident; )+) => {
+        $(
+            #[cfg(feature = "bench")]
+            #[bench]
+            fn $tokenize(b: &mut Bencher) {
+                let css = crate::bench_corpora::$corpus();
+                b.bytes = css.len() as u64;
+                b.iter(|| crate::bench_corpora::tokenize_all(&css))
+            }
+
+            #[cfg(feature = "bench")]
+            #[bench]
+            fn $parse(b: &mut Bencher) {
+                let css = crate::bench_corpora::$corpus();
+                b.bytes = css.len() as u64;
+                b.iter(|| crate::bench_corpora::parse_all(&css))
+            }
+        )+
+    };
+}
+
+#[cfg(feature = "bench")]
+corpus_benches! {
+    framework_like: tokenize_framework_like, parse_framework_like;
+    minified_app: tokenize_minified_app, parse_minified_app;
+    utility_classes: tokenize_utility_classes, parse_utility_classes;
+}
+
+/// Serialize 5000 style rules, into a string that is reserved first or that grows as needed.
+#[cfg(feature = "bench")]
+fn bench_serialize_rule_list(b: &mut Bencher, reserve: bool) {
+    let selectors: Vec<String> = (0..5000)
+        .map(|i| format!(".item-{} > .label:hover", i))
+        .collect();
+    let declaration = |name, value| {
+        SerializableDeclarationListItem::Declaration(SerializableDeclaration {
+            name,
+            value,
+            important: false,
+        })
+    };
+    let rules: Vec<_> = selectors
+        .iter()
+        .map(|selector| {
+            SerializableRule::QualifiedRule(SerializableQualifiedRule {
+                prelude: selector,
+                block: vec![
+                    declaration("color", "rgba(13, 110, 253, 0.5)"),
+                    declaration("margin", "0 auto"),
+                ],
+            })
+        })
+        .collect();
+    b.bytes = rule_list_len_estimate(&rules) as u64;
+    b.iter(|| {
+        let mut css = String::new();
+        if reserve {
+            serialize_rule_list_into(&rules, TrailingSemicolon::Always, &mut css)
+        } else {
+            serialize_rule_list(&rules, TrailingSemicolon::Always, &mut css).unwrap()
+        }
+        css
+    })
+}
+
+#[cfg(feature = "bench")]
+#[bench]
+fn serialize_rule_list_growing(b: &mut Bencher) {
+    bench_serialize_rule_list(b, false)
+}
+
+#[cfg(feature = "bench")]
+#[bench]
+fn serialize_rule_list_reserved(b: &mut Bencher) {
+    bench_serialize_rule_list(b, true)
+}
+
+#[cfg(feature = "bench")]
+macro_rules! token_kind_benches {
+    ($( $name: ident: $kind: ident, )+) => {
+        $(
+            #[cfg(feature = "bench")]
+            #[bench]
+            fn $name(b: &mut Bencher) {
+                let css = crate::bench_corpora::repeated_token(TokenKind::$kind);
+                b.bytes = css.len() as u64;
+                b.iter(|| crate::bench_corpora::tokenize_all(&css))
+            }
+        )+
+    };
+}
+
+#[cfg(feature = "bench")]
+token_kind_benches! {
+    tokenize_idents: Ident,
+    tokenize_hashes: Hash,
+    tokenize_id_hashes: IDHash,
+    tokenize_quoted_strings: QuotedString,
+    tokenize_unquoted_urls: UnquotedUrl,
+    tokenize_numbers: Number,
+    tokenize_percentages: Percentage,
+    tokenize_dimensions: Dimension,
+    tokenize_comments: Comment,
+}
+
+#[test]
+fn bench_corpora_are_valid_css() {
+    use crate::bench_corpora::*;
+    for css in &[framework_like(), minified_app(), utility_classes()] {
+        let counts = count_tokens_by_kind(css);
+        for kind in &[
+            TokenKind::BadUrl,
+            TokenKind::BadString,
+            TokenKind::CloseParenthesis,
+            TokenKind::CloseSquareBracket,
+            TokenKind::CloseCurlyBracket,
+        ] {
+            assert_eq!(counts.get(kind), None);
+        }
+        assert_eq!(counts.values().sum::<usize>(), tokenize_all(css));
+        assert!(parse_all(css) > 0);
+    }
+    // 2400 rules with one declaration each.
+    assert_eq!(parse_all(&utility_classes()), 4800);
+
+    for &kind in &[
+        TokenKind::Ident,
+        TokenKind::Hash,
+        TokenKind::IDHash,
+        TokenKind::QuotedString,
+        TokenKind::UnquotedUrl,
+        TokenKind::Number,
+        TokenKind::Percentage,
+        TokenKind::Dimension,
+        TokenKind::Comment,
+    ] {
+        let counts = count_tokens_by_kind(&repeated_token(kind));
+        assert!(counts[&kind] > 1000);
+        assert!(counts
+            .keys()
+            .all(|&other| other == kind || other == TokenKind::WhiteSpace));
+    }
+}
+
+struct JsonParser;
+
+#[test]
+fn error_reporter() {
+    let reported = Rc::new(RefCell::new(Vec::new()));
+    let reported_clone = reported.clone();
+    let mut input = ParserInput::new("a: b; 1; @charset x;\n c: d");
+    input.set_error_reporter(
+        Some("https://example.com/style.css"),
+        move |url: Option<&str>, location: SourceLocation, message: &str| {
+            reported_clone.borrow_mut().push((
+                url.unwrap().to_owned(),
+                location,
+                message.to_owned(),
+            ))
+        },
+    );
+    let mut parser = Parser::new(&mut input);
+    let results: Vec<_> = DeclarationListParser::new(&mut parser, JsonParser).collect();
+    assert_eq!(results.len(), 4);
+    assert_eq!(results.iter().filter(|result| result.is_err()).count(), 2);
+    assert_eq!(
+        *reported.borrow(),
+        vec![
+            (
+                "https://example.com/style.css".to_owned(),
+                SourceLocation { line: 0, column: 7 },
+                "invalid declaration: unexpected ‘1’".to_owned()
+            ),
+            (
+                "https://example.com/style.css".to_owned(),
+                SourceLocation {
+                    line: 0,
+                    column: 20
+                },
+                "invalid at-rule: invalid at-rule ‘@charset’".to_owned()
+            ),
+        ]
+    );
+}
+
+#[cfg(feature = "log")]
+#[test]
+fn trace_events() {
+    thread_local! {
+        static RECORDS: RefCell<Vec<String>> = RefCell::new(Vec::new());
+    }
+    struct Logger;
+    impl log::Log for Logger {
+        fn enabled(&self, metadata: &log::Metadata) -> bool {
+            metadata.target() == "cssparser"
+        }
+        fn log(&self, record: &log::Record) {
+            if self.enabled(record.metadata()) {
+                RECORDS.with(|records| records.borrow_mut().push(record.args().to_string()))
+            }
+        }
+        fn flush(&self) {}
+    }
+    static LOGGER: Logger = Logger;
+    log::set_logger(&LOGGER).unwrap();
+    log::set_max_level(log::LevelFilter::Trace);
+
+    let mut input = ParserInput::new("a: b; 4 (5);\n d: e");
+    let mut parser = Parser::new(&mut input);
+    let results: Vec<_> = DeclarationListParser::new(&mut parser, JsonParser).collect();
+    assert_eq!(results.len(), 3);
+    let mut input = ParserInput::new("@foo; x {} 1 {");
+    let mut parser = Parser::new(&mut input);
+    for _ in RuleListParser::new_for_stylesheet(&mut parser, JsonParser) {}
+    RECORDS.with(|records| {
+        assert_eq!(
+            *records.borrow(),
+            [
+                "declaration start at 1:1: a",
+                "declaration end, from 1:1",
+                "skipped 2 tokens from 1:8",
+                "declaration from 1:7 dropped, skipping 6 bytes: \
+                 invalid declaration: unexpected ‘4’ at 1:7",
+                "declaration start at 2:2: d",
+                "declaration end, from 2:2",
+                "at-rule start at 1:1: @foo",
+                "at-rule end, from 1:1",
+                "rule start at 1:7",
+                "rule end, from 1:7",
+                "rule start at 1:12",
+                "unclosed block at 1:14",
+                "rule end, from 1:12",
+            ]
+        )
+    });
+}
+
+#[test]
+fn errors_eq_ignoring_location() {
+    let mut input = ParserInput::new("a\n  b");
+    let mut parser = Parser::new(&mut input);
+    let first = parser.new_basic_unexpected_token_error(Token::Comma);
+    parser.next().unwrap();
+    parser.next().unwrap();
+    let second = parser.new_basic_unexpected_token_error(Token::Comma);
+    let end_of_input = parser.new_basic_error(BasicParseErrorKind::EndOfInput);
+    assert_ne!(first, second);
+    assert!(first.eq_ignoring_location(&second));
+    assert!(!first.eq_ignoring_location(&end_of_input));
+
+    let (first, second): (ParseError<u32>, ParseError<u32>) = (first.into(), second.into());
+    assert!(first.eq_ignoring_location(&second));
+    let custom: ParseError<u32> = parser.new_custom_error(1_u32);
+    let other_custom = SourceLocation { line: 9, column: 9 }.new_custom_error(1_u32);
+    assert!(!first.eq_ignoring_location(&custom));
+    assert!(custom.eq_ignoring_location(&other_custom));
+
+    let url = |line| UnresolvedUrl {
+        url: "a.png".into(),
+        location: SourceLocation { line, column: 1 },
+    };
+    assert!(url(0).eq_ignoring_location(&url(1)));
+}
+
+#[test]
+fn invalid_charset_rule() {
+    let mut input = ParserInput::new("@charset \"utf-8\"; a {}\n@CHARSET \"utf-8\"; b {}");
+    let mut parser = Parser::new(&mut input);
+    let rules: Vec<_> = RuleListParser::new_for_stylesheet(&mut parser, JsonParser).collect();
+    assert_eq!(rules.len(), 3);
+    let (error, slice) = rules[1].clone().unwrap_err();
+    assert_eq!(
+        error.kind,
+        ParseErrorKind::Basic(BasicParseErrorKind::InvalidCharsetRule)
+    );
+    assert_eq!(error.location, SourceLocation { line: 1, column: 1 });
+    assert_eq!(slice, "@CHARSET \"utf-8\";");
+    assert_eq!(
+        error.basic().to_string(),
+        "‘@charset’ is only valid as ‘@charset \"…\";’ at the start of a stylesheet at 2:1"
+    );
+
+    // Not byte-exact, or not at the first byte.
+    for &(css, rule) in &[
+        ("@charset 'utf-8';", "@charset 'utf-8';"),
+        ("@charset  \"utf-8\";", "@charset  \"utf-8\";"),
+        ("@charset \"utf-8\"/**/;", "@charset \"utf-8\"/**/;"),
+        ("  @charset \"utf-8\";", "@charset \"utf-8\";"),
+        ("/**/@charset \"x\";", "@charset \"x\";"),
+        ("<!--@charset \"x\";", "@charset \"x\";"),
+    ] {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        let rules: Vec<_> = RuleListParser::new_for_stylesheet(&mut parser, JsonParser).collect();
+        assert_eq!(rules.len(), 1);
+        let (error, slice) = rules[0].clone().unwrap_err();
+        assert_eq!(
+            error.kind,
+            ParseErrorKind::Basic(BasicParseErrorKind::InvalidCharsetRule)
+        );
+        assert_eq!(slice, rule);
+    }
+
+    // Not a stylesheet, so left to the at-rule parser.
+    let mut input = ParserInput::new("@charset \"utf-8\";");
+    let mut parser = Parser::new(&mut input);
+    let mut rules = RuleListParser::new_for_nested_rule(&mut parser, JsonParser);
+    assert_eq!(
+        rules.next().unwrap().unwrap_err().0.kind,
+        ParseErrorKind::Basic(BasicParseErrorKind::AtRuleInvalid("charset".into()))
+    );
+}
+
+#[test]
+fn import_observer() {
+    let css = r#"@charset "utf-8";
+        @layer base;
+        @import "a.css";
+        @import url(b.css) layer(base.reset) supports(display: grid) screen and (color) ;
+        @import 'c.css' layer print;
+        @import 4;
+        @import "with-block.css" { }
+        a { }
+        @import "too-late.css";
+    "#;
+    let mut imports = Vec::new();
+    let mut input = ParserInput::new(css);
+    let mut parser = Parser::new(&mut input);
+    let rules: Vec<_> = RuleListParser::new_for_stylesheet(
+        &mut parser,
+        ImportObserver::new(JsonParser, |import| imports.push(import)),
+    )
+    .collect();
+    assert_eq!(rules.len(), 8);
+    assert_eq!(rules.iter().filter(|rule| rule.is_err()).count(), 1);
+    assert!(rules[5].is_err());
+    assert_eq!(
+        imports,
+        vec![
+            ImportPrelude {
+                url: "a.css".into(),
+                layer: None,
+                supports: None,
+                media: "",
+            },
+            ImportPrelude {
+                url: "b.css".into(),
+                layer: Some(ImportLayer::Named("base.reset")),
+                supports: Some("display: grid"),
+                media: "screen and (color)",
+            },
+            ImportPrelude {
+                url: "c.css".into(),
+                layer: Some(ImportLayer::Anonymous),
+                supports: None,
+                media: "print",
+            },
+        ]
+    );
+
+    /// Rejects every rule.
+    struct RejectRules;
+
+    impl<'i> AtRuleParser<'i> for RejectRules {
+        type PreludeNoBlock = ();
+        type PreludeBlock = ();
+        type AtRule = ();
+        type Error = ();
+    }
+
+    impl<'i> QualifiedRuleParser<'i> for RejectRules {
+        type Prelude = ();
+        type QualifiedRule = ();
+        type Error = ();
+    }
+
+    let mut imports = Vec::new();
+    let mut input = ParserInput::new(r#"@import "a.css"; @import "b.css";"#);
+    let mut parser = Parser::new(&mut input);
+    let rules: Vec<_> = RuleListParser::new_for_stylesheet(
+        &mut parser,
+        ImportObserver::new(RejectRules, |import| imports.push(import)),
+    )
+    .collect();
+    assert!(rules.iter().all(|rule| rule.is_err()));
+    assert_eq!(imports, vec![]);
+}
+
+#[test]
+fn no_stack_overflow_multiple_nested_blocks() {
+    let mut input: String = "{{".into();
+    for _ in 0..20 {
+        let dup = input.clone();
+        input.push_str(&dup);
+    }
+    let mut input = ParserInput::new(&input);
+    let mut input = Parser::new(&mut input);
+    while let Ok(..) = input.next() {}
+}
+
+impl<'i> DeclarationParser<'i> for JsonParser {
+    type Declaration = Value;
+    type Error = ();
+
+    fn parse_value<'t>(
+        &mut self,
+        name: CowRcStr<'i>,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Value, ParseError<'i, ()>> {
+        let mut value = vec![];
+        let mut important = false;
+        loop {
+            let start = input.state();
+            if let Ok(mut token) = input.next_including_whitespace().map(|t| t.clone()) {
+                // Hack to deal with css-parsing-tests assuming that
+                // `!important` in the middle of a declaration value is OK.
+                // This can never happen per spec
+                // (even CSS Variables forbid top-level `!`)
+                if token == Token::Delim('!') {
+                    input.reset(&start);
+                    if parse_important(input).is_ok() {
+                        if input.is_exhausted() {
+                            important = true;
+                            break;
+                        }
+                    }
+                    input.reset(&start);
+                    token = input.next_including_whitespace().unwrap().clone();
+                }
+                value.push(one_component_value_to_json(token, input));
+            } else {
+                break;
+            }
+        }
+        Ok(JArray!["declaration", name, value, important,])
+    }
+}
+
+impl<'i> AtRuleParser<'i> for JsonParser {
+    type PreludeNoBlock = Vec<Value>;
+    type PreludeBlock = Vec<Value>;
+    type AtRule = Value;
+    type Error = ();
+
+    fn parse_prelude<'t>(
+        &mut self,
+        name: CowRcStr<'i>,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<AtRuleType<Vec<Value>, Vec<Value>>, ParseError<'i, ()>> {
+        let prelude = vec![
+            "at-rule".to_json(),
+            name.to_json(),
+            Value::Array(component_values_to_json(input)),
+        ];
+        match_ignore_ascii_case! { &*name,
+            "media" | "foo-with-block" => Ok(AtRuleType::WithBlock(prelude)),
+            "charset" => {
+                Err(input.new_error(BasicParseErrorKind::AtRuleInvalid(name.clone()).into()))
+            },
+            _ => Ok(AtRuleType::WithoutBlock(prelude)),
+        }
+    }
+
+    fn rule_without_block(&mut self, mut prelude: Vec<Value>, _location: SourceLocation) -> Value {
+        prelude.push(Value::Null);
+        Value::Array(prelude)
+    }
+
+    fn parse_block<'t>(
+        &mut self,
+        mut prelude: Vec<Value>,
+        _location: SourceLocation,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Value, ParseError<'i, ()>> {
+        prelude.push(Value::Array(component_values_to_json(input)));
+        Ok(Value::Array(prelude))
+    }
+}
+
+impl<'i> QualifiedRuleParser<'i> for JsonParser {
+    type Prelude = Vec<Value>;
+    type QualifiedRule = Value;
+    type Error = ();
+
+    fn parse_prelude<'t>(
+        &mut self,
+        input: &mut Parser<'i, 't>,
     ) -> Result<Vec<Value>, ParseError<'i, ()>> {
         Ok(component_values_to_json(input))
     }
 
     fn parse_block<'t>(
         &mut self,
-        prelude: Vec<Value>,
+        prelude: Vec<Value>,
+        _location: SourceLocation,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Value, ParseError<'i, ()>> {
+        Ok(JArray![
+            "qualified rule",
+            prelude,
+            component_values_to_json(input),
+        ])
+    }
+}
+
+fn component_values_to_json(input: &mut Parser) -> Vec<Value> {
+    let mut values = vec![];
+    while let Ok(token) = input.next_including_whitespace().map(|t| t.clone()) {
+        values.push(one_component_value_to_json(token, input));
+    }
+    values
+}
+
+fn one_component_value_to_json(token: Token, input: &mut Parser) -> Value {
+    fn numeric(value: f32, int_value: Option<i32>, has_sign: bool) -> Vec<Value> {
+        vec![
+            Token::Number {
+                value: value,
+                int_value: int_value,
+                has_sign: has_sign,
+            }
+            .to_css_string()
+            .to_json(),
+            match int_value {
+                Some(i) => i.to_json(),
+                None => value.to_json(),
+            },
+            match int_value {
+                Some(_) => "integer",
+                None => "number",
+            }
+            .to_json(),
+        ]
+    }
+
+    fn nested(input: &mut Parser) -> Vec<Value> {
+        let result: Result<_, ParseError<()>> =
+            input.parse_nested_block(|input| Ok(component_values_to_json(input)));
+        result.unwrap()
+    }
+
+    match token {
+        Token::Ident(value) => JArray!["ident", value],
+        Token::AtKeyword(value) => JArray!["at-keyword", value],
+        Token::Hash(value) => JArray!["hash", value, "unrestricted"],
+        Token::IDHash(value) => JArray!["hash", value, "id"],
+        Token::QuotedString(value) => JArray!["string", value],
+        Token::UnquotedUrl(value) => JArray!["url", value],
+        Token::Delim('\\') => "\\".to_json(),
+        Token::Delim(value) => value.to_string().to_json(),
+
+        Token::Number {
+            value,
+            int_value,
+            has_sign,
+        } => Value::Array({
+            let mut v = vec!["number".to_json()];
+            v.extend(numeric(value, int_value, has_sign));
+            v
+        }),
+        Token::Percentage {
+            unit_value,
+            int_value,
+            has_sign,
+        } => Value::Array({
+            let mut v = vec!["percentage".to_json()];
+            v.extend(numeric(unit_value * 100., int_value, has_sign));
+            v
+        }),
+        Token::Dimension {
+            value,
+            int_value,
+            has_sign,
+            unit,
+        } => Value::Array({
+            let mut v = vec!["dimension".to_json()];
+            v.extend(numeric(value, int_value, has_sign));
+            v.push(unit.to_json());
+            v
+        }),
+
+        Token::WhiteSpace(_) => " ".to_json(),
+        Token::Comment(_) => "/**/".to_json(),
+        Token::Colon => ":".to_json(),
+        Token::Semicolon => ";".to_json(),
+        Token::Comma => ",".to_json(),
+        Token::IncludeMatch => "~=".to_json(),
+        Token::DashMatch => "|=".to_json(),
+        Token::PrefixMatch => "^=".to_json(),
+        Token::SuffixMatch => "$=".to_json(),
+        Token::SubstringMatch => "*=".to_json(),
+        Token::CDO => "<!--".to_json(),
+        Token::CDC => "-->".to_json(),
+
+        Token::Function(name) => Value::Array({
+            let mut v = vec!["function".to_json(), name.to_json()];
+            v.extend(nested(input));
+            v
+        }),
+        Token::ParenthesisBlock => Value::Array({
+            let mut v = vec!["()".to_json()];
+            v.extend(nested(input));
+            v
+        }),
+        Token::SquareBracketBlock => Value::Array({
+            let mut v = vec!["[]".to_json()];
+            v.extend(nested(input));
+            v
+        }),
+        Token::CurlyBracketBlock => Value::Array({
+            let mut v = vec!["{}".to_json()];
+            v.extend(nested(input));
+            v
+        }),
+        Token::BadUrl(_) => JArray!["error", "bad-url"],
+        Token::BadString(_) => JArray!["error", "bad-string"],
+        Token::CloseParenthesis => JArray!["error", ")"],
+        Token::CloseSquareBracket => JArray!["error", "]"],
+        Token::CloseCurlyBracket => JArray!["error", "}"],
+        _ => unreachable!(),
+    }
+}
+
+/// A previous version of procedural-masquerade had a bug where it
+/// would normalize consecutive whitespace to a single space,
+/// including in string literals.
+#[test]
+fn procedural_masquerade_whitespace() {
+    ascii_case_insensitive_phf_map! {
+        map -> () = {
+            "  \t\n" => ()
+        }
+    }
+    assert_eq!(map("  \t\n"), Some(&()));
+    assert_eq!(map(" "), None);
+
+    match_ignore_ascii_case! { "  \t\n",
+        " " => panic!("1"),
+        "  \t\n" => {},
+        _ => panic!("2"),
+    }
+
+    match_ignore_ascii_case! { " ",
+        "  \t\n" => panic!("3"),
+        " " => {},
+        _ => panic!("4"),
+    }
+}
+
+#[test]
+fn parse_until_before_stops_at_delimiter_or_end_of_input() {
+    // For all j and k, inputs[i].1[j] should parse the same as inputs[i].1[k]
+    // when we use delimiters inputs[i].0.
+    let inputs = vec![
+        (
+            Delimiter::Bang | Delimiter::Semicolon,
+            // Note that the ';extra' is fine, because the ';' acts the same as
+            // the end of input.
+            vec!["token stream;extra", "token stream!", "token stream"],
+        ),
+        (Delimiter::Bang | Delimiter::Semicolon, vec![";", "!", ""]),
+    ];
+    for equivalent in inputs {
+        for (j, x) in equivalent.1.iter().enumerate() {
+            for y in equivalent.1[j + 1..].iter() {
+                let mut ix = ParserInput::new(x);
+                let mut ix = Parser::new(&mut ix);
+
+                let mut iy = ParserInput::new(y);
+                let mut iy = Parser::new(&mut iy);
+
+                let _ = ix.parse_until_before::<_, _, ()>(equivalent.0, |ix| {
+                    iy.parse_until_before::<_, _, ()>(equivalent.0, |iy| {
+                        loop {
+                            let ox = ix.next();
+                            let oy = iy.next();
+                            assert_eq!(ox, oy);
+                            if let Err(_) = ox {
+                                break;
+                            }
+                        }
+                        Ok(())
+                    })
+                });
+            }
+        }
+    }
+}
+
+#[test]
+fn parser_maintains_current_line() {
+    let mut input = ParserInput::new("ident ident;\nident ident ident;\nident");
+    let mut parser = Parser::new(&mut input);
+    assert_eq!(parser.current_line(), "ident ident;");
+    assert_eq!(parser.next(), Ok(&Token::Ident("ident".into())));
+    assert_eq!(parser.next(), Ok(&Token::Ident("ident".into())));
+    assert_eq!(parser.next(), Ok(&Token::Semicolon));
+
+    assert_eq!(parser.next(), Ok(&Token::Ident("ident".into())));
+    assert_eq!(parser.current_line(), "ident ident ident;");
+    assert_eq!(parser.next(), Ok(&Token::Ident("ident".into())));
+    assert_eq!(parser.next(), Ok(&Token::Ident("ident".into())));
+    assert_eq!(parser.next(), Ok(&Token::Semicolon));
+
+    assert_eq!(parser.next(), Ok(&Token::Ident("ident".into())));
+    assert_eq!(parser.current_line(), "ident");
+}
+
+#[test]
+fn parser_with_line_number_offset() {
+    let mut input = ParserInput::new_with_line_number_offset("ident\nident", 72);
+    let mut parser = Parser::new(&mut input);
+    assert_eq!(
+        parser.current_source_location(),
+        SourceLocation {
+            line: 72,
+            column: 1
+        }
+    );
+    assert_eq!(
+        parser.next_including_whitespace_and_comments(),
+        Ok(&Token::Ident("ident".into()))
+    );
+    assert_eq!(
+        parser.current_source_location(),
+        SourceLocation {
+            line: 72,
+            column: 6
+        }
+    );
+    assert_eq!(
+        parser.next_including_whitespace_and_comments(),
+        Ok(&Token::WhiteSpace("\n".into()))
+    );
+    assert_eq!(
+        parser.current_source_location(),
+        SourceLocation {
+            line: 73,
+            column: 1
+        }
+    );
+    assert_eq!(
+        parser.next_including_whitespace_and_comments(),
+        Ok(&Token::Ident("ident".into()))
+    );
+    assert_eq!(
+        parser.current_source_location(),
+        SourceLocation {
+            line: 73,
+            column: 6
+        }
+    );
+}
+
+#[test]
+fn cdc_regression_test() {
+    let mut input = ParserInput::new("-->x");
+    let mut parser = Parser::new(&mut input);
+    parser.skip_cdc_and_cdo();
+    assert_eq!(parser.next(), Ok(&Token::Ident("x".into())));
+    assert_eq!(
+        parser.next(),
+        Err(BasicParseError {
+            kind: BasicParseErrorKind::EndOfInput,
+            location: SourceLocation { line: 0, column: 5 }
+        })
+    );
+}
+
+#[test]
+fn parse_entirely_reports_first_error() {
+    #[derive(PartialEq, Debug)]
+    enum E {
+        Foo,
+    }
+    let mut input = ParserInput::new("ident");
+    let mut parser = Parser::new(&mut input);
+    let result: Result<(), _> = parser.parse_entirely(|p| Err(p.new_custom_error(E::Foo)));
+    assert_eq!(
+        result,
+        Err(ParseError {
+            kind: ParseErrorKind::Custom(E::Foo),
+            location: SourceLocation { line: 0, column: 1 },
+        })
+    );
+}
+
+#[test]
+fn parse_sourcemapping_comments() {
+    let tests = vec![
+        ("/*# sourceMappingURL=here*/", Some("here")),
+        ("/*# sourceMappingURL=here  */", Some("here")),
+        ("/*@ sourceMappingURL=here*/", Some("here")),
+        (
+            "/*@ sourceMappingURL=there*/ /*# sourceMappingURL=here*/",
+            Some("here"),
+        ),
+        ("/*# sourceMappingURL=here there  */", Some("here")),
+        ("/*# sourceMappingURL=  here  */", Some("")),
+        ("/*# sourceMappingURL=*/", Some("")),
+        ("/*# sourceMappingUR=here  */", None),
+        ("/*! sourceMappingURL=here  */", None),
+        ("/*# sourceMappingURL = here  */", None),
+        ("/*   # sourceMappingURL=here   */", None),
+    ];
+
+    for test in tests {
+        let mut input = ParserInput::new(test.0);
+        let mut parser = Parser::new(&mut input);
+        while let Ok(_) = parser.next_including_whitespace() {}
+        assert_eq!(parser.current_source_map_url(), test.1);
+    }
+}
+
+#[test]
+fn parse_sourceurl_comments() {
+    let tests = vec![
+        ("/*# sourceURL=here*/", Some("here")),
+        ("/*# sourceURL=here  */", Some("here")),
+        ("/*@ sourceURL=here*/", Some("here")),
+        ("/*@ sourceURL=there*/ /*# sourceURL=here*/", Some("here")),
+        ("/*# sourceURL=here there  */", Some("here")),
+        ("/*# sourceURL=  here  */", Some("")),
+        ("/*# sourceURL=*/", Some("")),
+        ("/*# sourceMappingUR=here  */", None),
+        ("/*! sourceURL=here  */", None),
+        ("/*# sourceURL = here  */", None),
+        ("/*   # sourceURL=here   */", None),
+    ];
+
+    for test in tests {
+        let mut input = ParserInput::new(test.0);
+        let mut parser = Parser::new(&mut input);
+        while let Ok(_) = parser.next_including_whitespace() {}
+        assert_eq!(parser.current_source_url(), test.1);
+    }
+}
+
+#[test]
+fn roundtrip_percentage_token() {
+    fn test_roundtrip(value: &str) {
+        let mut input = ParserInput::new(value);
+        let mut parser = Parser::new(&mut input);
+        let token = parser.next().unwrap();
+        assert_eq!(token.to_css_string(), value);
+    }
+    // Test simple number serialization
+    for i in 0..101 {
+        test_roundtrip(&format!("{}%", i));
+        for j in 0..10 {
+            if j != 0 {
+                test_roundtrip(&format!("{}.{}%", i, j));
+            }
+            for k in 1..10 {
+                test_roundtrip(&format!("{}.{}{}%", i, j, k));
+            }
+        }
+    }
+}
+
+#[test]
+fn utf16_columns() {
+    // This particular test serves two purposes.  First, it checks
+    // that the column number computations are correct.  Second, it
+    // checks that tokenizer code paths correctly differentiate
+    // between the different UTF-8 encoding bytes.  In particular
+    // different leader bytes and continuation bytes are treated
+    // differently, so we make sure to include all lengths in the
+    // tests, using the string "QΡ✈🆒".  Also, remember that because
+    // the column is in units of UTF-16, the 4-byte sequence results
+    // in two columns.
+    let tests = vec![
+        ("", 1),
+        ("ascii", 6),
+        ("/*QΡ✈🆒*/", 10),
+        ("'QΡ✈🆒*'", 9),
+        ("\"\\\"'QΡ✈🆒*'", 12),
+        ("\\Q\\Ρ\\✈\\🆒", 10),
+        ("QΡ✈🆒", 6),
+        ("QΡ✈🆒\\Q\\Ρ\\✈\\🆒", 15),
+        ("newline\r\nQΡ✈🆒", 6),
+        ("url(QΡ✈🆒\\Q\\Ρ\\✈\\🆒)", 20),
+        ("url(QΡ✈🆒)", 11),
+        ("url(\r\nQΡ✈🆒\\Q\\Ρ\\✈\\🆒)", 16),
+        ("url(\r\nQΡ✈🆒\\Q\\Ρ\\✈\\🆒", 15),
+        ("url(\r\nQΡ✈🆒\\Q\\Ρ\\✈\\🆒 x", 17),
+        ("QΡ✈🆒()", 8),
+        // Test that under/over-flow of current_line_start_position is
+        // handled properly; see the special case in consume_4byte_intro.
+        ("🆒", 3),
+    ];
+
+    for test in tests {
+        let mut input = ParserInput::new(test.0);
+        let mut parser = Parser::new(&mut input);
+
+        // Read all tokens.
+        loop {
+            match parser.next() {
+                Err(BasicParseError {
+                    kind: BasicParseErrorKind::EndOfInput,
+                    ..
+                }) => {
+                    break;
+                }
+                Err(_) => {
+                    assert!(false);
+                }
+                Ok(_) => {}
+            };
+        }
+
+        // Check the resulting column.
+        assert_eq!(parser.current_source_location().column, test.1);
+    }
+}
+
+#[test]
+fn servo_define_css_keyword_enum() {
+    macro_rules! define_css_keyword_enum {
+        (pub enum $name:ident { $($variant:ident = $css:expr,)+ }) => {
+            #[derive(PartialEq, Debug)]
+            pub enum $name {
+                $($variant),+
+            }
+
+            impl $name {
+                pub fn from_ident(ident: &str) -> Result<$name, ()> {
+                    match_ignore_ascii_case! { ident,
+                        $($css => Ok($name::$variant),)+
+                        _ => Err(())
+                    }
+                }
+            }
+        }
+    }
+    define_css_keyword_enum! {
+        pub enum UserZoom {
+            Zoom = "zoom",
+            Fixed = "fixed",
+        }
+    }
+
+    assert_eq!(UserZoom::from_ident("fixed"), Ok(UserZoom::Fixed));
+}
+
+#[cfg(feature = "color")]
+#[test]
+fn parsed_values_are_send_and_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<RGBA>();
+    assert_send_sync::<Color>();
+    assert_send_sync::<UnicodeRange>();
+    assert_send_sync::<SourceLocation>();
+    assert_send_sync::<crate::SourcePosition>();
+    assert_send_sync::<crate::ParserState>();
+    #[cfg(feature = "sync")]
+    {
+        assert_send_sync::<CowRcStr<'static>>();
+        assert_send_sync::<Token<'static>>();
+        assert_send_sync::<BasicParseError<'static>>();
+        assert_send_sync::<ParseError<'static, ()>>();
+    }
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn owned_tokens_shared_across_threads() {
+    let mut input = ParserInput::new("f\\6fo 'b\\61r'");
+    let mut parser = Parser::new(&mut input);
+    let tokens = std::sync::Arc::new(vec![
+        parser.next().unwrap().clone(),
+        parser.next().unwrap().clone(),
+    ]);
+    let shared = tokens.clone();
+    let serialized = std::thread::spawn(move || {
+        shared
+            .iter()
+            .map(|token| token.to_css_string())
+            .collect::<Vec<_>>()
+    })
+    .join()
+    .unwrap();
+    assert_eq!(serialized, ["foo", "\"bar\""]);
+    assert_eq!(tokens[0], Token::Ident("foo".into()));
+}
+
+#[test]
+fn non_ascii_in_comments_strings_and_urls() {
+    let mut input = ParserInput::new("/* plain ascii é */ 'plain ✈ ascii' url(plain/🆒/ascii) x");
+    let mut parser = Parser::new(&mut input);
+    assert_eq!(
+        parser.next_including_whitespace_and_comments(),
+        Ok(&Token::Comment(" plain ascii é "))
+    );
+    assert_eq!(
+        parser.next(),
+        Ok(&Token::QuotedString("plain ✈ ascii".into()))
+    );
+    assert_eq!(
+        parser.next(),
+        Ok(&Token::UnquotedUrl("plain/🆒/ascii".into()))
+    );
+    assert_eq!(
+        parser.current_source_location(),
+        SourceLocation {
+            line: 0,
+            column: 56
+        }
+    );
+    assert_eq!(parser.next(), Ok(&Token::Ident("x".into())));
+}
+
+#[test]
+fn interner_shares_allocated_values() {
+    let css = r"\66oo 'b\61r' \66oo 'b\61r' \66oo(bar) foo";
+    let interner = Rc::new(RefCell::new(Interner::new()));
+    let mut input = ParserInput::new_with_interner(css, Box::new(interner.clone()));
+    let mut parser = Parser::new(&mut input);
+    let mut tokens = vec![];
+    while let Ok(token) = parser.next() {
+        tokens.push(token.clone());
+    }
+    let strings = tokens
+        .iter()
+        .map(|token| match *token {
+            Token::Ident(ref s) | Token::QuotedString(ref s) | Token::Function(ref s) => s.as_ptr(),
+            ref token => panic!("unexpected token {:?}", token),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(tokens[0], Token::Ident("foo".into()));
+    assert_eq!(tokens[1], Token::QuotedString("bar".into()));
+    assert_eq!(strings[0], strings[2]);
+    assert_eq!(strings[0], strings[4]);
+    assert_eq!(strings[1], strings[3]);
+    assert_ne!(strings[0], strings[1]);
+    // Values borrowed from the input are left alone.
+    assert_ne!(strings[0], strings[5]);
+    assert_eq!(interner.borrow().len(), 2);
+}
+
+#[test]
+fn overridden_declarations() {
+    let mut declarations = vec![
+        ("color", "red", false),
+        ("margin", "0", true),
+        ("COLOR", "blue", false),
+        ("--x", "1", false),
+        ("margin", "1px", false),
+        ("--X", "2", false),
+        ("--x", "3", false),
+        ("margin", "2px", true),
+    ];
+    remove_overridden_declarations(&mut declarations, |&(name, _, important)| (name, important));
+    assert_eq!(
+        declarations,
+        [
+            ("COLOR", "blue", false),
+            ("--X", "2", false),
+            ("--x", "3", false),
+            ("margin", "2px", true),
+        ]
+    );
+}
+
+#[test]
+fn adjacent_rules_merged() {
+    let mut rules = vec![
+        ("a, b", vec!["color: red"]),
+        ("a,  b", vec!["margin: 0"]),
+        ("a,/* x */b", vec!["padding: 0"]),
+        ("c", vec!["color: blue"]),
+        ("a, b", vec!["color: green"]),
+        ("a, b", vec![]),
+    ];
+    merge_adjacent_rules(
+        &mut rules,
+        |&(prelude, _)| prelude,
+        |first, second| first.1.extend(second.1),
+    );
+    assert_eq!(
+        rules,
+        [
+            ("a, b", vec!["color: red", "margin: 0", "padding: 0"]),
+            ("c", vec!["color: blue"]),
+            ("a, b", vec!["color: green"]),
+        ]
+    );
+}
+
+#[test]
+fn rules_found_by_path() {
+    let css =
+        "a {}\n@media print {\n  b { x: 1 }\n  @supports (display: grid) { c {} @page {} }\n}\n\
+               @supports (x: y) { d {} }\n@MEDIA screen { @supports not (x: y) {\n e {} } }";
+    let found = |path| {
+        find_rules(css, path)
+            .map(|rule| (rule.conditions, rule.source, rule.location.to_string()))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        found(&["@media", "@supports"]),
+        [
+            (vec!["print", "(display: grid)"], "c {}", "4:31".to_owned()),
+            (
+                vec!["print", "(display: grid)"],
+                "@page {}",
+                "4:36".to_owned()
+            ),
+            (vec!["screen", "not (x: y)"], "e {}", "8:2".to_owned()),
+        ]
+    );
+    assert_eq!(
+        found(&["media"]),
+        [
+            (vec!["print"], "b { x: 1 }", "3:3".to_owned()),
+            (
+                vec!["print"],
+                "@supports (display: grid) { c {} @page {} }",
+                "4:3".to_owned()
+            ),
+            (
+                vec!["screen"],
+                "@supports not (x: y) {\n e {} }",
+                "7:17".to_owned()
+            ),
+        ]
+    );
+    assert_eq!(found(&[]).len(), 4);
+    assert_eq!(found(&["@supports", "@media"]), []);
+
+    // Calling `next` again after the end keeps returning `None`.
+    let mut rules = find_rules("a {} b {}", &[]);
+    assert_eq!(rules.by_ref().count(), 2);
+    assert!(rules.next().is_none());
+    assert!(rules.next().is_none());
+}
+
+#[test]
+fn rules_diffed() {
+    let diff = |old: &[(&str, &str)], new: &[(&str, &str)]| {
+        diff_rules(old, new, |rule| rule.0, |rule| rule.1)
+    };
+    let old = [
+        ("a", "a { color: red }"),
+        ("b", "b { x: 1 }"),
+        ("c", "c {}"),
+        ("d", "d {}"),
+    ];
+    let new = [
+        ("b", "b {x: 1}"),
+        ("a", "a { color: red }"),
+        ("c", "c { color: blue }"),
+        ("e", "e {}"),
+    ];
+    assert_eq!(
+        diff(&old, &new),
+        [
+            RuleChange::Removed(0),
+            RuleChange::Removed(3),
+            RuleChange::Inserted(1),
+            RuleChange::Modified { old: 2, new: 2 },
+            RuleChange::Inserted(3),
+        ]
+    );
+    assert_eq!(diff(&old, &old), []);
+    assert_eq!(
+        diff(&[("x", "x {}"), ("x", "x {}")], &[("x", "x {}")]),
+        [RuleChange::Removed(1)]
+    );
+    let all_new: Vec<_> = (0..4).map(RuleChange::Inserted).collect();
+    assert_eq!(diff(&[], &new), all_new);
+
+    // Moving the last rule to the start only removes and inserts that rule.
+    let rotated = [old[3], old[0], old[1], old[2]];
+    assert_eq!(
+        diff(&old, &rotated),
+        [RuleChange::Removed(3), RuleChange::Inserted(0)]
+    );
+    let interleaved = [old[1], ("x", "x {}"), old[3], ("y", "y {}")];
+    assert_eq!(
+        diff(&old, &interleaved),
+        [
+            RuleChange::Removed(0),
+            RuleChange::Removed(2),
+            RuleChange::Inserted(1),
+            RuleChange::Inserted(3),
+        ]
+    );
+}
+
+#[test]
+fn vendor_prefixes() {
+    let table: &[(&str, &[&str])] = &[
+        ("user-select", &["-webkit-", "-moz-"]),
+        ("appearance", &["-webkit-"]),
+    ];
+    let mut declarations = vec![
+        ("color".to_owned(), "red", false),
+        ("-MOZ-user-select".to_owned(), "none", false),
+        ("User-Select".to_owned(), "none", true),
+        ("-webkit-appearance".to_owned(), "none", false),
+    ];
+    add_vendor_prefixes(
+        &mut declarations,
+        table,
+        |d| &d.0,
+        |d, name| (name.to_owned(), d.1, d.2),
+    );
+    assert_eq!(
+        declarations,
+        [
+            ("color".to_owned(), "red", false),
+            ("-MOZ-user-select".to_owned(), "none", false),
+            ("-webkit-user-select".to_owned(), "none", true),
+            ("User-Select".to_owned(), "none", true),
+            ("-webkit-appearance".to_owned(), "none", false),
+        ]
+    );
+
+    remove_vendor_prefixes(&mut declarations, table, |d| &d.0);
+    assert_eq!(
+        declarations,
+        [
+            ("color".to_owned(), "red", false),
+            ("User-Select".to_owned(), "none", true),
+            // There is no unprefixed `appearance`.
+            ("-webkit-appearance".to_owned(), "none", false),
+        ]
+    );
+}
+
+#[test]
+fn normalized_values() {
+    fn normalize(css: &str) -> String {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        let mut serialized = String::new();
+        serialize_normalized_value(&mut parser, &mut serialized).unwrap();
+        serialized
+    }
+    assert_eq!(
+        normalize("  /* a */ 1px \n\t solid  red /**/ "),
+        "1px solid red"
+    );
+    assert_eq!(normalize("calc( 1px  +  2px )"), "calc(1px + 2px)");
+    assert_eq!(normalize("a/**/b a/**/(b) a/**/,b"), "a/**/b a/**/(b) a,b");
+    assert_eq!(normalize("{ a:b;  [ c ] }"), "{a:b; [c]}");
+    assert_eq!(normalize(" /**/ "), "");
+}
+
+#[test]
+fn rules_serialized() {
+    let color = |important| {
+        SerializableDeclarationListItem::Declaration(SerializableDeclaration {
+            name: "color",
+            value: "red",
+            important,
+        })
+    };
+    let rule = SerializableQualifiedRule {
+        prelude: "a, .b",
+        block: vec![
+            color(false),
+            SerializableDeclarationListItem::Declaration(SerializableDeclaration {
+                name: "--my var",
+                value: "{ x }",
+                important: false,
+            }),
+            SerializableDeclarationListItem::QualifiedRule(SerializableQualifiedRule {
+                prelude: "&:hover",
+                block: vec![color(true)],
+            }),
+        ],
+    };
+    assert_eq!(
+        rule.to_css_string(),
+        "a, .b { color: red; --my\\ var: { x }; &:hover { color: red !important; } }"
+    );
+    let rules = vec![
+        SerializableRule::AtRule(SerializableAtRule {
+            name: "import",
+            prelude: "url(a.css)",
+            block: None,
+        }),
+        SerializableRule::AtRule(SerializableAtRule {
+            name: "media",
+            prelude: "print",
+            block: Some(vec![SerializableDeclarationListItem::QualifiedRule(rule)]),
+        }),
+        SerializableRule::AtRule(SerializableAtRule {
+            name: "font-face",
+            prelude: "",
+            block: Some(vec![
+                color(false),
+                SerializableDeclarationListItem::AtRule(SerializableAtRule {
+                    name: "apply",
+                    prelude: "--x",
+                    block: None,
+                }),
+            ]),
+        }),
+        SerializableRule::QualifiedRule(SerializableQualifiedRule {
+            prelude: "b",
+            block: vec![],
+        }),
+    ];
+    let serialize = |trailing_semicolon| {
+        let mut css = String::new();
+        serialize_rule_list(&rules, trailing_semicolon, &mut css).unwrap();
+        css
+    };
+    assert_eq!(
+        serialize(TrailingSemicolon::Always),
+        "@import url(a.css);\n\
+         @media print { a, .b { color: red; --my\\ var: { x }; &:hover { color: red !important; } } }\n\
+         @font-face { color: red; @apply --x; }\n\
+         b { }"
+    );
+    let mut css = String::new();
+    serialize_rule_list_into(&rules, TrailingSemicolon::Always, &mut css);
+    assert_eq!(css, serialize(TrailingSemicolon::Always));
+    assert_eq!(rule_list_len_estimate(&rules), css.len() - "\\".len());
+    for rule in &rules {
+        let mut css = String::from("/**/");
+        rule.serialize_into(&mut css);
+        assert_eq!(css, format!("/**/{}", rule.to_css_string()));
+    }
+    assert_eq!(
+        serialize(TrailingSemicolon::Omit),
+        "@import url(a.css);\n\
+         @media print { a, .b { color: red; --my\\ var: { x }; &:hover { color: red !important } } }\n\
+         @font-face { color: red; @apply --x }\n\
+         b { }"
+    );
+}
+
+#[test]
+fn declarations_serialized_as_in_cssom() {
+    let cssom = |name, value, important| {
+        let declaration = SerializableDeclaration {
+            name,
+            value,
+            important,
+        };
+        let mut css = String::new();
+        declaration.serialize_cssom(&mut css).unwrap();
+        let mut value = String::new();
+        declaration.serialize_cssom_value(&mut value).unwrap();
+        (declaration.to_css_string(), css, value)
+    };
+    assert_eq!(
+        cssom("Margin-Top", " 1px  /* a */\n2px ", true),
+        (
+            "Margin-Top:  1px  /* a */\n2px  !important".to_owned(),
+            "margin-top: 1px 2px !important;".to_owned(),
+            "1px 2px".to_owned(),
+        )
+    );
+    assert_eq!(
+        cssom("--My-Var", "  { a  b } ", false),
+        (
+            "--My-Var:   { a  b } ".to_owned(),
+            "--My-Var: { a  b };".to_owned(),
+            "{ a  b }".to_owned(),
+        )
+    );
+}
+
+#[test]
+fn minified() {
+    fn minify(css: &str, options: &MinifyOptions) -> String {
+        let mut input = ParserInput::new(css);
+        let mut serialized = String::new();
+        serialize_minified(&mut Parser::new(&mut input), options, &mut serialized).unwrap();
+        serialized
+    }
+    let css = "a , b:nth-child( 2n+1 ) { margin : +0.50px  -0.5em 2.0% /* x */ 10 ;\n \
+               content: 'say \"hi\"' }  c{}";
+    assert_eq!(
+        minify(css, &MinifyOptions::default()),
+        "a,b:nth-child(2n+1){margin : .5px -.5em 2% 10;content: 'say \"hi\"'}c{}"
+    );
+    let faithful = MinifyOptions {
+        quote_style: QuoteStyle::Double,
+        trim_numbers: false,
+    };
+    assert_eq!(
+        minify(css, &faithful),
+        "a,b:nth-child(2n+1){margin : +0.5px -0.5em 2.0% 10;content: \"say \\\"hi\\\"\"}c{}"
+    );
+    assert_eq!(
+        minify("1/**/+2 a/**/+.5", &MinifyOptions::default()),
+        "1/**/2 a/**/.5"
+    );
+    assert_eq!(minify("a+.5 -0", &MinifyOptions::default()), "a+.5 -0");
+    // Integers keep their digits, since `1e3` is not an `<integer>`.
+    assert_eq!(
+        minify(
+            "a { z-index: 1000; order:-2000; animation: x steps(1000) 1000.0s 2000.0 }",
+            &MinifyOptions::default()
+        ),
+        "a{z-index: 1000;order:-2000;animation: x steps(1000) 1e3s 2e3}"
+    );
+
+    let css = "a { margin: 0px -0em 0.0% 1px; flex: 1 0px; transition: 0s }";
+    let mut input = ParserInput::new(css);
+    let mut collapsed = String::new();
+    let mut zeros = Vec::new();
+    serialize_minified_with(
+        &mut Parser::new(&mut input),
+        &MinifyOptions::default(),
+        |token, location| {
+            zeros.push(location.column);
+            match *token {
+                Token::Dimension { ref unit, .. } => *unit != "s" && location.column < 40,
+                _ => true,
+            }
+        },
+        &mut collapsed,
+    )
+    .unwrap();
+    assert_eq!(collapsed, "a{margin: 0 0 0 1px;flex: 1 0px;transition: 0s}");
+    assert_eq!(zeros, vec![13, 17, 22, 40, 57]);
+
+    let number = |value| {
+        let mut css = String::new();
+        serialize_minified_number(value, &mut css).unwrap();
+        css
+    };
+    assert_eq!(number(0.5), ".5");
+    assert_eq!(number(-0.25), "-.25");
+    assert_eq!(number(2.0), "2");
+    assert_eq!(number(10.5), "10.5");
+    assert_eq!(number(0.0), "0");
+    assert_eq!(number(1e20), "1e20");
+    assert_eq!(number(1000.0), "1e3");
+    assert_eq!(number(100.0), "100");
+}
+
+#[test]
+fn rewritten_values() {
+    let css = "a /* c */ url(x.png) f(url( \"y.png\" ) red) [blue";
+    let mut input = ParserInput::new(css);
+    let mut parser = Parser::new(&mut input);
+    let mut rewritten = Vec::new();
+    let mut serialized = String::new();
+    serialize_rewritten(
+        &mut parser,
+        &[
+            TokenKind::UnquotedUrl,
+            TokenKind::QuotedString,
+            TokenKind::Ident,
+        ],
+        |token, location| {
+            rewritten.push((token.clone(), location.column));
+            match *token {
+                Token::UnquotedUrl(ref url) => Some(format!("url(/{})", url)),
+                Token::QuotedString(ref url) => Some(format!("\"/{}\"", url)),
+                Token::Ident(ref name) if *name == "red" => Some("#f00".to_owned()),
+                _ => None,
+            }
+        },
+        &mut serialized,
+    )
+    .unwrap();
+    assert_eq!(
+        serialized,
+        "a /* c */ url(/x.png) f(url( \"/y.png\" ) #f00) [blue"
+    );
+    assert_eq!(
+        rewritten,
+        vec![
+            (Token::Ident("a".into()), 1),
+            (Token::UnquotedUrl("x.png".into()), 11),
+            (Token::QuotedString("y.png".into()), 29),
+            (Token::Ident("red".into()), 39),
+            (Token::Ident("blue".into()), 45),
+        ]
+    );
+}
+
+#[test]
+fn token_streams_equivalent_modulo_formatting() {
+    fn hash(css: &str) -> u64 {
+        let mut input = ParserInput::new(css);
+        let mut hasher = DefaultHasher::new();
+        hash_token_stream(&mut Parser::new(&mut input), &mut hasher);
+        hasher.finish()
+    }
+    fn equivalent(a: &str, b: &str) -> bool {
+        let mut input_a = ParserInput::new(a);
+        let mut input_b = ParserInput::new(b);
+        let result = token_stream_eq(
+            &mut Parser::new(&mut input_a),
+            &mut Parser::new(&mut input_b),
+        );
+        if result {
+            assert_eq!(hash(a), hash(b), "{:?} {:?}", a, b);
+        }
+        result
+    }
+    assert!(equivalent(
+        " 1px  solid\n red ",
+        "1.0px /* border */ solid red"
+    ));
+    assert!(equivalent("calc( +1% * 2 )", "calc(1% * 2e0)"));
+    assert!(equivalent("f(a) -0 [b]", "f( a )  0 [ b ]"));
+    assert!(equivalent("a/**/b", "a b"));
+    assert!(!equivalent("a b", "ab"));
+    assert!(!equivalent("a,b", "a ,b"));
+    assert!(!equivalent("1px", "1em"));
+    assert!(!equivalent("f(a) b", "f(a b)"));
+    assert!(!equivalent("(a)", "[a]"));
+    assert!(!equivalent("a", "a b"));
+    assert_ne!(hash("f(a) b"), hash("f(a b)"));
+}
+
+#[test]
+fn stable_token_stream_hashes() {
+    fn hash(css: &str) -> u64 {
+        let mut input = ParserInput::new(css);
+        stable_hash_token_stream(&mut Parser::new(&mut input))
+    }
+    assert_eq!(STABLE_HASH_VERSION, 1);
+    // The FNV-1a offset basis.
+    assert_eq!(hash(""), 0xcbf2_9ce4_8422_2325);
+    assert_eq!(hash(" /**/ "), hash(""));
+    assert_eq!(hash("a { color: red }"), hash("a {color: red}"));
+    assert_eq!(hash("1.0px -0"), hash("+1px 0"));
+    assert_ne!(hash("a b"), hash("ab"));
+    assert_ne!(hash("(a) b"), hash("(a b)"));
+    assert_ne!(hash("1px"), hash("1PX"));
+    assert_ne!(hash("a"), hash("\"a\""));
+    // Changing these values requires incrementing `STABLE_HASH_VERSION`.
+    assert_eq!(hash("a"), 575_645_952_866_152_596);
+    assert_eq!(
+        hash("a { color: red !important }"),
+        12_926_085_776_985_822_621
+    );
+}
+
+#[test]
+fn duplicate_rules() {
+    let mut rules = vec![
+        (1, "a { color: red }"),
+        (2, "b { color: red }"),
+        (3, "a{color: red}"),
+        (4, "a { color: blue }"),
+        (5, "b /* again */ { color : red ; }"),
+        (6, "a {\n  color: red\n}"),
+    ];
+    remove_duplicate_rules(&mut rules, |&(_, css)| css);
+    let ids: Vec<_> = rules.iter().map(|&(id, _)| id).collect();
+    // Whether there is whitespace between two tokens is significant,
+    // as well as the trailing semicolon.
+    assert_eq!(ids, [2, 3, 4, 5, 6]);
+
+    let mut rules = vec!["a{}", "a {}", "a { }"];
+    remove_duplicate_rules(&mut rules, |css| css);
+    assert_eq!(rules, ["a{}", "a { }"]);
+}
+
+#[test]
+fn token_accessors() {
+    let mut input = ParserInput::new("foo 4.5 / bar(");
+    let mut parser = Parser::new(&mut input);
+    let mut tokens = vec![];
+    while let Ok(token) = parser.next_including_whitespace() {
+        tokens.push(token.clone());
+    }
+    assert_eq!(tokens[0].as_ident().map(|s| &**s), Some("foo"));
+    assert_eq!(tokens[1].as_ident(), None);
+    assert!(tokens[1].is_whitespace());
+    assert_eq!(tokens[2].as_number(), Some(4.5));
+    assert_eq!(tokens[4].as_number(), None);
+    assert_eq!(tokens[4].as_delim(), Some('/'));
+    assert!(!tokens[4].is_block());
+    assert!(tokens[6].is_block());
+    assert_eq!(tokens[0].clone().into_ident(), Ok(CowRcStr::from("foo")));
+    assert_eq!(tokens[6].clone().into_ident(), Err(tokens[6].clone()));
+}
+
+#[test]
+fn token_value_accessors() {
+    let mut input = ParserInput::new("50% 2em @media #x 'y' url(z)");
+    let mut parser = Parser::new(&mut input);
+    let mut tokens = vec![];
+    while let Ok(token) = parser.next() {
+        tokens.push(token.clone());
+    }
+    assert_eq!(tokens[0].as_percentage(), Some(0.5));
+    assert_eq!(tokens[0].as_dimension(), None);
+    assert_eq!(tokens[1].as_dimension(), Some((2., &"em".into())));
+    assert_eq!(tokens[1].as_number(), None);
+    let strings: Vec<_> = tokens
+        .iter()
+        .map(|token| token.string_value().map(|s| (token.kind(), s.to_string())))
+        .collect();
+    assert_eq!(
+        strings,
+        [
+            None,
+            None,
+            Some((TokenKind::AtKeyword, "media".into())),
+            Some((TokenKind::IDHash, "x".into())),
+            Some((TokenKind::QuotedString, "y".into())),
+            Some((TokenKind::UnquotedUrl, "z".into())),
+        ]
+    );
+}
+
+#[test]
+fn token_kinds() {
+    let mut input = ParserInput::new("a 1 f(]");
+    let mut parser = Parser::new(&mut input);
+    let mut kinds = vec![];
+    while let Ok(token) = parser.next_including_whitespace() {
+        kinds.push(token.kind());
+        if token.is_block() {
+            break;
+        }
+    }
+    assert_eq!(
+        kinds,
+        [
+            TokenKind::Ident,
+            TokenKind::WhiteSpace,
+            TokenKind::Number,
+            TokenKind::WhiteSpace,
+            TokenKind::Function,
+        ]
+    );
+    assert_eq!(
+        Token::Dimension {
+            has_sign: false,
+            value: 1.,
+            int_value: Some(1),
+            unit: "px".into()
+        }
+        .kind(),
+        TokenKind::Dimension
+    );
+    assert_eq!(TokenKind::Ident.to_string(), "an identifier");
+    assert_eq!(TokenKind::CloseSquareBracket.to_string(), "‘]’");
+}
+
+#[test]
+fn parse_error_messages() {
+    let mut input = ParserInput::new("a {\n  b: c }\n  }");
+    let mut parser = Parser::new(&mut input);
+    while parser.next().map(|t| *t != Token::CloseCurlyBracket) == Ok(true) {}
+    let location = parser.current_source_location();
+    assert_eq!(location.to_string(), "3:4");
+    let error: BasicParseError =
+        location.new_basic_unexpected_token_error(Token::CloseCurlyBracket);
+    assert_eq!(error.to_string(), "unexpected ‘}’ at 3:4");
+    let error = parser.new_basic_error(BasicParseErrorKind::EndOfInput);
+    assert_eq!(error.to_string(), "unexpected end of input at 3:4");
+    let error = location.new_basic_unexpected_token_error(Token::QuotedString("x".into()));
+    assert_eq!(error.to_string(), "unexpected ‘\"x\"’ at 3:4");
+    let error = location.new_basic_unexpected_token_error(Token::WhiteSpace(" "));
+    assert_eq!(error.to_string(), "unexpected whitespace at 3:4");
+    let error = parser.new_basic_error(BasicParseErrorKind::AtRuleInvalid("foo bar".into()));
+    assert_eq!(error.to_string(), "invalid at-rule ‘@foo\\ bar’ at 3:4");
+    let error: ParseError<&str> = location.new_custom_error("expected a color");
+    assert_eq!(error.to_string(), "expected a color at 3:4");
+    let _: &dyn std::error::Error = &error;
+}
+
+#[test]
+fn column_units() {
+    fn location(unit: ColumnUnit, tab_width: u8) -> SourceLocation {
+        let mut input = ParserInput::new("a\n\tb 🆒é x");
+        input.set_column_unit(unit);
+        input.set_tab_width(tab_width);
+        let mut parser = Parser::new(&mut input);
+        while parser.next() != Ok(&Token::Ident("x".into())) {}
+        parser.current_source_location()
+    }
+    assert_eq!(location(ColumnUnit::Utf16CodeUnits, 0).column, 9);
+    assert_eq!(location(ColumnUnit::Utf8Bytes, 0).column, 12);
+    assert_eq!(location(ColumnUnit::CodePoints, 0).column, 8);
+    assert_eq!(location(ColumnUnit::CodePoints, 4).column, 11);
+    assert_eq!(location(ColumnUnit::Utf16CodeUnits, 8).column, 16);
+    assert_eq!(location(ColumnUnit::Utf8Bytes, 0).line, 1);
+
+    // The same locations forwards, backwards and across lines.
+    let css = "a\t\u{1F192} b{c:d}".repeat(3) + "\n\u{e9}\te f";
+    let mut index = LineIndex::new(&css);
+    index.set_column_unit(ColumnUnit::CodePoints);
+    index.set_tab_width(4);
+    let mut input = ParserInput::new(&css);
+    input.set_column_unit(ColumnUnit::CodePoints);
+    input.set_tab_width(4);
+    let mut parser = Parser::new(&mut input);
+    let mut states = vec![parser.state()];
+    while parser.next_including_whitespace().is_ok() {
+        states.push(parser.state());
+    }
+    for state in states.iter().chain(states.iter().rev()) {
+        assert_eq!(
+            parser.source_location_of(state),
+            index.location(state.position())
+        );
+    }
+}
+
+#[test]
+fn compat_quirks() {
+    fn tokens(css: &str, quirks: bool) -> Vec<String> {
+        let mut input = ParserInput::new(css);
+        input.set_compat_quirks(quirks);
+        let mut parser = Parser::new(&mut input);
+        let mut tokens = Vec::new();
+        while let Ok(token) = parser.next() {
+            tokens.push(format!("{:?}", token));
+        }
+        tokens.push(format!("{:?}", parser.current_source_location()));
+        tokens
+    }
+    let css = "progid:DXImageTransform.Microsoft.gradient(startColorstr='#80(000000', \
+               GradientType=0) expression(\n  a.b ? \"x)\" : f(1)\n) url(progid:x)";
+    assert_eq!(
+        tokens(css, true),
+        [
+            "ProgId(\"DXImageTransform.Microsoft.gradient(startColorstr='#80(000000', \
+             GradientType=0)\")",
+            "Expression(\"\\n  a.b ? \\\"x)\\\" : f(1)\\n\")",
+            "UnquotedUrl(\"progid:x\")",
+            "SourceLocation { line: 2, column: 16 }",
+        ]
+    );
+    let standard = tokens(css, false);
+    assert_eq!(
+        &standard[..4],
+        [
+            "Ident(\"progid\")",
+            "Colon",
+            "Ident(\"DXImageTransform\")",
+            "Delim('.')"
+        ]
+    );
+    assert!(!standard
+        .iter()
+        .any(|token| token.starts_with("ProgId") || token.starts_with("Expression")));
+
+    let mut input = ParserInput::new("filter: progid:Alpha(Opacity=80); width: expression(1 + 2");
+    input.set_compat_quirks(true);
+    let mut parser = Parser::new(&mut input);
+    let mut serialized = String::new();
+    while let Ok(token) = parser.next_including_whitespace() {
+        token.to_css(&mut serialized).unwrap();
+    }
+    assert_eq!(
+        serialized,
+        "filter: progid:Alpha(Opacity=80); width: expression(1 + 2)"
+    );
+    assert_eq!(
+        Token::ProgId("Alpha").kind().description(),
+        "a ‘progid:’ filter"
+    );
+
+    // Only with arguments: this is a selector.
+    assert_eq!(
+        tokens(".progid:hover {}", true),
+        [
+            "Delim('.')",
+            "Ident(\"progid\")",
+            "Colon",
+            "Ident(\"hover\")",
+            "CurlyBracketBlock",
+            "SourceLocation { line: 0, column: 17 }",
+        ]
+    );
+}
+
+#[derive(Debug, PartialEq)]
+enum NestedRule {
+    Style(String),
+    Group(String, Vec<NestedRule>),
+}
+
+struct NestingParser<'a> {
+    errors: &'a RefCell<Vec<String>>,
+}
+
+fn parse_nested_rules_for_test<'i, 't>(
+    input: &mut Parser<'i, 't>,
+    errors: &RefCell<Vec<String>>,
+) -> Vec<NestedRule> {
+    parse_nested_rules(input, NestingParser { errors }, |_, slice| {
+        errors.borrow_mut().push(slice.to_owned())
+    })
+}
+
+fn remaining_prelude<'i, 't>(input: &mut Parser<'i, 't>) -> String {
+    let start = input.position();
+    while input.next().is_ok() {}
+    input.slice_from(start).trim().to_owned()
+}
+
+impl<'a, 'i> AtRuleParser<'i> for NestingParser<'a> {
+    type PreludeNoBlock = ();
+    type PreludeBlock = String;
+    type AtRule = NestedRule;
+    type Error = ();
+
+    fn parse_prelude<'t>(
+        &mut self,
+        name: CowRcStr<'i>,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<AtRuleType<(), String>, ParseError<'i, ()>> {
+        match_ignore_ascii_case! { &*name,
+            "media" | "supports" => Ok(AtRuleType::WithBlock(remaining_prelude(input))),
+            _ => Err(input.new_error(BasicParseErrorKind::AtRuleInvalid(name))),
+        }
+    }
+
+    fn parse_block<'t>(
+        &mut self,
+        prelude: String,
+        _location: SourceLocation,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<NestedRule, ParseError<'i, ()>> {
+        Ok(NestedRule::Group(
+            prelude,
+            parse_nested_rules_for_test(input, self.errors),
+        ))
+    }
+}
+
+impl<'a, 'i> QualifiedRuleParser<'i> for NestingParser<'a> {
+    type Prelude = String;
+    type QualifiedRule = NestedRule;
+    type Error = ();
+
+    fn parse_prelude<'t>(
+        &mut self,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<String, ParseError<'i, ()>> {
+        Ok(remaining_prelude(input))
+    }
+
+    fn parse_block<'t>(
+        &mut self,
+        prelude: String,
+        _location: SourceLocation,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<NestedRule, ParseError<'i, ()>> {
+        while input.next().is_ok() {}
+        Ok(NestedRule::Style(prelude))
+    }
+}
+
+#[test]
+fn nested_rules() {
+    let mut input =
+        ParserInput::new("@media print { a {} @supports (x: y) { b {} @page {} } @bad {} c {} }");
+    let mut parser = Parser::new(&mut input);
+    let errors = RefCell::new(vec![]);
+    let rules = parse_nested_rules_for_test(&mut parser, &errors);
+    assert_eq!(
+        rules,
+        [NestedRule::Group(
+            "print".into(),
+            vec![
+                NestedRule::Style("a".into()),
+                NestedRule::Group("(x: y)".into(), vec![NestedRule::Style("b".into())]),
+                NestedRule::Style("c".into()),
+            ]
+        )]
+    );
+    assert_eq!(*errors.borrow(), ["@page ", "@bad "]);
+}
+
+#[test]
+fn at_rule_classification() {
+    assert_eq!(
+        classify_at_rule("import"),
+        Some(AtRuleContents::PreludeOnly)
+    );
+    assert_eq!(classify_at_rule("MEDIA"), Some(AtRuleContents::Rules));
+    assert_eq!(
+        classify_at_rule("font-face"),
+        Some(AtRuleContents::Declarations)
+    );
+    assert_eq!(classify_at_rule("unknown"), None);
+
+    let mut classifier = AtRuleClassifier::new();
+    classifier.set("Tailwind", Some(AtRuleContents::PreludeOnly));
+    classifier.set("page", None);
+    assert_eq!(
+        classifier.classify("tailwind"),
+        Some(AtRuleContents::PreludeOnly)
+    );
+    assert_eq!(classifier.classify("page"), None);
+    assert_eq!(classifier.classify("supports"), Some(AtRuleContents::Rules));
+
+    match AtRuleContents::PreludeOnly.at_rule_type("x") {
+        AtRuleType::WithoutBlock("x") => {}
+        _ => panic!("expected an at-rule without block"),
+    }
+}
+
+#[test]
+fn url_rewriter() {
+    let css = "a { b: url(x.png) url( \"y.png\" ) \"z.png\" url(\"ok\") f(\"w.png\") }";
+    let mut input = ParserInput::new(css);
+    let locations = Rc::new(RefCell::new(vec![]));
+    let seen = locations.clone();
+    input.set_url_rewriter(move |url, location| {
+        seen.borrow_mut().push(location);
+        if url == "ok" {
+            None
+        } else {
+            Some(format!("https://example.com/{}", url))
+        }
+    });
+    let mut parser = Parser::new(&mut input);
+    let mut strings = vec![];
+    fn collect<'i, 't>(parser: &mut Parser<'i, 't>, strings: &mut Vec<String>) {
+        while let Ok(token) = parser.next() {
+            match *token {
+                Token::UnquotedUrl(ref s) | Token::QuotedString(ref s) => {
+                    strings.push(s.to_string())
+                }
+                _ => {}
+            }
+            if token.is_block() {
+                let result: Result<_, ParseError<()>> = parser.parse_nested_block(|parser| {
+                    collect(parser, strings);
+                    Ok(())
+                });
+                result.unwrap();
+            }
+        }
+    }
+    collect(&mut parser, &mut strings);
+    assert_eq!(
+        strings,
+        [
+            "https://example.com/x.png",
+            "https://example.com/y.png",
+            "z.png",
+            "ok",
+            "w.png"
+        ]
+    );
+    assert_eq!(
+        *locations.borrow(),
+        [
+            SourceLocation { line: 0, column: 8 },
+            SourceLocation {
+                line: 0,
+                column: 24
+            },
+            SourceLocation {
+                line: 0,
+                column: 46
+            }
+        ]
+    );
+}
+
+#[test]
+fn base_url_resolution() {
+    fn resolve(base: &str, url: &str) -> Option<String> {
+        if url.contains(':') {
+            Some(url.to_owned())
+        } else if url.starts_with('/') {
+            None
+        } else {
+            Some(format!("{}{}", base, url))
+        }
+    }
+    let mut input = ParserInput::new("url(x.png) url('data:,') url(/abs)");
+    input.set_base_url("https://example.com/css/", resolve);
+    let mut urls = vec![];
+    {
+        let mut parser = Parser::new(&mut input);
+        while let Ok(token) = parser.next() {
+            if let Token::UnquotedUrl(ref url) = *token {
+                urls.push(url.to_string());
+            }
+            if token.is_block() {
+                let result: Result<_, ParseError<()>> = parser.parse_nested_block(|parser| {
+                    if let Token::QuotedString(ref url) = *parser.next()? {
+                        urls.push(url.to_string());
+                    }
+                    Ok(())
+                });
+                result.unwrap();
+            }
+        }
+    }
+    assert_eq!(urls, ["https://example.com/css/x.png", "data:,", "/abs"]);
+    assert_eq!(
+        input.take_unresolved_urls(),
+        [UnresolvedUrl {
+            url: "/abs".into(),
+            location: SourceLocation {
+                line: 0,
+                column: 26
+            },
+        }]
+    );
+    assert_eq!(input.take_unresolved_urls(), []);
+}
+
+#[test]
+fn unclosed_blocks() {
+    let unclosed = |line, column, closing| UnclosedBlock {
+        location: SourceLocation { line, column },
+        closing,
+    };
+    let mut input = ParserInput::new("a {\n  b: f(1, [2");
+    input.set_record_unclosed_blocks(true);
+    {
+        let mut parser = Parser::new(&mut input);
+        let result: Result<(), ParseError<()>> = parser.try_parse(|parser| {
+            while parser.next().is_ok() {}
+            Err(parser.new_custom_error(()))
+        });
+        assert!(result.is_err());
+        while parser.next().is_ok() {}
+    }
+    assert_eq!(
+        input.take_unclosed_blocks(),
+        [
+            unclosed(1, 11, ']'),
+            unclosed(1, 7, ')'),
+            unclosed(0, 3, '}'),
+        ]
+    );
+    assert_eq!(input.take_unclosed_blocks(), []);
+
+    let mut input = ParserInput::new("(a) (b");
+    input.set_record_unclosed_blocks(true);
+    {
+        let mut parser = Parser::new(&mut input);
+        while parser.next().is_ok() {
+            let result: Result<_, ParseError<()>> =
+                parser.parse_nested_block(|parser| Ok(parser.expect_ident_cloned()?));
+            assert!(result.is_ok());
+        }
+    }
+    assert_eq!(input.take_unclosed_blocks(), [unclosed(0, 5, ')')]);
+
+    let mut input = ParserInput::new("a { b: f(1, [2]) }");
+    input.set_record_unclosed_blocks(true);
+    {
+        let mut parser = Parser::new(&mut input);
+        while parser.next().is_ok() {}
+    }
+    assert_eq!(input.take_unclosed_blocks(), []);
+
+    // Not recorded by default.
+    let mut input = ParserInput::new("a { b: f(1");
+    {
+        let mut parser = Parser::new(&mut input);
+        while parser.next().is_ok() {}
+    }
+    assert_eq!(input.take_unclosed_blocks(), []);
+}
+
+#[test]
+fn urls_extracted() {
+    let css = r#"@import "a.css" layer(x);
+@import url( 'b.css' ) print;
+@namespace svg url(http://www.w3.org/2000/svg);
+@font-face { font-family: F; src: local("F"), url(f.woff2) format("woff2") }
+@media print {
+  a { background: image-set("c.png" 1x, url(d.png) 2x), url(e.png); mask: URL("f.svg") }
+}"#;
+    let urls: Vec<_> = extract_urls(css)
+        .into_iter()
+        .map(|url| (url.url.to_string(), url.role, url.location.to_string()))
+        .collect();
+    let url = |url: &str, role, location: &str| (url.to_owned(), role, location.to_owned());
+    assert_eq!(
+        urls,
+        [
+            url("a.css", UrlRole::Import, "1:9"),
+            url("b.css", UrlRole::Import, "2:9"),
+            url("http://www.w3.org/2000/svg", UrlRole::Namespace, "3:16"),
+            url("f.woff2", UrlRole::FontFaceSource, "4:47"),
+            url("c.png", UrlRole::ImageSet, "6:29"),
+            url("d.png", UrlRole::ImageSet, "6:41"),
+            url("e.png", UrlRole::Value, "6:57"),
+            url("f.svg", UrlRole::Value, "6:75"),
+        ]
+    );
+}
+
+#[cfg(feature = "values")]
+#[test]
+fn image_set_options() {
+    let css = r#"image-set("a.png" 1x, url(b.png) TYPE("image/webp") 192dpi, "c.avif" type("image/avif"))"#;
+    let mut input = ParserInput::new(css);
+    let mut parser = Parser::new(&mut input);
+    parser.expect_function_matching("image-set").unwrap();
+    let options = parser
+        .parse_nested_block(|input| {
+            parse_image_set(input, |input| -> Result<_, ParseError<()>> {
+                let location = input.current_source_location();
+                match *input.next()? {
+                    Token::QuotedString(ref url) | Token::UnquotedUrl(ref url) => {
+                        Ok(url.to_string())
+                    }
+                    ref token => Err(location.new_unexpected_token_error(token.clone())),
+                }
+            })
+        })
+        .unwrap();
+    assert_eq!(
+        options,
+        [
+            ImageSetOption {
+                image: "a.png".to_owned(),
+                resolution: Some(1.),
+                mime_type: None,
+            },
+            ImageSetOption {
+                image: "b.png".to_owned(),
+                resolution: Some(2.),
+                mime_type: Some("image/webp".into()),
+            },
+            ImageSetOption {
+                image: "c.avif".to_owned(),
+                resolution: None,
+                mime_type: Some("image/avif".into()),
+            },
+        ]
+    );
+
+    let mut input = ParserInput::new("\"a.png\" 2em");
+    let mut parser = Parser::new(&mut input);
+    let result = parse_image_set(&mut parser, |input| -> Result<_, ParseError<()>> {
+        Ok(input.expect_string()?.to_string())
+    });
+    assert!(result.is_err());
+}
+
+#[cfg(feature = "values")]
+#[test]
+fn gradient_arguments() {
+    fn parse(css: &str) -> Result<GradientArguments<String, f32>, String> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        let result: Result<_, ParseError<()>> = parse_gradient_arguments(
+            &mut parser,
+            |input| {
+                let start = input.position();
+                while input.next().is_ok() {}
+                Ok(input.slice_from(start).to_owned())
+            },
+            |input| {
+                let location = input.current_source_location();
+                match *input.next()? {
+                    Token::Percentage { unit_value, .. } => Ok(unit_value),
+                    ref token => Err(location.new_unexpected_token_error(token.clone())),
+                }
+            },
+        );
+        result.map_err(|error| error.basic().to_string())
+    }
+    let red = Color::RGBA(RGBA::new(255, 0, 0, 255));
+    let blue = Color::RGBA(RGBA::new(0, 0, 255, 255));
+    assert_eq!(
+        parse("to right, red, 30%, blue 50% 100%"),
+        Ok(GradientArguments {
+            direction: Some("to right".into()),
+            stops: vec![
+                ColorStop {
+                    color: Some(red),
+                    positions: vec![],
+                },
+                ColorStop {
+                    color: None,
+                    positions: vec![0.3],
+                },
+                ColorStop {
+                    color: Some(blue),
+                    positions: vec![0.5, 1.],
+                },
+            ],
+        })
+    );
+    assert_eq!(
+        parse("red 10%, blue"),
+        Ok(GradientArguments {
+            direction: None,
+            stops: vec![
+                ColorStop {
+                    color: Some(red),
+                    positions: vec![0.1],
+                },
+                ColorStop {
+                    color: Some(blue),
+                    positions: vec![],
+                },
+            ],
+        })
+    );
+    assert_eq!(
+        parse("red, 10%, 20%, blue"),
+        Err("unexpected ‘20%’ at 1:11".into())
+    );
+    assert_eq!(
+        parse("red, blue, 10%"),
+        Err("unexpected ‘10%’ at 1:12".into())
+    );
+    assert!(parse("red, blue 1% 2% 3%").is_err());
+}
+
+#[cfg(feature = "values")]
+#[test]
+fn function_lists() {
+    fn parse(css: &str) -> Result<Vec<(String, Vec<f32>)>, String> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        let result: Result<_, ParseError<()>> = parse_function_list(&mut parser, |name, input| {
+            let arguments = input.parse_comma_separated(|input| Ok(input.expect_number()?))?;
+            Ok((name.to_string(), arguments))
+        });
+        result.map_err(|error| error.basic().to_string())
+    }
+    assert_eq!(
+        parse(" scale(2)translate(1, 2)  rotate(3) "),
+        Ok(vec![
+            ("scale".into(), vec![2.]),
+            ("translate".into(), vec![1., 2.]),
+            ("rotate".into(), vec![3.]),
+        ])
+    );
+    assert_eq!(
+        parse("scale(2) none rotate(3)"),
+        Err("unexpected ‘none’ at 1:10".into())
+    );
+    assert_eq!(parse("  "), Err("unexpected end of input at 1:3".into()));
+    assert!(parse("scale(a)").is_err());
+}
+
+#[cfg(feature = "values")]
+#[test]
+fn raw_functions() {
+    fn parse(css: &str) -> Result<Vec<(String, usize, String)>, String> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        let result: Result<_, ParseError<()>> = parse_raw_function_list(&mut parser, |_, input| {
+            let mut tokens = Vec::new();
+            while let Ok(token) = input.next_including_whitespace_and_comments() {
+                tokens.push(token.clone())
+            }
+            Ok(tokens.len())
+        });
+        let functions = result.map_err(|error| error.basic().to_string())?;
+        Ok(functions
+            .into_iter()
+            .map(|function| {
+                assert_eq!(function.to_css_string(), function.source);
+                (
+                    function.name.to_string(),
+                    function.arguments,
+                    function.source.to_owned(),
+                )
+            })
+            .collect())
+    }
+    assert_eq!(
+        parse("-moz-element(#a)  foo( 1.0e0 /*x*/ ) "),
+        Ok(vec![
+            ("-moz-element".into(), 1, "-moz-element(#a)".into()),
+            ("foo".into(), 5, "foo( 1.0e0 /*x*/ )".into()),
+        ])
+    );
+    assert_eq!(parse("foo(1) bar"), Err("unexpected ‘bar’ at 1:8".into()));
+    assert_eq!(parse(""), Err("unexpected end of input at 1:1".into()));
+
+    let mut input = ParserInput::new("  -webkit-gradient(linear, 0 0) x");
+    let mut parser = Parser::new(&mut input);
+    let result: Result<_, ParseError<()>> = parse_raw_function(&mut parser, |name, input| {
+        while input.next().is_ok() {}
+        Ok(name.len())
+    });
+    let function = result.unwrap();
+    assert_eq!(function.arguments, 16);
+    assert_eq!(function.source, "-webkit-gradient(linear, 0 0)");
+    assert_eq!(parser.expect_ident_cloned(), Ok("x".into()));
+}
+
+#[test]
+fn delim_separated() {
+    fn parse(css: &str) -> Vec<Vec<String>> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        let result: Result<_, ParseError<()>> = parser.parse_comma_separated(|input| {
+            input.split_top_level('/', |input| {
+                let start = input.position();
+                while input.next().is_ok() {}
+                Ok(input.slice_from(start).trim().to_owned())
+            })
+        });
+        result.unwrap()
+    }
+    assert_eq!(parse("12px/1.5 serif"), vec![vec!["12px", "1.5 serif"]]);
+    assert_eq!(
+        parse("1 / calc(2/3) / 'a/b' /* / */ x"),
+        vec![vec!["1", "calc(2/3)", "'a/b' /* / */ x"]]
+    );
+    assert_eq!(parse("-1/+2, a"), vec![vec!["-1", "+2"], vec!["a"]]);
+    assert_eq!(parse("a/"), vec![vec!["a", ""]]);
+
+    let mut input = ParserInput::new("a b / c");
+    let mut parser = Parser::new(&mut input);
+    let result: Result<_, ParseError<()>> =
+        parser.split_top_level('/', |input| Ok(input.expect_ident()?.clone()));
+    assert!(result.is_err());
+
+    // A nested split also stops before the enclosing delimiter.
+    let mut input = ParserInput::new("1 / 2 + 3 / 4");
+    let mut parser = Parser::new(&mut input);
+    let result: Result<_, ParseError<()>> = parser.split_top_level('+', |input| {
+        input.split_top_level('/', |input| Ok(input.expect_number()?))
+    });
+    assert_eq!(result.unwrap(), vec![vec![1., 2.], vec![3., 4.]]);
+}
+
+#[test]
+#[should_panic(expected = "not a delimiter")]
+fn non_ascii_delim_separated() {
+    let mut input = ParserInput::new("a\u{e9}b");
+    let mut parser = Parser::new(&mut input);
+    let _: Result<_, ParseError<()>> =
+        parser.split_top_level('\u{e9}', |input| Ok(input.expect_ident()?.clone()));
+}
+
+#[test]
+#[should_panic(expected = "not a delimiter")]
+fn comma_delim_separated() {
+    let mut input = ParserInput::new("a, b");
+    let mut parser = Parser::new(&mut input);
+    let _: Result<_, ParseError<()>> =
+        parser.split_top_level(',', |input| Ok(input.expect_ident()?.clone()));
+}
+
+#[cfg(feature = "values")]
+#[test]
+fn counters() {
+    fn parse(css: &str) -> Result<String, ()> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        let counter = parser
+            .parse_entirely(|input| Counter::parse(input).map_err(ParseError::<()>::from))
+            .map_err(|_| ())?;
+        Ok(counter.to_css_string())
+    }
+    assert_eq!(parse("counter(item)"), Ok("counter(item)".into()));
+    assert_eq!(
+        parse("COUNTER( item ,upper-roman )"),
+        Ok("counter(item, upper-roman)".into())
+    );
+    assert_eq!(
+        parse("counters(section, '.' , decimal)"),
+        Ok("counters(section, \".\", decimal)".into())
+    );
+    assert_eq!(
+        parse("counters(section,\"-\")"),
+        Ok("counters(section, \"-\")".into())
+    );
+    assert_eq!(parse("counter(item, 'disc')"), Err(()));
+    assert_eq!(parse("counter('item')"), Err(()));
+    assert_eq!(parse("counter(none)"), Err(()));
+    assert_eq!(parse("counter(revert-layer)"), Err(()));
+    assert_eq!(parse("counter(x, revert-layer)"), Err(()));
+    assert_eq!(parse("counters(section)"), Err(()));
+    assert_eq!(parse("counters(section, a)"), Err(()));
+    assert_eq!(parse("counter(item, disc, disc)"), Err(()));
+    assert_eq!(parse("attr(item)"), Err(()));
+
+    let counter = Counter {
+        name: "a b".into(),
+        separator: None,
+        style: None,
+    };
+    assert_eq!(counter.to_css_string(), "counter(a\\ b)");
+}
+
+#[cfg(feature = "values")]
+#[test]
+fn css_wide_keywords() {
+    fn parse(css: &str) -> (Option<CssWideKeyword>, bool) {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        let keyword = parse_css_wide_keyword(&mut parser);
+        (keyword, parser.is_exhausted())
+    }
+    assert_eq!(parse("initial"), (Some(CssWideKeyword::Initial), true));
+    assert_eq!(parse(" INHERIT "), (Some(CssWideKeyword::Inherit), true));
+    assert_eq!(parse("/**/unset/**/"), (Some(CssWideKeyword::Unset), true));
+    assert_eq!(parse("revert"), (Some(CssWideKeyword::Revert), true));
+    assert_eq!(
+        parse("Revert-Layer"),
+        (Some(CssWideKeyword::RevertLayer), true)
+    );
+    assert_eq!(parse("inherit inherit"), (None, false));
+    assert_eq!(parse("initial, red"), (None, false));
+    assert_eq!(parse("red"), (None, false));
+    assert_eq!(parse("'initial'"), (None, false));
+    assert_eq!(parse(""), (None, true));
+    assert_eq!(CssWideKeyword::RevertLayer.to_css_string(), "revert-layer");
+}
+
+#[test]
+fn invalid_important() {
+    fn parse(css: &str, accept_invalid: bool) -> Result<(bool, bool), String> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        let result = parser.try_parse(|input| parse_importance(input, accept_invalid));
+        match result {
+            Ok(important) => Ok((important, parser.is_exhausted())),
+            Err(error) => Err(error.to_string()),
+        }
+    }
+    assert_eq!(parse("!important", false), Ok((true, true)));
+    assert_eq!(parse("! IMPORTANT", true), Ok((true, true)));
+    assert_eq!(
+        parse("!ie", false),
+        Err("invalid importance ‘!ie’ at 1:2".into())
+    );
+    assert_eq!(parse("!ie", true), Ok((false, true)));
+    assert_eq!(
+        parse("! 'important'", true),
+        Err("unexpected ‘\"important\"’ at 1:3".into())
+    );
+    assert_eq!(
+        parse("important", true),
+        Err("unexpected ‘important’ at 1:1".into())
+    );
+
+    let mut input = ParserInput::new("!ie");
+    let mut parser = Parser::new(&mut input);
+    assert_eq!(
+        parser.try_parse(parse_important).map_err(|e| e.kind),
+        Err(BasicParseErrorKind::InvalidImportant("ie".into()))
+    );
+}
+
+#[test]
+fn round_trip() {
+    let mut input = ParserInput::new("a/**/b f(+1.0 2e3px, 'x\\'y') [#id] url(a b");
+    let mut parser = Parser::new(&mut input);
+    let mut tokens = Vec::new();
+    while let Ok(token) = parser.next_including_whitespace_and_comments() {
+        let closing_token = match *token {
+            Token::Function(_) | Token::ParenthesisBlock => Some(Token::CloseParenthesis),
+            Token::SquareBracketBlock => Some(Token::CloseSquareBracket),
+            _ => None,
+        };
+        tokens.push(token.clone());
+        if let Some(closing_token) = closing_token {
+            let result: Result<_, ParseError<()>> = parser.parse_nested_block(|input| {
+                while let Ok(token) = input.next_including_whitespace_and_comments() {
+                    tokens.push(token.clone())
+                }
+                Ok(())
+            });
+            result.unwrap();
+            tokens.push(closing_token);
+        }
+    }
+    assert_eq!(round_trip_check(&tokens), Ok(()));
+    assert_eq!(
+        round_trip_check(&[Token::Ident("a".into()), Token::Ident("b".into())]),
+        Ok(())
+    );
+
+    let number = Token::Number {
+        has_sign: false,
+        value: 1.,
+        int_value: Some(2),
+    };
+    assert_eq!(
+        round_trip_check(&[Token::Colon, number.clone()]),
+        Err(Mismatch {
+            index: 1,
+            expected: Some(number),
+            found: Some("1".into()),
+            serialization: ":1".into(),
+        })
+    );
+    assert_eq!(
+        round_trip_check(&[Token::WhiteSpace("a")]),
+        Err(Mismatch {
+            index: 0,
+            expected: Some(Token::WhiteSpace("a")),
+            found: Some("a".into()),
+            serialization: "a".into(),
+        })
+    );
+    assert_eq!(
+        round_trip_check(&[Token::Comment(" x */ y ")]).map_err(|m| (m.index, m.found)),
+        Err((0, Some("/* x */".into())))
+    );
+}
+
+#[test]
+fn whitespace_original_text() {
+    let mut input = ParserInput::new("a {}\r\n\n\t b {}");
+    let mut parser = Parser::new(&mut input);
+    let mut serialized = String::new();
+    let mut whitespace = Vec::new();
+    while let Ok(token) = parser.next_including_whitespace() {
+        if let Token::WhiteSpace(text) = *token {
+            whitespace.push(text);
+        }
+        token.to_css(&mut serialized).unwrap();
+        if token.is_block() {
+            serialized.push('}')
+        }
+    }
+    assert_eq!(whitespace, [" ", "\r\n\n\t ", " "]);
+    assert_eq!(serialized, "a {}\r\n\n\t b {}");
+}
+
+#[test]
+fn whitespace_has_newline() {
+    assert!(!Token::WhiteSpace(" \t").has_newline());
+    assert!(Token::WhiteSpace(" \n ").has_newline());
+    assert!(Token::WhiteSpace("\r").has_newline());
+    assert!(Token::WhiteSpace("\x0C").has_newline());
+    assert!(!Token::Comment("\n").has_newline());
+    assert!(!Token::QuotedString("\n".into()).has_newline());
+}
+
+struct SourceTextParser;
+
+impl<'i> AtRuleParser<'i> for SourceTextParser {
+    type PreludeNoBlock = &'i str;
+    type PreludeBlock = &'i str;
+    type AtRule = (&'i str, &'i str);
+    type Error = ();
+
+    fn parse_prelude<'t>(
+        &mut self,
+        _name: CowRcStr<'i>,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<AtRuleType<&'i str, &'i str>, ParseError<'i, ()>> {
+        let ((), source) = input.parse_entirely_with_source(|input| {
+            while input.next().is_ok() {}
+            Ok(())
+        })?;
+        Ok(AtRuleType::WithBlock(source))
+    }
+
+    fn parse_block<'t>(
+        &mut self,
+        prelude: &'i str,
         _location: SourceLocation,
         input: &mut Parser<'i, 't>,
-    ) -> Result<Value, ParseError<'i, ()>> {
-        Ok(JArray![
-            "qualified rule",
-            prelude,
-            component_values_to_json(input),
-        ])
+    ) -> Result<(&'i str, &'i str), ParseError<'i, ()>> {
+        while input.next().is_ok() {}
+        Ok(("@", prelude))
     }
 }
 
-fn component_values_to_json(input: &mut Parser) -> Vec<Value> {
-    let mut values = vec![];
-    while let Ok(token) = input.next_including_whitespace().map(|t| t.clone()) {
-        values.push(one_component_value_to_json(token, input));
+impl<'i> QualifiedRuleParser<'i> for SourceTextParser {
+    type Prelude = &'i str;
+    type QualifiedRule = (&'i str, &'i str);
+    type Error = ();
+
+    fn parse_prelude<'t>(
+        &mut self,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<&'i str, ParseError<'i, ()>> {
+        let (_, source) =
+            input.parse_entirely_with_source(|input| Ok(input.expect_ident()?.clone()))?;
+        Ok(source)
     }
-    values
+
+    fn parse_block<'t>(
+        &mut self,
+        prelude: &'i str,
+        _location: SourceLocation,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<(&'i str, &'i str), ParseError<'i, ()>> {
+        while input.next().is_ok() {}
+        Ok(("", prelude))
+    }
+}
+
+#[test]
+fn prelude_source_text() {
+    fn parse(css: &str) -> Result<(&str, &str), ()> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        parse_one_rule(&mut parser, &mut SourceTextParser).map_err(|_| ())
+    }
+    assert_eq!(parse(" \\61 /**/ {}"), Ok(("", "\\61 /**/")));
+    assert_eq!(parse("/* a */ b {}"), Ok(("", "b")));
+    assert_eq!(parse("a b {}"), Err(()));
+    assert_eq!(
+        parse("@media  screen AND (x:1)\n{}"),
+        Ok(("@", "screen AND (x:1)"))
+    );
+    assert_eq!(parse("@page{}"), Ok(("@", "")));
+}
+
+#[test]
+fn input_completed() {
+    fn complete(css: &str) -> (String, Vec<(IncompleteKind, String)>) {
+        let completion = complete_input(css);
+        let completed = format!("{}{}", css, completion.suffix);
+        // The suffix does not change the tokens of the input.
+        let tokens = |css: &str| {
+            let mut input = ParserInput::new(css);
+            let mut input = Parser::new(&mut input);
+            let mut tokens = Vec::new();
+            while let Ok(token) = input.next_including_whitespace_and_comments() {
+                tokens.push(token.clone().to_css_string())
+            }
+            tokens
+        };
+        let expected = tokens(css);
+        assert_eq!(
+            tokens(&completed)[..expected.len()],
+            expected[..],
+            "{:?}",
+            css
+        );
+        let incomplete = completion
+            .incomplete
+            .iter()
+            .map(|incomplete| (incomplete.kind, incomplete.location.to_string()))
+            .collect();
+        (completion.suffix, incomplete)
+    }
+    assert_eq!(complete("a { color: red }"), ("".into(), vec![]));
+    assert_eq!(
+        complete("a { b: f(1, [2"),
+        (
+            "])}".into(),
+            vec![
+                (IncompleteKind::Block(']'), "1:13".into()),
+                (IncompleteKind::Block(')'), "1:9".into()),
+                (IncompleteKind::Block('}'), "1:3".into()),
+            ]
+        )
+    );
+    assert_eq!(
+        complete("a { content: 'it\\'s"),
+        (
+            "'}".into(),
+            vec![
+                (IncompleteKind::QuotedString('\''), "1:14".into()),
+                (IncompleteKind::Block('}'), "1:3".into()),
+            ]
+        )
+    );
+    assert_eq!(
+        complete("a { content: \"a\\"),
+        (
+            "\n\"}".into(),
+            vec![
+                (IncompleteKind::QuotedString('"'), "1:14".into()),
+                (IncompleteKind::Block('}'), "1:3".into()),
+            ]
+        )
+    );
+    assert_eq!(
+        complete("b: url(a.png"),
+        (")".into(), vec![(IncompleteKind::Url, "1:4".into())])
+    );
+    assert_eq!(
+        complete("b: url(a\\)"),
+        (")".into(), vec![(IncompleteKind::Url, "1:4".into())])
+    );
+    assert_eq!(
+        complete("/* a *"),
+        ("*/".into(), vec![(IncompleteKind::Comment, "1:1".into())])
+    );
+    assert_eq!(
+        complete("f(a\\"),
+        (
+            "\u{FFFD})".into(),
+            vec![(IncompleteKind::Block(')'), "1:2".into())]
+        )
+    );
+    assert_eq!(complete("a { b: ')' }"), ("".into(), vec![]));
+}
+
+#[test]
+fn completion_contexts() {
+    fn context(css: &str) -> CompletionContext<'static> {
+        // `|` marks the offset.
+        let offset = css.find('|').unwrap();
+        let input = format!("{}{}", &css[..offset], &css[offset + 1..]);
+        let context = match completion_context(&input, offset) {
+            CompletionContext::AtRulePrelude(name) => {
+                CompletionContext::AtRulePrelude(name.to_string().into())
+            }
+            CompletionContext::PropertyValue(name) => {
+                CompletionContext::PropertyValue(name.to_string().into())
+            }
+            CompletionContext::Selector => CompletionContext::Selector,
+            CompletionContext::AtRuleName => CompletionContext::AtRuleName,
+            CompletionContext::PropertyName => CompletionContext::PropertyName,
+            CompletionContext::Comment => CompletionContext::Comment,
+            CompletionContext::Unknown => CompletionContext::Unknown,
+        };
+        context
+    }
+    use CompletionContext::*;
+    assert_eq!(context("|"), Selector);
+    assert_eq!(context("a.b|"), Selector);
+    assert_eq!(context("a { } b:hov|"), Selector);
+    assert_eq!(context("@|"), AtRuleName);
+    assert_eq!(context("@med|ia"), AtRuleName);
+    assert_eq!(context("@media |"), AtRulePrelude("media".into()));
+    assert_eq!(
+        context("@media (min-width: |"),
+        AtRulePrelude("media".into())
+    );
+    assert_eq!(context("@media screen { a { } |"), Selector);
+    assert_eq!(context("@media screen { a { |"), PropertyName);
+    assert_eq!(context("a { |"), PropertyName);
+    assert_eq!(context("a { col|or: red }"), PropertyName);
+    assert_eq!(context("a { color: red; |}"), PropertyName);
+    assert_eq!(context("a { color |"), PropertyName);
+    assert_eq!(context("a { color:|"), PropertyValue("color".into()));
+    assert_eq!(
+        context("a { Color: var(--x, |"),
+        PropertyValue("Color".into())
+    );
+    assert_eq!(context("a { b: url(|"), PropertyValue("b".into()));
+    assert_eq!(context("a { b: c } |"), Selector);
+    assert_eq!(context("a { b: c } d { e: f(g) } |"), Selector);
+    assert_eq!(context("a { b: (c } |"), Selector);
+    assert_eq!(context("a { &:hov| { } }"), Selector);
+    assert_eq!(context("a { em|: x { } }"), Selector);
+    assert_eq!(context("a { b:ho|ver; }"), PropertyValue("b".into()));
+    assert_eq!(context("a { b:ho|ver { } }"), Selector);
+    assert_eq!(context("a { b:ho|ver"), PropertyValue("b".into()));
+    assert_eq!(context("a { @media print { |"), PropertyName);
+    assert_eq!(context("@font-face { |"), PropertyName);
+    assert_eq!(context("@font-face { src: |"), PropertyValue("src".into()));
+    assert_eq!(context("@keyframes x { fr|"), Selector);
+    assert_eq!(context("@keyframes x { from { |"), PropertyName);
+    assert_eq!(context("@page :first { @top-left { |"), PropertyName);
+    assert_eq!(context("@unknown { |"), Unknown);
+    assert_eq!(context("a { /* |"), Comment);
+    assert_eq!(context("a { /* b */|"), PropertyName);
+    assert_eq!(context("a { /*|*/ }"), Comment);
+}
+
+#[test]
+fn outline() {
+    let css = "@import 'a.css';\n\
+               a, b { color: red; --x: { c {} }; &:hover { d: e } }\n\
+               @media print {\n  f { }\n  @page { @top-left { g: h } }\n}\n\
+               @unknown { i { } }\n\
+               j";
+    let symbols = document_symbols(css)
+        .into_iter()
+        .map(|symbol| {
+            (
+                symbol.at_keyword.map(|name| name.to_string()),
+                symbol.prelude,
+                symbol.depth,
+                symbol.start.to_string(),
+                symbol.end.to_string(),
+                symbol.prelude_location.to_string(),
+            )
+        })
+        .collect::<Vec<_>>();
+    let at = |name: &str| Some(name.to_owned());
+    assert_eq!(
+        symbols,
+        vec![
+            (
+                at("import"),
+                "'a.css'",
+                0,
+                "1:1".into(),
+                "1:17".into(),
+                "1:9".into()
+            ),
+            (None, "a, b", 0, "2:1".into(), "2:53".into(), "2:1".into()),
+            (
+                None,
+                "&:hover",
+                1,
+                "2:35".into(),
+                "2:51".into(),
+                "2:35".into()
+            ),
+            (
+                at("media"),
+                "print",
+                0,
+                "3:1".into(),
+                "6:2".into(),
+                "3:8".into()
+            ),
+            (None, "f", 1, "4:3".into(), "4:8".into(), "4:3".into()),
+            (at("page"), "", 1, "5:3".into(), "5:31".into(), "5:9".into()),
+            (
+                at("top-left"),
+                "",
+                2,
+                "5:11".into(),
+                "5:29".into(),
+                "5:21".into()
+            ),
+            (
+                at("unknown"),
+                "",
+                0,
+                "7:1".into(),
+                "7:19".into(),
+                "7:10".into()
+            ),
+        ]
+    );
 }
 
-fn one_component_value_to_json(token: Token, input: &mut Parser) -> Value {
-    fn numeric(value: f32, int_value: Option<i32>, has_sign: bool) -> Vec<Value> {
+#[test]
+fn folding() {
+    let css = "/* a\n * b */\na {\n  b: c;\n}\n@media print {\n  d { e: f }\n  g {\n    h: \
+               i(\n      j\n    );\n  }\n}\n/* k */ l {";
+    let block = FoldingRangeKind::Block;
+    let ranges = folding_ranges(css)
+        .into_iter()
+        .map(|range| (range.kind, range.start_line, range.end_line))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        ranges,
         vec![
-            Token::Number {
-                value: value,
-                int_value: int_value,
-                has_sign: has_sign,
-            }
-            .to_css_string()
-            .to_json(),
-            match int_value {
-                Some(i) => i.to_json(),
-                None => value.to_json(),
+            (FoldingRangeKind::Comment, 0, 1),
+            (block, 2, 4),
+            (block, 5, 12),
+            (block, 7, 11),
+        ]
+    );
+    assert_eq!(
+        folding_ranges("a {\n b {\n c: d"),
+        vec![
+            FoldingRange {
+                kind: block,
+                start_line: 0,
+                end_line: 2
+            },
+            FoldingRange {
+                kind: block,
+                start_line: 1,
+                end_line: 2
             },
-            match int_value {
-                Some(_) => "integer",
-                None => "number",
-            }
-            .to_json(),
         ]
-    }
+    );
+}
 
-    fn nested(input: &mut Parser) -> Vec<Value> {
-        let result: Result<_, ParseError<()>> =
-            input.parse_nested_block(|input| Ok(component_values_to_json(input)));
-        result.unwrap()
+#[test]
+fn rendered_errors() {
+    let css = "a {\n\tcolor: red;\n\tb: 'é' }}\n";
+    let error = ParseError::<()> {
+        kind: ParseErrorKind::Basic(BasicParseErrorKind::UnexpectedToken(
+            Token::CloseCurlyBracket,
+        )),
+        location: SourceLocation {
+            line: 2,
+            column: 10,
+        },
+    };
+    assert_eq!(
+        error.render(css, Some("style.css"), "rule"),
+        "error: invalid rule\n \
+         --> style.css:3:10\n  \
+         |\n\
+         3 | \tb: 'é' }}\n  \
+         | \t        ^ unexpected ‘}’\n"
+    );
+    let error = ParseError::<()> {
+        kind: ParseErrorKind::Basic(BasicParseErrorKind::UnexpectedToken(Token::QuotedString(
+            "é".into(),
+        ))),
+        location: SourceLocation { line: 2, column: 5 },
+    };
+    assert_eq!(
+        error.render(css, None, "declaration"),
+        "error: invalid declaration\n \
+         --> 3:5\n  \
+         |\n\
+         3 | \tb: 'é' }}\n  \
+         | \t   ^^^ unexpected ‘\"é\"’\n"
+    );
+    let mut rendered = String::new();
+    let location = SourceLocation { line: 9, column: 1 };
+    render_error(css, None, location, "unclosed block", "", &mut rendered).unwrap();
+    assert_eq!(rendered, "error: unclosed block\n  --> 10:1\n");
+    let mut rendered = String::new();
+    let location = SourceLocation { line: 0, column: 4 };
+    render_error("a {", None, location, "unclosed block", "", &mut rendered).unwrap();
+    assert_eq!(
+        rendered,
+        "error: unclosed block\n --> 1:4\n  |\n1 | a {\n  |    ^\n"
+    );
+}
+
+#[test]
+fn ident_start_and_valid_escape() {
+    for &(input, expected) in &[
+        ("a", true),
+        ("_", true),
+        ("é", true),
+        ("\0", true),
+        ("-a", true),
+        ("--", true),
+        ("-\\a", true),
+        ("\\", true),
+        ("", false),
+        ("-", false),
+        ("-1", false),
+        ("1", false),
+        ("-\\\n", false),
+        ("\\\n", false),
+        (".a", false),
+    ] {
+        assert_eq!(is_ident_start(input), expected, "{:?}", input);
     }
+    assert!(starts_with_valid_escape("\\a"));
+    assert!(starts_with_valid_escape("\\"));
+    assert!(!starts_with_valid_escape("\\\r\n"));
+    assert!(!starts_with_valid_escape("a\\"));
+    assert!(!starts_with_valid_escape(""));
+}
 
-    match token {
-        Token::Ident(value) => JArray!["ident", value],
-        Token::AtKeyword(value) => JArray!["at-keyword", value],
-        Token::Hash(value) => JArray!["hash", value, "unrestricted"],
-        Token::IDHash(value) => JArray!["hash", value, "id"],
-        Token::QuotedString(value) => JArray!["string", value],
-        Token::UnquotedUrl(value) => JArray!["url", value],
-        Token::Delim('\\') => "\\".to_json(),
-        Token::Delim(value) => value.to_string().to_json(),
+#[cfg(feature = "values")]
+#[test]
+fn custom_idents() {
+    fn parse(css: &str, excluded: &[&str]) -> Result<String, String> {
+        let mut input = ParserInput::new(css);
+        let mut input = Parser::new(&mut input);
+        parse_custom_ident(&mut input, excluded)
+            .map(|ident| ident.to_string())
+            .map_err(|error| error.to_string())
+    }
+    assert_eq!(parse("Slide-In", &[]), Ok("Slide-In".into()));
+    assert_eq!(parse("\\66oo", &[]), Ok("foo".into()));
+    assert_eq!(parse("none", &[]), Ok("none".into()));
+    assert_eq!(
+        parse("NONE", &["none"]),
+        Err("unexpected ‘NONE’ at 1:1".into())
+    );
+    assert_eq!(
+        parse("Inherit", &[]),
+        Err("unexpected ‘Inherit’ at 1:1".into())
+    );
+    assert_eq!(
+        parse("revert-layer", &[]),
+        Err("unexpected ‘revert-layer’ at 1:1".into())
+    );
+    assert_eq!(
+        parse("default", &[]),
+        Err("unexpected ‘default’ at 1:1".into())
+    );
+    assert_eq!(parse("'a'", &[]), Err("unexpected ‘\"a\"’ at 1:1".into()));
+}
 
-        Token::Number {
-            value,
-            int_value,
-            has_sign,
-        } => Value::Array({
-            let mut v = vec!["number".to_json()];
-            v.extend(numeric(value, int_value, has_sign));
-            v
-        }),
-        Token::Percentage {
-            unit_value,
-            int_value,
-            has_sign,
-        } => Value::Array({
-            let mut v = vec!["percentage".to_json()];
-            v.extend(numeric(unit_value * 100., int_value, has_sign));
-            v
-        }),
-        Token::Dimension {
-            value,
-            int_value,
-            has_sign,
-            unit,
-        } => Value::Array({
-            let mut v = vec!["dimension".to_json()];
-            v.extend(numeric(value, int_value, has_sign));
-            v.push(unit.to_json());
-            v
-        }),
+#[cfg(feature = "values")]
+#[test]
+fn dashed_idents() {
+    fn parse(css: &str) -> Result<String, String> {
+        let mut input = ParserInput::new(css);
+        let mut input = Parser::new(&mut input);
+        let result: Result<_, ParseError<()>> = DashedIdent::from_css(&mut input);
+        result
+            .map(|ident| ident.to_css_string())
+            .map_err(|error| error.basic().to_string())
+    }
+    assert_eq!(parse("--Brand"), Ok("--Brand".into()));
+    assert_eq!(parse("--0"), Ok("--0".into()));
+    assert_eq!(parse("\\2d-a\\ b"), Ok("--a\\ b".into()));
+    assert_eq!(parse("--"), Err("unexpected ‘--’ at 1:1".into()));
+    assert_eq!(parse("-a"), Err("unexpected ‘-a’ at 1:1".into()));
+    assert_eq!(parse("a"), Err("unexpected ‘a’ at 1:1".into()));
 
-        Token::WhiteSpace(_) => " ".to_json(),
-        Token::Comment(_) => "/**/".to_json(),
-        Token::Colon => ":".to_json(),
-        Token::Semicolon => ";".to_json(),
-        Token::Comma => ",".to_json(),
-        Token::IncludeMatch => "~=".to_json(),
-        Token::DashMatch => "|=".to_json(),
-        Token::PrefixMatch => "^=".to_json(),
-        Token::SuffixMatch => "$=".to_json(),
-        Token::SubstringMatch => "*=".to_json(),
-        Token::CDO => "<!--".to_json(),
-        Token::CDC => "-->".to_json(),
+    let mut input = ParserInput::new("--x");
+    let mut input = Parser::new(&mut input);
+    assert_eq!(parse_dashed_ident(&mut input), Ok("--x".into()));
+}
 
-        Token::Function(name) => Value::Array({
-            let mut v = vec!["function".to_json(), name.to_json()];
-            v.extend(nested(input));
-            v
-        }),
-        Token::ParenthesisBlock => Value::Array({
-            let mut v = vec!["()".to_json()];
-            v.extend(nested(input));
-            v
-        }),
-        Token::SquareBracketBlock => Value::Array({
-            let mut v = vec!["[]".to_json()];
-            v.extend(nested(input));
-            v
-        }),
-        Token::CurlyBracketBlock => Value::Array({
-            let mut v = vec!["{}".to_json()];
-            v.extend(nested(input));
-            v
-        }),
-        Token::BadUrl(_) => JArray!["error", "bad-url"],
-        Token::BadString(_) => JArray!["error", "bad-string"],
-        Token::CloseParenthesis => JArray!["error", ")"],
-        Token::CloseSquareBracket => JArray!["error", "]"],
-        Token::CloseCurlyBracket => JArray!["error", "}"],
-    }
+#[test]
+fn string_or_ident_sequences() {
+    let mut input = ParserInput::new("Times  New/**/\n Roman, 'Times  New', serif, 12px");
+    let mut input = Parser::new(&mut input);
+    let families: Result<_, ParseError<()>> = input
+        .parse_comma_separated(|input| Ok(input.expect_string_or_ident_sequence()?.to_string()));
+    assert_eq!(
+        families.map_err(|error| error.basic().to_string()),
+        Err("unexpected ‘12px’ at 2:30".into())
+    );
+    let mut input = ParserInput::new("Times  New/**/\n Roman, 'Times  New', serif");
+    let mut input = Parser::new(&mut input);
+    let families: Result<_, ParseError<()>> = input
+        .parse_comma_separated(|input| Ok(input.expect_string_or_ident_sequence()?.to_string()));
+    assert_eq!(
+        families,
+        Ok(vec![
+            "Times New Roman".into(),
+            "Times  New".into(),
+            "serif".into()
+        ])
+    );
 }
 
-/// A previous version of procedural-masquerade had a bug where it
-/// would normalize consecutive whitespace to a single space,
-/// including in string literals.
+#[cfg(feature = "values")]
 #[test]
-fn procedural_masquerade_whitespace() {
-    ascii_case_insensitive_phf_map! {
-        map -> () = {
-            "  \t\n" => ()
-        }
+fn positions() {
+    fn parse(css: &str, bg_position: bool) -> Result<String, String> {
+        let mut input = ParserInput::new(css);
+        let mut input = Parser::new(&mut input);
+        let result: Result<_, ParseError<()>> = input.parse_entirely(|input| {
+            Ok(if bg_position {
+                Position::parse_bg_position(input)?
+            } else {
+                Position::parse(input)?
+            })
+        });
+        result
+            .map(|position| position.to_css_string())
+            .map_err(|error| error.basic().to_string())
     }
-    assert_eq!(map("  \t\n"), Some(&()));
-    assert_eq!(map(" "), None);
-
-    match_ignore_ascii_case! { "  \t\n",
-        " " => panic!("1"),
-        "  \t\n" => {},
-        _ => panic!("2"),
+    for &(css, expected) in &[
+        ("left", "left center"),
+        ("TOP", "center top"),
+        ("center", "center center"),
+        ("10%", "10% center"),
+        ("0", "0px center"),
+        ("top left", "left top"),
+        ("center bottom", "center bottom"),
+        ("bottom center", "center bottom"),
+        ("left 10px", "left 10px"),
+        ("10px bottom", "10px bottom"),
+        ("1em 50%", "1em 50%"),
+        ("right 10px bottom 20%", "right 10px bottom 20%"),
+        ("bottom 20% right 10px", "right 10px bottom 20%"),
+    ] {
+        assert_eq!(parse(css, false), Ok(expected.into()), "{:?}", css);
+        assert_eq!(parse(css, true), Ok(expected.into()), "{:?}", css);
     }
-
-    match_ignore_ascii_case! { " ",
-        "  \t\n" => panic!("3"),
-        " " => {},
-        _ => panic!("4"),
+    for &(css, expected) in &[
+        ("left 10px top", "left 10px top"),
+        ("top left 10px", "left 10px top"),
+        ("center top 10px", "center top 10px"),
+    ] {
+        assert_eq!(parse(css, true), Ok(expected.into()), "{:?}", css);
+        assert!(parse(css, false).is_err(), "{:?}", css);
+    }
+    for &(css, error) in &[
+        ("left right", "unexpected ‘right’ at 1:5"),
+        ("top 10px", "unexpected ‘10px’ at 1:4"),
+        ("center 10px top 5px", "unexpected ‘top’ at 1:12"),
+        ("10px left", "unexpected ‘left’ at 1:5"),
+        ("middle", "unexpected ‘middle’ at 1:1"),
+        ("left top left", "unexpected ‘left’ at 1:9"),
+    ] {
+        assert_eq!(parse(css, true), Err(error.into()), "{:?}", css);
     }
 }
 
+#[cfg(feature = "values")]
 #[test]
-fn parse_until_before_stops_at_delimiter_or_end_of_input() {
-    // For all j and k, inputs[i].1[j] should parse the same as inputs[i].1[k]
-    // when we use delimiters inputs[i].0.
-    let inputs = vec![
+fn basic_shapes() {
+    fn parse(css: &str) -> Result<String, String> {
+        let mut input = ParserInput::new(css);
+        let mut input = Parser::new(&mut input);
+        let result: Result<_, ParseError<()>> = input.parse_entirely(|input| {
+            Ok(BasicShape::parse_with_path_validator(input, |path| {
+                path.starts_with('M')
+            })?)
+        });
+        result
+            .map(|shape| shape.to_css_string())
+            .map_err(|error| error.basic().to_string())
+    }
+    for &(css, expected) in &[
+        ("inset(10px)", "inset(10px)"),
         (
-            Delimiter::Bang | Delimiter::Semicolon,
-            // Note that the ';extra' is fine, because the ';' acts the same as
-            // the end of input.
-            vec!["token stream;extra", "token stream!", "token stream"],
+            "INSET(1px 2% 3em 0 round 5px / 1px 2px)",
+            "inset(1px 2% 3em 0px round 5px / 1px 2px)",
         ),
-        (Delimiter::Bang | Delimiter::Semicolon, vec![";", "!", ""]),
-    ];
-    for equivalent in inputs {
-        for (j, x) in equivalent.1.iter().enumerate() {
-            for y in equivalent.1[j + 1..].iter() {
-                let mut ix = ParserInput::new(x);
-                let mut ix = Parser::new(&mut ix);
-
-                let mut iy = ParserInput::new(y);
-                let mut iy = Parser::new(&mut iy);
-
-                let _ = ix.parse_until_before::<_, _, ()>(equivalent.0, |ix| {
-                    iy.parse_until_before::<_, _, ()>(equivalent.0, |iy| {
-                        loop {
-                            let ox = ix.next();
-                            let oy = iy.next();
-                            assert_eq!(ox, oy);
-                            if let Err(_) = ox {
-                                break;
-                            }
-                        }
-                        Ok(())
-                    })
-                });
-            }
-        }
+        (
+            "inset(-1px round 0 1px 2px 3px)",
+            "inset(-1px round 0px 1px 2px 3px)",
+        ),
+        ("circle()", "circle()"),
+        ("circle(50%)", "circle(50%)"),
+        (
+            "circle(farthest-side at left top)",
+            "circle(farthest-side at left top)",
+        ),
+        ("circle(at 10px)", "circle(at 10px center)"),
+        (
+            "ellipse(1px closest-side at center)",
+            "ellipse(1px closest-side at center center)",
+        ),
+        ("ellipse()", "ellipse()"),
+        (
+            "polygon(0 0, 100% 0, 50% 100%)",
+            "polygon(0px 0px, 100% 0px, 50% 100%)",
+        ),
+        ("polygon(evenodd, 0 0)", "polygon(evenodd, 0px 0px)"),
+        ("path('M 0 0 L 1 1')", "path(\"M 0 0 L 1 1\")"),
+        ("path(nonzero, \"M0 0\")", "path(nonzero, \"M0 0\")"),
+    ] {
+        assert_eq!(parse(css), Ok(expected.into()), "{:?}", css);
+    }
+    for &(css, error) in &[
+        ("square(1px)", "unexpected ‘square(’ at 1:1"),
+        ("circle(-1px)", "unexpected ‘-1px’ at 1:8"),
+        ("circle(1px 2px)", "unexpected ‘2px’ at 1:11"),
+        ("ellipse(1px)", "unexpected ‘1px’ at 1:9"),
+        ("inset(1px round -1px)", "unexpected ‘-1px’ at 1:16"),
+        ("inset(1px 2px 3px 4px 5px)", "unexpected ‘5px’ at 1:22"),
+        ("polygon()", "unexpected end of input at 1:9"),
+        ("polygon(evenodd)", "unexpected ‘evenodd’ at 1:9"),
+        ("path('0 0')", "unexpected ‘\"0 0\"’ at 1:6"),
+    ] {
+        assert_eq!(parse(css), Err(error.into()), "{:?}", css);
     }
 }
 
+#[cfg(feature = "values")]
 #[test]
-fn parser_maintains_current_line() {
-    let mut input = ParserInput::new("ident ident;\nident ident ident;\nident");
-    let mut parser = Parser::new(&mut input);
-    assert_eq!(parser.current_line(), "ident ident;");
-    assert_eq!(parser.next(), Ok(&Token::Ident("ident".into())));
-    assert_eq!(parser.next(), Ok(&Token::Ident("ident".into())));
-    assert_eq!(parser.next(), Ok(&Token::Semicolon));
-
-    assert_eq!(parser.next(), Ok(&Token::Ident("ident".into())));
-    assert_eq!(parser.current_line(), "ident ident ident;");
-    assert_eq!(parser.next(), Ok(&Token::Ident("ident".into())));
-    assert_eq!(parser.next(), Ok(&Token::Ident("ident".into())));
-    assert_eq!(parser.next(), Ok(&Token::Semicolon));
-
-    assert_eq!(parser.next(), Ok(&Token::Ident("ident".into())));
-    assert_eq!(parser.current_line(), "ident");
+fn grid_track_list_items() {
+    fn parse(css: &str) -> Result<Vec<GridTrackListItem<'_>>, String> {
+        let mut input = ParserInput::new(css);
+        let mut input = Parser::new(&mut input);
+        parse_grid_track_list_items(&mut input).map_err(|error| error.to_string())
+    }
+    fn names(names: &[&'static str]) -> GridTrackListItem<'static> {
+        GridTrackListItem::LineNames(names.iter().map(|&name| name.into()).collect())
+    }
+    assert_eq!(
+        parse(" [Main-start  a] 1fr\n minmax(10px, auto) [ b ] repeat(2, [c] 1fr) "),
+        Ok(vec![
+            names(&["Main-start", "a"]),
+            GridTrackListItem::Tracks("1fr\n minmax(10px, auto)"),
+            names(&["b"]),
+            GridTrackListItem::Tracks("repeat(2, [c] 1fr)"),
+        ])
+    );
+    assert_eq!(
+        parse("[a] [] [b]"),
+        Ok(vec![names(&["a"]), names(&[]), names(&["b"])])
+    );
+    assert_eq!(parse("none"), Ok(vec![GridTrackListItem::Tracks("none")]));
+    assert_eq!(parse(""), Ok(vec![]));
+    assert_eq!(parse("[a span]"), Err("unexpected ‘span’ at 1:3".into()));
+    assert_eq!(parse("1fr [a 1px]"), Err("unexpected ‘1px’ at 1:7".into()));
 }
 
+#[cfg(feature = "values")]
 #[test]
-fn parser_with_line_number_offset() {
-    let mut input = ParserInput::new_with_line_number_offset("ident\nident", 72);
-    let mut parser = Parser::new(&mut input);
+fn easing_functions() {
+    fn parse(css: &str) -> Result<EasingFunction, String> {
+        let mut input = ParserInput::new(css);
+        let mut input = Parser::new(&mut input);
+        let result: Result<_, ParseError<()>> =
+            input.parse_entirely(|input| Ok(EasingFunction::parse(input)?));
+        result.map_err(|error| error.basic().to_string())
+    }
+    fn serialize(css: &str) -> Result<String, String> {
+        parse(css).map(|easing| easing.to_css_string())
+    }
+    assert_eq!(parse("Ease-In-Out"), Ok(EasingFunction::EaseInOut));
+    assert_eq!(parse("step-start"), Ok(EasingFunction::StepStart));
     assert_eq!(
-        parser.current_source_location(),
-        SourceLocation {
-            line: 72,
-            column: 1
-        }
+        parse("cubic-bezier(0.1, -2, 1, 3.5)"),
+        Ok(EasingFunction::CubicBezier {
+            x1: 0.1,
+            y1: -2.,
+            x2: 1.,
+            y2: 3.5
+        })
     );
+    assert_eq!(parse("steps(3)"), Ok(EasingFunction::Steps(3, None)));
     assert_eq!(
-        parser.next_including_whitespace_and_comments(),
-        Ok(&Token::Ident("ident".into()))
+        parse("STEPS(2, jump-none)"),
+        Ok(EasingFunction::Steps(2, Some(StepPosition::JumpNone)))
     );
     assert_eq!(
-        parser.current_source_location(),
-        SourceLocation {
-            line: 72,
-            column: 6
-        }
+        parse("linear(0, 0.5 25% 75%, 100% 1)"),
+        Ok(EasingFunction::LinearStops(vec![
+            LinearStop {
+                output: 0.,
+                input: (None, None)
+            },
+            LinearStop {
+                output: 0.5,
+                input: (Some(0.25), Some(0.75))
+            },
+            LinearStop {
+                output: 1.,
+                input: (Some(1.), None)
+            },
+        ]))
     );
+    assert_eq!(serialize("linear"), Ok("linear".into()));
     assert_eq!(
-        parser.next_including_whitespace_and_comments(),
-        Ok(&Token::WhiteSpace("\n".into()))
+        serialize("linear(0,0.5 25% 75%,1)"),
+        Ok("linear(0, 0.5 25% 75%, 1)".into())
     );
     assert_eq!(
-        parser.current_source_location(),
-        SourceLocation {
-            line: 73,
-            column: 1
-        }
+        serialize("cubic-bezier(0, 0, .58, 1.0)"),
+        Ok("cubic-bezier(0, 0, 0.58, 1)".into())
     );
+    assert_eq!(serialize("steps(4,start)"), Ok("steps(4, start)".into()));
     assert_eq!(
-        parser.next_including_whitespace_and_comments(),
-        Ok(&Token::Ident("ident".into()))
+        parse("cubic-bezier(0, 0, 1.5, 1)"),
+        Err("unexpected ‘1.5’ at 1:20".into())
     );
+    assert_eq!(parse("steps(0)"), Err("unexpected ‘0’ at 1:7".into()));
     assert_eq!(
-        parser.current_source_location(),
-        SourceLocation {
-            line: 73,
-            column: 6
-        }
+        parse("steps(1, jump-none)"),
+        Err("unexpected ‘1’ at 1:7".into())
     );
-}
-
-#[test]
-fn cdc_regression_test() {
-    let mut input = ParserInput::new("-->x");
-    let mut parser = Parser::new(&mut input);
-    parser.skip_cdc_and_cdo();
-    assert_eq!(parser.next(), Ok(&Token::Ident("x".into())));
+    assert_eq!(parse("steps(1.5)"), Err("unexpected ‘1.5’ at 1:7".into()));
     assert_eq!(
-        parser.next(),
-        Err(BasicParseError {
-            kind: BasicParseErrorKind::EndOfInput,
-            location: SourceLocation { line: 0, column: 5 }
-        })
+        parse("linear(1)"),
+        Err("unexpected ‘linear(’ at 1:1".into())
+    );
+    assert_eq!(
+        parse("linear(0, 25% 0.5 75%, 1)"),
+        Err("unexpected ‘75%’ at 1:18".into())
+    );
+    assert_eq!(
+        parse("linear(0 10% 20% 30%, 1)"),
+        Err("unexpected ‘30%’ at 1:17".into())
+    );
+    assert_eq!(
+        parse("ease-in-out(1)"),
+        Err("unexpected ‘ease-in-out(’ at 1:1".into())
     );
 }
 
+#[cfg(feature = "values")]
 #[test]
-fn parse_entirely_reports_first_error() {
-    #[derive(PartialEq, Debug)]
-    enum E {
-        Foo,
+fn custom_ident_lists() {
+    fn parse(css: &str, excluded: &[&str]) -> Result<Vec<String>, String> {
+        let mut input = ParserInput::new(css);
+        let mut input = Parser::new(&mut input);
+        let result: Result<_, ParseError<()>> =
+            input.parse_entirely(|input| Ok(parse_custom_ident_list(input, excluded)?));
+        result
+            .map(|idents| idents.iter().map(|ident| ident.to_string()).collect())
+            .map_err(|error| error.basic().to_string())
     }
-    let mut input = ParserInput::new("ident");
-    let mut parser = Parser::new(&mut input);
-    let result: Result<(), _> = parser.parse_entirely(|p| Err(p.new_custom_error(E::Foo)));
+    let will_change = &[
+        "will-change",
+        "none",
+        "all",
+        "auto",
+        "scroll-position",
+        "contents",
+    ];
     assert_eq!(
-        result,
-        Err(ParseError {
-            kind: ParseErrorKind::Custom(E::Foo),
-            location: SourceLocation { line: 0, column: 1 },
-        })
+        parse(" transform ,Opacity", will_change),
+        Ok(vec!["transform".into(), "Opacity".into()])
+    );
+    assert_eq!(parse("slide", &["none"]), Ok(vec!["slide".into()]));
+    assert_eq!(
+        parse("transform, Contents", will_change),
+        Err("unexpected ‘Contents’ at 1:12".into())
+    );
+    assert_eq!(
+        parse("a,\n  none", &["none"]),
+        Err("unexpected ‘none’ at 2:3".into())
+    );
+    assert_eq!(
+        parse("a, inherit", &[]),
+        Err("unexpected ‘inherit’ at 1:4".into())
+    );
+    assert_eq!(
+        parse("a,, b", &[]),
+        Err("unexpected end of input at 1:3".into())
     );
+    assert_eq!(parse("a b", &[]), Err("unexpected ‘b’ at 1:2".into()));
+    assert_eq!(parse("", &[]), Err("unexpected end of input at 1:1".into()));
 }
 
 #[test]
-fn parse_sourcemapping_comments() {
-    let tests = vec![
-        ("/*# sourceMappingURL=here*/", Some("here")),
-        ("/*# sourceMappingURL=here  */", Some("here")),
-        ("/*@ sourceMappingURL=here*/", Some("here")),
-        (
-            "/*@ sourceMappingURL=there*/ /*# sourceMappingURL=here*/",
-            Some("here"),
-        ),
-        ("/*# sourceMappingURL=here there  */", Some("here")),
-        ("/*# sourceMappingURL=  here  */", Some("")),
-        ("/*# sourceMappingURL=*/", Some("")),
-        ("/*# sourceMappingUR=here  */", None),
-        ("/*! sourceMappingURL=here  */", None),
-        ("/*# sourceMappingURL = here  */", None),
-        ("/*   # sourceMappingURL=here   */", None),
-    ];
-
-    for test in tests {
-        let mut input = ParserInput::new(test.0);
-        let mut parser = Parser::new(&mut input);
-        while let Ok(_) = parser.next_including_whitespace() {}
-        assert_eq!(parser.current_source_map_url(), test.1);
+fn token_lists() {
+    let css = ".a > .b, #c ~ d + e:hover";
+    let mut input = ParserInput::new(css);
+    let mut input = Parser::new(&mut input);
+    let mut tokens = Vec::new();
+    while let Ok(token) = input.next_including_whitespace_and_comments() {
+        tokens.push(token.clone())
     }
-}
-
-#[test]
-fn parse_sourceurl_comments() {
-    let tests = vec![
-        ("/*# sourceURL=here*/", Some("here")),
-        ("/*# sourceURL=here  */", Some("here")),
-        ("/*@ sourceURL=here*/", Some("here")),
-        ("/*@ sourceURL=there*/ /*# sourceURL=here*/", Some("here")),
-        ("/*# sourceURL=here there  */", Some("here")),
-        ("/*# sourceURL=  here  */", Some("")),
-        ("/*# sourceURL=*/", Some("")),
-        ("/*# sourceMappingUR=here  */", None),
-        ("/*! sourceURL=here  */", None),
-        ("/*# sourceURL = here  */", None),
-        ("/*   # sourceURL=here   */", None),
-    ];
-
-    for test in tests {
-        let mut input = ParserInput::new(test.0);
-        let mut parser = Parser::new(&mut input);
-        while let Ok(_) = parser.next_including_whitespace() {}
-        assert_eq!(parser.current_source_url(), test.1);
+    assert_eq!(tokens.len(), 20);
+    let mut list = tokens.iter().cloned().collect::<TokenList>();
+    list.shrink_to_fit();
+    assert_eq!(list.len(), 20);
+    assert_eq!(list.iter().collect::<Vec<_>>(), tokens);
+    assert_eq!(list.get(13), Some(Token::Ident("d".into())));
+    assert_eq!(list.get(11), Some(Token::Delim('~')));
+    assert_eq!(list.get(20), None);
+    if std::mem::size_of::<usize>() == 8 {
+        assert_eq!(tokens.len() * std::mem::size_of::<Token>(), 640);
+        assert_eq!(list.heap_size(), 352);
     }
+
+    let mut list = TokenList::new();
+    assert!(list.is_empty());
+    list.push(Token::WhiteSpace("\n    "));
+    list.push(Token::WhiteSpace("\t"));
+    list.push(Token::Delim('*'));
+    list.push(Token::CloseCurlyBracket);
+    assert_eq!(
+        list.iter().collect::<Vec<_>>(),
+        vec![
+            Token::WhiteSpace("\n    "),
+            Token::WhiteSpace("\t"),
+            Token::Delim('*'),
+            Token::CloseCurlyBracket,
+        ]
+    );
 }
 
 #[test]
-fn roundtrip_percentage_token() {
-    fn test_roundtrip(value: &str) {
-        let mut input = ParserInput::new(value);
-        let mut parser = Parser::new(&mut input);
-        let token = parser.next().unwrap();
-        assert_eq!(token.to_css_string(), value);
-    }
-    // Test simple number serialization
-    for i in 0..101 {
-        test_roundtrip(&format!("{}%", i));
-        for j in 0..10 {
-            if j != 0 {
-                test_roundtrip(&format!("{}.{}%", i, j));
+fn line_index() {
+    let css = "a\t{ b: \"\u{e9}\u{1F600}\" }\r\n\x0C\rc\n\t\td: e;\r";
+    for &unit in &[
+        ColumnUnit::Utf8Bytes,
+        ColumnUnit::Utf16CodeUnits,
+        ColumnUnit::CodePoints,
+    ] {
+        for &tab_width in &[0, 4] {
+            let mut index = LineIndex::new(css);
+            index.set_column_unit(unit);
+            index.set_tab_width(tab_width);
+            let mut input = ParserInput::new(css);
+            input.set_column_unit(unit);
+            input.set_tab_width(tab_width);
+            let mut input = Parser::new(&mut input);
+            loop {
+                let position = input.position();
+                let location = input.current_source_location();
+                assert_eq!(index.location(position), location);
+                if input.next_including_whitespace_and_comments().is_err() {
+                    break;
+                }
             }
-            for k in 1..10 {
-                test_roundtrip(&format!("{}.{}{}%", i, j, k));
+        }
+    }
+    // Longer than the interval between checkpoints.
+    let long_css = "a { b: \"\u{e9}\u{1F600}\" }".repeat(100) + "\n" + &"c\u{e9} ".repeat(200);
+    for &unit in &[ColumnUnit::Utf16CodeUnits, ColumnUnit::CodePoints] {
+        let mut index = LineIndex::new(&long_css);
+        index.set_column_unit(unit);
+        let mut input = ParserInput::new(&long_css);
+        input.set_column_unit(unit);
+        let mut input = Parser::new(&mut input);
+        loop {
+            let position = input.position();
+            let location = input.current_source_location();
+            assert_eq!(index.location(position), location);
+            if input.next_including_whitespace_and_comments().is_err() {
+                break;
             }
         }
     }
+
+    let index = LineIndex::new(css);
+    assert_eq!(index.line_count(), 6);
+    assert_eq!(index.line_start(2).map(|p| p.byte_index()), Some(20));
+    assert_eq!(index.line_start(6), None);
+    assert_eq!(
+        index.location_of_byte_index(css.len()),
+        SourceLocation { line: 5, column: 1 }
+    );
 }
 
 #[test]
-fn utf16_columns() {
-    // This particular test serves two purposes.  First, it checks
-    // that the column number computations are correct.  Second, it
-    // checks that tokenizer code paths correctly differentiate
-    // between the different UTF-8 encoding bytes.  In particular
-    // different leader bytes and continuation bytes are treated
-    // differently, so we make sure to include all lengths in the
-    // tests, using the string "QΡ✈🆒".  Also, remember that because
-    // the column is in units of UTF-16, the 4-byte sequence results
-    // in two columns.
-    let tests = vec![
-        ("", 1),
-        ("ascii", 6),
-        ("/*QΡ✈🆒*/", 10),
-        ("'QΡ✈🆒*'", 9),
-        ("\"\\\"'QΡ✈🆒*'", 12),
-        ("\\Q\\Ρ\\✈\\🆒", 10),
-        ("QΡ✈🆒", 6),
-        ("QΡ✈🆒\\Q\\Ρ\\✈\\🆒", 15),
-        ("newline\r\nQΡ✈🆒", 6),
-        ("url(QΡ✈🆒\\Q\\Ρ\\✈\\🆒)", 20),
-        ("url(QΡ✈🆒)", 11),
-        ("url(\r\nQΡ✈🆒\\Q\\Ρ\\✈\\🆒)", 16),
-        ("url(\r\nQΡ✈🆒\\Q\\Ρ\\✈\\🆒", 15),
-        ("url(\r\nQΡ✈🆒\\Q\\Ρ\\✈\\🆒 x", 17),
-        ("QΡ✈🆒()", 8),
-        // Test that under/over-flow of current_line_start_position is
-        // handled properly; see the special case in consume_4byte_intro.
-        ("🆒", 3),
-    ];
-
-    for test in tests {
-        let mut input = ParserInput::new(test.0);
+fn one_rule_and_declaration_with_errors() {
+    type Errors<'a> = Vec<(String, &'a str)>;
+    fn parse_rule(css: &str) -> (Option<(&str, &str)>, Errors<'_>) {
+        let mut input = ParserInput::new(css);
         let mut parser = Parser::new(&mut input);
+        let (rule, errors) = parse_one_rule_with_errors(&mut parser, &mut SourceTextParser);
+        let errors = errors
+            .into_iter()
+            .map(|(error, slice)| (error.location.to_string(), slice))
+            .collect();
+        (rule, errors)
+    }
+    assert_eq!(parse_rule(" a {} "), (Some(("", "a")), vec![]));
+    assert_eq!(
+        parse_rule(" a {} b; c"),
+        (Some(("", "a")), vec![("1:7".into(), "b; c")])
+    );
+    assert_eq!(
+        parse_rule("a b {} c"),
+        (None, vec![("1:2".into(), "a b {"), ("1:8".into(), "c")])
+    );
 
-        // Read all tokens.
-        loop {
-            match parser.next() {
-                Err(BasicParseError {
-                    kind: BasicParseErrorKind::EndOfInput,
-                    ..
-                }) => {
-                    break;
-                }
-                Err(_) => {
-                    assert!(false);
-                }
-                Ok(_) => {}
-            };
+    struct IdentParser;
+    impl<'i> DeclarationParser<'i> for IdentParser {
+        type Declaration = (CowRcStr<'i>, CowRcStr<'i>);
+        type Error = ();
+        fn parse_value<'t>(
+            &mut self,
+            name: CowRcStr<'i>,
+            input: &mut Parser<'i, 't>,
+        ) -> Result<Self::Declaration, ParseError<'i, ()>> {
+            Ok((name, input.expect_ident_cloned()?))
         }
-
-        // Check the resulting column.
-        assert_eq!(parser.current_source_location().column, test.1);
     }
+    impl<'i> AtRuleParser<'i> for IdentParser {
+        type PreludeBlock = ();
+        type PreludeNoBlock = ();
+        type AtRule = (CowRcStr<'i>, CowRcStr<'i>);
+        type Error = ();
+    }
+    fn parse_declaration(css: &str) -> (Option<(String, String)>, Errors<'_>) {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        let (declaration, errors) =
+            parse_one_declaration_with_errors(&mut parser, &mut IdentParser);
+        let declaration = declaration.map(|(name, value)| (name.to_string(), value.to_string()));
+        let errors = errors
+            .into_iter()
+            .map(|(error, slice)| (error.location.to_string(), slice))
+            .collect();
+        (declaration, errors)
+    }
+    assert_eq!(
+        parse_declaration(" color: red "),
+        (Some(("color".into(), "red".into())), vec![])
+    );
+    assert_eq!(
+        parse_declaration("color: red blu"),
+        (
+            Some(("color".into(), "red".into())),
+            vec![("1:12".into(), "blu")]
+        )
+    );
+    assert_eq!(
+        parse_declaration("color: 1px; x"),
+        (
+            None,
+            vec![("1:7".into(), "color: 1px"), ("1:11".into(), "; x")]
+        )
+    );
+    assert_eq!(
+        parse_declaration("color"),
+        (None, vec![("1:6".into(), "color")])
+    );
 }
 
 #[test]
-fn servo_define_css_keyword_enum() {
-    macro_rules! define_css_keyword_enum {
-        (pub enum $name:ident { $($variant:ident = $css:expr,)+ }) => {
-            #[derive(PartialEq, Debug)]
-            pub enum $name {
-                $($variant),+
-            }
-
-            impl $name {
-                pub fn from_ident(ident: &str) -> Result<$name, ()> {
-                    match_ignore_ascii_case! { ident,
-                        $($css => Ok($name::$variant),)+
-                        _ => Err(())
-                    }
-                }
-            }
-        }
-    }
-    define_css_keyword_enum! {
-        pub enum UserZoom {
-            Zoom = "zoom",
-            Fixed = "fixed",
-        }
+fn declaration_value() {
+    fn parse(css: &str) -> Result<(String, bool), String> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        let result: Result<_, ParseError<()>> =
+            parse_declaration_value(&mut parser, |input| Ok(remaining_prelude(input)));
+        result.map_err(|error| error.basic().to_string())
     }
-
-    assert_eq!(UserZoom::from_ident("fixed"), Ok(UserZoom::Fixed));
+    assert_eq!(
+        parse(" 1px solid red "),
+        Ok(("1px solid red".into(), false))
+    );
+    assert_eq!(parse("red !IMPORTANT "), Ok(("red".into(), true)));
+    assert_eq!(parse("f(a; b) ! important"), Ok(("f(a; b)".into(), true)));
+    assert_eq!(
+        parse("red; color: blue"),
+        Err("unexpected ‘;’ at 1:4".into())
+    );
+    assert_eq!(
+        parse("red !important;"),
+        Err("unexpected ‘;’ at 1:15".into())
+    );
+    assert_eq!(parse("red !ie"), Err("unexpected ‘!’ at 1:5".into()));
 }
@@ -0,0 +1,161 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use crate::tokenizer::{Token, TokenKind};
+use std::iter::FromIterator;
+
+/// The whitespace tokens that are stored without a value in a `TokenList`.
+const COMMON_WHITESPACE: [&str; 4] = [" ", "\n", "\n  ", "\n    "];
+
+/// A list of tokens that uses less memory than a `Vec<Token>`
+/// when most of them have no value, such as a selector, which is mostly punctuation.
+///
+/// On 64-bit platforms a `Token` takes 32 bytes.
+/// In a `TokenList`, punctuation, delimiters and whitespace of a single space or newline
+/// (optionally followed by two or four spaces of indentation) take 8 bytes each,
+/// and other tokens take 40 bytes.
+/// For example, the 20 tokens of `.a > .b, #c ~ d + e:hover` take 352 bytes instead of 640.
+#[derive(Clone, Debug, Default)]
+pub struct TokenList<'i> {
+    entries: Vec<Entry>,
+    /// The tokens that can not be stored in `entries`.
+    values: Vec<Token<'i>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Entry {
+    /// A token without a value, other than `Delim` and `WhiteSpace`.
+    Unit(TokenKind),
+    Delim(char),
+    /// A whitespace token, by index in `COMMON_WHITESPACE`.
+    WhiteSpace(u8),
+    /// A token by index in `values`.
+    Value(u32),
+}
+
+impl<'i> TokenList<'i> {
+    /// Create an empty list.
+    pub fn new() -> Self {
+        TokenList {
+            entries: Vec::new(),
+            values: Vec::new(),
+        }
+    }
+
+    /// Append a token to the list.
+    pub fn push(&mut self, token: Token<'i>) {
+        let entry = match token {
+            Token::Delim(c) => Entry::Delim(c),
+            Token::WhiteSpace(whitespace) => {
+                match COMMON_WHITESPACE.iter().position(|&s| s == whitespace) {
+                    Some(index) => Entry::WhiteSpace(index as u8),
+                    None => self.push_value(token),
+                }
+            }
+            Token::Colon
+            | Token::Semicolon
+            | Token::Comma
+            | Token::IncludeMatch
+            | Token::DashMatch
+            | Token::PrefixMatch
+            | Token::SuffixMatch
+            | Token::SubstringMatch
+            | Token::CDO
+            | Token::CDC
+            | Token::ParenthesisBlock
+            | Token::SquareBracketBlock
+            | Token::CurlyBracketBlock
+            | Token::CloseParenthesis
+            | Token::CloseSquareBracket
+            | Token::CloseCurlyBracket => Entry::Unit(token.kind()),
+            _ => self.push_value(token),
+        };
+        self.entries.push(entry)
+    }
+
+    fn push_value(&mut self, token: Token<'i>) -> Entry {
+        self.values.push(token);
+        Entry::Value((self.values.len() - 1) as u32)
+    }
+
+    /// Return the number of tokens in the list.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Return whether the list has no tokens.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Return the token at the given index, if any.
+    ///
+    /// Tokens with a value are cloned, which does not copy strings.
+    pub fn get(&self, index: usize) -> Option<Token<'i>> {
+        self.entries.get(index).map(|&entry| self.token(entry))
+    }
+
+    /// Return an iterator over the tokens of the list, cloned as with `get`.
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = Token<'i>> + 'a {
+        self.entries.iter().map(move |&entry| self.token(entry))
+    }
+
+    /// Return the number of bytes that the list has allocated on the heap,
+    /// not counting the strings of tokens.
+    pub fn heap_size(&self) -> usize {
+        self.entries.capacity() * std::mem::size_of::<Entry>()
+            + self.values.capacity() * std::mem::size_of::<Token>()
+    }
+
+    /// Free the memory that was allocated for tokens that were not pushed,
+    /// for lists that are kept after they are built.
+    pub fn shrink_to_fit(&mut self) {
+        self.entries.shrink_to_fit();
+        self.values.shrink_to_fit();
+    }
+
+    fn token(&self, entry: Entry) -> Token<'i> {
+        match entry {
+            Entry::Unit(kind) => unit_token(kind),
+            Entry::Delim(c) => Token::Delim(c),
+            Entry::WhiteSpace(index) => Token::WhiteSpace(COMMON_WHITESPACE[index as usize]),
+            Entry::Value(index) => self.values[index as usize].clone(),
+        }
+    }
+}
+
+fn unit_token(kind: TokenKind) -> Token<'static> {
+    match kind {
+        TokenKind::Colon => Token::Colon,
+        TokenKind::Semicolon => Token::Semicolon,
+        TokenKind::Comma => Token::Comma,
+        TokenKind::IncludeMatch => Token::IncludeMatch,
+        TokenKind::DashMatch => Token::DashMatch,
+        TokenKind::PrefixMatch => Token::PrefixMatch,
+        TokenKind::SuffixMatch => Token::SuffixMatch,
+        TokenKind::SubstringMatch => Token::SubstringMatch,
+        TokenKind::CDO => Token::CDO,
+        TokenKind::CDC => Token::CDC,
+        TokenKind::ParenthesisBlock => Token::ParenthesisBlock,
+        TokenKind::SquareBracketBlock => Token::SquareBracketBlock,
+        TokenKind::CurlyBracketBlock => Token::CurlyBracketBlock,
+        TokenKind::CloseParenthesis => Token::CloseParenthesis,
+        TokenKind::CloseSquareBracket => Token::CloseSquareBracket,
+        TokenKind::CloseCurlyBracket => Token::CloseCurlyBracket,
+        _ => unreachable!(),
+    }
+}
+
+impl<'i> FromIterator<Token<'i>> for TokenList<'i> {
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = Token<'i>>,
+    {
+        let mut list = TokenList::new();
+        for token in iter {
+            list.push(token)
+        }
+        list
+    }
+}
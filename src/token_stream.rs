@@ -0,0 +1,500 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Comparing and hashing token streams regardless of their formatting.
+
+use crate::parser::{ParseError, Parser, ParserInput};
+use crate::tokenizer::Token;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::mem;
+
+/// Return whether the rest of `a` and the rest of `b` are equivalent.
+///
+/// Both inputs are consumed, including the contents of nested blocks.
+/// They are equivalent if they only differ in:
+///
+/// * The amount of whitespace and comments between two tokens.
+///   Whether there is any is significant, except at the start and end of the input
+///   and of each block.
+///   (Comments count as whitespace here, so `a/**/b` is equivalent to `a b`.)
+/// * The representation of numbers: `+1`, `1.0` and `10e-1` are equivalent.
+///
+/// This is consistent with `hash_token_stream`.
+pub fn token_stream_eq<'i, 't, 'j, 'u>(a: &mut Parser<'i, 't>, b: &mut Parser<'j, 'u>) -> bool {
+    let mut a = SignificantTokens::new(a);
+    let mut b = SignificantTokens::new(b);
+    loop {
+        let (token_a, token_b) = match (a.next(), b.next()) {
+            (None, None) => return true,
+            (Some(token_a), Some(token_b)) => (token_a, token_b),
+            _ => return false,
+        };
+        if !token_eq(&token_a, &token_b) {
+            return false;
+        }
+        if token_a.is_block() {
+            let result: Result<bool, ParseError<()>> = a.input.parse_nested_block(|a| {
+                let result: Result<bool, ParseError<()>> =
+                    b.input.parse_nested_block(|b| Ok(token_stream_eq(a, b)));
+                Ok(result.unwrap_or(false))
+            });
+            if !result.unwrap_or(false) {
+                return false;
+            }
+        }
+    }
+}
+
+/// Feed the rest of `input` into the given hasher,
+/// such that equivalent inputs per `token_stream_eq` have the same hash.
+///
+/// This consumes the input, including the contents of nested blocks.
+pub fn hash_token_stream<'i, 't, H: Hasher>(input: &mut Parser<'i, 't>, state: &mut H) {
+    let mut tokens = SignificantTokens::new(input);
+    while let Some(token) = tokens.next() {
+        hash_token(&token, state);
+        if token.is_block() {
+            let result: Result<(), ParseError<()>> = tokens.input.parse_nested_block(|input| {
+                hash_token_stream(input, state);
+                Ok(())
+            });
+            result.unwrap();
+            // Distinguish `(a) b` from `(a b)`.
+            state.write_u8(0xFF);
+        }
+    }
+}
+
+/// The version of the algorithm of `stable_hash_token_stream`.
+///
+/// It will be incremented whenever a change to the algorithm changes any hash,
+/// so that it can be made part of cache keys that are persisted.
+pub const STABLE_HASH_VERSION: u32 = 1;
+
+/// Return a hash of the rest of `input`,
+/// such that equivalent inputs per `token_stream_eq` have the same hash.
+///
+/// Unlike `hash_token_stream` with a `Hasher` of the standard library,
+/// the hash is the same for every process, platform and version of this crate
+/// with the same `STABLE_HASH_VERSION`, so it can be used as a key of a persistent cache.
+///
+/// Version 1 is the 64-bit FNV-1a hash of an encoding of the significant tokens:
+/// per token, a byte for its type, followed by its value if any.
+/// Strings are encoded as their length as a little-endian `u32` and their UTF-8 bytes,
+/// numbers as the little-endian bits of their `f32` value,
+/// where negative zero is zero, and the end of a block as `0xFF`.
+/// It is not a cryptographic hash.
+///
+/// This consumes the input, including the contents of nested blocks.
+pub fn stable_hash_token_stream<'i, 't>(input: &mut Parser<'i, 't>) -> u64 {
+    let mut hasher = Fnv1a(0xcbf2_9ce4_8422_2325);
+    stable_hash_tokens(input, &mut hasher);
+    hasher.0
+}
+
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_str(&mut self, value: &str) {
+        self.write(&(value.len() as u32).to_le_bytes());
+        self.write(value.as_bytes())
+    }
+
+    fn write_f32(&mut self, value: f32) {
+        let value = if value == 0. { 0. } else { value };
+        self.write(&value.to_bits().to_le_bytes())
+    }
+}
+
+fn stable_hash_tokens<'i, 't>(input: &mut Parser<'i, 't>, hasher: &mut Fnv1a) {
+    let mut tokens = SignificantTokens::new(input);
+    while let Some(token) = tokens.next() {
+        let (tag, value) = match token {
+            Token::Ident(ref value) => (1, Some(value)),
+            Token::AtKeyword(ref value) => (2, Some(value)),
+            Token::Hash(ref value) => (3, Some(value)),
+            Token::IDHash(ref value) => (4, Some(value)),
+            Token::QuotedString(ref value) => (5, Some(value)),
+            Token::UnquotedUrl(ref value) => (6, Some(value)),
+            Token::Function(ref value) => (7, Some(value)),
+            Token::BadUrl(ref value) => (8, Some(value)),
+            Token::BadString(ref value) => (9, Some(value)),
+            Token::Delim(_) => (10, None),
+            Token::Number { .. } => (11, None),
+            Token::Percentage { .. } => (12, None),
+            Token::Dimension { ref unit, .. } => (13, Some(unit)),
+            Token::WhiteSpace(_) | Token::Comment(_) => (14, None),
+            Token::Colon => (15, None),
+            Token::Semicolon => (16, None),
+            Token::Comma => (17, None),
+            Token::IncludeMatch => (18, None),
+            Token::DashMatch => (19, None),
+            Token::PrefixMatch => (20, None),
+            Token::SuffixMatch => (21, None),
+            Token::SubstringMatch => (22, None),
+            Token::CDO => (23, None),
+            Token::CDC => (24, None),
+            Token::ParenthesisBlock => (25, None),
+            Token::SquareBracketBlock => (26, None),
+            Token::CurlyBracketBlock => (27, None),
+            Token::CloseParenthesis => (28, None),
+            Token::CloseSquareBracket => (29, None),
+            Token::CloseCurlyBracket => (30, None),
+            Token::ProgId(_) => (31, None),
+            Token::Expression(_) => (32, None),
+            Token::__Nonexhaustive(void) => match void {},
+        };
+        hasher.write(&[tag]);
+        match token {
+            Token::ProgId(value) | Token::Expression(value) => hasher.write_str(value),
+            Token::Delim(value) => hasher.write_str(value.encode_utf8(&mut [0; 4])),
+            Token::Number { value, .. }
+            | Token::Percentage {
+                unit_value: value, ..
+            }
+            | Token::Dimension { value, .. } => hasher.write_f32(value),
+            _ => {}
+        }
+        if let Some(value) = value {
+            hasher.write_str(value)
+        }
+        if token.is_block() {
+            let result: Result<(), ParseError<()>> = tokens.input.parse_nested_block(|input| {
+                stable_hash_tokens(input, hasher);
+                Ok(())
+            });
+            result.unwrap();
+            hasher.write(&[0xFF]);
+        }
+    }
+}
+
+/// Remove rules that are exactly duplicated by a later rule of the same list,
+/// keeping the remaining ones in order.
+///
+/// `source` returns the source text of a rule, such as `a, b { color: red }`
+/// for a qualified rule.
+/// Two rules are duplicates if their source texts are equivalent per `token_stream_eq`.
+/// Keeping the last occurrence preserves the cascade,
+/// since an earlier identical rule is always overridden by the later one.
+pub fn remove_duplicate_rules<R, F>(rules: &mut Vec<R>, source: F)
+where
+    F: Fn(&R) -> &str,
+{
+    let mut later_rules: HashMap<u64, Vec<usize>> = HashMap::new();
+    let mut keep = vec![true; rules.len()];
+    for (index, rule) in rules.iter().enumerate().rev() {
+        let css = source(rule);
+        let candidates = later_rules.entry(hash(css)).or_default();
+        let is_duplicate = candidates
+            .iter()
+            .any(|&later| css_eq(css, source(&rules[later])));
+        if is_duplicate {
+            keep[index] = false
+        } else {
+            candidates.push(index)
+        }
+    }
+    let mut keep = keep.into_iter();
+    rules.retain(|_| keep.next().unwrap())
+}
+
+/// Merge each run of consecutive rules whose preludes are equivalent per `token_stream_eq`
+/// into its first rule.
+///
+/// `prelude` returns the source text of the prelude of a rule, such as `a, b`,
+/// and `merge` appends the contents of the second rule to the block of the first one,
+/// for example by concatenating their declaration lists.
+/// Only consecutive rules are merged, since moving a rule past another one
+/// can change which declarations win in the cascade.
+pub fn merge_adjacent_rules<R, F, G>(rules: &mut Vec<R>, prelude: F, mut merge: G)
+where
+    F: Fn(&R) -> &str,
+    G: FnMut(&mut R, R),
+{
+    let mut merged: Vec<R> = Vec::with_capacity(rules.len());
+    for rule in rules.drain(..) {
+        if let Some(last) = merged.last_mut() {
+            if css_eq(prelude(last), prelude(&rule)) {
+                merge(last, rule);
+                continue;
+            }
+        }
+        merged.push(rule)
+    }
+    *rules = merged
+}
+
+/// A difference between two lists of rules, found by `diff_rules`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RuleChange {
+    /// The rule at this index of the old list is not in the new list.
+    Removed(usize),
+    /// The rule at this index of the new list is not in the old list.
+    Inserted(usize),
+    /// The rules at these indices have equivalent preludes, but different contents.
+    Modified {
+        /// The index in the old list.
+        old: usize,
+        /// The index in the new list.
+        new: usize,
+    },
+}
+
+/// Compare two versions of a list of rules, such as the top-level rules of a stylesheet
+/// before and after it was edited, so that the changes can be applied incrementally.
+///
+/// `prelude` and `source` return the source text of the prelude of a rule
+/// and of the whole rule, like for `merge_adjacent_rules` and `remove_duplicate_rules`.
+/// Rules are matched by their prelude, per `token_stream_eq`,
+/// and a matched pair of rules is modified if their source texts are not equivalent.
+/// Matched rules stay in the same order relative to each other,
+/// so a rule that moved before another matched rule is reported as removed and inserted.
+///
+/// The diff is minimal: as many rules as possible are matched, with Myers’ algorithm.
+/// This takes `O((N + M) D)` time and `O(D²)` memory,
+/// where `D` is the number of removed and inserted rules.
+///
+/// Removed rules are returned first, in order, followed by inserted and modified rules
+/// in the order of the new list. Unchanged rules are not returned.
+pub fn diff_rules<R, F, G>(old: &[R], new: &[R], prelude: F, source: G) -> Vec<RuleChange>
+where
+    F: Fn(&R) -> &str,
+    G: Fn(&R) -> &str,
+{
+    // Number the preludes so that rules are compared without tokenizing them again.
+    let mut preludes: HashMap<u64, Vec<(&str, usize)>> = HashMap::new();
+    let mut next_id = 0;
+    let mut prelude_id = |rule| {
+        let css = prelude(rule);
+        let same_hash = preludes.entry(hash(css)).or_default();
+        match same_hash.iter().find(|&&(other, _)| css_eq(css, other)) {
+            Some(&(_, id)) => id,
+            None => {
+                same_hash.push((css, next_id));
+                next_id += 1;
+                next_id - 1
+            }
+        }
+    };
+    let old_ids: Vec<usize> = old.iter().map(&mut prelude_id).collect();
+    let new_ids: Vec<usize> = new.iter().map(&mut prelude_id).collect();
+
+    let mut matched_old = vec![false; old.len()];
+    let mut matched_new = vec![None; new.len()];
+    for (old_index, new_index) in longest_common_subsequence(&old_ids, &new_ids) {
+        matched_old[old_index] = true;
+        matched_new[new_index] = Some(old_index);
+    }
+    let removed = matched_old
+        .iter()
+        .enumerate()
+        .filter(|&(_, &matched)| !matched)
+        .map(|(index, _)| RuleChange::Removed(index));
+    let changes = new
+        .iter()
+        .enumerate()
+        .filter_map(|(index, rule)| match matched_new[index] {
+            Some(old_index) if css_eq(source(&old[old_index]), source(rule)) => None,
+            Some(old_index) => Some(RuleChange::Modified {
+                old: old_index,
+                new: index,
+            }),
+            None => Some(RuleChange::Inserted(index)),
+        });
+    removed.chain(changes).collect()
+}
+
+/// Return the pairs of indices of a longest common subsequence of `a` and `b`, in order.
+///
+/// This is the algorithm of “An O(ND) Difference Algorithm and Its Variations”
+/// by Eugene W. Myers, keeping the furthest reaching paths of each step to backtrack.
+fn longest_common_subsequence(a: &[usize], b: &[usize]) -> Vec<(usize, usize)> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let offset = n + m + 1;
+    // The furthest `x` reached on each diagonal `k = x - y`, at index `offset + k`.
+    let mut furthest = vec![0; 2 * offset as usize + 1];
+    // The values of `furthest` for diagonals `-d..=d` before each step `d`.
+    let mut trace = Vec::new();
+    'steps: for d in 0..=n + m {
+        trace.push(furthest[(offset - d) as usize..=(offset + d) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let i = (offset + k) as usize;
+            let mut x = if k == -d || (k != d && furthest[i - 1] < furthest[i + 1]) {
+                furthest[i + 1]
+            } else {
+                furthest[i - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            furthest[i] = x;
+            if x >= n && y >= m {
+                break 'steps;
+            }
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, furthest) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let (previous_x, previous_y) = if d == 0 {
+            (0, 0)
+        } else {
+            let k = x - y;
+            let at = |k: isize| furthest[(k + d) as usize];
+            let previous_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+                k + 1
+            } else {
+                k - 1
+            };
+            (at(previous_k), at(previous_k) - previous_k)
+        };
+        while x > previous_x && y > previous_y {
+            x -= 1;
+            y -= 1;
+            pairs.push((x as usize, y as usize));
+        }
+        x = previous_x;
+        y = previous_y;
+    }
+    pairs.reverse();
+    pairs
+}
+
+fn hash(css: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hash_token_stream(&mut Parser::new(&mut ParserInput::new(css)), &mut hasher);
+    hasher.finish()
+}
+
+fn css_eq(a: &str, b: &str) -> bool {
+    token_stream_eq(
+        &mut Parser::new(&mut ParserInput::new(a)),
+        &mut Parser::new(&mut ParserInput::new(b)),
+    )
+}
+
+/// The tokens of a parser, with each run of whitespace and comments
+/// replaced with a single space and removed at the start and end.
+struct SignificantTokens<'a, 'i: 'a, 't: 'a> {
+    input: &'a mut Parser<'i, 't>,
+    at_start: bool,
+    pending: Option<Token<'i>>,
+}
+
+impl<'a, 'i, 't> SignificantTokens<'a, 'i, 't> {
+    fn new(input: &'a mut Parser<'i, 't>) -> Self {
+        SignificantTokens {
+            input,
+            at_start: true,
+            pending: None,
+        }
+    }
+
+    fn next(&mut self) -> Option<Token<'i>> {
+        if let Some(token) = self.pending.take() {
+            return Some(token);
+        }
+        let mut whitespace_before = false;
+        loop {
+            match *self.input.next_including_whitespace_and_comments().ok()? {
+                Token::WhiteSpace(_) | Token::Comment(_) => whitespace_before = true,
+                ref token => {
+                    let token = token.clone();
+                    if whitespace_before && !self.at_start {
+                        self.pending = Some(token);
+                        return Some(Token::WhiteSpace(" "));
+                    }
+                    self.at_start = false;
+                    return Some(token);
+                }
+            }
+        }
+    }
+}
+
+fn token_eq(a: &Token, b: &Token) -> bool {
+    match (a, b) {
+        (&Token::Number { value: a, .. }, &Token::Number { value: b, .. }) => a == b,
+        (&Token::Percentage { unit_value: a, .. }, &Token::Percentage { unit_value: b, .. }) => {
+            a == b
+        }
+        (
+            &Token::Dimension {
+                value: a,
+                unit: ref unit_a,
+                ..
+            },
+            &Token::Dimension {
+                value: b,
+                unit: ref unit_b,
+                ..
+            },
+        ) => a == b && unit_a == unit_b,
+        _ => a == b,
+    }
+}
+
+fn hash_f32<H: Hasher>(value: f32, state: &mut H) {
+    // Negative zero is equal to zero.
+    let value = if value == 0. { 0. } else { value };
+    value.to_bits().hash(state)
+}
+
+fn hash_token<H: Hasher>(token: &Token, state: &mut H) {
+    mem::discriminant(token).hash(state);
+    match *token {
+        Token::Ident(ref value)
+        | Token::AtKeyword(ref value)
+        | Token::Hash(ref value)
+        | Token::IDHash(ref value)
+        | Token::QuotedString(ref value)
+        | Token::UnquotedUrl(ref value)
+        | Token::Function(ref value)
+        | Token::BadUrl(ref value)
+        | Token::BadString(ref value) => value.hash(state),
+        Token::Delim(value) => value.hash(state),
+        Token::ProgId(value) | Token::Expression(value) => value.hash(state),
+        Token::Number { value, .. } => hash_f32(value, state),
+        Token::Percentage { unit_value, .. } => hash_f32(unit_value, state),
+        Token::Dimension {
+            value, ref unit, ..
+        } => {
+            hash_f32(value, state);
+            unit.hash(state)
+        }
+        // `SignificantTokens` only returns single spaces, and no comments.
+        Token::WhiteSpace(_) | Token::Comment(_) => {}
+        Token::Colon
+        | Token::Semicolon
+        | Token::Comma
+        | Token::IncludeMatch
+        | Token::DashMatch
+        | Token::PrefixMatch
+        | Token::SuffixMatch
+        | Token::SubstringMatch
+        | Token::CDO
+        | Token::CDC
+        | Token::ParenthesisBlock
+        | Token::SquareBracketBlock
+        | Token::CurlyBracketBlock
+        | Token::CloseParenthesis
+        | Token::CloseSquareBracket
+        | Token::CloseCurlyBracket
+        | Token::__Nonexhaustive(_) => {}
+    }
+}
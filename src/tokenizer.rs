@@ -7,15 +7,24 @@
 use self::Token::*;
 use crate::cow_rc_str::CowRcStr;
 use crate::parser::ParserState;
+use crate::Void;
 use matches::matches;
+use std::cell::Cell;
 use std::char;
+use std::fmt;
 use std::i32;
 use std::ops::Range;
+use std::u32;
 
 /// One of the pieces the CSS input is broken into.
 ///
 /// Some components use `Cow` in order to borrow from the original input string
 /// and avoid allocating/copying when possible.
+///
+/// New variants may be added as CSS Syntax evolves.
+/// Code that only needs some of them can use `Token::kind` and accessors
+/// such as `Token::as_ident` or `Token::string_value`,
+/// or a `_` wildcard arm, rather than matching exhaustively.
 #[derive(PartialEq, Debug, Clone)]
 pub enum Token<'a> {
     /// A [`<ident-token>`](https://drafts.csswg.org/css-syntax/#ident-token-diagram)
@@ -95,6 +104,9 @@ pub enum Token<'a> {
     },
 
     /// A [`<whitespace-token>`](https://drafts.csswg.org/css-syntax/#whitespace-token-diagram)
+    ///
+    /// The value is the original whitespace text, including any newlines,
+    /// and is serialized unchanged.
     WhiteSpace(&'a str),
 
     /// A comment.
@@ -176,6 +188,26 @@ pub enum Token<'a> {
     /// When obtained from one of the `Parser::next*` methods,
     /// this token is always unmatched and indicates a parse error.
     CloseCurlyBracket,
+
+    /// A legacy `progid:` filter value of Internet Explorer,
+    /// such as `progid:DXImageTransform.Microsoft.Alpha(Opacity=80)`.
+    ///
+    /// Only returned with `ParserInput::set_compat_quirks`.
+    /// The value is the source text after `progid:`, including the parenthesized arguments.
+    /// Without arguments, as in the `.progid:hover` selector, `progid` is an `Ident`.
+    ProgId(&'a str),
+
+    /// A legacy `expression()` value of Internet Explorer, whose argument is JavaScript.
+    ///
+    /// Only returned with `ParserInput::set_compat_quirks`.
+    /// The value is the source text between the parentheses, which is not tokenized:
+    /// nested parentheses and quoted strings are skipped.
+    Expression(&'a str),
+
+    /// Never returned. Matches on `Token` need a wildcard arm,
+    /// so that new variants can be added without a breaking change.
+    #[doc(hidden)]
+    __Nonexhaustive(Void),
 }
 
 impl<'a> Token<'a> {
@@ -191,6 +223,270 @@ impl<'a> Token<'a> {
             BadUrl(_) | BadString(_) | CloseParenthesis | CloseSquareBracket | CloseCurlyBracket
         )
     }
+
+    /// Return whether this is a `WhiteSpace` token.
+    pub fn is_whitespace(&self) -> bool {
+        matches!(*self, WhiteSpace(_))
+    }
+
+    /// Return whether this is a `WhiteSpace` token that contains a newline,
+    /// i.e. whether the tokens around it are on different lines.
+    ///
+    /// Newlines are `\n`, `\r`, and `\x0C` form feeds.
+    pub fn has_newline(&self) -> bool {
+        match *self {
+            WhiteSpace(text) => text.bytes().any(|b| matches!(b, b'\n' | b'\r' | b'\x0C')),
+            _ => false,
+        }
+    }
+
+    /// Return whether this token opens a block, whose contents can be parsed
+    /// with `Parser::parse_nested_block`.
+    ///
+    /// This is the case of `Function`, `ParenthesisBlock`, `SquareBracketBlock`,
+    /// and `CurlyBracketBlock`.
+    pub fn is_block(&self) -> bool {
+        matches!(
+            *self,
+            Function(_) | ParenthesisBlock | SquareBracketBlock | CurlyBracketBlock
+        )
+    }
+
+    /// Return the kind of this token, without its value.
+    pub fn kind(&self) -> TokenKind {
+        match *self {
+            Ident(_) => TokenKind::Ident,
+            AtKeyword(_) => TokenKind::AtKeyword,
+            Hash(_) => TokenKind::Hash,
+            IDHash(_) => TokenKind::IDHash,
+            QuotedString(_) => TokenKind::QuotedString,
+            UnquotedUrl(_) => TokenKind::UnquotedUrl,
+            Delim(_) => TokenKind::Delim,
+            Number { .. } => TokenKind::Number,
+            Percentage { .. } => TokenKind::Percentage,
+            Dimension { .. } => TokenKind::Dimension,
+            WhiteSpace(_) => TokenKind::WhiteSpace,
+            Comment(_) => TokenKind::Comment,
+            Colon => TokenKind::Colon,
+            Semicolon => TokenKind::Semicolon,
+            Comma => TokenKind::Comma,
+            IncludeMatch => TokenKind::IncludeMatch,
+            DashMatch => TokenKind::DashMatch,
+            PrefixMatch => TokenKind::PrefixMatch,
+            SuffixMatch => TokenKind::SuffixMatch,
+            SubstringMatch => TokenKind::SubstringMatch,
+            CDO => TokenKind::CDO,
+            CDC => TokenKind::CDC,
+            Function(_) => TokenKind::Function,
+            ParenthesisBlock => TokenKind::ParenthesisBlock,
+            SquareBracketBlock => TokenKind::SquareBracketBlock,
+            CurlyBracketBlock => TokenKind::CurlyBracketBlock,
+            BadUrl(_) => TokenKind::BadUrl,
+            BadString(_) => TokenKind::BadString,
+            CloseParenthesis => TokenKind::CloseParenthesis,
+            CloseSquareBracket => TokenKind::CloseSquareBracket,
+            CloseCurlyBracket => TokenKind::CloseCurlyBracket,
+            ProgId(_) => TokenKind::ProgId,
+            Expression(_) => TokenKind::Expression,
+            __Nonexhaustive(void) => match void {},
+        }
+    }
+
+    /// If this is an `Ident` token, return its value.
+    pub fn as_ident(&self) -> Option<&CowRcStr<'a>> {
+        match *self {
+            Ident(ref value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// If this is an `Ident` token, return its value, or give back any other token.
+    pub fn into_ident(self) -> Result<CowRcStr<'a>, Token<'a>> {
+        match self {
+            Ident(value) => Ok(value),
+            token => Err(token),
+        }
+    }
+
+    /// If this is a `Number` token, return its value.
+    pub fn as_number(&self) -> Option<f32> {
+        match *self {
+            Number { value, .. } => Some(value),
+            _ => None,
+        }
+    }
+
+    /// If this is a `Delim` token, return its character.
+    pub fn as_delim(&self) -> Option<char> {
+        match *self {
+            Delim(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// If this is a `Percentage` token, return its value divided by 100.
+    pub fn as_percentage(&self) -> Option<f32> {
+        match *self {
+            Percentage { unit_value, .. } => Some(unit_value),
+            _ => None,
+        }
+    }
+
+    /// If this is a `Dimension` token, return its value and unit.
+    pub fn as_dimension(&self) -> Option<(f32, &CowRcStr<'a>)> {
+        match *self {
+            Dimension {
+                value, ref unit, ..
+            } => Some((value, unit)),
+            _ => None,
+        }
+    }
+
+    /// Return the string value of a token that has one:
+    /// `Ident`, `AtKeyword`, `Hash`, `IDHash`, `QuotedString`, `UnquotedUrl`, `Function`,
+    /// `BadUrl`, or `BadString`.
+    ///
+    /// Use `Token::kind` to tell them apart.
+    pub fn string_value(&self) -> Option<&CowRcStr<'a>> {
+        match *self {
+            Ident(ref value)
+            | AtKeyword(ref value)
+            | Hash(ref value)
+            | IDHash(ref value)
+            | QuotedString(ref value)
+            | UnquotedUrl(ref value)
+            | Function(ref value)
+            | BadUrl(ref value)
+            | BadString(ref value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// The kind of a `Token`, without its value.
+///
+/// This is cheap to copy and compare, for example to dispatch on
+/// or to describe which tokens were expected in an error message.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum TokenKind {
+    /// See `Token::Ident`.
+    Ident,
+    /// See `Token::AtKeyword`.
+    AtKeyword,
+    /// See `Token::Hash`.
+    Hash,
+    /// See `Token::IDHash`.
+    IDHash,
+    /// See `Token::QuotedString`.
+    QuotedString,
+    /// See `Token::UnquotedUrl`.
+    UnquotedUrl,
+    /// See `Token::Delim`.
+    Delim,
+    /// See `Token::Number`.
+    Number,
+    /// See `Token::Percentage`.
+    Percentage,
+    /// See `Token::Dimension`.
+    Dimension,
+    /// See `Token::WhiteSpace`.
+    WhiteSpace,
+    /// See `Token::Comment`.
+    Comment,
+    /// See `Token::Colon`.
+    Colon,
+    /// See `Token::Semicolon`.
+    Semicolon,
+    /// See `Token::Comma`.
+    Comma,
+    /// See `Token::IncludeMatch`.
+    IncludeMatch,
+    /// See `Token::DashMatch`.
+    DashMatch,
+    /// See `Token::PrefixMatch`.
+    PrefixMatch,
+    /// See `Token::SuffixMatch`.
+    SuffixMatch,
+    /// See `Token::SubstringMatch`.
+    SubstringMatch,
+    /// See `Token::CDO`.
+    CDO,
+    /// See `Token::CDC`.
+    CDC,
+    /// See `Token::Function`.
+    Function,
+    /// See `Token::ParenthesisBlock`.
+    ParenthesisBlock,
+    /// See `Token::SquareBracketBlock`.
+    SquareBracketBlock,
+    /// See `Token::CurlyBracketBlock`.
+    CurlyBracketBlock,
+    /// See `Token::BadUrl`.
+    BadUrl,
+    /// See `Token::BadString`.
+    BadString,
+    /// See `Token::CloseParenthesis`.
+    CloseParenthesis,
+    /// See `Token::CloseSquareBracket`.
+    CloseSquareBracket,
+    /// See `Token::CloseCurlyBracket`.
+    CloseCurlyBracket,
+    /// See `Token::ProgId`.
+    ProgId,
+    /// See `Token::Expression`.
+    Expression,
+    /// See `Token::__Nonexhaustive`.
+    #[doc(hidden)]
+    __Nonexhaustive(Void),
+}
+
+impl TokenKind {
+    /// Return a short description of this kind of token for use in error messages,
+    /// such as “an identifier” or “‘{’”.
+    pub fn description(self) -> &'static str {
+        match self {
+            TokenKind::Ident => "an identifier",
+            TokenKind::AtKeyword => "an at-keyword",
+            TokenKind::Hash => "a hash",
+            TokenKind::IDHash => "a hash",
+            TokenKind::QuotedString => "a string",
+            TokenKind::UnquotedUrl => "a URL",
+            TokenKind::Delim => "a delimiter",
+            TokenKind::Number => "a number",
+            TokenKind::Percentage => "a percentage",
+            TokenKind::Dimension => "a dimension",
+            TokenKind::WhiteSpace => "whitespace",
+            TokenKind::Comment => "a comment",
+            TokenKind::Colon => "‘:’",
+            TokenKind::Semicolon => "‘;’",
+            TokenKind::Comma => "‘,’",
+            TokenKind::IncludeMatch => "‘~=’",
+            TokenKind::DashMatch => "‘|=’",
+            TokenKind::PrefixMatch => "‘^=’",
+            TokenKind::SuffixMatch => "‘$=’",
+            TokenKind::SubstringMatch => "‘*=’",
+            TokenKind::CDO => "‘<!--’",
+            TokenKind::CDC => "‘-->’",
+            TokenKind::Function => "a function",
+            TokenKind::ParenthesisBlock => "‘(’",
+            TokenKind::SquareBracketBlock => "‘[’",
+            TokenKind::CurlyBracketBlock => "‘{’",
+            TokenKind::BadUrl => "an invalid URL",
+            TokenKind::BadString => "an unterminated string",
+            TokenKind::CloseParenthesis => "‘)’",
+            TokenKind::CloseSquareBracket => "‘]’",
+            TokenKind::CloseCurlyBracket => "‘}’",
+            TokenKind::ProgId => "a ‘progid:’ filter",
+            TokenKind::Expression => "an ‘expression()’",
+            TokenKind::__Nonexhaustive(void) => match void {},
+        }
+    }
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.description())
+    }
 }
 
 #[derive(Clone)]
@@ -206,6 +502,13 @@ pub struct Tokenizer<'a> {
     var_or_env_functions: SeenStatus,
     source_map_url: Option<&'a str>,
     source_url: Option<&'a str>,
+    column_unit: ColumnUnit,
+    /// 0 if tabs are one column wide.
+    tab_width: u8,
+    compat_quirks: bool,
+    /// The position and column of the last location computed by `column_in_unit`,
+    /// to continue from it on the same line rather than scan the line from its start.
+    last_column: Cell<(u32, u32)>,
 }
 
 #[derive(Copy, Clone, PartialEq, Eq)]
@@ -231,9 +534,30 @@ impl<'a> Tokenizer<'a> {
             var_or_env_functions: SeenStatus::DontCare,
             source_map_url: None,
             source_url: None,
+            column_unit: ColumnUnit::Utf16CodeUnits,
+            tab_width: 0,
+            compat_quirks: false,
+            last_column: Cell::new(NO_LAST_COLUMN),
         }
     }
 
+    #[inline]
+    pub fn set_column_unit(&mut self, unit: ColumnUnit) {
+        self.column_unit = unit;
+        self.last_column.set(NO_LAST_COLUMN);
+    }
+
+    #[inline]
+    pub fn set_tab_width(&mut self, width: u8) {
+        self.tab_width = width;
+        self.last_column.set(NO_LAST_COLUMN);
+    }
+
+    #[inline]
+    pub fn set_compat_quirks(&mut self, enabled: bool) {
+        self.compat_quirks = enabled;
+    }
+
     #[inline]
     pub fn look_for_var_or_env_functions(&mut self) {
         self.var_or_env_functions = SeenStatus::LookingForThem;
@@ -267,10 +591,64 @@ impl<'a> Tokenizer<'a> {
 
     #[inline]
     pub fn current_source_location(&self) -> SourceLocation {
+        self.source_location(
+            self.position,
+            self.current_line_start_position,
+            self.current_line_number,
+        )
+    }
+
+    #[inline]
+    pub fn source_location_of(&self, state: &ParserState) -> SourceLocation {
+        self.source_location(
+            state.position,
+            state.current_line_start_position,
+            state.current_line_number,
+        )
+    }
+
+    #[inline]
+    fn source_location(
+        &self,
+        position: usize,
+        current_line_start_position: usize,
+        current_line_number: u32,
+    ) -> SourceLocation {
+        let column = if self.column_unit == ColumnUnit::Utf16CodeUnits && self.tab_width == 0 {
+            // `current_line_start_position` is adjusted for this case.
+            (position - current_line_start_position + 1) as u32
+        } else {
+            self.column_in_unit(position)
+        };
         SourceLocation {
-            line: self.current_line_number,
-            column: (self.position - self.current_line_start_position + 1) as u32,
+            line: current_line_number,
+            column,
+        }
+    }
+
+    #[cold]
+    fn column_in_unit(&self, position: usize) -> u32 {
+        let before = &self.input.as_bytes()[..position];
+        let (last_position, last_column) = self.last_column.get();
+        let (since, column) =
+            if last_position != NO_LAST_COLUMN.0 && last_position as usize <= position {
+                (&before[last_position as usize..], last_column)
+            } else {
+                (before, 1)
+            };
+        let column = match since
+            .iter()
+            .rposition(|&b| matches!(b, b'\n' | b'\r' | b'\x0C'))
+        {
+            Some(newline) => {
+                column_in_unit(&since[newline + 1..], self.column_unit, self.tab_width)
+            }
+            None => column_after(column, since, self.column_unit, self.tab_width),
+        };
+        if position < NO_LAST_COLUMN.0 as usize {
+            self.last_column.set((position as u32, column));
         }
+        column
     }
 
     #[inline]
@@ -516,6 +894,36 @@ impl<'a> Tokenizer<'a> {
     }
 }
 
+/// Whether `c` is whitespace in CSS: space, tab, or a newline before preprocessing.
+pub(crate) fn is_css_whitespace(c: char) -> bool {
+    matches!(c, ' ' | '\t' | '\n' | '\r' | '\x0C')
+}
+
+/// No location computed yet, or only past the positions that fit in `Tokenizer::last_column`.
+const NO_LAST_COLUMN: (u32, u32) = (u32::MAX, 1);
+
+/// The column number of the end of `line`, the start of a line up to some position.
+pub(crate) fn column_in_unit(line: &[u8], unit: ColumnUnit, tab_width: u8) -> u32 {
+    column_after(1, line, unit, tab_width)
+}
+
+/// The column number of the end of `bytes`, which start at the given column of a line.
+fn column_after(column: u32, bytes: &[u8], unit: ColumnUnit, tab_width: u8) -> u32 {
+    let tab_width = u32::from(tab_width);
+    let mut column = column - 1;
+    for &b in bytes {
+        column += match b {
+            b'\t' if tab_width > 0 => tab_width - column % tab_width,
+            // UTF-8 continuation bytes
+            0x80..=0xBF if unit != ColumnUnit::Utf8Bytes => 0,
+            // Leading byte of a 4-byte UTF-8 sequence, a surrogate pair in UTF-16
+            0xF0..=0xFF if unit == ColumnUnit::Utf16CodeUnits => 2,
+            _ => 1,
+        }
+    }
+    column + 1
+}
+
 /// A position from the start of the input, counted in UTF-8 bytes.
 #[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
 pub struct SourcePosition(pub(crate) usize);
@@ -528,6 +936,19 @@ impl SourcePosition {
     }
 }
 
+/// The unit in which column numbers are counted in a `SourceLocation`.
+///
+/// See `ParserInput::set_column_unit`.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum ColumnUnit {
+    /// Bytes of the UTF-8 input.
+    Utf8Bytes,
+    /// UTF-16 code units, as in JavaScript strings and the DevTools protocol. This is the default.
+    Utf16CodeUnits,
+    /// Unicode code points.
+    CodePoints,
+}
+
 /// The line and column number for a given position within the input.
 #[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub struct SourceLocation {
@@ -535,10 +956,19 @@ pub struct SourceLocation {
     pub line: u32,
 
     /// The column number within a line, starting at 1 for first the character of the line.
-    /// Column numbers are counted in UTF-16 code units.
+    /// Column numbers are counted in UTF-16 code units,
+    /// unless `ParserInput::set_column_unit` or `ParserInput::set_tab_width` was used.
     pub column: u32,
 }
 
+/// Formats as `line:column`, with one added to the line number
+/// so that the first line is 1 like the first column (unless `with_first_line_number` was used).
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line + 1, self.column)
+    }
+}
+
 fn next_token<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<Token<'a>, ()> {
     if tokenizer.is_eof() {
         return Err(());
@@ -554,7 +984,7 @@ fn next_token<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<Token<'a>, ()> {
         b'"' => { consume_string(tokenizer, false) },
         b'#' => {
             tokenizer.advance(1);
-            if is_ident_start(tokenizer) { IDHash(consume_name(tokenizer)) }
+            if would_start_identifier(tokenizer) { IDHash(consume_name(tokenizer)) }
             else if !tokenizer.is_eof() && match tokenizer.next_byte_unchecked() {
                 // Any other valid case here already resulted in IDHash.
                 b'0'..=b'9' | b'-' => true,
@@ -602,7 +1032,7 @@ fn next_token<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<Token<'a>, ()> {
             } else if tokenizer.starts_with(b"-->") {
                 tokenizer.advance(3);
                 CDC
-            } else if is_ident_start(tokenizer) {
+            } else if would_start_identifier(tokenizer) {
                 consume_ident_like(tokenizer)
             } else {
                 tokenizer.advance(1);
@@ -641,7 +1071,7 @@ fn next_token<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<Token<'a>, ()> {
         },
         b'@' => {
             tokenizer.advance(1);
-            if is_ident_start(tokenizer) { AtKeyword(consume_name(tokenizer)) }
+            if would_start_identifier(tokenizer) { AtKeyword(consume_name(tokenizer)) }
             else { Delim('@') }
         },
         b'a'..=b'z' | b'A'..=b'Z' | b'_' | b'\0' => { consume_ident_like(tokenizer) },
@@ -878,8 +1308,24 @@ fn consume_quoted_string<'a>(
     )
 }
 
+/// Whether `input` starts with an identifier, such as the value of a `Token::Ident`
+/// or the name of a `Token::AtKeyword` after `@`.
+///
+/// This is the check that the tokenizer does on at most the first three characters,
+/// “would start an identifier” in the CSS Syntax specification.
+pub fn is_ident_start(input: &str) -> bool {
+    would_start_identifier(&mut Tokenizer::new(input))
+}
+
+/// Whether `input` starts with a valid escape: a `\` that is not followed by a newline.
+///
+/// A `\` at the end of the input is valid, and is U+FFFD REPLACEMENT CHARACTER.
+pub fn starts_with_valid_escape(input: &str) -> bool {
+    input.starts_with('\\') && !Tokenizer::new(input).has_newline_at(1)
+}
+
 #[inline]
-fn is_ident_start(tokenizer: &mut Tokenizer) -> bool {
+fn would_start_identifier(tokenizer: &mut Tokenizer) -> bool {
     !tokenizer.is_eof()
         && match_byte! { tokenizer.next_byte_unchecked(),
             b'a'..=b'z' | b'A'..=b'Z' | b'_' | b'\0' => { true },
@@ -888,7 +1334,8 @@ fn is_ident_start(tokenizer: &mut Tokenizer) -> bool {
                     b'a'..=b'z' | b'A'..=b'Z' | b'-' | b'_' | b'\0' => {
                         true
                     }
-                    b'\\' => { !tokenizer.has_newline_at(1) }
+                    // A valid escape after the `-`.
+                    b'\\' => { !tokenizer.has_newline_at(2) }
                     b => { !b.is_ascii() },
                 }
             },
@@ -903,15 +1350,90 @@ fn consume_ident_like<'a>(tokenizer: &mut Tokenizer<'a>) -> Token<'a> {
         tokenizer.advance(1);
         if value.eq_ignore_ascii_case("url") {
             consume_unquoted_url(tokenizer).unwrap_or(Function(value))
+        } else if tokenizer.compat_quirks && value.eq_ignore_ascii_case("expression") {
+            let contents = consume_raw_parenthesized(tokenizer);
+            if tokenizer.next_byte() == Some(b')') {
+                tokenizer.advance(1);
+            }
+            Expression(contents)
         } else {
             tokenizer.see_function(&value);
             Function(value)
         }
+    } else if tokenizer.compat_quirks
+        && value.eq_ignore_ascii_case("progid")
+        && tokenizer.next_byte() == Some(b':')
+    {
+        let colon = tokenizer.state();
+        tokenizer.advance(1);
+        match consume_progid(tokenizer) {
+            Some(token) => token,
+            None => {
+                // Not a filter, such as in the `.progid:hover` selector.
+                tokenizer.reset(&colon);
+                Ident(value)
+            }
+        }
     } else {
         Ident(value)
     }
 }
 
+/// Consume the name and arguments of a `progid:` filter, after the colon.
+///
+/// Returns `None` without a parenthesized argument list after the name.
+fn consume_progid<'a>(tokenizer: &mut Tokenizer<'a>) -> Option<Token<'a>> {
+    let start_position = tokenizer.position();
+    while let Some(byte) = tokenizer.next_byte() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_' | b'-' | b'.' => tokenizer.advance(1),
+            b'(' => {
+                tokenizer.advance(1);
+                consume_raw_parenthesized(tokenizer);
+                if tokenizer.next_byte() == Some(b')') {
+                    tokenizer.advance(1);
+                }
+                return Some(ProgId(tokenizer.slice_from(start_position)));
+            }
+            _ => break,
+        }
+    }
+    None
+}
+
+/// Consume the contents of a parenthesized block that was just opened, without tokenizing them,
+/// up to but excluding the matching `)` or the end of the input, and return them.
+///
+/// Nested parentheses and quoted strings are skipped.
+fn consume_raw_parenthesized<'a>(tokenizer: &mut Tokenizer<'a>) -> &'a str {
+    let start_position = tokenizer.position();
+    let mut depth = 0_usize;
+    let mut quote = None;
+    while let Some(byte) = tokenizer.next_byte() {
+        if matches!(byte, b'\n' | b'\r' | b'\x0C') {
+            tokenizer.consume_newline();
+            continue;
+        }
+        match (byte, quote) {
+            (b'\\', Some(_)) => {
+                tokenizer.advance(1);
+                if let Some(b'"') | Some(b'\'') | Some(b'\\') = tokenizer.next_byte() {
+                    tokenizer.advance(1)
+                }
+                continue;
+            }
+            (b'"', None) | (b'\'', None) => quote = Some(byte),
+            (_, Some(q)) if byte == q => quote = None,
+            (b'(', None) => depth += 1,
+            (b')', None) if depth == 0 => break,
+            (b')', None) => depth -= 1,
+            _ => {}
+        }
+        tokenizer.consume_known_byte(byte);
+    }
+    tokenizer.slice_from(start_position)
+}
+
 fn consume_name<'a>(tokenizer: &mut Tokenizer<'a>) -> CowRcStr<'a> {
     // start_pos is the end of the previous token, therefore at a code point boundary
     let start_pos = tokenizer.position();
@@ -1098,7 +1620,7 @@ fn consume_numeric<'a>(tokenizer: &mut Tokenizer<'a>) -> Token<'a> {
         };
     }
     let value = value as f32;
-    if is_ident_start(tokenizer) {
+    if would_start_identifier(tokenizer) {
         let unit = consume_name(tokenizer);
         Dimension {
             value: value,
@@ -5,7 +5,7 @@
 //! https://drafts.csswg.org/css-syntax/#urange
 
 use crate::tokenizer::Token;
-use crate::{BasicParseError, Parser, ToCss};
+use crate::{BasicParseError, FromCss, ParseError, Parser, ToCss};
 use std::char;
 use std::fmt;
 
@@ -161,6 +161,12 @@ fn consume_question_marks(text: &mut &[u8]) -> usize {
     question_marks
 }
 
+impl<'i, E> FromCss<'i, E> for UnicodeRange {
+    fn from_css<'t>(input: &mut Parser<'i, 't>) -> Result<Self, ParseError<'i, E>> {
+        Ok(UnicodeRange::parse(input)?)
+    }
+}
+
 impl fmt::Debug for UnicodeRange {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
         self.to_css(formatter)
@@ -0,0 +1,218 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use crate::cow_rc_str::CowRcStr;
+use crate::tokenizer::{SourceLocation, Token, Tokenizer};
+
+/// A URL found by `extract_urls`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExtractedUrl<'i> {
+    /// The URL, unescaped but not resolved.
+    pub url: CowRcStr<'i>,
+    /// Where the URL appears.
+    pub role: UrlRole,
+    /// The location of the `url(` function or token, or of the string.
+    pub location: SourceLocation,
+}
+
+/// The syntactic role of an `ExtractedUrl`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UrlRole {
+    /// The stylesheet of an `@import` rule.
+    Import,
+    /// The namespace of an `@namespace` rule, which is an identifier that is not fetched.
+    Namespace,
+    /// A font in the `src` descriptor of an `@font-face` rule.
+    FontFaceSource,
+    /// An image in an `image-set()` or `-webkit-image-set()` function,
+    /// either as a `url()` or as a string.
+    ImageSet,
+    /// A `url()` anywhere else, such as in a `background-image` declaration.
+    Value,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum BlockKind {
+    /// `url(`
+    Url,
+    /// `image-set(` or `-webkit-image-set(`
+    ImageSet,
+    /// The block of an `@font-face` rule.
+    FontFace,
+    Other,
+}
+
+struct Block {
+    closing: Token<'static>,
+    kind: BlockKind,
+}
+
+/// Return every URL of a stylesheet with its role, in source order.
+///
+/// This includes `url()` tokens and functions anywhere in the stylesheet,
+/// strings in `@import` and `@namespace` preludes and strings in `image-set()` functions,
+/// so that the subresources of a stylesheet can be audited or collected without parsing it.
+/// Strings elsewhere, such as `local("Font")` in `@font-face`, are not URLs.
+///
+/// Invalid rules are not excluded: their URLs are still found,
+/// though browsers would ignore them.
+pub fn extract_urls(input: &str) -> Vec<ExtractedUrl<'_>> {
+    let mut urls = Vec::new();
+    let mut tokenizer = Tokenizer::new(input);
+    let mut blocks: Vec<Block> = Vec::new();
+    // The at-rule whose prelude is being tokenized, with the block depth of the rule.
+    let mut at_rule: Option<(CowRcStr, usize)> = None;
+    // The name of the declaration whose value is being tokenized in an `@font-face` block.
+    let mut descriptor: Option<CowRcStr> = None;
+    let mut previous = None;
+    loop {
+        let location = tokenizer.current_source_location();
+        let token = match tokenizer.next() {
+            Ok(Token::WhiteSpace(_)) | Ok(Token::Comment(_)) => continue,
+            Ok(token) => token,
+            Err(()) => break,
+        };
+        let depth = blocks.len();
+        // The role of a URL in the prelude of the at-rule at the given depth, if any.
+        let prelude_role = |prelude_depth| match at_rule {
+            Some((ref name, at_rule_depth)) if at_rule_depth == prelude_depth => {
+                if name.eq_ignore_ascii_case("import") {
+                    Some(UrlRole::Import)
+                } else if name.eq_ignore_ascii_case("namespace") {
+                    Some(UrlRole::Namespace)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+        let is_font_face_prelude = match at_rule {
+            Some((ref name, at_rule_depth)) => {
+                at_rule_depth == depth && name.eq_ignore_ascii_case("font-face")
+            }
+            None => false,
+        };
+        let top = blocks.last().map(|block| block.kind);
+        // The role of a string here, if it is a URL.
+        let string_role = match top {
+            Some(BlockKind::ImageSet) => Some(UrlRole::ImageSet),
+            Some(BlockKind::Url) => Some(
+                prelude_role(depth - 1)
+                    .unwrap_or_else(|| url_role(&blocks[..depth - 1], &descriptor)),
+            ),
+            _ => prelude_role(depth),
+        };
+
+        match token {
+            Token::UnquotedUrl(ref url) => urls.push(ExtractedUrl {
+                url: url.clone(),
+                role: string_role.unwrap_or_else(|| url_role(&blocks, &descriptor)),
+                location,
+            }),
+            Token::QuotedString(ref url) => {
+                if let Some(role) = string_role {
+                    let location = match (top, previous) {
+                        // The location of `url(`.
+                        (Some(BlockKind::Url), Some((Token::Function(_), function_location))) => {
+                            function_location
+                        }
+                        _ => location,
+                    };
+                    urls.push(ExtractedUrl {
+                        url: url.clone(),
+                        role,
+                        location,
+                    })
+                }
+            }
+            Token::AtKeyword(ref name) => at_rule = Some((name.clone(), depth)),
+            Token::Ident(ref name) if top == Some(BlockKind::FontFace) => {
+                if let Some((Token::CurlyBracketBlock, _)) | Some((Token::Semicolon, _)) = previous
+                {
+                    descriptor = Some(name.clone())
+                }
+            }
+            Token::Semicolon => {
+                if top == Some(BlockKind::FontFace) {
+                    descriptor = None
+                }
+                at_rule = None
+            }
+            _ => {}
+        }
+
+        match token {
+            Token::Function(ref name) => {
+                let kind = if name.eq_ignore_ascii_case("url") {
+                    BlockKind::Url
+                } else if name.eq_ignore_ascii_case("image-set")
+                    || name.eq_ignore_ascii_case("-webkit-image-set")
+                {
+                    BlockKind::ImageSet
+                } else {
+                    BlockKind::Other
+                };
+                blocks.push(Block {
+                    closing: Token::CloseParenthesis,
+                    kind,
+                })
+            }
+            Token::ParenthesisBlock => blocks.push(Block {
+                closing: Token::CloseParenthesis,
+                kind: BlockKind::Other,
+            }),
+            Token::SquareBracketBlock => blocks.push(Block {
+                closing: Token::CloseSquareBracket,
+                kind: BlockKind::Other,
+            }),
+            Token::CurlyBracketBlock => {
+                let kind = if is_font_face_prelude {
+                    BlockKind::FontFace
+                } else {
+                    BlockKind::Other
+                };
+                at_rule = None;
+                descriptor = None;
+                blocks.push(Block {
+                    closing: Token::CloseCurlyBracket,
+                    kind,
+                })
+            }
+            Token::CloseParenthesis | Token::CloseSquareBracket | Token::CloseCurlyBracket
+                if blocks.last().map(|block| &block.closing) == Some(&token) =>
+            {
+                if let Some(Block {
+                    kind: BlockKind::FontFace,
+                    ..
+                }) = blocks.pop()
+                {
+                    descriptor = None
+                }
+                if token == Token::CloseCurlyBracket {
+                    at_rule = None
+                }
+            }
+            _ => {}
+        }
+        previous = Some((token, location));
+    }
+    urls
+}
+
+/// The role of a `url()` in the given blocks.
+fn url_role(blocks: &[Block], descriptor: &Option<CowRcStr>) -> UrlRole {
+    for block in blocks.iter().rev() {
+        match block.kind {
+            BlockKind::ImageSet => return UrlRole::ImageSet,
+            BlockKind::FontFace => {
+                return match *descriptor {
+                    Some(ref name) if name.eq_ignore_ascii_case("src") => UrlRole::FontFaceSource,
+                    _ => UrlRole::Value,
+                }
+            }
+            _ => {}
+        }
+    }
+    UrlRole::Value
+}
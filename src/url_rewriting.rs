@@ -0,0 +1,128 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use crate::tokenizer::{is_css_whitespace, SourceLocation, SourcePosition, Token, Tokenizer};
+
+/// Resolves URLs found in a stylesheet against its base URL.
+///
+/// See `ParserInput::set_base_url`.
+/// This crate does not include a URL parser:
+/// an implementation can for example use `Url::join` from the `url` crate.
+pub trait UrlResolver {
+    /// Return `url` resolved against `base_url`, or `None` if it cannot be resolved.
+    fn resolve(&self, base_url: &str, url: &str) -> Option<String>;
+}
+
+impl<F> UrlResolver for F
+where
+    F: Fn(&str, &str) -> Option<String>,
+{
+    fn resolve(&self, base_url: &str, url: &str) -> Option<String> {
+        self(base_url, url)
+    }
+}
+
+/// A URL that a `UrlResolver` could not resolve.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnresolvedUrl {
+    /// The URL, as it appears in the token.
+    pub url: String,
+    /// The location of the token.
+    pub location: SourceLocation,
+}
+
+impl UnresolvedUrl {
+    /// Whether the two URLs are the same, wherever they occurred.
+    pub fn eq_ignoring_location(&self, other: &Self) -> bool {
+        self.url == other.url
+    }
+}
+
+type RewriteUrl = dyn FnMut(&str, SourceLocation) -> Result<Option<String>, ()>;
+
+pub(crate) struct UrlRewriter {
+    rewrite: Box<RewriteUrl>,
+    /// The end of the last `url(` function token, if any.
+    url_function_end: Option<SourcePosition>,
+    unresolved_urls: Vec<UnresolvedUrl>,
+}
+
+impl UrlRewriter {
+    pub fn new<F>(mut rewrite: F) -> UrlRewriter
+    where
+        F: FnMut(&str, SourceLocation) -> Option<String> + 'static,
+    {
+        UrlRewriter {
+            rewrite: Box::new(move |url, location| Ok(rewrite(url, location))),
+            url_function_end: None,
+            unresolved_urls: Vec::new(),
+        }
+    }
+
+    pub fn with_base_url<R>(base_url: String, resolver: R) -> UrlRewriter
+    where
+        R: UrlResolver + 'static,
+    {
+        UrlRewriter {
+            rewrite: Box::new(move |url, _| resolver.resolve(&base_url, url).map(Some).ok_or(())),
+            url_function_end: None,
+            unresolved_urls: Vec::new(),
+        }
+    }
+
+    pub fn take_unresolved_urls(&mut self) -> Vec<UnresolvedUrl> {
+        self.unresolved_urls.drain(..).collect()
+    }
+
+    pub fn see_token(&mut self, token: &Token, end: SourcePosition) {
+        if let Token::Function(ref name) = *token {
+            if name.eq_ignore_ascii_case("url") {
+                self.url_function_end = Some(end)
+            }
+        }
+    }
+
+    pub fn rewrite_token<'i>(
+        &mut self,
+        token: Token<'i>,
+        (start, location): (SourcePosition, SourceLocation),
+        tokenizer: &Tokenizer<'i>,
+    ) -> Token<'i> {
+        let url = match token {
+            Token::UnquotedUrl(ref url) => url,
+            Token::QuotedString(ref url) => {
+                // Only whitespace between `url(` and the string.
+                let in_url_function = match self.url_function_end {
+                    Some(end) if end <= start => {
+                        tokenizer.slice(end..start).chars().all(is_css_whitespace)
+                    }
+                    _ => false,
+                };
+                if !in_url_function {
+                    return token;
+                }
+                url
+            }
+            _ => return token,
+        };
+        let rewritten = match (self.rewrite)(url, location) {
+            Ok(rewritten) => rewritten,
+            Err(()) => {
+                // The same token is tokenized again if the parser backtracks.
+                if !self.unresolved_urls.iter().any(|u| u.location == location) {
+                    self.unresolved_urls.push(UnresolvedUrl {
+                        url: url.to_string(),
+                        location,
+                    })
+                }
+                None
+            }
+        };
+        match (rewritten, token) {
+            (Some(url), Token::UnquotedUrl(_)) => Token::UnquotedUrl(url.into()),
+            (Some(url), Token::QuotedString(_)) => Token::QuotedString(url.into()),
+            (_, token) => token,
+        }
+    }
+}